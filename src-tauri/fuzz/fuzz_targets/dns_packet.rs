@@ -0,0 +1,22 @@
+//! Fuzz the DNS proxy's packet handling with arbitrary bytes.
+//!
+//! Run with `cargo +nightly fuzz run dns_packet` from `src-tauri/`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use parentshield_lib::blocking::network::dns_proxy::{
+    create_nxdomain_response, filter_upgrade_records, parse_dns_domain,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(domain) = parse_dns_domain(data) {
+        assert!(domain.len() <= 253);
+    }
+    if let Some(response) = create_nxdomain_response(data) {
+        assert_eq!(response.len(), data.len());
+    }
+    if let Some(filtered) = filter_upgrade_records(data) {
+        assert!(filtered.len() <= data.len());
+    }
+});
@@ -5,20 +5,60 @@
 //! - Manage hosts file blocking without password prompts
 //! - Apply firewall rules for DoH blocking
 //! - Communicate with the GUI via Unix domain socket
+//!
+//! Run with `--recover` (as root/Administrator) to remove all blocking and
+//! reset the parent password when a parent is locked out.
 
-use parentshield_lib::daemon::runner;
+use parentshield_lib::config::ConfigManager;
+use parentshield_lib::daemon::{logs, runner};
+use parentshield_lib::security::recovery;
+use std::io::BufRead;
+use std::path::Path;
 use std::process::ExitCode;
+use std::sync::Mutex;
+use tracing_subscriber::prelude::*;
 
 fn main() -> ExitCode {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // Initialize logging: stdout for the service manager, plus a rotated file
+    let log_settings = ConfigManager::new()
+        .and_then(|m| m.load())
+        .map(|c| c.log_settings)
+        .unwrap_or_default();
+
+    // launchd appends our stdout/stderr to its files on every relaunch and
+    // never rotates them, so cap them here before writing anything
+    #[cfg(target_os = "macos")]
+    for path in parentshield_lib::daemon::macos::LAUNCHD_LOG_PATHS {
+        let max_bytes = log_settings.retention_for("launchd").max_bytes;
+        if let Err(e) = logs::rotate_if_larger(Path::new(path), max_bytes) {
+            eprintln!("Failed to rotate {}: {}", path, e);
+        }
+    }
+    let file_layer = logs::RotatingLog::open(
+        Path::new(logs::LOG_DIR).join("daemon.log"),
+        log_settings.retention_for("daemon"),
+    )
+    .ok()
+    .map(|log| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_target(false)
+            .with_writer(Mutex::new(log))
+    });
+
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive("parentshield=info".parse().unwrap()),
         )
-        .with_target(false)
+        .with(tracing_subscriber::fmt::layer().with_target(false))
+        .with(file_layer)
         .init();
 
+    if std::env::args().any(|arg| arg == "--recover") {
+        return run_recovery();
+    }
+
     tracing::info!("ParentShield daemon starting...");
 
     // Check if running as root (Linux only - nix crate not available on macOS)
@@ -42,3 +82,36 @@ fn main() -> ExitCode {
         }
     }
 }
+
+/// Interactive admin recovery (see `parentshield_lib::security::recovery`)
+fn run_recovery() -> ExitCode {
+    eprintln!("{}\n", recovery::RECOVERY_WARNING);
+    eprint!("Type {} to continue: ", recovery::RECOVERY_CONFIRMATION);
+
+    let mut confirmation = String::new();
+    if std::io::stdin().lock().read_line(&mut confirmation).is_err() {
+        return ExitCode::from(1);
+    }
+
+    match recovery::run_recovery(
+        recovery::is_admin(),
+        &confirmation,
+        &recovery::audit_log_path(),
+        recovery::remove_all_blocking_and_reset,
+    ) {
+        Ok(steps) => {
+            for step in &steps {
+                match &step.error {
+                    None => eprintln!("  ok      {}", step.step),
+                    Some(e) => eprintln!("  failed  {}: {}", step.step, e),
+                }
+            }
+            eprintln!("\nRecovery finished. Open ParentShield to set a new password.");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Recovery aborted: {}", e);
+            ExitCode::from(1)
+        }
+    }
+}
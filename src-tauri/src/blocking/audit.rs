@@ -0,0 +1,300 @@
+//! Bypass risk audit: aggregates the individual detectors into one report.
+
+use crate::config::AppConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Public resolver used to test whether outside DNS is reachable
+const EXTERNAL_DNS_PROBE: &str = "1.1.1.1:53";
+
+/// How far the clock may lag the last config save before tampering is suspected
+const CLOCK_SKEW_TOLERANCE_MINUTES: i64 = 5;
+
+/// Interface name prefixes used by VPN and tunnel software
+const VPN_INTERFACE_PREFIXES: &[&str] = &["tun", "tap", "wg", "ppp", "utun", "ipsec", "nordlynx", "proton"];
+
+/// How serious a bypass risk is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// A way blocking could currently be bypassed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BypassRisk {
+    /// Stable identifier for the check (e.g. "browser_doh")
+    pub id: String,
+    pub severity: RiskSeverity,
+    pub description: String,
+    pub remediation: String,
+}
+
+/// Raw detector results the report is assembled from
+#[derive(Debug, Clone, Default)]
+pub struct AuditInputs {
+    /// Whether domain blocking is expected to be in effect right now
+    pub domain_blocking_expected: bool,
+    /// Whether firewall rules are expected (supported platform and blocking active)
+    pub firewall_expected: bool,
+    /// Browsers (or browser/user pairs) with DNS-over-HTTPS still enabled
+    pub doh_enabled_browsers: Vec<String>,
+    /// Whether a public DNS server answered a direct query
+    pub external_dns_reachable: bool,
+    /// Network interfaces that look like VPN tunnels
+    pub vpn_interfaces: Vec<String>,
+    /// Whether the DoH/VPN firewall rules are installed
+    pub firewall_active: bool,
+    /// Whether the system clock appears to have been moved backwards
+    pub clock_tamper_suspected: bool,
+    /// Nameservers from resolv.conf
+    pub nameservers: Vec<String>,
+}
+
+/// Build the risk report from detector outputs, most severe first
+pub fn assemble_report(inputs: &AuditInputs) -> Vec<BypassRisk> {
+    let mut risks = Vec::new();
+    let mut add = |id: &str, severity: RiskSeverity, description: String, remediation: &str| {
+        risks.push(BypassRisk {
+            id: id.to_string(),
+            severity,
+            description,
+            remediation: remediation.to_string(),
+        });
+    };
+
+    if inputs.domain_blocking_expected && !inputs.doh_enabled_browsers.is_empty() {
+        add(
+            "browser_doh",
+            RiskSeverity::High,
+            format!(
+                "DNS-over-HTTPS is still enabled in: {}",
+                inputs.doh_enabled_browsers.join(", ")
+            ),
+            "Apply browser policies from the daemon settings, then restart the affected browsers.",
+        );
+    }
+
+    if inputs.firewall_expected && !inputs.firewall_active {
+        add(
+            "firewall_missing",
+            RiskSeverity::High,
+            "Firewall rules blocking DoH providers and VPN ports are not installed".to_string(),
+            "Re-apply blocking or restart the ParentShield service.",
+        );
+    }
+
+    if !inputs.vpn_interfaces.is_empty() {
+        add(
+            "vpn_interface",
+            RiskSeverity::High,
+            format!("VPN interface present: {}", inputs.vpn_interfaces.join(", ")),
+            "Disconnect or uninstall the VPN software and enable firewall blocking.",
+        );
+    }
+
+    if inputs.domain_blocking_expected && inputs.external_dns_reachable {
+        add(
+            "external_dns",
+            RiskSeverity::Medium,
+            "Public DNS servers can be queried directly".to_string(),
+            "Block outbound port 53 except to the local resolver on your router or firewall.",
+        );
+    }
+
+    let local_resolver = inputs
+        .nameservers
+        .iter()
+        .all(|ns| ns.starts_with("127.") || ns == "::1");
+    if inputs.domain_blocking_expected && !inputs.nameservers.is_empty() && !local_resolver {
+        add(
+            "resolv_conf",
+            RiskSeverity::Medium,
+            format!(
+                "resolv.conf points at external nameservers: {}",
+                inputs.nameservers.join(", ")
+            ),
+            "Point the system resolver at 127.0.0.1 so queries pass through local filtering.",
+        );
+    }
+
+    if inputs.clock_tamper_suspected {
+        add(
+            "clock_tamper",
+            RiskSeverity::Medium,
+            "The system clock is earlier than the last settings change".to_string(),
+            "Check the date and time settings and enable automatic time sync.",
+        );
+    }
+
+    risks.sort_by_key(|r| std::cmp::Reverse(r.severity));
+    risks
+}
+
+/// Extract nameserver addresses from resolv.conf content
+pub fn parse_nameservers(resolv_conf: &str) -> Vec<String> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => fields.next().map(|s| s.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Keep interface names that look like VPN tunnels
+pub fn filter_vpn_interfaces<I: IntoIterator<Item = String>>(names: I) -> Vec<String> {
+    let mut vpn: Vec<String> = names
+        .into_iter()
+        .filter(|name| VPN_INTERFACE_PREFIXES.iter().any(|p| name.starts_with(p)))
+        .collect();
+    vpn.sort();
+    vpn
+}
+
+/// Whether the clock is behind the last recorded config change
+pub fn clock_moved_backwards(last_modified: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    last_modified - now > chrono::Duration::minutes(CLOCK_SKEW_TOLERANCE_MINUTES)
+}
+
+/// Run all detectors against the live system
+pub fn gather_audit_inputs(config: &AppConfig) -> AuditInputs {
+    let blocking_now = crate::scheduler::should_block_with_budget(config);
+    let domain_blocking_expected =
+        blocking_now && !super::get_effective_blocked_domains(config).is_empty();
+    let any_blocking =
+        config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+
+    let mut doh_enabled_browsers = Vec::new();
+    for status in super::get_user_doh_status() {
+        if !status.firefox_doh_disabled {
+            doh_enabled_browsers.push(format!("Firefox ({})", status.user));
+        }
+        if !status.chromium_doh_disabled && !doh_enabled_browsers.iter().any(|b| b == "Chromium") {
+            doh_enabled_browsers.push("Chromium".to_string());
+        }
+    }
+
+    AuditInputs {
+        domain_blocking_expected,
+        firewall_expected: super::get_capabilities().firewall_doh && blocking_now && any_blocking,
+        doh_enabled_browsers,
+        external_dns_reachable: probe_external_dns(),
+        vpn_interfaces: filter_vpn_interfaces(list_interfaces()),
+        firewall_active: super::is_doh_blocked(),
+        clock_tamper_suspected: clock_moved_backwards(config.last_modified, Utc::now()),
+        nameservers: std::fs::read_to_string("/etc/resolv.conf")
+            .map(|c| parse_nameservers(&c))
+            .unwrap_or_default(),
+    }
+}
+
+/// Check the live system for ways around the current blocking
+pub fn audit_bypass_risks(config: &AppConfig) -> Vec<BypassRisk> {
+    assemble_report(&gather_audit_inputs(config))
+}
+
+/// Send a minimal DNS query straight to a public resolver
+fn probe_external_dns() -> bool {
+    // ID 0x5053, RD set, one question: example.com A IN
+    let mut query = vec![0x50, 0x53, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    query.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+
+    let socket = match std::net::UdpSocket::bind("0.0.0.0:0") {
+        Ok(socket) => socket,
+        Err(_) => return false,
+    };
+    let _ = socket.set_read_timeout(Some(Duration::from_secs(2)));
+    if socket.send_to(&query, EXTERNAL_DNS_PROBE).is_err() {
+        return false;
+    }
+
+    let mut buf = [0u8; 512];
+    matches!(socket.recv_from(&mut buf), Ok((len, _)) if len >= 12 && buf[..2] == query[..2])
+}
+
+#[cfg(target_os = "linux")]
+fn list_interfaces() -> Vec<String> {
+    std::fs::read_dir("/sys/class/net")
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_interfaces() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_system_has_no_risks() {
+        let inputs = AuditInputs {
+            domain_blocking_expected: true,
+            firewall_expected: true,
+            firewall_active: true,
+            nameservers: vec!["127.0.0.53".to_string()],
+            ..Default::default()
+        };
+        assert!(assemble_report(&inputs).is_empty());
+    }
+
+    #[test]
+    fn test_report_from_mocked_detectors() {
+        let inputs = AuditInputs {
+            domain_blocking_expected: true,
+            firewall_expected: true,
+            doh_enabled_browsers: vec!["Firefox (kid)".to_string()],
+            external_dns_reachable: true,
+            vpn_interfaces: filter_vpn_interfaces(vec!["lo".to_string(), "wg0".to_string(), "eth0".to_string()]),
+            firewall_active: false,
+            clock_tamper_suspected: false,
+            nameservers: parse_nameservers("# generated\nnameserver 8.8.8.8\noptions edns0\n"),
+        };
+
+        let report = assemble_report(&inputs);
+        let ids: Vec<&str> = report.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["browser_doh", "firewall_missing", "vpn_interface", "external_dns", "resolv_conf"]
+        );
+        assert!(report[2].description.contains("wg0"));
+        assert!(report.iter().all(|r| !r.remediation.is_empty()));
+    }
+
+    #[test]
+    fn test_domain_risks_ignored_when_not_blocking() {
+        let inputs = AuditInputs {
+            doh_enabled_browsers: vec!["Chromium".to_string()],
+            external_dns_reachable: true,
+            nameservers: vec!["1.1.1.1".to_string()],
+            clock_tamper_suspected: true,
+            ..Default::default()
+        };
+
+        let report = assemble_report(&inputs);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].id, "clock_tamper");
+    }
+
+    #[test]
+    fn test_clock_moved_backwards() {
+        let now = Utc::now();
+        assert!(clock_moved_backwards(now + chrono::Duration::hours(2), now));
+        assert!(!clock_moved_backwards(now - chrono::Duration::hours(2), now));
+    }
+}
@@ -1,9 +1,157 @@
 //! Default blocklists for games, AI services, and gaming websites.
 
+use crate::blocking::defaults::{system_defaults_override, DefaultsOverride};
+use crate::config::AppConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Largest blocklist accepted for import, in bytes
+pub const MAX_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Most domains accepted from a single imported blocklist
+pub const MAX_IMPORTED_DOMAINS: usize = 250_000;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    #[error("Blocklist is larger than {} MB", MAX_IMPORT_BYTES / (1024 * 1024))]
+    TooLarge,
+    #[error("Blocklist has {0} domains, more than the {} allowed", MAX_IMPORTED_DOMAINS)]
+    TooManyDomains(usize),
+}
+
+/// A default blocklist category that can be lifted as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockCategory {
+    Games,
+    Ai,
+}
+
+impl BlockCategory {
+    /// Key used for this category in the config
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockCategory::Games => "games",
+            BlockCategory::Ai => "ai",
+        }
+    }
+}
+
+impl FromStr for BlockCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "games" | "game" | "gaming" => Ok(BlockCategory::Games),
+            "ai" => Ok(BlockCategory::Ai),
+            other => Err(format!("Unknown category: {}", other)),
+        }
+    }
+}
+
+/// A group of default entries as listed by the blocklist editor. The id is
+/// stable, unlike the display name, so the GUI can translate the name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlocklistGroup {
+    GamingProcesses,
+    AiServices,
+    GamingWebsites,
+}
+
+impl BlocklistGroup {
+    pub const ALL: [BlocklistGroup; 3] = [
+        BlocklistGroup::GamingProcesses,
+        BlocklistGroup::AiServices,
+        BlocklistGroup::GamingWebsites,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            BlocklistGroup::GamingProcesses => "gaming_processes",
+            BlocklistGroup::AiServices => "ai_services",
+            BlocklistGroup::GamingWebsites => "gaming_websites",
+        }
+    }
+
+    /// English name shown in the blocklist editor
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BlocklistGroup::GamingProcesses => "Gaming Processes",
+            BlocklistGroup::AiServices => "AI Services",
+            BlocklistGroup::GamingWebsites => "Gaming Websites",
+        }
+    }
+
+    /// Whether the group's entries are process names rather than domains
+    pub fn is_process_group(&self) -> bool {
+        matches!(self, BlocklistGroup::GamingProcesses)
+    }
+
+    /// The default entries in this group
+    pub fn default_items(&self) -> HashSet<String> {
+        match self {
+            BlocklistGroup::GamingProcesses => get_default_gaming_processes(),
+            BlocklistGroup::AiServices => get_default_ai_domains(),
+            BlocklistGroup::GamingWebsites => get_default_gaming_domains(),
+        }
+    }
+}
+
+impl FromStr for BlocklistGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BlocklistGroup::ALL
+            .into_iter()
+            .find(|group| group.id() == s.trim())
+            .ok_or_else(|| format!("Unknown blocklist category: {}", s))
+    }
+}
+
+/// Switch every default entry of `group` on or off at once. The whitelist is
+/// left alone, so entries the parent allowed by hand stay allowed either way.
+/// Returns whether the setting changed.
+pub fn set_group_blocking(config: &mut AppConfig, group: BlocklistGroup, enabled: bool) -> bool {
+    if enabled {
+        config.disabled_blocklist_groups.remove(&group)
+    } else {
+        config.disabled_blocklist_groups.insert(group)
+    }
+}
+
+/// Whether the default entries of `group` are enforced
+pub fn is_group_enabled(config: &AppConfig, group: BlocklistGroup) -> bool {
+    !config.disabled_blocklist_groups.contains(&group)
+}
+
+/// Check whether a category is temporarily allowed at the given time
+pub fn is_category_allowed_at(config: &AppConfig, category: BlockCategory, now: DateTime<Utc>) -> bool {
+    config
+        .category_allowances
+        .get(category.as_str())
+        .is_some_and(|until| *until > now)
+}
+
+/// Merge a compiled-in list with the system `defaults.json` override, if any
+fn with_override<F>(compiled: HashSet<String>, list: F) -> HashSet<String>
+where
+    F: FnOnce(&DefaultsOverride) -> Option<&Vec<String>>,
+{
+    match system_defaults_override() {
+        Some(overrides) => overrides.merge(compiled, list(&overrides)),
+        None => compiled,
+    }
+}
 
 /// Get default list of gaming process names to block
 pub fn get_default_gaming_processes() -> HashSet<String> {
+    with_override(compiled_gaming_processes(), |o| o.gaming_processes.as_ref())
+}
+
+fn compiled_gaming_processes() -> HashSet<String> {
     let processes = [
         // Steam
         "steam",
@@ -91,6 +239,10 @@ pub fn get_default_gaming_processes() -> HashSet<String> {
 
 /// Get default list of browser process names to block
 pub fn get_default_browser_processes() -> HashSet<String> {
+    with_override(compiled_browser_processes(), |o| o.browser_processes.as_ref())
+}
+
+fn compiled_browser_processes() -> HashSet<String> {
     let processes = [
         // Google Chrome
         "chrome",
@@ -160,6 +312,10 @@ pub fn get_default_browser_processes() -> HashSet<String> {
 
 /// Get default list of AI service process names to block
 pub fn get_default_ai_processes() -> HashSet<String> {
+    with_override(compiled_ai_processes(), |o| o.ai_processes.as_ref())
+}
+
+fn compiled_ai_processes() -> HashSet<String> {
     let processes = [
         // ChatGPT Desktop
         "chatgpt",
@@ -204,6 +360,10 @@ pub fn get_default_ai_processes() -> HashSet<String> {
 
 /// Get default list of AI service domains to block
 pub fn get_default_ai_domains() -> HashSet<String> {
+    with_override(compiled_ai_domains(), |o| o.ai_domains.as_ref())
+}
+
+fn compiled_ai_domains() -> HashSet<String> {
     let domains = [
         // OpenAI / ChatGPT
         "chat.openai.com",
@@ -327,6 +487,10 @@ pub fn get_default_ai_domains() -> HashSet<String> {
 
 /// Get default list of gaming website domains to block (150+ sites)
 pub fn get_default_gaming_domains() -> HashSet<String> {
+    with_override(compiled_gaming_domains(), |o| o.gaming_domains.as_ref())
+}
+
+fn compiled_gaming_domains() -> HashSet<String> {
     let domains = [
         // ===== MAJOR GAMING PLATFORMS =====
         "steam.com",
@@ -691,18 +855,208 @@ pub fn get_default_gaming_domains() -> HashSet<String> {
     domains.iter().map(|s| s.to_lowercase()).collect()
 }
 
+/// Get the process names currently enforced for a configuration
+/// (enabled default categories plus user-added processes)
+pub fn get_effective_blocked_processes(config: &AppConfig) -> HashSet<String> {
+    get_effective_blocked_processes_at(config, crate::scheduler::trusted_now())
+}
+
+/// Same as [`get_effective_blocked_processes`], evaluating temporary
+/// category allowances at `now`
+pub fn get_effective_blocked_processes_at(config: &AppConfig, now: DateTime<Utc>) -> HashSet<String> {
+    let games = !is_category_allowed_at(config, BlockCategory::Games, now)
+        && is_group_enabled(config, BlocklistGroup::GamingProcesses);
+    let ai = !is_category_allowed_at(config, BlockCategory::Ai, now);
+
+    let mut blocked = HashSet::new();
+    if config.game_blocking_enabled && games {
+        blocked.extend(get_default_gaming_processes());
+    }
+    if config.ai_blocking_enabled && ai {
+        blocked.extend(get_default_ai_processes());
+    }
+    blocked.extend(config.blocked_processes.clone());
+    blocked
+}
+
+/// Get the domains currently enforced for a configuration
+/// (enabled default categories plus user-added domains, minus the whitelist)
+pub fn get_effective_blocked_domains(config: &AppConfig) -> HashSet<String> {
+    get_effective_blocked_domains_at(config, crate::scheduler::trusted_now())
+}
+
+/// Same as [`get_effective_blocked_domains`], evaluating temporary
+/// category allowances at `now`
+pub fn get_effective_blocked_domains_at(config: &AppConfig, now: DateTime<Utc>) -> HashSet<String> {
+    let games = !is_category_allowed_at(config, BlockCategory::Games, now)
+        && is_group_enabled(config, BlocklistGroup::GamingWebsites);
+    let ai = !is_category_allowed_at(config, BlockCategory::Ai, now)
+        && is_group_enabled(config, BlocklistGroup::AiServices);
+
+    let mut domains = HashSet::new();
+
+    if config.ai_blocking_enabled && ai {
+        domains.extend(get_default_ai_domains());
+    }
+    if config.game_blocking_enabled && games {
+        domains.extend(get_default_gaming_domains());
+    }
+    if config.dns_blocking_enabled {
+        // Custom Websites blocks all domains in the blocklist (defaults + user-added)
+        if games {
+            domains.extend(get_default_gaming_domains());
+        }
+        if ai {
+            domains.extend(get_default_ai_domains());
+        }
+        domains.extend(config.blocked_domains.clone());
+        domains.extend(config.subscribed_domains.clone());
+    }
+
+    // Drop entries the whitelist overrides. The most specific rule wins, as in
+    // `is_domain_blocked`: allowing example.com doesn't unblock a listed
+    // games.example.com
+    let listed = domains.clone();
+    domains.retain(|domain| is_domain_blocked(domain, &listed, &config.allowed_domains));
+
+    domains
+}
+
+/// Which list a blocked domain matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainListSource {
+    Games,
+    Ai,
+    Custom,
+    /// Regex/suffix pattern, enforced by the DNS proxy only
+    Pattern,
+}
+
+/// Why a domain is or isn't blocked right now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum BlockDecision {
+    Blocked { source: DomainListSource, rule: String },
+    /// On the whitelist (the matching whitelist entry)
+    Whitelisted { rule: String },
+    /// On a list, but its category is temporarily allowed
+    CategoryAllowed { category: String },
+    /// On a list, but the schedules don't block right now
+    OutsideSchedule { source: DomainListSource, rule: String },
+    /// Not on any enabled list
+    NotListed,
+}
+
+/// Most specific entry in `set` matching `domain` (already lowercased), walking
+/// its label hierarchy as [`is_domain_blocked`] describes. Returns the entry and
+/// how many labels above the queried name it sits (0 for the name itself).
+fn matching_rule(domain: &str, set: &HashSet<String>) -> Option<(usize, String)> {
+    let mut level = domain;
+    let mut depth = 0;
+    loop {
+        if set.contains(level) {
+            return Some((depth, level.to_string()));
+        }
+        // Wildcards only apply once we've moved above the queried name
+        if depth > 0 {
+            let wildcard = format!("*.{}", level);
+            if set.contains(&wildcard) {
+                return Some((depth, wildcard));
+            }
+        }
+        let (_, parent) = level.split_once('.')?;
+        level = parent;
+        depth += 1;
+    }
+}
+
+/// Run a domain through the full matching logic: whitelist, category lists,
+/// custom entries, proxy patterns, temporary allowances and schedules at `now`
+pub fn check_domain(config: &AppConfig, domain: &str, now: DateTime<Utc>) -> BlockDecision {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+
+    // A list entry only applies when it's more specific than the whitelist match
+    let allowed = matching_rule(&domain, &config.allowed_domains);
+    let beats_whitelist = |depth: usize| allowed.as_ref().is_none_or(|(allowed, _)| depth < *allowed);
+
+    let mut lifted = None;
+    let mut matched: Option<(usize, DomainListSource, String)> = None;
+    let categories = [
+        (BlockCategory::Games, BlocklistGroup::GamingWebsites, DomainListSource::Games, config.game_blocking_enabled),
+        (BlockCategory::Ai, BlocklistGroup::AiServices, DomainListSource::Ai, config.ai_blocking_enabled),
+    ];
+    for (category, group, source, enabled) in &categories {
+        // Custom Websites blocking also enforces the default lists
+        if !(*enabled || config.dns_blocking_enabled) || !is_group_enabled(config, *group) {
+            continue;
+        }
+        let Some((depth, rule)) = matching_rule(&domain, &group.default_items()) else {
+            continue;
+        };
+        if !beats_whitelist(depth) {
+            continue;
+        }
+        if is_category_allowed_at(config, *category, now) {
+            lifted.get_or_insert(category.as_str().to_string());
+        } else if matched.as_ref().is_none_or(|(best, ..)| depth < *best) {
+            matched = Some((depth, *source, rule));
+        }
+    }
+
+    if config.dns_blocking_enabled {
+        if let Some((depth, rule)) = matching_rule(&domain, &config.blocked_domains) {
+            if beats_whitelist(depth) && matched.as_ref().is_none_or(|(best, ..)| depth < *best) {
+                matched = Some((depth, DomainListSource::Custom, rule));
+            }
+        }
+    }
+    if matched.is_none() && allowed.is_none() {
+        // Invalid patterns are reported when saved; here they just don't match
+        let patterns = super::network::compile_domain_patterns(&config.blocked_domain_patterns).unwrap_or_default();
+        matched = patterns
+            .iter()
+            .find(|p| p.is_match(&domain))
+            .map(|p| (0, DomainListSource::Pattern, p.as_str().to_string()));
+    }
+
+    let Some((_, source, rule)) = matched else {
+        return match (lifted, allowed) {
+            (Some(category), _) => BlockDecision::CategoryAllowed { category },
+            (None, Some((_, rule))) => BlockDecision::Whitelisted { rule },
+            (None, None) => BlockDecision::NotListed,
+        };
+    };
+
+    // Same answer the blocking loop gets: unlock, date exceptions, zones and budget included
+    if !crate::scheduler::should_block_with_budget_at(config, now) {
+        return BlockDecision::OutsideSchedule { source, rule };
+    }
+
+    BlockDecision::Blocked { source, rule }
+}
+
 /// Check if a process name matches any blocked process
 pub fn is_process_blocked(
     process_name: &str,
     blocked_processes: &HashSet<String>,
     allowed_processes: &HashSet<String>,
     allowed_domains: &HashSet<String>,
+) -> bool {
+    match_blocked_process(process_name, blocked_processes, allowed_processes, allowed_domains).is_some()
+}
+
+/// Check if a process is exempt from blocking via the whitelist
+pub fn is_process_whitelisted(
+    process_name: &str,
+    allowed_processes: &HashSet<String>,
+    allowed_domains: &HashSet<String>,
 ) -> bool {
     let name_lower = process_name.to_lowercase();
 
     // Check whitelist first
     if allowed_processes.contains(&name_lower) {
-        return false;
+        return true;
     }
 
     // Check if Claude Code should be allowed based on whitelisted domains
@@ -712,13 +1066,53 @@ pub fn is_process_blocked(
             d.contains("claude.ai") || d.contains("anthropic.com")
         });
         if claude_domains_allowed {
-            return false;
+            return true;
         }
     }
 
+    false
+}
+
+/// Whether a whitelist entry names an executable by absolute path
+/// (`/usr/bin/python3`, `C:\Tools\python.exe`) rather than by process name
+pub fn is_exe_path_entry(entry: &str) -> bool {
+    let bytes = entry.as_bytes();
+    entry.starts_with('/')
+        || (bytes.len() > 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && matches!(bytes[2], b'\\' | b'/'))
+}
+
+/// Check if a process's executable is whitelisted by an absolute-path entry.
+/// Only that exact executable is allowed, not others with the same name.
+/// Entries are stored lowercased, so the comparison ignores case.
+pub fn is_exe_path_whitelisted(exe_path: Option<&str>, allowed_processes: &HashSet<String>) -> bool {
+    let Some(exe_path) = exe_path else {
+        return false;
+    };
+    let exe_lower = exe_path.to_lowercase();
+    allowed_processes
+        .iter()
+        .any(|entry| is_exe_path_entry(entry) && *entry == exe_lower)
+}
+
+/// Find the blocklist rule that causes a process to be blocked, if any
+pub fn match_blocked_process(
+    process_name: &str,
+    blocked_processes: &HashSet<String>,
+    allowed_processes: &HashSet<String>,
+    allowed_domains: &HashSet<String>,
+) -> Option<String> {
+    let name_lower = process_name.to_lowercase();
+
+    if is_process_whitelisted(&name_lower, allowed_processes, allowed_domains) {
+        return None;
+    }
+
     // Check if explicitly blocked
     if blocked_processes.contains(&name_lower) {
-        return true;
+        return Some(name_lower);
     }
 
     // Check partial matches for gaming processes
@@ -726,7 +1120,7 @@ pub fn is_process_blocked(
 
     for keyword in gaming_keywords {
         if name_lower.contains(keyword) && !allowed_processes.iter().any(|p| name_lower.contains(p)) {
-            return blocked_processes.iter().any(|p| p.contains(keyword));
+            return blocked_processes.iter().find(|p| p.contains(keyword)).cloned();
         }
     }
 
@@ -735,41 +1129,140 @@ pub fn is_process_blocked(
 
     for keyword in ai_keywords {
         if name_lower.contains(keyword) && !allowed_processes.iter().any(|p| name_lower.contains(p)) {
-            return blocked_processes.iter().any(|p| p.contains(keyword));
+            return blocked_processes.iter().find(|p| p.contains(keyword)).cloned();
         }
     }
 
-    false
+    None
 }
 
-/// Check if a domain matches any blocked domain
+/// Check if a domain is blocked by walking its label hierarchy.
+///
+/// For `a.b.c.com` the levels `a.b.c.com`, `b.c.com`, `c.com` and `com` are
+/// checked in that order and the most specific level listed in either set
+/// decides, with the allowed set winning a tie. A plain entry matches its
+/// level and everything below it; a `*.example.com` entry matches subdomains
+/// of example.com but not example.com itself.
 pub fn is_domain_blocked(
     domain: &str,
     blocked_domains: &HashSet<String>,
     allowed_domains: &HashSet<String>,
 ) -> bool {
-    let domain_lower = domain.to_lowercase();
+    let domain_lower = domain.trim_end_matches('.').to_lowercase();
+    match (
+        matching_rule(&domain_lower, blocked_domains),
+        matching_rule(&domain_lower, allowed_domains),
+    ) {
+        (Some((blocked, _)), Some((allowed, _))) => blocked < allowed,
+        (blocked, None) => blocked.is_some(),
+        (None, Some(_)) => false,
+    }
+}
 
-    // Check whitelist first
-    if allowed_domains.contains(&domain_lower) {
-        return false;
+/// Parse a community blocklist in hosts (`0.0.0.0 example.com`) or plain
+/// one-domain-per-line format (as used by Pi-hole adlists). Comments, local
+/// names and lines that aren't either format are skipped.
+pub fn parse_imported_list(content: &str) -> Vec<String> {
+    const LOCAL_NAMES: &[&str] = &["localhost", "localhost.localdomain", "local", "broadcasthost"];
+
+    let mut domains: Vec<String> = content
+        .lines()
+        .filter_map(|line| line.split('#').next())
+        .flat_map(|line| {
+            let mut fields = line.split_whitespace();
+            let first = fields.next();
+            match first.map(|f| f.parse::<std::net::IpAddr>().is_ok()) {
+                // Hosts syntax: an address followed by one or more names
+                Some(true) => fields.collect(),
+                // Plain syntax: the domain alone on its line
+                Some(false) if fields.next().is_none() => first.into_iter().collect(),
+                _ => Vec::new(),
+            }
+        })
+        .map(|domain| domain.trim_end_matches('.').to_lowercase())
+        .filter(|domain| domain.contains('.') && !LOCAL_NAMES.contains(&domain.as_str()))
+        .filter(|domain| domain.parse::<std::net::IpAddr>().is_err() && is_valid_hostname(domain))
+        .collect();
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+/// `parse_imported_list` with the size and domain-count caps applied, so a
+/// huge or hostile list can't bloat the config
+pub fn parse_import_limited(content: &str) -> Result<Vec<String>, ImportError> {
+    if content.len() > MAX_IMPORT_BYTES {
+        return Err(ImportError::TooLarge);
+    }
+    let domains = parse_imported_list(content);
+    if domains.len() > MAX_IMPORTED_DOMAINS {
+        return Err(ImportError::TooManyDomains(domains.len()));
     }
+    Ok(domains)
+}
+
+/// Whether `name` is a syntactically valid DNS hostname
+fn is_valid_hostname(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
 
-    // Check for subdomain matches
-    for allowed in allowed_domains {
-        if domain_lower.ends_with(&format!(".{}", allowed)) || domain_lower == *allowed {
-            return false;
+/// Add imported domains to the custom blocklist, tagged with their source.
+/// A domain already imported from another list gains this source too; domains
+/// the parent added by hand stay untagged. Returns how many were added.
+pub fn import_domains(config: &mut AppConfig, source: &str, domains: &[String]) -> usize {
+    let mut added = 0;
+    for domain in domains {
+        if let Some(sources) = config.imported_domain_sources.get_mut(domain) {
+            sources.insert(source.to_string());
+            continue;
+        }
+        if config.blocked_domains.contains(domain) {
+            continue;
         }
+        config.blocked_domains.insert(domain.clone());
+        config
+            .imported_domain_sources
+            .insert(domain.clone(), HashSet::from([source.to_string()]));
+        added += 1;
     }
+    added
+}
 
-    // Check if domain or parent domain is blocked
-    for blocked in blocked_domains {
-        if domain_lower == *blocked || domain_lower.ends_with(&format!(".{}", blocked)) {
-            return true;
+/// Remove imported domains (from `source`, or from every source when None).
+/// A domain stays blocked while another source still lists it; hand-added and
+/// default entries are left alone. Returns how many were removed.
+pub fn clear_imported_domains(config: &mut AppConfig, source: Option<&str>) -> usize {
+    let mut removed = Vec::new();
+    config.imported_domain_sources.retain(|domain, sources| {
+        match source {
+            Some(source) => {
+                sources.remove(source);
+            }
+            None => sources.clear(),
         }
+        if sources.is_empty() {
+            removed.push(domain.clone());
+        }
+        !sources.is_empty()
+    });
+
+    for domain in &removed {
+        config.blocked_domains.remove(domain);
     }
+    removed.len()
+}
 
-    false
+/// Check if a domain or one of its parents is whitelisted
+pub fn is_domain_allowed(domain: &str, allowed_domains: &HashSet<String>) -> bool {
+    let domain_lower = domain.trim_end_matches('.').to_lowercase();
+    matching_rule(&domain_lower, allowed_domains).is_some()
 }
 
 #[cfg(test)]
@@ -805,6 +1298,50 @@ mod tests {
         assert!(!is_domain_blocked("google.com", &blocked, &allowed));
     }
 
+    #[test]
+    fn test_is_domain_blocked_wildcard_is_subdomains_only() {
+        let blocked: HashSet<String> = ["*.discord.com".to_string()].into_iter().collect();
+        let allowed = HashSet::new();
+
+        assert!(is_domain_blocked("gateway.discord.com", &blocked, &allowed));
+        assert!(is_domain_blocked("a.b.discord.com.", &blocked, &allowed));
+        assert!(!is_domain_blocked("discord.com", &blocked, &allowed));
+        assert!(!is_domain_blocked("notdiscord.com", &blocked, &allowed));
+    }
+
+    #[test]
+    fn test_is_domain_blocked_apex_covers_subdomains() {
+        let blocked: HashSet<String> = ["discord.com".to_string()].into_iter().collect();
+        let allowed = HashSet::new();
+
+        assert!(is_domain_blocked("discord.com", &blocked, &allowed));
+        assert!(is_domain_blocked("Gateway.Discord.com", &blocked, &allowed));
+        assert!(!is_domain_blocked("discord.co", &blocked, &allowed));
+    }
+
+    #[test]
+    fn test_is_domain_blocked_most_specific_level_wins() {
+        let blocked: HashSet<String> = ["example.com".to_string(), "ads.safe.org".to_string()]
+            .into_iter()
+            .collect();
+        let allowed: HashSet<String> = ["mail.example.com".to_string(), "safe.org".to_string()]
+            .into_iter()
+            .collect();
+
+        assert!(is_domain_blocked("example.com", &blocked, &allowed));
+        assert!(is_domain_blocked("www.example.com", &blocked, &allowed));
+        assert!(!is_domain_blocked("mail.example.com", &blocked, &allowed));
+        assert!(!is_domain_blocked("imap.mail.example.com", &blocked, &allowed));
+
+        // A block listed below a whitelisted parent still applies
+        assert!(!is_domain_blocked("safe.org", &blocked, &allowed));
+        assert!(is_domain_blocked("x.ads.safe.org", &blocked, &allowed));
+
+        // Listed in both at the same level: the whitelist wins
+        let both: HashSet<String> = ["example.com".to_string()].into_iter().collect();
+        assert!(!is_domain_blocked("example.com", &both, &both));
+    }
+
     #[test]
     fn test_whitelist_overrides() {
         let blocked = get_default_gaming_processes();
@@ -814,4 +1351,375 @@ mod tests {
 
         assert!(!is_process_blocked("steam.exe", &blocked, &allowed, &allowed_domains));
     }
+
+    #[test]
+    fn test_group_toggle_leaves_whitelist_alone() {
+        let mut config = AppConfig {
+            ai_blocking_enabled: true,
+            ..Default::default()
+        };
+        let ai_domains = get_default_ai_domains();
+        let whitelisted = ai_domains.iter().next().unwrap().clone();
+        config.allowed_domains.insert(whitelisted.clone());
+
+        let group: BlocklistGroup = "ai_services".parse().unwrap();
+        assert!(set_group_blocking(&mut config, group, false));
+        assert!(!set_group_blocking(&mut config, group, false));
+        assert!(get_effective_blocked_domains(&config).is_disjoint(&ai_domains));
+        assert!(matches!(check_domain(&config, &whitelisted, Utc::now()), BlockDecision::Whitelisted { .. }));
+        assert_eq!(config.allowed_domains, [whitelisted.clone()].into());
+        // The AI domains being off doesn't let "claude" processes through
+        assert!(!is_process_whitelisted("claude", &config.allowed_processes, &HashSet::new()));
+
+        // Re-enabling keeps the entry the parent whitelisted by hand
+        assert!(set_group_blocking(&mut config, group, true));
+        assert!(config.allowed_domains.contains(&whitelisted));
+        let effective = get_effective_blocked_domains(&config);
+        assert!(!effective.contains(&whitelisted));
+        let covered = |d: &String| *d == whitelisted || d.ends_with(&format!(".{}", whitelisted));
+        assert!(ai_domains.iter().filter(|d| !covered(d)).all(|d| effective.contains(d)));
+
+        assert!("AI Services".parse::<BlocklistGroup>().is_err());
+    }
+
+    #[test]
+    fn test_exe_path_whitelist_entries() {
+        assert!(is_exe_path_entry("/usr/bin/python3"));
+        assert!(is_exe_path_entry(r"c:\tools\python.exe"));
+        assert!(is_exe_path_entry("d:/games/chess.exe"));
+        assert!(!is_exe_path_entry("python3"));
+        assert!(!is_exe_path_entry("bin/python3"));
+
+        let allowed: HashSet<String> = ["/usr/bin/python3".to_string(), "python2".to_string()].into();
+        assert!(is_exe_path_whitelisted(Some("/usr/bin/python3"), &allowed));
+        assert!(!is_exe_path_whitelisted(Some("/home/kid/.local/bin/python3"), &allowed));
+        assert!(!is_exe_path_whitelisted(None, &allowed));
+        // A name entry never whitelists by path
+        assert!(!is_exe_path_whitelisted(Some("python2"), &allowed));
+    }
+
+    #[test]
+    fn test_temporarily_allowed_category() {
+        let mut config = AppConfig {
+            ai_blocking_enabled: true,
+            game_blocking_enabled: true,
+            ..Default::default()
+        };
+
+        let now = Utc::now();
+        config
+            .category_allowances
+            .insert(BlockCategory::Ai.as_str().to_string(), now + chrono::Duration::minutes(60));
+
+        // During the window AI domains resolve, games stay blocked
+        let during = get_effective_blocked_domains_at(&config, now);
+        assert!(!during.contains("chat.openai.com"));
+        assert!(!get_effective_blocked_processes_at(&config, now).contains("chatgpt"));
+        assert!(during.iter().any(|d| get_default_gaming_domains().contains(d)));
+
+        // After expiry they are blocked again
+        let after = get_effective_blocked_domains_at(&config, now + chrono::Duration::minutes(61));
+        assert!(after.contains("chat.openai.com"));
+    }
+
+    #[test]
+    fn test_parse_block_category() {
+        assert_eq!("Gaming".parse::<BlockCategory>(), Ok(BlockCategory::Games));
+        assert_eq!("ai".parse::<BlockCategory>(), Ok(BlockCategory::Ai));
+        assert!("social".parse::<BlockCategory>().is_err());
+    }
+
+    #[test]
+    fn test_parse_imported_list() {
+        let content = "# Community list\n127.0.0.1 localhost\n0.0.0.0 ads.example.com\nTRACKER.example.org # trailing\n\n0.0.0.0 0.0.0.0\n";
+        assert_eq!(
+            parse_imported_list(content),
+            vec!["ads.example.com".to_string(), "tracker.example.org".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_mixed_format_list() {
+        let content = "\
+! Adblock-style header is not a domain
+# Pi-hole adlist
+0.0.0.0 ads.example.com tracker.example.com
+127.0.0.1\tmetrics.example.net   # inline comment
+::1 ip6-localhost
+:: v6.example.org
+plain.example.io
+Plain.Example.io.
+192.168.1.10
+not a domain line
+bad..example.com
+-leading.example.com
+||adblock.example.com^
+";
+        assert_eq!(
+            parse_imported_list(content),
+            vec![
+                "ads.example.com".to_string(),
+                "metrics.example.net".to_string(),
+                "plain.example.io".to_string(),
+                "tracker.example.com".to_string(),
+                "v6.example.org".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_limits() {
+        let oversized = "a".repeat(MAX_IMPORT_BYTES + 1);
+        assert_eq!(parse_import_limited(&oversized), Err(ImportError::TooLarge));
+
+        let many: String = (0..=MAX_IMPORTED_DOMAINS).map(|i| format!("d{}.example\n", i)).collect();
+        assert_eq!(
+            parse_import_limited(&many),
+            Err(ImportError::TooManyDomains(MAX_IMPORTED_DOMAINS + 1))
+        );
+        assert_eq!(parse_import_limited("ok.example\n").unwrap(), vec!["ok.example".to_string()]);
+    }
+
+    #[test]
+    fn test_clearing_imported_keeps_custom_and_defaults() {
+        let mut config = AppConfig {
+            game_blocking_enabled: true,
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("mygame.example".to_string());
+
+        let list_a = vec!["a1.example".to_string(), "mygame.example".to_string()];
+        let list_b = vec!["b1.example".to_string()];
+        assert_eq!(import_domains(&mut config, "list-a", &list_a), 1);
+        assert_eq!(import_domains(&mut config, "list-b", &list_b), 1);
+
+        // Only list-a's own entry goes; the hand-added duplicate stays
+        assert_eq!(clear_imported_domains(&mut config, Some("list-a")), 1);
+        assert!(!config.blocked_domains.contains("a1.example"));
+        assert!(config.blocked_domains.contains("mygame.example"));
+        assert!(config.blocked_domains.contains("b1.example"));
+
+        assert_eq!(clear_imported_domains(&mut config, None), 1);
+        assert_eq!(
+            config.blocked_domains,
+            ["mygame.example".to_string()].into_iter().collect()
+        );
+        assert!(config.imported_domain_sources.is_empty());
+
+        // Defaults are untouched
+        let effective = get_effective_blocked_domains(&config);
+        assert!(effective.contains("mygame.example"));
+        assert!(get_default_gaming_domains().iter().all(|d| effective.contains(d)));
+    }
+
+    #[test]
+    fn test_overlapping_imports_keep_shared_domains() {
+        let mut config = AppConfig::default();
+
+        let list_a = vec!["a1.example".to_string(), "shared.example".to_string()];
+        let list_b = vec!["b1.example".to_string(), "shared.example".to_string()];
+        assert_eq!(import_domains(&mut config, "list-a", &list_a), 2);
+        assert_eq!(import_domains(&mut config, "list-b", &list_b), 1);
+
+        // list-b still lists the shared domain
+        assert_eq!(clear_imported_domains(&mut config, Some("list-a")), 1);
+        assert!(!config.blocked_domains.contains("a1.example"));
+        assert!(config.blocked_domains.contains("shared.example"));
+        assert_eq!(
+            config.imported_domain_sources["shared.example"],
+            HashSet::from(["list-b".to_string()])
+        );
+
+        assert_eq!(clear_imported_domains(&mut config, Some("list-b")), 2);
+        assert!(config.blocked_domains.is_empty());
+        assert!(config.imported_domain_sources.is_empty());
+    }
+
+    #[test]
+    fn test_single_source_configs_still_load() {
+        let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+        value["imported_domain_sources"] = serde_json::json!({ "old.example": "list-a" });
+        let config: AppConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            config.imported_domain_sources["old.example"],
+            HashSet::from(["list-a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_whitelisted_parent_keeps_more_specific_block() {
+        let mut config = AppConfig {
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("games.example.com".to_string());
+        config.blocked_domains.insert("ads.example.com".to_string());
+        config.blocked_domains.insert("tracker.net".to_string());
+        config.allowed_domains.insert("example.com".to_string());
+        config.allowed_domains.insert("ads.example.com".to_string());
+        config.allowed_domains.insert("*.tracker.net".to_string());
+
+        let effective = get_effective_blocked_domains(&config);
+        assert!(effective.contains("games.example.com"));
+        assert!(!effective.contains("ads.example.com"));
+        // A wildcard allow covers subdomains, not the listed name itself
+        assert!(effective.contains("tracker.net"));
+    }
+
+    #[test]
+    fn test_check_domain_decisions() {
+        let mut config = AppConfig {
+            game_blocking_enabled: true,
+            dns_blocking_enabled: true,
+            blocked_domain_patterns: vec!["*.gaming-cdn.net".to_string()],
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("mygame.example".to_string());
+        config.allowed_domains.insert("education.roblox.com".to_string());
+        let now = Utc::now();
+
+        let game_domain = get_default_gaming_domains().into_iter().next().unwrap();
+        assert!(matches!(
+            check_domain(&config, &format!("www.{}", game_domain), now),
+            BlockDecision::Blocked { source: DomainListSource::Games, .. }
+        ));
+        assert_eq!(
+            check_domain(&config, "Play.MyGame.example.", now),
+            BlockDecision::Blocked {
+                source: DomainListSource::Custom,
+                rule: "mygame.example".to_string()
+            }
+        );
+        assert!(matches!(
+            check_domain(&config, "eu.gaming-cdn.net", now),
+            BlockDecision::Blocked { source: DomainListSource::Pattern, .. }
+        ));
+        assert_eq!(
+            check_domain(&config, "education.roblox.com", now),
+            BlockDecision::Whitelisted {
+                rule: "education.roblox.com".to_string()
+            }
+        );
+        assert_eq!(check_domain(&config, "wikipedia.org", now), BlockDecision::NotListed);
+
+        // Temporary allowance lifts the games list but not custom entries
+        config
+            .category_allowances
+            .insert("games".to_string(), now + chrono::Duration::hours(1));
+        assert_eq!(
+            check_domain(&config, &game_domain, now),
+            BlockDecision::CategoryAllowed {
+                category: "games".to_string()
+            }
+        );
+        assert!(matches!(
+            check_domain(&config, "mygame.example", now),
+            BlockDecision::Blocked { .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_domain_matches_like_the_proxy() {
+        let mut config = AppConfig {
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("*.cdn.example".to_string());
+        config.blocked_domains.insert("games.example.org".to_string());
+        config.allowed_domains.insert("example.org".to_string());
+        let now = Utc::now();
+
+        // Wildcards cover subdomains only
+        assert_eq!(
+            check_domain(&config, "eu.cdn.example", now),
+            BlockDecision::Blocked {
+                source: DomainListSource::Custom,
+                rule: "*.cdn.example".to_string()
+            }
+        );
+        assert_eq!(check_domain(&config, "cdn.example", now), BlockDecision::NotListed);
+
+        // The more specific entry wins over a whitelisted parent, and vice versa
+        assert!(matches!(
+            check_domain(&config, "play.games.example.org", now),
+            BlockDecision::Blocked { source: DomainListSource::Custom, .. }
+        ));
+        assert_eq!(
+            check_domain(&config, "www.example.org", now),
+            BlockDecision::Whitelisted {
+                rule: "example.org".to_string()
+            }
+        );
+
+        for domain in ["eu.cdn.example", "cdn.example", "play.games.example.org", "www.example.org"] {
+            let blocked = matches!(check_domain(&config, domain, now), BlockDecision::Blocked { .. });
+            assert_eq!(
+                blocked,
+                is_domain_blocked(domain, &config.blocked_domains, &config.allowed_domains),
+                "{}",
+                domain
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_domain_outside_schedule() {
+        let mut config = AppConfig {
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("mygame.example".to_string());
+        config.schedules.push(crate::config::ScheduleEntry {
+            id: uuid::Uuid::new_v4(),
+            name: "Always free".to_string(),
+            enabled: true,
+            days: (0..7).collect(),
+            start_minutes: 0,
+            end_minutes: 24 * 60,
+            blocking_enabled: false,
+            warn_minutes: None,
+            warn_message: None,
+            timezone: None,
+            priority: 0,
+            cron: None,
+        });
+
+        assert!(matches!(
+            check_domain(&config, "mygame.example", Utc::now()),
+            BlockDecision::OutsideSchedule { source: DomainListSource::Custom, .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_domain_follows_unlock_and_date_exceptions() {
+        let now = Utc::now();
+        let mut config = AppConfig {
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("mygame.example".to_string());
+        assert!(matches!(check_domain(&config, "mygame.example", now), BlockDecision::Blocked { .. }));
+
+        config.temporary_unlock = Some(crate::scheduler::TemporaryUnlock::for_minutes(30, now));
+        assert!(matches!(
+            check_domain(&config, "mygame.example", now),
+            BlockDecision::OutsideSchedule { .. }
+        ));
+        assert!(matches!(
+            check_domain(&config, "mygame.example", now + chrono::Duration::minutes(31)),
+            BlockDecision::Blocked { .. }
+        ));
+
+        config.temporary_unlock = None;
+        let today = now.with_timezone(&chrono::Local).date_naive();
+        config.date_exceptions.push(crate::scheduler::DateException {
+            date: today,
+            mode: crate::scheduler::ExceptionMode::Allow,
+        });
+        assert!(matches!(
+            check_domain(&config, "mygame.example", now),
+            BlockDecision::OutsideSchedule { .. }
+        ));
+    }
 }
@@ -1,31 +1,91 @@
 //! Browser configuration to disable DNS-over-HTTPS (DoH).
 //! DoH bypasses /etc/hosts blocking, so we need to disable it for effective blocking.
 
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
-/// Disable DNS-over-HTTPS in all detected browsers
-pub fn disable_doh_all_browsers() -> io::Result<Vec<String>> {
+/// System-wide Firefox enterprise policy locations (Linux)
+const FIREFOX_POLICY_PATHS: &[&str] = &[
+    "/etc/firefox/policies/policies.json",
+    "/usr/lib/firefox/distribution/policies.json",
+    "/usr/lib64/firefox/distribution/policies.json",
+    "/usr/lib/firefox-esr/distribution/policies.json",
+    "/opt/firefox/distribution/policies.json",
+];
+
+/// Identifier of the configuration profile that keeps Safari on the system resolver
+#[cfg(any(target_os = "macos", test))]
+const SAFARI_PROFILE_IDENTIFIER: &str = "com.parentshield.dns";
+
+/// Where the Safari profile is written before it is installed
+#[cfg(target_os = "macos")]
+const SAFARI_PROFILE_PATH: &str = "/Library/Application Support/ParentShield/parentshield-dns.mobileconfig";
+
+/// Firefox `network.trr.mode` value that turns DoH off
+const FIREFOX_TRR_OFF: i64 = 5;
+
+/// Chromium `IncognitoModeAvailability` value that disables incognito windows
+const INCOGNITO_DISABLED: u64 = 1;
+
+/// Managed Chromium policy that turns off DoH and the built-in resolver,
+/// and optionally incognito mode
+fn chromium_policy(block_private_browsing: bool) -> String {
+    let mut policy = serde_json::json!({
+        "DnsOverHttpsMode": "off",
+        "BuiltInDnsClientEnabled": false
+    });
+    if block_private_browsing {
+        policy["IncognitoModeAvailability"] = serde_json::json!(INCOGNITO_DISABLED);
+    }
+    serde_json::to_string_pretty(&policy).unwrap_or_default()
+}
+
+/// DoH status of a single user's browser profiles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDohStatus {
+    pub user: String,
+    pub firefox_doh_disabled: bool,
+    pub chromium_doh_disabled: bool,
+    /// Private windows are disabled by policy in Firefox and Chromium browsers
+    #[serde(default)]
+    pub private_browsing_disabled: bool,
+}
+
+/// Disable DNS-over-HTTPS in all detected browsers, and private browsing
+/// too when `block_private_browsing` is set
+pub fn disable_doh_all_browsers(block_private_browsing: bool) -> io::Result<Vec<String>> {
     let mut disabled_in = Vec::new();
 
+    // Firefox only takes DisablePrivateBrowsing from the system policies.json
+    if block_private_browsing {
+        match apply_firefox_policies(true) {
+            Ok(paths) if !paths.is_empty() => disabled_in.push("Firefox (policy)".to_string()),
+            Ok(_) => {}
+            Err(e) => warn!("Could not write Firefox policy: {}", e),
+        }
+    }
+
     // Firefox
     match disable_firefox_doh() {
-        Ok(profiles) => {
-            for profile in profiles {
-                disabled_in.push(format!("Firefox ({})", profile));
-            }
-        }
+        Ok(profiles) => disabled_in.extend(profiles),
         Err(e) => warn!("Could not configure Firefox: {}", e),
     }
 
     // Chrome/Chromium
-    match disable_chrome_doh() {
+    match disable_chrome_doh(block_private_browsing) {
         Ok(browsers) => disabled_in.extend(browsers),
         Err(e) => warn!("Could not configure Chrome/Chromium: {}", e),
     }
 
+    #[cfg(target_os = "macos")]
+    match disable_safari_doh() {
+        Ok(browsers) => disabled_in.extend(browsers),
+        Err(e) => warn!("Could not configure Safari: {}", e),
+    }
+
     if disabled_in.is_empty() {
         info!("No browsers were configured (none found or already configured)");
     } else {
@@ -40,11 +100,7 @@ pub fn enable_doh_all_browsers() -> io::Result<Vec<String>> {
     let mut enabled_in = Vec::new();
 
     match enable_firefox_doh() {
-        Ok(profiles) => {
-            for profile in profiles {
-                enabled_in.push(format!("Firefox ({})", profile));
-            }
-        }
+        Ok(profiles) => enabled_in.extend(profiles),
         Err(e) => warn!("Could not restore Firefox: {}", e),
     }
 
@@ -53,118 +109,237 @@ pub fn enable_doh_all_browsers() -> io::Result<Vec<String>> {
         Err(e) => warn!("Could not restore Chrome/Chromium: {}", e),
     }
 
+    #[cfg(target_os = "macos")]
+    match enable_safari_doh() {
+        Ok(browsers) => enabled_in.extend(browsers),
+        Err(e) => warn!("Could not restore Safari: {}", e),
+    }
+
     Ok(enabled_in)
 }
 
-/// Disable DoH in Firefox by adding user.js preferences
-fn disable_firefox_doh() -> io::Result<Vec<String>> {
-    let firefox_dir = get_firefox_dir()?;
-    let mut configured_profiles = Vec::new();
+/// Firefox and its forks: (name shown to parents, profiles.ini directory
+/// relative to the home directory), including Snap and Flatpak installs
+const FIREFOX_FAMILY: &[(&str, &str)] = &[
+    ("Firefox", ".mozilla/firefox"),
+    ("Firefox Snap", "snap/firefox/common/.mozilla/firefox"),
+    ("Firefox Flatpak", ".var/app/org.mozilla.firefox/.mozilla/firefox"),
+    ("LibreWolf", ".librewolf"),
+    ("LibreWolf Flatpak", ".var/app/io.gitlab.librewolf-community/.librewolf"),
+    ("Waterfox", ".waterfox"),
+    ("Waterfox Flatpak", ".var/app/net.waterfox.waterfox/.waterfox"),
+    (
+        "Tor Browser",
+        ".local/share/torbrowser/tbb/x86_64/tor-browser/Browser/TorBrowser/Data/Browser",
+    ),
+    (
+        "Tor Browser Flatpak",
+        ".var/app/org.torproject.torbrowser-launcher/data/torbrowser/tbb/x86_64/tor-browser/Browser/TorBrowser/Data/Browser",
+    ),
+];
 
-    // Find all Firefox profiles
-    let profiles_ini = firefox_dir.join("profiles.ini");
-    if !profiles_ini.exists() {
-        return Err(io::Error::new(io::ErrorKind::NotFound, "Firefox not installed"));
-    }
+/// Firefox-family browsers with a profiles.ini under `home`
+fn firefox_installs(home: &Path) -> Vec<(&'static str, PathBuf)> {
+    FIREFOX_FAMILY
+        .iter()
+        .map(|(name, dir)| (*name, home.join(dir)))
+        .filter(|(_, base)| base.join("profiles.ini").exists())
+        .collect()
+}
 
-    // Read profiles.ini to find profile directories
-    let content = fs::read_to_string(&profiles_ini)?;
+/// Profile directories listed in a profiles.ini directory, leaving out any
+/// that don't resolve to somewhere inside `home`
+fn firefox_profile_dirs(home: &Path, base: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(base.join("profiles.ini")) else {
+        return Vec::new();
+    };
 
-    for line in content.lines() {
-        if line.starts_with("Path=") {
-            let profile_path = line.trim_start_matches("Path=");
-            let profile_dir = if profile_path.starts_with('/') {
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("Path="))
+        .map(|profile_path| {
+            if profile_path.starts_with('/') {
                 PathBuf::from(profile_path)
             } else {
-                firefox_dir.join(profile_path)
-            };
-
-            if profile_dir.exists() {
-                let user_js = profile_dir.join("user.js");
-
-                // Read existing user.js or create new
-                let mut content = if user_js.exists() {
-                    fs::read_to_string(&user_js)?
-                } else {
-                    String::new()
-                };
-
-                // Check if already configured
-                if content.contains("network.trr.mode") {
-                    // Update existing setting
-                    let lines: Vec<&str> = content.lines()
-                        .filter(|l| !l.contains("network.trr.mode"))
-                        .collect();
-                    content = lines.join("\n");
-                    if !content.is_empty() && !content.ends_with('\n') {
-                        content.push('\n');
-                    }
-                }
+                base.join(profile_path)
+            }
+        })
+        .filter(|profile_dir| is_within_home(home, profile_dir))
+        .collect()
+}
 
-                // Add DoH disable setting (mode 5 = DoH disabled)
-                content.push_str("\n// ParentShield: Disable DNS-over-HTTPS for website blocking\n");
-                content.push_str("user_pref(\"network.trr.mode\", 5);\n");
+/// Whether `path` resolves to somewhere inside `home`; profiles.ini belongs
+/// to the user and may name any absolute path
+fn is_within_home(home: &Path, path: &Path) -> bool {
+    match (home.canonicalize(), path.canonicalize()) {
+        (Ok(home), Ok(path)) => path.starts_with(home),
+        _ => false,
+    }
+}
+
+/// Open a file in a user's home as whoever owns that home would see it: a
+/// symlink in its place is not followed, and anything but a regular,
+/// singly-linked file owned by the home's owner is refused, so the daemon
+/// can't be pointed at a file the user couldn't touch themselves
+#[cfg(unix)]
+fn open_user_file(home: &Path, path: &Path, options: &mut fs::OpenOptions) -> io::Result<fs::File> {
+    use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
 
-                fs::write(&user_js, content)?;
+    let owner = fs::metadata(home)?.uid();
+    // O_NONBLOCK so a FIFO in its place can't hang the open
+    let file = options.custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK).open(path)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || metadata.uid() != owner || metadata.nlink() != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a file of {}'s owner", path.display(), home.display()),
+        ));
+    }
+    Ok(file)
+}
 
-                let profile_name = profile_dir.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                configured_profiles.push(profile_name);
-                info!("Configured Firefox profile: {:?}", profile_dir);
+/// Read a file from a user's home (see `open_user_file`)
+#[cfg(unix)]
+fn read_user_file(home: &Path, path: &Path) -> io::Result<String> {
+    use std::io::Read;
+
+    let mut content = String::new();
+    open_user_file(home, path, fs::OpenOptions::new().read(true))?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Replace a file in a user's home (see `open_user_file`), creating it owned
+/// by the home's owner if it doesn't exist
+#[cfg(unix)]
+fn write_user_file(home: &Path, path: &Path, content: &str) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+
+    let mut file = match open_user_file(home, path, fs::OpenOptions::new().write(true)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let home_metadata = fs::metadata(home)?;
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .custom_flags(libc::O_NOFOLLOW)
+                .open(path)?;
+            std::os::unix::fs::fchown(&file, Some(home_metadata.uid()), Some(home_metadata.gid()))?;
+            file
+        }
+        other => other?,
+    };
+    file.set_len(0)?;
+    file.write_all(content.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn read_user_file(_home: &Path, path: &Path) -> io::Result<String> {
+    fs::read_to_string(path)
+}
+
+#[cfg(not(unix))]
+fn write_user_file(_home: &Path, path: &Path, content: &str) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+fn firefox_profile_name(profile_dir: &Path) -> String {
+    profile_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Disable DoH in Firefox-family browsers by adding user.js preferences
+fn disable_firefox_doh() -> io::Result<Vec<String>> {
+    disable_firefox_doh_in(&home_dir()?)
+}
+
+/// Disable DoH in every Firefox-family profile under a user's home directory
+pub fn disable_firefox_doh_for_home(home: &Path) -> io::Result<Vec<String>> {
+    if firefox_profiles_doh_disabled(home) {
+        return Ok(Vec::new());
+    }
+    disable_firefox_doh_in(home)
+}
+
+/// Disable DoH in every profile of every Firefox-family browser under `home`.
+/// Returns entries like "LibreWolf (default-release)".
+fn disable_firefox_doh_in(home: &Path) -> io::Result<Vec<String>> {
+    let mut configured_profiles = Vec::new();
+
+    for (browser_name, base) in firefox_installs(home) {
+        for profile_dir in firefox_profile_dirs(home, &base) {
+            if !profile_dir.exists() {
+                continue;
             }
+            write_firefox_doh_pref(home, &profile_dir)?;
+            configured_profiles.push(format!("{} ({})", browser_name, firefox_profile_name(&profile_dir)));
+            info!("Configured {} profile: {:?}", browser_name, profile_dir);
         }
     }
 
     Ok(configured_profiles)
 }
 
-/// Re-enable DoH in Firefox
-fn enable_firefox_doh() -> io::Result<Vec<String>> {
-    let firefox_dir = get_firefox_dir()?;
-    let mut restored_profiles = Vec::new();
+/// Set `network.trr.mode` to 5 (DoH disabled) in a profile's user.js
+fn write_firefox_doh_pref(home: &Path, profile_dir: &Path) -> io::Result<()> {
+    let user_js = profile_dir.join("user.js");
 
-    let profiles_ini = firefox_dir.join("profiles.ini");
-    if !profiles_ini.exists() {
-        return Ok(restored_profiles);
+    // Read existing user.js or create new
+    let mut content = match read_user_file(home, &user_js) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    // Check if already configured
+    if content.contains("network.trr.mode") {
+        // Update existing setting
+        let lines: Vec<&str> = content.lines()
+            .filter(|l| !l.contains("network.trr.mode"))
+            .collect();
+        content = lines.join("\n");
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
     }
 
-    let content = fs::read_to_string(&profiles_ini)?;
+    // Add DoH disable setting (mode 5 = DoH disabled)
+    content.push_str("\n// ParentShield: Disable DNS-over-HTTPS for website blocking\n");
+    content.push_str(&format!("user_pref(\"network.trr.mode\", {});\n", FIREFOX_TRR_OFF));
 
-    for line in content.lines() {
-        if line.starts_with("Path=") {
-            let profile_path = line.trim_start_matches("Path=");
-            let profile_dir = if profile_path.starts_with('/') {
-                PathBuf::from(profile_path)
-            } else {
-                firefox_dir.join(profile_path)
-            };
+    write_user_file(home, &user_js, &content)
+}
 
-            let user_js = profile_dir.join("user.js");
-            if user_js.exists() {
-                let content = fs::read_to_string(&user_js)?;
+/// Re-enable DoH in Firefox-family browsers
+fn enable_firefox_doh() -> io::Result<Vec<String>> {
+    let mut restored_profiles = Vec::new();
 
-                // Remove ParentShield DoH settings
-                let lines: Vec<&str> = content.lines()
-                    .filter(|l| !l.contains("ParentShield") && !l.contains("network.trr.mode"))
-                    .collect();
+    let home = home_dir()?;
+    for (browser_name, base) in firefox_installs(&home) {
+        for profile_dir in firefox_profile_dirs(&home, &base) {
+            let user_js = profile_dir.join("user.js");
+            if !user_js.exists() {
+                continue;
+            }
+            let content = read_user_file(&home, &user_js)?;
 
-                let new_content = lines.join("\n");
+            // Remove ParentShield DoH settings
+            let lines: Vec<&str> = content.lines()
+                .filter(|l| !l.contains("ParentShield") && !l.contains("network.trr.mode"))
+                .collect();
 
-                if new_content.trim().is_empty() {
-                    // Remove empty user.js
-                    fs::remove_file(&user_js)?;
-                } else {
-                    fs::write(&user_js, new_content)?;
-                }
+            let new_content = lines.join("\n");
 
-                let profile_name = profile_dir.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                restored_profiles.push(profile_name);
+            if new_content.trim().is_empty() {
+                // Remove empty user.js
+                fs::remove_file(&user_js)?;
+            } else {
+                write_user_file(&home, &user_js, &new_content)?;
             }
+
+            restored_profiles.push(format!("{} ({})", browser_name, firefox_profile_name(&profile_dir)));
         }
     }
 
@@ -185,14 +360,26 @@ fn get_chromium_browsers() -> Vec<(&'static str, &'static str, &'static str)> {
     ]
 }
 
+/// Flatpak app id of a browser from [`get_chromium_browsers`], if it ships as one
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn chromium_flatpak_id(browser_name: &str) -> Option<&'static str> {
+    match browser_name {
+        "Chrome" => Some("com.google.Chrome"),
+        "Chromium" => Some("org.chromium.Chromium"),
+        "Brave" => Some("com.brave.Browser"),
+        "Edge" => Some("com.microsoft.Edge"),
+        "Opera" => Some("com.opera.Opera"),
+        "Vivaldi" => Some("com.vivaldi.Vivaldi"),
+        _ => None,
+    }
+}
+
 /// Disable DoH in all Chromium-based browsers via policies and user config
-fn disable_chrome_doh() -> io::Result<Vec<String>> {
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn disable_chrome_doh(block_private_browsing: bool) -> io::Result<Vec<String>> {
     let mut configured = Vec::new();
 
-    let policy_content = r#"{
-    "DnsOverHttpsMode": "off",
-    "BuiltInDnsClientEnabled": false
-}"#;
+    let policy_content = chromium_policy(block_private_browsing);
 
     let home = std::env::var_os("HOME")
         .map(PathBuf::from)
@@ -203,7 +390,7 @@ fn disable_chrome_doh() -> io::Result<Vec<String>> {
         let policy_dir = PathBuf::from(format!("/etc/{}/policies/managed", policy_suffix));
         if fs::create_dir_all(&policy_dir).is_ok() {
             let policy_file = policy_dir.join("parentshield.json");
-            if fs::write(&policy_file, policy_content).is_ok() {
+            if fs::write(&policy_file, &policy_content).is_ok() {
                 configured.push(browser_name.to_string());
                 info!("Created {} policy: {:?}", browser_name, policy_file);
             }
@@ -214,7 +401,7 @@ fn disable_chrome_doh() -> io::Result<Vec<String>> {
         if user_config.exists() {
             let local_state = user_config.join("Local State");
             if local_state.exists() {
-                if modify_chromium_local_state(&local_state, true).is_ok() {
+                if modify_chromium_local_state(&home, &local_state, true).is_ok() {
                     let entry = format!("{} (user)", browser_name);
                     if !configured.contains(&browser_name.to_string()) && !configured.contains(&entry) {
                         configured.push(entry);
@@ -225,11 +412,11 @@ fn disable_chrome_doh() -> io::Result<Vec<String>> {
         }
 
         // Flatpak locations
-        let flatpak_config = home.join(".var/app").join(format!("com.{}.Browser", browser_name.to_lowercase().replace(" ", "")));
-        if flatpak_config.exists() {
+        if let Some(app_id) = chromium_flatpak_id(browser_name) {
+            let flatpak_config = home.join(".var/app").join(app_id);
             let local_state = flatpak_config.join("config").join(config_dir_name).join("Local State");
             if local_state.exists() {
-                if modify_chromium_local_state(&local_state, true).is_ok() {
+                if modify_chromium_local_state(&home, &local_state, true).is_ok() {
                     configured.push(format!("{} (Flatpak)", browser_name));
                 }
             }
@@ -240,7 +427,7 @@ fn disable_chrome_doh() -> io::Result<Vec<String>> {
         if snap_config.exists() {
             let local_state = snap_config.join("Local State");
             if local_state.exists() {
-                if modify_chromium_local_state(&local_state, true).is_ok() {
+                if modify_chromium_local_state(&home, &local_state, true).is_ok() {
                     configured.push(format!("{} (Snap)", browser_name));
                 }
             }
@@ -250,9 +437,219 @@ fn disable_chrome_doh() -> io::Result<Vec<String>> {
     Ok(configured)
 }
 
-/// Modify Chromium Local State file to enable/disable DoH
-fn modify_chromium_local_state(local_state: &PathBuf, disable: bool) -> io::Result<()> {
-    let content = fs::read_to_string(local_state)?;
+/// Where a Chromium-based browser keeps its policy and settings on Windows and macOS
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+struct ChromiumLocations {
+    name: &'static str,
+    /// Policy key under `HKLM\SOFTWARE\Policies`, if the browser reads policies
+    windows_policy_key: Option<&'static str>,
+    /// User data directory under `%LOCALAPPDATA%`
+    windows_user_data: &'static str,
+    /// Preferences domain for managed policy, if the browser reads policies
+    macos_policy_domain: Option<&'static str>,
+    /// User data directory under `~/Library/Application Support`
+    macos_user_data: &'static str,
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+const CHROMIUM_LOCATIONS: &[ChromiumLocations] = &[
+    ChromiumLocations {
+        name: "Chrome",
+        windows_policy_key: Some(r"Google\Chrome"),
+        windows_user_data: r"Google\Chrome\User Data",
+        macos_policy_domain: Some("com.google.Chrome"),
+        macos_user_data: "Google/Chrome",
+    },
+    ChromiumLocations {
+        name: "Chromium",
+        windows_policy_key: Some("Chromium"),
+        windows_user_data: r"Chromium\User Data",
+        macos_policy_domain: Some("org.chromium.Chromium"),
+        macos_user_data: "Chromium",
+    },
+    ChromiumLocations {
+        name: "Brave",
+        windows_policy_key: Some(r"BraveSoftware\Brave"),
+        windows_user_data: r"BraveSoftware\Brave-Browser\User Data",
+        macos_policy_domain: Some("com.brave.Browser"),
+        macos_user_data: "BraveSoftware/Brave-Browser",
+    },
+    ChromiumLocations {
+        name: "Edge",
+        windows_policy_key: Some(r"Microsoft\Edge"),
+        windows_user_data: r"Microsoft\Edge\User Data",
+        macos_policy_domain: Some("com.microsoft.Edge"),
+        macos_user_data: "Microsoft Edge",
+    },
+    ChromiumLocations {
+        name: "Vivaldi",
+        windows_policy_key: None,
+        windows_user_data: r"Vivaldi\User Data",
+        macos_policy_domain: None,
+        macos_user_data: "Vivaldi",
+    },
+    ChromiumLocations {
+        name: "Opera",
+        windows_policy_key: None,
+        windows_user_data: r"Opera Software\Opera Stable",
+        macos_policy_domain: None,
+        macos_user_data: "com.operasoftware.Opera",
+    },
+];
+
+/// `Local State` inside a user data directory given with either separator
+#[cfg(any(target_os = "windows", target_os = "macos", test))]
+fn user_data_local_state(base: &Path, user_data: &str) -> PathBuf {
+    user_data
+        .split(['\\', '/'])
+        .fold(base.to_path_buf(), |path, component| path.join(component))
+        .join("Local State")
+}
+
+/// The current user's `Local State` for a browser
+#[cfg(target_os = "windows")]
+fn chromium_local_state(browser: &ChromiumLocations) -> Option<PathBuf> {
+    // Opera keeps its profile in Roaming rather than Local
+    let base = if browser.name == "Opera" { "APPDATA" } else { "LOCALAPPDATA" };
+    let base = PathBuf::from(std::env::var_os(base)?);
+    Some(user_data_local_state(&base, browser.windows_user_data))
+}
+
+#[cfg(target_os = "macos")]
+fn chromium_local_state(browser: &ChromiumLocations) -> Option<PathBuf> {
+    let base = home_dir().ok()?.join("Library/Application Support");
+    Some(user_data_local_state(&base, browser.macos_user_data))
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn run_policy_command(program: &str, args: &[&str]) -> io::Result<()> {
+    let output = std::process::Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+/// Write the managed DoH policy values to `HKLM\SOFTWARE\Policies\<key>` (requires Administrator)
+#[cfg(target_os = "windows")]
+fn write_chromium_policy(browser: &ChromiumLocations, block_private_browsing: bool) -> io::Result<bool> {
+    let Some(key) = browser.windows_policy_key else {
+        return Ok(false);
+    };
+    let key = format!(r"HKLM\SOFTWARE\Policies\{}", key);
+    run_policy_command("reg", &["add", &key, "/v", "DnsOverHttpsMode", "/t", "REG_SZ", "/d", "off", "/f"])?;
+    run_policy_command("reg", &["add", &key, "/v", "BuiltInDnsClientEnabled", "/t", "REG_DWORD", "/d", "0", "/f"])?;
+    if block_private_browsing {
+        let incognito = INCOGNITO_DISABLED.to_string();
+        run_policy_command("reg", &["add", &key, "/v", "IncognitoModeAvailability", "/t", "REG_DWORD", "/d", &incognito, "/f"])?;
+    } else {
+        let _ = run_policy_command("reg", &["delete", &key, "/v", "IncognitoModeAvailability", "/f"]);
+    }
+    Ok(true)
+}
+
+/// Remove the values written by `write_chromium_policy`, leaving other policies alone
+#[cfg(target_os = "windows")]
+fn remove_chromium_policy(browser: &ChromiumLocations) -> bool {
+    let Some(key) = browser.windows_policy_key else {
+        return false;
+    };
+    let key = format!(r"HKLM\SOFTWARE\Policies\{}", key);
+    ["DnsOverHttpsMode", "BuiltInDnsClientEnabled", "IncognitoModeAvailability"]
+        .iter()
+        .map(|value| run_policy_command("reg", &["delete", &key, "/v", value, "/f"]).is_ok())
+        .fold(false, |removed, ok| removed || ok)
+}
+
+/// Write the managed DoH policy to `/Library/Managed Preferences/<domain>.plist` (requires root)
+#[cfg(target_os = "macos")]
+fn write_chromium_policy(browser: &ChromiumLocations, block_private_browsing: bool) -> io::Result<bool> {
+    let Some(domain) = browser.macos_policy_domain else {
+        return Ok(false);
+    };
+    let plist = format!("/Library/Managed Preferences/{}", domain);
+    run_policy_command("defaults", &["write", &plist, "DnsOverHttpsMode", "-string", "off"])?;
+    run_policy_command("defaults", &["write", &plist, "BuiltInDnsClientEnabled", "-bool", "false"])?;
+    if block_private_browsing {
+        let incognito = INCOGNITO_DISABLED.to_string();
+        run_policy_command("defaults", &["write", &plist, "IncognitoModeAvailability", "-int", &incognito])?;
+    } else {
+        let _ = run_policy_command("defaults", &["delete", &plist, "IncognitoModeAvailability"]);
+    }
+    Ok(true)
+}
+
+/// Remove the keys written by `write_chromium_policy`, leaving other policies alone
+#[cfg(target_os = "macos")]
+fn remove_chromium_policy(browser: &ChromiumLocations) -> bool {
+    let Some(domain) = browser.macos_policy_domain else {
+        return false;
+    };
+    let plist = format!("/Library/Managed Preferences/{}", domain);
+    ["DnsOverHttpsMode", "BuiltInDnsClientEnabled", "IncognitoModeAvailability"]
+        .iter()
+        .map(|key| run_policy_command("defaults", &["delete", &plist, key]).is_ok())
+        .fold(false, |removed, ok| removed || ok)
+}
+
+/// Disable DoH in all Chromium-based browsers via managed policy and the user's Local State
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn disable_chrome_doh(block_private_browsing: bool) -> io::Result<Vec<String>> {
+    let mut configured = Vec::new();
+    let home = home_dir().unwrap_or_default();
+
+    for browser in CHROMIUM_LOCATIONS {
+        match write_chromium_policy(browser, block_private_browsing) {
+            Ok(true) => {
+                configured.push(browser.name.to_string());
+                info!("Applied {} DoH policy", browser.name);
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Could not write {} policy: {}", browser.name, e),
+        }
+
+        let Some(local_state) = chromium_local_state(browser).filter(|path| path.exists()) else {
+            continue;
+        };
+        if modify_chromium_local_state(&home, &local_state, true).is_ok() {
+            if !configured.iter().any(|entry| entry == browser.name) {
+                configured.push(format!("{} (user)", browser.name));
+            }
+            info!("Configured {} user profile: {:?}", browser.name, local_state);
+        }
+    }
+
+    Ok(configured)
+}
+
+/// Re-enable DoH in all Chromium-based browsers
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn enable_chrome_doh() -> io::Result<Vec<String>> {
+    let mut restored = Vec::new();
+    let home = home_dir().unwrap_or_default();
+
+    for browser in CHROMIUM_LOCATIONS {
+        if remove_chromium_policy(browser) {
+            restored.push(browser.name.to_string());
+        }
+
+        let Some(local_state) = chromium_local_state(browser).filter(|path| path.exists()) else {
+            continue;
+        };
+        if modify_chromium_local_state(&home, &local_state, false).is_ok()
+            && !restored.iter().any(|entry| entry == browser.name)
+        {
+            restored.push(format!("{} (user)", browser.name));
+        }
+    }
+
+    Ok(restored)
+}
+
+/// Modify a Chromium Local State file under `home` to enable/disable DoH
+fn modify_chromium_local_state(home: &Path, local_state: &Path, disable: bool) -> io::Result<()> {
+    let content = read_user_file(home, local_state)?;
 
     let mut json: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
@@ -279,13 +676,14 @@ fn modify_chromium_local_state(local_state: &PathBuf, disable: bool) -> io::Resu
 
         let new_content = serde_json::to_string_pretty(&json)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        fs::write(local_state, new_content)?;
+        write_user_file(home, local_state, &new_content)?;
     }
 
     Ok(())
 }
 
 /// Re-enable DoH in all Chromium-based browsers
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
 fn enable_chrome_doh() -> io::Result<Vec<String>> {
     let mut restored = Vec::new();
 
@@ -307,7 +705,7 @@ fn enable_chrome_doh() -> io::Result<Vec<String>> {
         if user_config.exists() {
             let local_state = user_config.join("Local State");
             if local_state.exists() {
-                if modify_chromium_local_state(&local_state, false).is_ok() {
+                if modify_chromium_local_state(&home, &local_state, false).is_ok() {
                     let entry = format!("{} (user)", browser_name);
                     if !restored.contains(&browser_name.to_string()) && !restored.contains(&entry) {
                         restored.push(entry);
@@ -317,11 +715,12 @@ fn enable_chrome_doh() -> io::Result<Vec<String>> {
         }
 
         // Flatpak
-        let flatpak_config = home.join(".var/app").join(format!("com.{}.Browser", browser_name.to_lowercase().replace(" ", "")));
-        let flatpak_local_state = flatpak_config.join("config").join(config_dir_name).join("Local State");
-        if flatpak_local_state.exists() {
-            if modify_chromium_local_state(&flatpak_local_state, false).is_ok() {
-                restored.push(format!("{} (Flatpak)", browser_name));
+        if let Some(app_id) = chromium_flatpak_id(browser_name) {
+            let flatpak_local_state = home.join(".var/app").join(app_id).join("config").join(config_dir_name).join("Local State");
+            if flatpak_local_state.exists() {
+                if modify_chromium_local_state(&home, &flatpak_local_state, false).is_ok() {
+                    restored.push(format!("{} (Flatpak)", browser_name));
+                }
             }
         }
 
@@ -329,7 +728,7 @@ fn enable_chrome_doh() -> io::Result<Vec<String>> {
         let snap_config = home.join("snap").join(browser_name.to_lowercase().replace(" ", "-")).join("current/.config").join(config_dir_name);
         let snap_local_state = snap_config.join("Local State");
         if snap_local_state.exists() {
-            if modify_chromium_local_state(&snap_local_state, false).is_ok() {
+            if modify_chromium_local_state(&home, &snap_local_state, false).is_ok() {
                 restored.push(format!("{} (Snap)", browser_name));
             }
         }
@@ -338,45 +737,471 @@ fn enable_chrome_doh() -> io::Result<Vec<String>> {
     Ok(restored)
 }
 
-/// Get Firefox directory path
-fn get_firefox_dir() -> io::Result<PathBuf> {
-    let home = std::env::var_os("HOME")
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME not set"))?;
+/// Configuration profile for Safari. Safari has no DoH setting of its own and
+/// resolves through the system, so the bypass to close is iCloud Private
+/// Relay, which tunnels Safari's DNS past the local resolver.
+#[cfg(any(target_os = "macos", test))]
+fn safari_dns_profile() -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>PayloadContent</key>
+    <array>
+        <dict>
+            <key>PayloadType</key>
+            <string>com.apple.applicationaccess</string>
+            <key>PayloadIdentifier</key>
+            <string>{id}.restrictions</string>
+            <key>PayloadUUID</key>
+            <string>6F0C7C52-3E0B-4B5C-9A43-5B1C2D0E8A11</string>
+            <key>PayloadVersion</key>
+            <integer>1</integer>
+            <key>allowCloudPrivateRelay</key>
+            <false/>
+        </dict>
+    </array>
+    <key>PayloadDisplayName</key>
+    <string>ParentShield DNS</string>
+    <key>PayloadDescription</key>
+    <string>Keeps Safari on the system DNS resolver so ParentShield blocking applies.</string>
+    <key>PayloadIdentifier</key>
+    <string>{id}</string>
+    <key>PayloadRemovalDisallowed</key>
+    <true/>
+    <key>PayloadScope</key>
+    <string>System</string>
+    <key>PayloadType</key>
+    <string>Configuration</string>
+    <key>PayloadUUID</key>
+    <string>2B8E5F3A-7D4C-4E21-8F6B-0C9D1A2E3B44</string>
+    <key>PayloadVersion</key>
+    <integer>1</integer>
+</dict>
+</plist>
+"#,
+        id = SAFARI_PROFILE_IDENTIFIER
+    )
+}
 
-    let firefox_dir = PathBuf::from(home).join(".mozilla/firefox");
+/// Whether the ParentShield profile is installed
+#[cfg(target_os = "macos")]
+fn safari_profile_installed() -> bool {
+    std::process::Command::new("profiles")
+        .args(["list", "-all"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(SAFARI_PROFILE_IDENTIFIER))
+        .unwrap_or(false)
+}
 
-    if firefox_dir.exists() {
-        Ok(firefox_dir)
+/// Install the Safari configuration profile (requires root)
+#[cfg(target_os = "macos")]
+fn disable_safari_doh() -> io::Result<Vec<String>> {
+    if safari_profile_installed() {
+        return Ok(vec!["Safari".to_string()]);
+    }
+
+    let path = Path::new(SAFARI_PROFILE_PATH);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, safari_dns_profile())?;
+
+    let output = std::process::Command::new("profiles")
+        .args(["install", "-type", "configuration", "-path", SAFARI_PROFILE_PATH])
+        .output()?;
+    if !output.status.success() || !safari_profile_installed() {
+        // Newer macOS only installs profiles after approval in System Settings
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "profile written to {} but not installed; approve it in System Settings: {}",
+                SAFARI_PROFILE_PATH,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    info!("Installed Safari DNS profile {}", SAFARI_PROFILE_IDENTIFIER);
+    Ok(vec!["Safari".to_string()])
+}
+
+/// Remove the Safari configuration profile
+#[cfg(target_os = "macos")]
+fn enable_safari_doh() -> io::Result<Vec<String>> {
+    if !safari_profile_installed() {
+        return Ok(Vec::new());
+    }
+
+    let output = std::process::Command::new("profiles")
+        .args(["remove", "-identifier", SAFARI_PROFILE_IDENTIFIER])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    let _ = fs::remove_file(SAFARI_PROFILE_PATH);
+
+    Ok(vec!["Safari".to_string()])
+}
+
+/// Re-apply system-wide managed browser policies that disable DoH (and private
+/// browsing when `block_private_browsing` is set).
+/// Unlike profile edits these survive changes made by the user, so the daemon
+/// (running as root) calls this periodically. Returns the policy files written.
+pub fn apply_managed_browser_policies(block_private_browsing: bool) -> io::Result<Vec<String>> {
+    let mut applied = apply_firefox_policies(block_private_browsing)?;
+
+    // Chromium-based browsers
+    let policy = chromium_policy(block_private_browsing);
+    for (browser_name, policy_suffix, _) in get_chromium_browsers() {
+        let policy_file = PathBuf::from(format!("/etc/{}/policies/managed/parentshield.json", policy_suffix));
+        let existing = fs::read_to_string(&policy_file).ok();
+        if chromium_policy_needs_reapply(existing.as_deref(), block_private_browsing) {
+            if let Some(parent) = policy_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&policy_file, &policy)?;
+            applied.push(policy_file.display().to_string());
+            info!("Applied {} DoH policy: {:?}", browser_name, policy_file);
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Write the Firefox enterprise policies: always the /etc policy, plus any
+/// distribution dirs that exist. Returns the files written.
+fn apply_firefox_policies(block_private_browsing: bool) -> io::Result<Vec<String>> {
+    let mut applied = Vec::new();
+
+    for (i, path) in FIREFOX_POLICY_PATHS.iter().enumerate() {
+        let path = Path::new(path);
+        let dir_exists = path.parent().map(|p| p.exists()).unwrap_or(false);
+        if i > 0 && !dir_exists {
+            continue;
+        }
+
+        let existing = fs::read_to_string(path).ok();
+        if let Some(merged) = merge_firefox_policy(existing.as_deref(), block_private_browsing) {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, merged)?;
+            applied.push(path.display().to_string());
+            info!("Applied Firefox DoH policy: {:?}", path);
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Merge the DoH-disabling policy into an existing Firefox policies.json.
+/// `DisablePrivateBrowsing` is managed by ParentShield: set when
+/// `block_private_browsing` is on and removed when it is off.
+/// Returns `None` if the policy is already in place and nothing needs writing.
+fn merge_firefox_policy(existing: Option<&str>, block_private_browsing: bool) -> Option<String> {
+    let expected = serde_json::json!({ "Enabled": false, "Locked": true });
+
+    let mut json = existing
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+
+    let private_disabled = json.pointer("/policies/DisablePrivateBrowsing") == Some(&serde_json::json!(true));
+    if json.pointer("/policies/DNSOverHTTPS") == Some(&expected) && private_disabled == block_private_browsing {
+        return None;
+    }
+
+    let root = json.as_object_mut()?;
+    let policies = root
+        .entry("policies")
+        .or_insert_with(|| serde_json::json!({}));
+    if !policies.is_object() {
+        *policies = serde_json::json!({});
+    }
+    let policies = policies.as_object_mut()?;
+    policies.insert("DNSOverHTTPS".to_string(), expected);
+    if block_private_browsing {
+        policies.insert("DisablePrivateBrowsing".to_string(), serde_json::json!(true));
     } else {
-        Err(io::Error::new(io::ErrorKind::NotFound, "Firefox directory not found"))
+        policies.remove("DisablePrivateBrowsing");
+    }
+
+    serde_json::to_string_pretty(&json).ok()
+}
+
+/// Check whether a Chromium managed policy file is missing, was tampered with,
+/// or doesn't match the private browsing setting
+fn chromium_policy_needs_reapply(existing: Option<&str>, block_private_browsing: bool) -> bool {
+    match existing.and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok()) {
+        Some(json) => {
+            let incognito_disabled =
+                json.get("IncognitoModeAvailability") == Some(&serde_json::json!(INCOGNITO_DISABLED));
+            json.get("DnsOverHttpsMode") != Some(&serde_json::json!("off"))
+                || json.get("BuiltInDnsClientEnabled") != Some(&serde_json::json!(false))
+                || incognito_disabled != block_private_browsing
+        }
+        None => true,
+    }
+}
+
+/// Whether the Firefox system policy disables private windows
+fn firefox_private_browsing_disabled() -> bool {
+    FIREFOX_POLICY_PATHS.iter().any(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .and_then(|json| json.pointer("/policies/DisablePrivateBrowsing").cloned())
+            == Some(serde_json::json!(true))
+    })
+}
+
+/// Whether every Chromium managed policy disables incognito windows
+fn chromium_private_browsing_disabled() -> bool {
+    get_chromium_browsers().iter().all(|(_, policy_suffix, _)| {
+        let policy_file = format!("/etc/{}/policies/managed/parentshield.json", policy_suffix);
+        fs::read_to_string(policy_file)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .and_then(|json| json.get("IncognitoModeAvailability").cloned())
+            == Some(serde_json::json!(INCOGNITO_DISABLED))
+    })
+}
+
+/// Check if private browsing is disabled by policy in all supported browsers
+pub fn is_private_browsing_disabled() -> bool {
+    firefox_private_browsing_disabled() && chromium_private_browsing_disabled()
+}
+
+/// Report DoH status for each user with a home directory
+pub fn get_user_doh_status() -> Vec<UserDohStatus> {
+    let firefox_policy = FIREFOX_POLICY_PATHS.iter().any(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .and_then(|json| json.pointer("/policies/DNSOverHTTPS").cloned())
+            == Some(serde_json::json!({ "Enabled": false, "Locked": true }))
+    });
+    let chromium_policy = get_chromium_browsers().iter().all(|(_, policy_suffix, _)| {
+        let policy_file = format!("/etc/{}/policies/managed/parentshield.json", policy_suffix);
+        fs::read_to_string(policy_file)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .is_some_and(|json| {
+                json.get("DnsOverHttpsMode") == Some(&serde_json::json!("off"))
+                    && json.get("BuiltInDnsClientEnabled") == Some(&serde_json::json!(false))
+            })
+    });
+    let private_browsing_disabled = is_private_browsing_disabled();
+
+    let mut statuses = Vec::new();
+    if let Ok(entries) = fs::read_dir("/home") {
+        for entry in entries.flatten() {
+            let home = entry.path();
+            if !home.is_dir() {
+                continue;
+            }
+            let user = entry.file_name().to_string_lossy().to_string();
+
+            statuses.push(UserDohStatus {
+                user,
+                firefox_doh_disabled: firefox_policy || firefox_profiles_doh_disabled(&home),
+                chromium_doh_disabled: chromium_policy,
+                private_browsing_disabled,
+            });
+        }
+    }
+
+    statuses
+}
+
+/// Snap and Flatpak browsers installed for a user. Their sandboxes don't read
+/// the managed policies under /etc, so DoH may stay enabled in them.
+pub fn find_sandboxed_browsers(home: &Path) -> Vec<String> {
+    const SANDBOXED: &[(&str, &str)] = &[
+        ("snap/firefox", "Firefox (Snap)"),
+        ("snap/chromium", "Chromium (Snap)"),
+        ("snap/brave", "Brave (Snap)"),
+        (".var/app/org.mozilla.firefox", "Firefox (Flatpak)"),
+        (".var/app/org.chromium.Chromium", "Chromium (Flatpak)"),
+        (".var/app/com.google.Chrome", "Google Chrome (Flatpak)"),
+        (".var/app/com.brave.Browser", "Brave (Flatpak)"),
+        (".var/app/com.microsoft.Edge", "Microsoft Edge (Flatpak)"),
+    ];
+
+    SANDBOXED
+        .iter()
+        .filter(|(dir, _)| home.join(dir).is_dir())
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// `network.trr.mode` as set in a profile's user.js (the last assignment wins)
+fn firefox_trr_mode(user_js: &str) -> Option<i64> {
+    user_js
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let args = line.trim().strip_prefix("user_pref(")?;
+            let (name, value) = args.split_once(',')?;
+            if name.trim() != "\"network.trr.mode\"" {
+                return None;
+            }
+            value.trim().trim_end_matches(';').trim().strip_suffix(')')?.trim().parse().ok()
+        })
+}
+
+/// Whether a profile's user.js under `home` turns DoH off
+fn firefox_profile_doh_disabled(home: &Path, profile_dir: &Path) -> bool {
+    read_user_file(home, &profile_dir.join("user.js"))
+        .ok()
+        .and_then(|c| firefox_trr_mode(&c))
+        == Some(FIREFOX_TRR_OFF)
+}
+
+/// `dns_over_https.mode` in a Chromium Local State file
+fn chromium_doh_mode(local_state: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(local_state)
+        .ok()?
+        .pointer("/dns_over_https/mode")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Chromium Local State files under a home directory, labelled like the
+/// entries `disable_doh_all_browsers` returns
+fn chromium_local_states(home: &Path) -> Vec<(String, PathBuf)> {
+    let mut states = Vec::new();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    for (browser_name, _, config_dir_name) in get_chromium_browsers() {
+        states.push((
+            format!("{} (user)", browser_name),
+            home.join(".config").join(config_dir_name).join("Local State"),
+        ));
+        if let Some(app_id) = chromium_flatpak_id(browser_name) {
+            states.push((
+                format!("{} (Flatpak)", browser_name),
+                home.join(".var/app").join(app_id).join("config").join(config_dir_name).join("Local State"),
+            ));
+        }
+        states.push((
+            format!("{} (Snap)", browser_name),
+            home.join("snap")
+                .join(browser_name.to_lowercase().replace(" ", "-"))
+                .join("current/.config")
+                .join(config_dir_name)
+                .join("Local State"),
+        ));
+    }
+
+    #[cfg(target_os = "windows")]
+    for browser in CHROMIUM_LOCATIONS {
+        let base = if browser.name == "Opera" { "AppData/Roaming" } else { "AppData/Local" };
+        states.push((
+            format!("{} (user)", browser.name),
+            user_data_local_state(&home.join(base), browser.windows_user_data),
+        ));
+    }
+
+    #[cfg(target_os = "macos")]
+    for browser in CHROMIUM_LOCATIONS {
+        states.push((
+            format!("{} (user)", browser.name),
+            user_data_local_state(&home.join("Library/Application Support"), browser.macos_user_data),
+        ));
+    }
+
+    states.retain(|(_, path)| path.exists());
+    states
+}
+
+/// Disable DoH in the Firefox-family profiles and Chromium Local State files
+/// under another user's home directory
+pub fn disable_doh_for_home(home: &Path) -> io::Result<Vec<String>> {
+    let mut configured = disable_firefox_doh_for_home(home)?;
+    for (entry, local_state) in chromium_local_states(home) {
+        modify_chromium_local_state(home, &local_state, true)?;
+        configured.push(entry);
+    }
+    Ok(configured)
+}
+
+/// Browsers and profiles under `home` where DoH is no longer disabled: a
+/// Firefox-family profile whose user.js doesn't set `network.trr.mode` to 5,
+/// or a Chromium Local State whose `dns_over_https.mode` isn't "off"
+pub fn verify_doh_disabled_for_home(home: &Path) -> Vec<String> {
+    let mut drifted = Vec::new();
+
+    for (browser_name, base) in firefox_installs(home) {
+        for profile_dir in firefox_profile_dirs(home, &base) {
+            if !firefox_profile_doh_disabled(home, &profile_dir) {
+                drifted.push(format!("{} ({})", browser_name, firefox_profile_name(&profile_dir)));
+            }
+        }
+    }
+
+    for (entry, local_state) in chromium_local_states(home) {
+        let mode = read_user_file(home, &local_state).ok().and_then(|c| chromium_doh_mode(&c));
+        if mode.as_deref() != Some("off") {
+            drifted.push(entry);
+        }
+    }
+
+    drifted
+}
+
+/// Browsers where our DoH setting is missing or was changed, for the current
+/// user plus (on Linux) the managed Chromium policies
+pub fn verify_doh_disabled() -> Vec<String> {
+    let mut drifted = Vec::new();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    for (browser_name, policy_suffix, _) in get_chromium_browsers() {
+        let policy_file = format!("/etc/{}/policies/managed/parentshield.json", policy_suffix);
+        let intact = fs::read_to_string(policy_file)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .is_some_and(|json| {
+                json.get("DnsOverHttpsMode") == Some(&serde_json::json!("off"))
+                    && json.get("BuiltInDnsClientEnabled") == Some(&serde_json::json!(false))
+            });
+        if !intact {
+            drifted.push(browser_name.to_string());
+        }
     }
+
+    if let Ok(home) = home_dir() {
+        drifted.extend(verify_doh_disabled_for_home(&home));
+    }
+
+    drifted
+}
+
+/// Check whether every Firefox-family profile in a home directory has DoH disabled via user.js
+fn firefox_profiles_doh_disabled(home: &Path) -> bool {
+    // No Firefox-family browser for this user means nothing to bypass with
+    firefox_installs(home)
+        .iter()
+        .all(|(_, base)| firefox_profile_dirs(home, base).iter().all(|dir| firefox_profile_doh_disabled(home, dir)))
+}
+
+fn home_dir() -> io::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME not set"))
 }
 
 /// Check if DoH is currently disabled
 pub fn is_doh_disabled() -> bool {
-    // Check Firefox
-    if let Ok(firefox_dir) = get_firefox_dir() {
-        let profiles_ini = firefox_dir.join("profiles.ini");
-        if let Ok(content) = fs::read_to_string(&profiles_ini) {
-            for line in content.lines() {
-                if line.starts_with("Path=") {
-                    let profile_path = line.trim_start_matches("Path=");
-                    let profile_dir = if profile_path.starts_with('/') {
-                        PathBuf::from(profile_path)
-                    } else {
-                        firefox_dir.join(profile_path)
-                    };
-
-                    let user_js = profile_dir.join("user.js");
-                    if user_js.exists() {
-                        if let Ok(content) = fs::read_to_string(&user_js) {
-                            if content.contains("network.trr.mode\", 5") {
-                                return true;
-                            }
-                        }
-                    }
-                }
-            }
+    // Check Firefox and its forks
+    if let Ok(home) = home_dir() {
+        let any_profile_disabled = firefox_installs(&home)
+            .iter()
+            .any(|(_, base)| firefox_profile_dirs(&home, base).iter().any(|dir| firefox_profile_doh_disabled(&home, dir)));
+        if any_profile_disabled {
+            return true;
         }
     }
 
@@ -386,5 +1211,224 @@ pub fn is_doh_disabled() -> bool {
         return true;
     }
 
+    #[cfg(target_os = "macos")]
+    if safari_profile_installed() {
+        return true;
+    }
+
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_firefox_policy_applied_when_missing() {
+        let merged = merge_firefox_policy(None, false).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(json["policies"]["DNSOverHTTPS"]["Enabled"], false);
+        assert_eq!(json["policies"]["DNSOverHTTPS"]["Locked"], true);
+    }
+
+    #[test]
+    fn test_firefox_policy_preserves_other_policies() {
+        let existing = r#"{"policies": {"DisableTelemetry": true, "DNSOverHTTPS": {"Enabled": true}}}"#;
+        let merged = merge_firefox_policy(Some(existing), false).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(json["policies"]["DisableTelemetry"], true);
+        assert_eq!(json["policies"]["DNSOverHTTPS"]["Enabled"], false);
+    }
+
+    #[test]
+    fn test_firefox_policy_not_reapplied_when_intact() {
+        let existing = r#"{"policies": {"DNSOverHTTPS": {"Enabled": false, "Locked": true}}}"#;
+        assert!(merge_firefox_policy(Some(existing), false).is_none());
+    }
+
+    #[test]
+    fn test_firefox_private_browsing_policy() {
+        let merged = merge_firefox_policy(None, true).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(json["policies"]["DisablePrivateBrowsing"], true);
+        assert_eq!(json["policies"]["DNSOverHTTPS"]["Enabled"], false);
+
+        // Intact policy needs no rewrite; turning the option off removes the key
+        assert!(merge_firefox_policy(Some(&merged), true).is_none());
+        let relaxed = merge_firefox_policy(Some(&merged), false).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&relaxed).unwrap();
+        assert!(json["policies"].get("DisablePrivateBrowsing").is_none());
+    }
+
+    #[test]
+    fn test_chromium_private_browsing_policy() {
+        let json: serde_json::Value = serde_json::from_str(&chromium_policy(true)).unwrap();
+        assert_eq!(json["IncognitoModeAvailability"], 1);
+        assert_eq!(json["DnsOverHttpsMode"], "off");
+        assert_eq!(json["BuiltInDnsClientEnabled"], false);
+
+        let json: serde_json::Value = serde_json::from_str(&chromium_policy(false)).unwrap();
+        assert!(json.get("IncognitoModeAvailability").is_none());
+
+        // A policy written without the incognito setting is rewritten once it's enabled
+        assert!(chromium_policy_needs_reapply(Some(&chromium_policy(false)), true));
+        assert!(!chromium_policy_needs_reapply(Some(&chromium_policy(true)), true));
+        assert!(chromium_policy_needs_reapply(Some(&chromium_policy(true)), false));
+    }
+
+    #[test]
+    fn test_safari_profile_disables_private_relay() {
+        let profile = safari_dns_profile();
+        assert!(profile.contains(&format!("<string>{}</string>", SAFARI_PROFILE_IDENTIFIER)));
+        assert!(profile.contains("<key>allowCloudPrivateRelay</key>\n            <false/>"));
+    }
+
+    #[test]
+    fn test_firefox_forks_are_configured() {
+        let home = tempfile::tempdir().unwrap();
+        let librewolf = home.path().join(".librewolf");
+        fs::create_dir_all(librewolf.join("abc.default-release")).unwrap();
+        fs::write(librewolf.join("profiles.ini"), "[Profile0]\nPath=abc.default-release\n").unwrap();
+        let firefox = home.path().join(".mozilla/firefox");
+        fs::create_dir_all(firefox.join("xyz.default")).unwrap();
+        fs::write(firefox.join("profiles.ini"), "[Profile0]\nPath=xyz.default\n").unwrap();
+
+        assert!(!firefox_profiles_doh_disabled(home.path()));
+        let mut configured = disable_firefox_doh_for_home(home.path()).unwrap();
+        configured.sort();
+        assert_eq!(configured, vec!["Firefox (xyz.default)", "LibreWolf (abc.default-release)"]);
+        assert!(firefox_profiles_doh_disabled(home.path()));
+        assert!(disable_firefox_doh_for_home(home.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_firefox_trr_mode_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = dir.path().join(".mozilla/firefox");
+        let profile = profiles.join("abcd.default");
+        fs::create_dir_all(&profile).unwrap();
+        fs::write(profiles.join("profiles.ini"), "[Profile0]\nPath=abcd.default\n").unwrap();
+
+        // A profile we never configured counts as drifted
+        assert_eq!(verify_doh_disabled_for_home(dir.path()), ["Firefox (abcd.default)"]);
+
+        write_firefox_doh_pref(dir.path(), &profile).unwrap();
+        assert!(verify_doh_disabled_for_home(dir.path()).is_empty());
+
+        // Our marker comment is still there but the value was changed
+        let user_js = profile.join("user.js");
+        let tampered = fs::read_to_string(&user_js).unwrap().replace("\"network.trr.mode\", 5", "\"network.trr.mode\", 2");
+        fs::write(&user_js, tampered).unwrap();
+        assert_eq!(verify_doh_disabled_for_home(dir.path()), ["Firefox (abcd.default)"]);
+
+        assert_eq!(firefox_trr_mode("user_pref(\"network.trr.mode\", 5);\nuser_pref(\"network.trr.mode\",3);"), Some(3));
+        assert_eq!(firefox_trr_mode("user_pref(\"network.trr.uri\", \"https://dns.example\");"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_firefox_profiles_cannot_redirect_writes() {
+        let home = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let profiles = home.path().join(".mozilla/firefox");
+        let linked = profiles.join("linked.default");
+        fs::create_dir_all(&linked).unwrap();
+        fs::create_dir_all(outside.path().join("elsewhere.default")).unwrap();
+        fs::write(
+            profiles.join("profiles.ini"),
+            format!(
+                "[Profile0]\nPath=linked.default\n[Profile1]\nPath={}\n",
+                outside.path().join("elsewhere.default").display()
+            ),
+        )
+        .unwrap();
+
+        // user.js pointing at a file the daemon would otherwise overwrite
+        let victim = outside.path().join("victim");
+        fs::write(&victim, "untouched").unwrap();
+        std::os::unix::fs::symlink(&victim, linked.join("user.js")).unwrap();
+
+        assert!(disable_firefox_doh_for_home(home.path()).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "untouched");
+
+        // The absolute profile outside the home is skipped, not written
+        fs::remove_file(linked.join("user.js")).unwrap();
+        let configured = disable_firefox_doh_for_home(home.path()).unwrap();
+        assert_eq!(configured, ["Firefox (linked.default)"]);
+        assert!(!outside.path().join("elsewhere.default/user.js").exists());
+    }
+
+    #[test]
+    fn test_chromium_doh_mode_drift() {
+        assert_eq!(chromium_doh_mode(r#"{"dns_over_https":{"mode":"off","templates":""}}"#).as_deref(), Some("off"));
+        assert_eq!(chromium_doh_mode(r#"{"dns_over_https":{"mode":"secure"}}"#).as_deref(), Some("secure"));
+        assert_eq!(chromium_doh_mode(r#"{"browser":{}}"#), None);
+        assert_eq!(chromium_doh_mode("not json"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_flatpak_local_states_use_the_app_id() {
+        let home = tempfile::tempdir().unwrap();
+        for app_id in ["com.google.Chrome", "org.chromium.Chromium"] {
+            let config = home.path().join(".var/app").join(app_id).join("config");
+            let dir = config.join(if app_id == "com.google.Chrome" { "google-chrome" } else { "chromium" });
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("Local State"), r#"{"dns_over_https":{"mode":"secure"}}"#).unwrap();
+        }
+
+        let mut drifted = verify_doh_disabled_for_home(home.path());
+        drifted.sort();
+        assert_eq!(drifted, ["Chrome (Flatpak)", "Chromium (Flatpak)"]);
+        disable_doh_for_home(home.path()).unwrap();
+        assert!(verify_doh_disabled_for_home(home.path()).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_symlinked_local_state_is_not_written() {
+        let home = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let victim = outside.path().join("victim.json");
+        fs::write(&victim, r#"{"dns_over_https":{"mode":"secure"}}"#).unwrap();
+
+        let dir = home.path().join(".config/chromium");
+        fs::create_dir_all(&dir).unwrap();
+        std::os::unix::fs::symlink(&victim, dir.join("Local State")).unwrap();
+
+        assert!(disable_doh_for_home(home.path()).is_err());
+        assert_eq!(
+            fs::read_to_string(&victim).unwrap(),
+            r#"{"dns_over_https":{"mode":"secure"}}"#
+        );
+        // Not read through the link either, so it still counts as drifted
+        assert_eq!(verify_doh_disabled_for_home(home.path()), ["Chromium (user)"]);
+    }
+
+    #[test]
+    fn test_chromium_local_state_paths() {
+        let base = Path::new("/base");
+        let chrome = &CHROMIUM_LOCATIONS[0];
+        assert_eq!(chrome.name, "Chrome");
+        assert_eq!(
+            user_data_local_state(base, chrome.windows_user_data),
+            base.join("Google").join("Chrome").join("User Data").join("Local State")
+        );
+        assert_eq!(
+            user_data_local_state(base, chrome.macos_user_data),
+            base.join("Google").join("Chrome").join("Local State")
+        );
+        // A browser either reads managed policy on both platforms or on neither
+        assert!(CHROMIUM_LOCATIONS
+            .iter()
+            .all(|b| b.windows_policy_key.is_some() == b.macos_policy_domain.is_some()));
+    }
+
+    #[test]
+    fn test_chromium_policy_reapply_decision() {
+        assert!(chromium_policy_needs_reapply(None, false));
+        assert!(chromium_policy_needs_reapply(Some("not json"), false));
+        assert!(chromium_policy_needs_reapply(Some(r#"{"DnsOverHttpsMode": "automatic"}"#), false));
+        assert!(!chromium_policy_needs_reapply(Some(&chromium_policy(false)), false));
+    }
+}
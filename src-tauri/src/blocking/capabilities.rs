@@ -0,0 +1,72 @@
+//! Per-platform feature reporting so the UI can hide unsupported features.
+
+use serde::{Deserialize, Serialize};
+
+/// Features that are actually implemented on the current platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Firewall rules blocking DNS-over-HTTPS providers
+    pub firewall_doh: bool,
+    /// Firewall rules blocking common VPN ports
+    pub vpn_block: bool,
+    /// Terminating a blocked process together with its children
+    pub process_tree_kill: bool,
+    /// Domain blocking through the hosts file
+    pub hosts_block: bool,
+    /// Disabling DNS-over-HTTPS in browser profiles and policies
+    pub browser_doh: bool,
+    /// Protection against uninstalling without the parent password
+    pub uninstall_protection: bool,
+}
+
+/// Get the capabilities of the compiled target.
+/// Only features with a real implementation are reported; the no-op stubs
+/// used on other platforms don't count.
+pub fn get_capabilities() -> Capabilities {
+    let linux = cfg!(target_os = "linux");
+    let desktop = cfg!(any(target_os = "linux", target_os = "macos", target_os = "windows"));
+
+    Capabilities {
+        // iptables chain management only exists in network::linux
+        firewall_doh: linux,
+        // VPN rules are applied together with the iptables DoH rules
+        vpn_block: linux,
+        // Desktop blockers terminate the matched process together with its children
+        process_tree_kill: desktop,
+        hosts_block: desktop,
+        // Firefox profiles, Chromium Local State files and policies are handled on every desktop
+        browser_doh: desktop,
+        uninstall_protection: desktop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_match_target() {
+        let caps = get_capabilities();
+
+        #[cfg(target_os = "linux")]
+        {
+            assert!(caps.firewall_doh);
+            assert!(caps.vpn_block);
+            assert!(caps.browser_doh);
+            assert!(caps.hosts_block);
+            assert!(caps.uninstall_protection);
+            assert!(caps.process_tree_kill);
+        }
+
+        #[cfg(any(target_os = "macos", target_os = "windows"))]
+        {
+            assert!(!caps.firewall_doh);
+            assert!(!caps.vpn_block);
+            assert!(caps.browser_doh);
+            assert!(caps.hosts_block);
+            assert!(caps.uninstall_protection);
+            assert!(caps.process_tree_kill);
+        }
+    }
+}
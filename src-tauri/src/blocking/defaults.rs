@@ -0,0 +1,173 @@
+//! Updatable overrides for the compiled-in default blocklists.
+//! An update can drop a `defaults.json` into the system data directory to add
+//! to (or replace) the built-in lists without shipping a new binary:
+//!
+//! ```json
+//! { "mode": "augment", "gaming_domains": ["newgame.example"] }
+//! ```
+//!
+//! Lists left out of the file keep their compiled-in contents. A missing,
+//! unreadable, invalid, or (on Unix) non-root-owned or writable-by-others
+//! file is ignored and the compiled-in lists are used.
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Where an update places the override file
+#[cfg(unix)]
+pub const DEFAULTS_OVERRIDE_PATH: &str = "/etc/parentshield/defaults.json";
+#[cfg(windows)]
+pub const DEFAULTS_OVERRIDE_PATH: &str = r"C:\ProgramData\ParentShield\defaults.json";
+
+/// How override lists combine with the compiled-in ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideMode {
+    /// Add to the compiled-in list
+    #[default]
+    Augment,
+    /// Use the override list instead of the compiled-in one
+    Replace,
+}
+
+/// Contents of `defaults.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultsOverride {
+    #[serde(default)]
+    pub mode: OverrideMode,
+    pub gaming_processes: Option<Vec<String>>,
+    pub browser_processes: Option<Vec<String>>,
+    pub ai_processes: Option<Vec<String>>,
+    pub ai_domains: Option<Vec<String>>,
+    pub gaming_domains: Option<Vec<String>>,
+}
+
+impl DefaultsOverride {
+    /// Combine `compiled` with an override list according to `mode`
+    pub fn merge(&self, compiled: HashSet<String>, list: Option<&Vec<String>>) -> HashSet<String> {
+        let Some(list) = list else {
+            return compiled;
+        };
+        let entries = list
+            .iter()
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty());
+
+        match self.mode {
+            OverrideMode::Augment => compiled.into_iter().chain(entries).collect(),
+            OverrideMode::Replace => entries.collect(),
+        }
+    }
+}
+
+/// Only root may be able to change the file, or a child could empty the lists
+#[cfg(unix)]
+fn is_trusted(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.uid() == 0 && meta.mode() & 0o022 == 0
+}
+
+#[cfg(windows)]
+fn is_trusted(_meta: &fs::Metadata) -> bool {
+    // ProgramData\ParentShield is only writable by Administrators
+    true
+}
+
+/// Read and parse an override file; `None` means use the compiled-in lists
+pub fn load_defaults_override(path: &Path, require_trusted: bool) -> Option<DefaultsOverride> {
+    let meta = fs::metadata(path).ok()?;
+    if require_trusted && !is_trusted(&meta) {
+        tracing::warn!("Ignoring {}: not owned by root or writable by others", path.display());
+        return None;
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| tracing::warn!("Ignoring {}: {}", path.display(), e))
+        .ok()?;
+    serde_json::from_str(&content)
+        .map_err(|e| tracing::warn!("Ignoring invalid {}: {}", path.display(), e))
+        .ok()
+}
+
+/// A loaded override and the modification time of the file it came from
+type CachedOverride = (Option<SystemTime>, Option<DefaultsOverride>);
+
+/// Last loaded override, reloaded when the file's modification time changes
+static OVERRIDE_CACHE: Lazy<Mutex<Option<CachedOverride>>> = Lazy::new(|| Mutex::new(None));
+
+/// The system override, reloaded only when the file changes
+pub fn system_defaults_override() -> Option<DefaultsOverride> {
+    let path = Path::new(DEFAULTS_OVERRIDE_PATH);
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let mut cache = OVERRIDE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_at, loaded)) = cache.as_ref() {
+        if *cached_at == modified {
+            return loaded.clone();
+        }
+    }
+
+    let loaded = modified.and_then(|_| load_defaults_override(path, true));
+    *cache = Some((modified, loaded.clone()));
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled() -> HashSet<String> {
+        ["roblox.com".to_string(), "steampowered.com".to_string()].into()
+    }
+
+    #[test]
+    fn test_augment_and_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defaults.json");
+
+        fs::write(&path, r#"{"gaming_domains": ["NewGame.example", " "]}"#).unwrap();
+        let augment = load_defaults_override(&path, false).unwrap();
+        let merged = augment.merge(compiled(), augment.gaming_domains.as_ref());
+        assert_eq!(merged.len(), 3);
+        assert!(merged.contains("newgame.example"));
+        assert!(merged.contains("roblox.com"));
+        // Lists not in the file are untouched
+        assert_eq!(augment.merge(compiled(), augment.ai_domains.as_ref()), compiled());
+
+        fs::write(&path, r#"{"mode": "replace", "gaming_domains": ["only.example"]}"#).unwrap();
+        let replace = load_defaults_override(&path, false).unwrap();
+        let merged = replace.merge(compiled(), replace.gaming_domains.as_ref());
+        assert_eq!(merged, ["only.example".to_string()].into());
+    }
+
+    #[test]
+    fn test_missing_or_invalid_file_falls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defaults.json");
+        assert!(load_defaults_override(&path, false).is_none());
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(load_defaults_override(&path, false).is_none());
+
+        fs::write(&path, r#"{"gaming_domain": ["typo.example"]}"#).unwrap();
+        assert!(load_defaults_override(&path, false).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_untrusted_file_is_ignored() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defaults.json");
+        fs::write(&path, r#"{"mode": "replace", "gaming_domains": []}"#).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(load_defaults_override(&path, true).is_none());
+    }
+}
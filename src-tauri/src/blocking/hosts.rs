@@ -1,17 +1,400 @@
-//! Hosts file-based domain blocking for Linux and macOS.
-//! Adds blocked domains to /etc/hosts pointing to 127.0.0.1.
+//! Hosts file-based domain blocking.
+//! Adds blocked domains to the system hosts file pointing to 127.0.0.1.
 
 use std::collections::HashSet;
 use std::fs;
 use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tracing::{info, error};
+use std::time::{Duration, Instant};
+use tracing::{info, error, warn};
 
+use crate::config::{AppConfig, ConfigManager};
+
+/// Default hosts file location for this OS
+#[cfg(not(target_os = "windows"))]
 const HOSTS_PATH: &str = "/etc/hosts";
+#[cfg(target_os = "windows")]
+const HOSTS_PATH: &str = r"C:\Windows\System32\drivers\etc\hosts";
+
+/// Environment variable that points ParentShield at a relocated hosts file
+const HOSTS_PATH_ENV: &str = "PARENTSHIELD_HOSTS_FILE";
 const MARKER_START: &str = "# ParentShield START - DO NOT EDIT THIS SECTION";
 const MARKER_END: &str = "# ParentShield END";
 
-/// Block domains by adding them to /etc/hosts
+/// How long a hosts file edit waits for one in another process to finish
+const HOSTS_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const HOSTS_LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Appended to the hosts file path for the copy of its last known-good content
+const BACKUP_SUFFIX: &str = ".gameblocker.bak";
+/// Appended to the hosts file path for the file written before the rename
+const TEMP_SUFFIX: &str = ".parentshield.tmp";
+
+/// Directories a symlinked hosts file is expected to point into
+const EXPECTED_HOSTS_DIRS: &[&str] = &["/etc/", "/private/etc/", "/var/", "/run/"];
+
+/// Resolve the file to write for `path`. When the hosts file is a symlink
+/// (e.g. managed by a config tool) we write through to its target so the link
+/// itself is preserved.
+fn resolve_hosts_target(path: &Path) -> io::Result<PathBuf> {
+    if !fs::symlink_metadata(path)?.file_type().is_symlink() {
+        return Ok(path.to_path_buf());
+    }
+
+    let target = fs::canonicalize(path)?;
+    if !EXPECTED_HOSTS_DIRS.iter().any(|dir| target.starts_with(dir)) {
+        warn!("{} is a symlink to unexpected location {:?}", path.display(), target);
+    } else {
+        info!("{} is a symlink, writing through to {:?}", path.display(), target);
+    }
+    Ok(target)
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Copy the current hosts file aside before it is replaced. An empty file is
+/// never known-good, so it doesn't overwrite an earlier backup.
+fn backup_hosts(target: &Path) -> io::Result<()> {
+    match fs::metadata(target) {
+        Ok(metadata) if metadata.len() > 0 => {
+            fs::copy(target, with_suffix(target, BACKUP_SUFFIX))?;
+            Ok(())
+        }
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write `content` to a temp file next to `target` and rename it into place,
+/// so a reader (or a crash) only ever sees the old or the new file, never a
+/// truncated one
+fn replace_atomically(target: &Path, content: &str) -> io::Result<()> {
+    let temp = with_suffix(target, TEMP_SUFFIX);
+    let written = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(target) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, target)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Advisory lock serializing read-modify-write cycles on a hosts file between
+/// the GUI and the daemon; released when dropped
+struct HostsLock {
+    _file: Option<fs::File>,
+}
+
+/// Open the lock file, creating it if we can. The GUI can't create files in
+/// the root-owned runtime directory but can lock the daemon's file read-only.
+fn open_lock_file(lock_path: &Path) -> Option<fs::File> {
+    if let Some(parent) = lock_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+        .or_else(|_| fs::File::open(lock_path))
+        .ok()
+}
+
+/// Take the lock at `lock_path`, giving up after `timeout`
+fn lock_hosts(lock_path: &Path, timeout: Duration) -> io::Result<HostsLock> {
+    let Some(file) = open_lock_file(lock_path) else {
+        // Nobody has created the lock, so no daemon is running to race with
+        warn!("Could not open hosts lock {:?}, editing without it", lock_path);
+        return Ok(HostsLock { _file: None });
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(HostsLock { _file: Some(file) }),
+            Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(HOSTS_LOCK_RETRY);
+            }
+            Err(fs::TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "The hosts file is being changed by another ParentShield process; gave up after {}s",
+                        timeout.as_secs()
+                    ),
+                ));
+            }
+            Err(fs::TryLockError::Error(e)) => return Err(e),
+        }
+    }
+}
+
+/// Which lines are written for each blocked domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostsEntryOptions {
+    /// `::1` lines next to the `127.0.0.1` ones
+    pub ipv6: bool,
+    /// Lines for the `www.` form of the domain
+    pub www_variant: bool,
+}
+
+impl Default for HostsEntryOptions {
+    fn default() -> Self {
+        Self {
+            ipv6: true,
+            www_variant: true,
+        }
+    }
+}
+
+impl HostsEntryOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            ipv6: config.hosts_ipv6_entries,
+            www_variant: config.hosts_www_variant,
+        }
+    }
+
+    /// Options from the config, or every variant if it can't be read
+    pub fn configured() -> Self {
+        ConfigManager::new()
+            .and_then(|m| m.load())
+            .map(|config| Self::from_config(&config))
+            .unwrap_or_default()
+    }
+}
+
+/// How the ParentShield section differs from what should be blocked
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostsDrift {
+    /// The section markers are gone
+    pub section_missing: bool,
+    /// Listed but not expected (sorted)
+    pub added: Vec<String>,
+    /// Expected but not listed (sorted)
+    pub removed: Vec<String>,
+}
+
+impl HostsDrift {
+    pub fn is_intact(&self) -> bool {
+        !self.section_missing && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Reads and edits the ParentShield section of one hosts file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostsManager {
+    path: PathBuf,
+    /// Lock file every process editing `path` takes first
+    lock_path: PathBuf,
+    entry_options: HostsEntryOptions,
+}
+
+impl HostsManager {
+    /// Manager for the hosts file at `path`, locked through `<path>.lock`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            lock_path: with_suffix(&path, ".lock"),
+            path,
+            entry_options: HostsEntryOptions::default(),
+        }
+    }
+
+    /// Lock through `lock_path` instead of a file next to the hosts file
+    pub fn with_lock_path(mut self, lock_path: impl Into<PathBuf>) -> Self {
+        self.lock_path = lock_path.into();
+        self
+    }
+
+    /// Write only the entry variants `options` enables
+    pub fn with_entry_options(mut self, options: HostsEntryOptions) -> Self {
+        self.entry_options = options;
+        self
+    }
+
+    /// The OS hosts file, or `PARENTSHIELD_HOSTS_FILE` on systems that relocate it
+    pub fn system() -> Self {
+        let hosts = match std::env::var_os(HOSTS_PATH_ENV) {
+            Some(path) if !path.is_empty() => Self::new(path),
+            _ => Self::new(HOSTS_PATH),
+        };
+
+        // The GUI can't create files in /etc, so the lock lives in the
+        // daemon's runtime directory
+        #[cfg(unix)]
+        let hosts = hosts.with_lock_path(Path::new(crate::daemon::ipc::RUNTIME_DIR).join("hosts.lock"));
+
+        hosts
+    }
+
+    /// Wait for other processes editing this hosts file to finish
+    fn lock(&self) -> io::Result<HostsLock> {
+        lock_hosts(&self.lock_path, HOSTS_LOCK_TIMEOUT)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Current content with the ParentShield section replaced by `domains`
+    fn content_with_blocked(&self, domains: &HashSet<String>) -> io::Result<String> {
+        let content = fs::read_to_string(&self.path)?;
+        Ok(self.with_section_for(&content, domains))
+    }
+
+    /// `content` with the ParentShield section replaced by `domains`
+    fn with_section_for(&self, content: &str, domains: &HashSet<String>) -> String {
+        let mut new_section = String::new();
+        new_section.push_str(&format!("{}\n", MARKER_START));
+        new_section.push_str(&build_hosts_entries(domains, self.entry_options));
+        new_section.push_str(&format!("{}\n", MARKER_END));
+
+        with_parentshield_section(content, &new_section)
+    }
+
+    /// Current content without the ParentShield section
+    fn content_without_section(&self) -> io::Result<String> {
+        let content = fs::read_to_string(&self.path)?;
+        Ok(remove_parentshield_section(&content))
+    }
+
+    /// Copy of the last known-good hosts file, next to the file itself
+    pub fn backup_path(&self) -> io::Result<PathBuf> {
+        Ok(with_suffix(&resolve_hosts_target(&self.path)?, BACKUP_SUFFIX))
+    }
+
+    /// Back up the current file and atomically replace it with `content`,
+    /// through a symlink if there is one. Needs write access to the directory
+    /// (root for the system hosts file).
+    fn write(&self, content: &str) -> io::Result<()> {
+        let target = resolve_hosts_target(&self.path)?;
+        backup_hosts(&target)?;
+        replace_atomically(&target, content)
+    }
+
+    /// Put the backed-up hosts file back in place
+    pub fn restore_backup(&self) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let content = self.backup_content()?;
+        replace_atomically(&resolve_hosts_target(&self.path)?, &content)
+    }
+
+    fn backup_content(&self) -> io::Result<String> {
+        let backup = self.backup_path()?;
+        fs::read_to_string(&backup).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(io::ErrorKind::NotFound, format!("No hosts backup at {}", backup.display()))
+            } else {
+                e
+            }
+        })
+    }
+
+    /// Write the ParentShield section for `domains`
+    pub fn block_domains(&self, domains: &HashSet<String>) -> io::Result<()> {
+        self.update_blocked(|_| domains.clone())
+    }
+
+    /// Rewrite the ParentShield section under the hosts lock: `update` gets
+    /// the domains listed now and returns the ones to list
+    pub fn update_blocked<F>(&self, update: F) -> io::Result<()>
+    where
+        F: FnOnce(HashSet<String>) -> HashSet<String>,
+    {
+        let _lock = self.lock()?;
+        let content = fs::read_to_string(&self.path)?;
+        let domains = update(parse_blocked_domains(&content));
+        self.write(&self.with_section_for(&content, &domains))
+    }
+
+    /// Remove the ParentShield section
+    pub fn unblock_all(&self) -> io::Result<()> {
+        let _lock = self.lock()?;
+        let content = self.content_without_section()?;
+        self.write(&content)
+    }
+
+    /// Check if the ParentShield section exists
+    pub fn is_blocking_active(&self) -> bool {
+        fs::read_to_string(&self.path)
+            .map(|content| content.contains(MARKER_START))
+            .unwrap_or(false)
+    }
+
+    /// Domains listed in the ParentShield section
+    pub fn blocked_domains(&self) -> HashSet<String> {
+        fs::read_to_string(&self.path)
+            .map(|content| parse_blocked_domains(&content))
+            .unwrap_or_default()
+    }
+
+    /// Compare the ParentShield section with `expected`. An empty `expected`
+    /// is satisfied by no section at all.
+    pub fn drift(&self, expected: &HashSet<String>) -> HostsDrift {
+        // Listed domains are read back without their www. prefix
+        let expected: HashSet<&str> = expected.iter().map(|d| d.trim_start_matches("www.")).collect();
+        let listed = self.blocked_domains();
+
+        let mut added: Vec<String> = listed.iter().filter(|d| !expected.contains(d.as_str())).cloned().collect();
+        let mut removed: Vec<String> = expected
+            .iter()
+            .filter(|d| !listed.contains(**d))
+            .map(|d| d.to_string())
+            .collect();
+        added.sort();
+        removed.sort();
+
+        HostsDrift {
+            section_missing: !expected.is_empty() && !self.is_blocking_active(),
+            added,
+            removed,
+        }
+    }
+}
+
+/// Domains listed in the ParentShield section of hosts content
+fn parse_blocked_domains(content: &str) -> HashSet<String> {
+    let mut domains = HashSet::new();
+    let mut in_section = false;
+
+    for line in content.lines() {
+        if line.contains(MARKER_START) {
+            in_section = true;
+            continue;
+        }
+        if line.contains(MARKER_END) {
+            break;
+        }
+        if in_section {
+            // Parse "127.0.0.1 domain.com" format
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2 {
+                let domain = parts[1].trim_start_matches("www.");
+                domains.insert(domain.to_string());
+            }
+        }
+    }
+
+    domains
+}
+
+/// Block domains by adding them to the system hosts file
 pub fn block_domains(domains: &HashSet<String>) -> io::Result<()> {
     info!("Blocking {} domains via hosts file", domains.len());
 
@@ -20,72 +403,172 @@ pub fn block_domains(domains: &HashSet<String>) -> io::Result<()> {
         return Ok(());
     }
 
-    // Read current hosts file
-    let content = fs::read_to_string(HOSTS_PATH)?;
+    // Write back using pkexec for root access
+    let hosts = HostsManager::system().with_entry_options(HostsEntryOptions::configured());
+    let _lock = hosts.lock()?;
+    let new_content = hosts.content_with_blocked(domains)?;
+    write_hosts_file(&hosts, &new_content)?;
+
+    // Flush DNS cache
+    flush_dns_cache();
+
+    Ok(())
+}
 
-    // Remove any existing ParentShield section
-    let cleaned = remove_parentshield_section(&content);
+/// Export formats for the effective blocklist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Standard hosts file ("127.0.0.1 domain")
+    Hosts,
+    /// dnsmasq configuration ("address=/domain/")
+    Dnsmasq,
+}
 
-    // Build new ParentShield section
-    let mut new_section = String::new();
-    new_section.push_str(&format!("\n{}\n", MARKER_START));
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
 
-    for domain in domains {
-        new_section.push_str(&format!("127.0.0.1 {}\n", domain));
-        new_section.push_str(&format!("127.0.0.1 www.{}\n", domain));
-        new_section.push_str(&format!("::1 {}\n", domain));
-        new_section.push_str(&format!("::1 www.{}\n", domain));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hosts" => Ok(ExportFormat::Hosts),
+            "dnsmasq" => Ok(ExportFormat::Dnsmasq),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
     }
+}
 
-    new_section.push_str(&format!("{}\n", MARKER_END));
+/// Build hosts file entries for a set of domains (sorted for stable output)
+fn build_hosts_entries(domains: &HashSet<String>, options: HostsEntryOptions) -> String {
+    let mut sorted: Vec<&String> = domains.iter().collect();
+    sorted.sort();
 
-    // Write back using pkexec for root access
-    let new_content = format!("{}{}", cleaned.trim_end(), new_section);
-    write_hosts_file(&new_content)?;
+    let mut addresses = vec!["127.0.0.1"];
+    if options.ipv6 {
+        addresses.push("::1");
+    }
 
-    // Flush DNS cache
-    flush_dns_cache();
+    let mut entries = String::new();
+    for domain in sorted {
+        for address in &addresses {
+            entries.push_str(&format!("{} {}\n", address, domain));
+            if options.www_variant {
+                entries.push_str(&format!("{} www.{}\n", address, domain));
+            }
+        }
+    }
+    entries
+}
 
-    Ok(())
+/// Render blocked domains for use outside ParentShield (e.g. on a router).
+/// This never touches the live hosts file.
+pub fn export_blocklist(domains: &HashSet<String>, format: ExportFormat) -> String {
+    let mut output = String::from("# Exported from ParentShield\n");
+
+    match format {
+        ExportFormat::Hosts => output.push_str(&build_hosts_entries(domains, HostsEntryOptions::default())),
+        ExportFormat::Dnsmasq => {
+            // dnsmasq matches subdomains automatically, so no www. entries are needed
+            let mut sorted: Vec<&String> = domains.iter().collect();
+            sorted.sort();
+            for domain in sorted {
+                output.push_str(&format!("address=/{}/\n", domain));
+            }
+        }
+    }
+
+    output
 }
 
-/// Unblock all domains by removing ParentShield section from /etc/hosts
+/// Unblock all domains by removing ParentShield section from the hosts file
 pub fn unblock_all_domains() -> io::Result<()> {
-    let content = fs::read_to_string(HOSTS_PATH)?;
-    let cleaned = remove_parentshield_section(&content);
-    write_hosts_file(&cleaned)?;
+    let hosts = HostsManager::system();
+    let _lock = hosts.lock()?;
+    let cleaned = hosts.content_without_section()?;
+    write_hosts_file(&hosts, &cleaned)?;
     flush_dns_cache();
     Ok(())
 }
 
-/// Remove the ParentShield section from hosts content
-fn remove_parentshield_section(content: &str) -> String {
-    let mut result = String::new();
-    let mut in_section = false;
-
-    for line in content.lines() {
-        if line.contains(MARKER_START) {
-            in_section = true;
-            continue;
+/// Byte range of the first ParentShield section: from the start of the
+/// `MARKER_START` line through the newline ending the `MARKER_END` line, or to
+/// the end of the content if the end marker is missing
+fn section_range(content: &str) -> Option<Range<usize>> {
+    let mut start = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_end = offset + line.len();
+        match start {
+            None if line.contains(MARKER_START) => start = Some(offset),
+            Some(start) if line.contains(MARKER_END) => return Some(start..line_end),
+            _ => {}
         }
-        if line.contains(MARKER_END) {
-            in_section = false;
-            continue;
+        offset = line_end;
+    }
+
+    start.map(|start| start..content.len())
+}
+
+/// Hosts content with the ParentShield section swapped for `section`. An
+/// existing section is replaced where it is; otherwise `section` is appended
+/// after a newline if the content doesn't already end in one. Everything
+/// outside the markers is left untouched.
+fn with_parentshield_section(content: &str, section: &str) -> String {
+    match section_range(content) {
+        Some(range) => {
+            let mut result = String::with_capacity(content.len() + section.len());
+            result.push_str(&content[..range.start]);
+            result.push_str(section);
+            result.push_str(&remove_parentshield_section(&content[range.end..]));
+            result
         }
-        if !in_section {
-            result.push_str(line);
-            result.push('\n');
+        None => {
+            let mut result = content.to_string();
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(section);
+            result
         }
     }
+}
 
+/// Remove every ParentShield section from hosts content, leaving the rest
+/// byte-for-byte as it was
+fn remove_parentshield_section(content: &str) -> String {
+    let mut result = content.to_string();
+    while let Some(range) = section_range(&result) {
+        result.replace_range(range, "");
+    }
     result
 }
 
+/// Put the last known-good hosts file back, asking for elevated access if
+/// needed, e.g. after an interrupted write left name resolution broken
+pub fn restore_hosts_backup() -> io::Result<()> {
+    let hosts = HostsManager::system();
+    let _lock = hosts.lock()?;
+    let content = hosts.backup_content()?;
+    replace_hosts_file(&hosts, &content, false)?;
+    info!("Restored hosts file from {:?}", hosts.backup_path()?);
+    flush_dns_cache();
+    Ok(())
+}
+
 /// Write to hosts file with elevated privileges (cross-platform)
-fn write_hosts_file(content: &str) -> io::Result<()> {
+fn write_hosts_file(hosts: &HostsManager, content: &str) -> io::Result<()> {
+    replace_hosts_file(hosts, content, true)
+}
+
+/// Atomically replace the hosts file, first backing it up when `backup` is set.
+/// Tries a direct write and falls back to asking for elevated access.
+fn replace_hosts_file(hosts: &HostsManager, content: &str, backup: bool) -> io::Result<()> {
+    let target = resolve_hosts_target(hosts.path())?;
+
     // Try direct write first (might work if running as root)
     info!("Attempting to write hosts file...");
-    if fs::write(HOSTS_PATH, content).is_ok() {
+    let direct = if backup { backup_hosts(&target) } else { Ok(()) }
+        .and_then(|_| replace_atomically(&target, content));
+    if direct.is_ok() {
         info!("Successfully wrote hosts file directly");
         return Ok(());
     }
@@ -94,17 +577,17 @@ fn write_hosts_file(content: &str) -> io::Result<()> {
 
     #[cfg(target_os = "macos")]
     {
-        write_hosts_file_macos(content)
+        write_hosts_file_macos(&target, content, backup)
     }
 
     #[cfg(target_os = "linux")]
     {
-        write_hosts_file_linux(content)
+        write_hosts_file_linux(&target, content, backup)
     }
 
     #[cfg(target_os = "windows")]
     {
-        write_hosts_file_windows(content)
+        write_hosts_file_windows(&target, content, backup)
     }
 
     #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
@@ -116,9 +599,25 @@ fn write_hosts_file(content: &str) -> io::Result<()> {
     }
 }
 
+/// Shell script run with admin rights to back up `$1` (if `backup` is set and
+/// it isn't empty) and atomically replace it with what arrives on stdin.
+/// Nothing is read from a path another user could have planted or swapped.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn elevated_replace_script(backup: bool) -> String {
+    let mut script = String::from("set -e; ");
+    if backup {
+        script.push_str(&format!("if [ -s \"$1\" ]; then cp -p \"$1\" \"$1{}\"; fi; ", BACKUP_SUFFIX));
+    }
+    script.push_str(&format!(
+        "cat > \"$1{tmp}\"; chmod 644 \"$1{tmp}\"; mv -f \"$1{tmp}\" \"$1\"",
+        tmp = TEMP_SUFFIX
+    ));
+    script
+}
+
 /// Write hosts file on Linux using pkexec
 #[cfg(target_os = "linux")]
-fn write_hosts_file_linux(content: &str) -> io::Result<()> {
+fn write_hosts_file_linux(target: &Path, content: &str, backup: bool) -> io::Result<()> {
     // Check if pkexec is available
     let pkexec_check = Command::new("which")
         .arg("pkexec")
@@ -132,8 +631,11 @@ fn write_hosts_file_linux(content: &str) -> io::Result<()> {
         ));
     }
 
+    // The root shell reads the content from stdin, writes it next to the
+    // target and renames it into place
     let mut child = Command::new("pkexec")
-        .args(["tee", HOSTS_PATH])
+        .args(["sh", "-c", &elevated_replace_script(backup), "sh"])
+        .arg(target)
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::piped())
@@ -149,7 +651,6 @@ fn write_hosts_file_linux(content: &str) -> io::Result<()> {
     if let Some(mut stdin) = child.stdin.take() {
         stdin.write_all(content.as_bytes())?;
     }
-
     let output = child.wait_with_output()?;
 
     if !output.status.success() {
@@ -177,18 +678,31 @@ fn write_hosts_file_linux(content: &str) -> io::Result<()> {
 
 /// Write hosts file on macOS using osascript for admin privileges
 #[cfg(target_os = "macos")]
-fn write_hosts_file_macos(content: &str) -> io::Result<()> {
-    use std::env;
-
-    // Write content to a temporary file first
-    let temp_path = env::temp_dir().join("parentshield_hosts_temp");
-    fs::write(&temp_path, content)?;
-
-    // Use osascript to copy with admin privileges
+fn write_hosts_file_macos(target: &Path, content: &str, backup: bool) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // `do shell script` has no stdin to pipe into, so stage the content in a
+    // new file with a random name that only we can open. create_new refuses a
+    // file someone planted under that name beforehand.
+    let temp_path = std::env::temp_dir().join(format!("parentshield_hosts_{:016x}", rand::random::<u64>()));
+    let mut staged = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)?;
+    staged.write_all(content.as_bytes())?;
+    drop(staged);
+
+    // Use osascript to back up and replace with admin privileges
+    let command = format!(
+        "sh -c '{}' sh '{}' < '{}'",
+        elevated_replace_script(backup),
+        target.display(),
+        temp_path.display()
+    );
     let script = format!(
-        r#"do shell script "cp '{}' '{}'" with administrator privileges"#,
-        temp_path.display(),
-        HOSTS_PATH
+        r#"do shell script "{}" with administrator privileges"#,
+        command.replace('\\', "\\\\").replace('"', "\\\"")
     );
 
     let output = Command::new("osascript")
@@ -225,13 +739,12 @@ fn write_hosts_file_macos(content: &str) -> io::Result<()> {
 
 /// Write hosts file on Windows (hosts file location is different)
 #[cfg(target_os = "windows")]
-fn write_hosts_file_windows(content: &str) -> io::Result<()> {
-    // Windows hosts file is at C:\Windows\System32\drivers\etc\hosts
+fn write_hosts_file_windows(target: &Path, content: &str, backup: bool) -> io::Result<()> {
     // Tauri apps on Windows can request admin via manifest, but for now
     // we'll try direct write which works if app is run as admin
-    let windows_hosts = r"C:\Windows\System32\drivers\etc\hosts";
-
-    fs::write(windows_hosts, content).map_err(|e| {
+    let written = if backup { backup_hosts(target) } else { Ok(()) }
+        .and_then(|_| replace_atomically(target, content));
+    written.map_err(|e| {
         error!("Failed to write Windows hosts file: {}", e);
         io::Error::new(
             io::ErrorKind::PermissionDenied,
@@ -308,40 +821,28 @@ fn flush_dns_cache_windows() {
 
 /// Check if ParentShield section exists in hosts file
 pub fn is_blocking_active() -> bool {
-    if let Ok(content) = fs::read_to_string(HOSTS_PATH) {
-        content.contains(MARKER_START)
-    } else {
-        false
-    }
+    HostsManager::system().is_blocking_active()
 }
 
 /// Get currently blocked domains from hosts file
 pub fn get_blocked_domains() -> HashSet<String> {
-    let mut domains = HashSet::new();
-
-    if let Ok(content) = fs::read_to_string(HOSTS_PATH) {
-        let mut in_section = false;
+    HostsManager::system().blocked_domains()
+}
 
-        for line in content.lines() {
-            if line.contains(MARKER_START) {
-                in_section = true;
-                continue;
-            }
-            if line.contains(MARKER_END) {
-                break;
-            }
-            if in_section {
-                // Parse "127.0.0.1 domain.com" format
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    let domain = parts[1].trim_start_matches("www.");
-                    domains.insert(domain.to_string());
-                }
-            }
-        }
+/// Check that the hosts file blocks exactly `expected`, logging what was
+/// added or removed if it doesn't
+pub fn verify_block(expected: &HashSet<String>) -> bool {
+    let drift = HostsManager::system().drift(expected);
+    if drift.section_missing {
+        warn!("ParentShield section is missing from the hosts file");
     }
-
-    domains
+    if !drift.added.is_empty() {
+        warn!("Hosts file blocks domains it shouldn't: {:?}", drift.added);
+    }
+    if !drift.removed.is_empty() {
+        warn!("Hosts file no longer blocks: {:?}", drift.removed);
+    }
+    drift.is_intact()
 }
 
 /// Block domains by directly writing to /etc/hosts (for daemon running as root)
@@ -354,29 +855,9 @@ pub fn block_domains_direct(domains: &HashSet<String>) -> io::Result<()> {
         return Ok(());
     }
 
-    // Read current hosts file
-    let content = fs::read_to_string(HOSTS_PATH)?;
-
-    // Remove any existing ParentShield section
-    let cleaned = remove_parentshield_section(&content);
-
-    // Build new ParentShield section
-    let mut new_section = String::new();
-    new_section.push_str(&format!("\n{}\n", MARKER_START));
-
-    for domain in domains {
-        new_section.push_str(&format!("127.0.0.1 {}\n", domain));
-        new_section.push_str(&format!("127.0.0.1 www.{}\n", domain));
-        new_section.push_str(&format!("::1 {}\n", domain));
-        new_section.push_str(&format!("::1 www.{}\n", domain));
-    }
-
-    new_section.push_str(&format!("{}\n", MARKER_END));
-
-    // Write directly (assumes running as root)
-    let new_content = format!("{}{}", cleaned.trim_end(), new_section);
-    fs::write(HOSTS_PATH, new_content)?;
-
+    HostsManager::system()
+        .with_entry_options(HostsEntryOptions::configured())
+        .block_domains(domains)?;
     info!("Successfully wrote hosts file directly");
 
     // Flush DNS cache
@@ -387,9 +868,303 @@ pub fn block_domains_direct(domains: &HashSet<String>) -> io::Result<()> {
 
 /// Unblock all domains by directly writing to /etc/hosts (for daemon running as root)
 pub fn unblock_all_domains_direct() -> io::Result<()> {
-    let content = fs::read_to_string(HOSTS_PATH)?;
-    let cleaned = remove_parentshield_section(&content);
-    fs::write(HOSTS_PATH, cleaned)?;
+    HostsManager::system().unblock_all()?;
     flush_dns_cache();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_domains() -> HashSet<String> {
+        ["roblox.com".to_string(), "chatgpt.com".to_string()].into()
+    }
+
+    #[test]
+    fn test_export_hosts_format() {
+        let output = export_blocklist(&sample_domains(), ExportFormat::Hosts);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[0].starts_with('#'));
+        assert_eq!(lines[1], "127.0.0.1 chatgpt.com");
+        assert_eq!(lines[2], "127.0.0.1 www.chatgpt.com");
+        assert!(lines.contains(&"::1 roblox.com"));
+        assert_eq!(lines.len(), 9);
+    }
+
+    #[test]
+    fn test_entry_variants_follow_options() {
+        let domains: HashSet<String> = ["roblox.com".to_string()].into();
+        let ipv4_only = HostsEntryOptions {
+            ipv6: false,
+            www_variant: true,
+        };
+        assert_eq!(build_hosts_entries(&domains, ipv4_only), "127.0.0.1 roblox.com\n127.0.0.1 www.roblox.com\n");
+        let no_www = HostsEntryOptions {
+            ipv6: true,
+            www_variant: false,
+        };
+        assert_eq!(build_hosts_entries(&domains, no_www), "127.0.0.1 roblox.com\n::1 roblox.com\n");
+
+        // Parsing doesn't depend on which variants were written
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+        for options in [ipv4_only, no_www, HostsEntryOptions { ipv6: false, www_variant: false }] {
+            let hosts = HostsManager::new(&path).with_entry_options(options);
+            hosts.block_domains(&sample_domains()).unwrap();
+            assert_eq!(hosts.blocked_domains(), sample_domains());
+        }
+    }
+
+    #[test]
+    fn test_export_dnsmasq_format() {
+        let output = export_blocklist(&sample_domains(), ExportFormat::Dnsmasq);
+        let lines: Vec<&str> = output.lines().skip(1).collect();
+
+        assert_eq!(lines, vec!["address=/chatgpt.com/", "address=/roblox.com/"]);
+    }
+
+    #[test]
+    fn test_export_format_parse() {
+        assert_eq!("hosts".parse::<ExportFormat>(), Ok(ExportFormat::Hosts));
+        assert_eq!("DNSMASQ".parse::<ExportFormat>(), Ok(ExportFormat::Dnsmasq));
+        assert!("pihole".parse::<ExportFormat>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_hosts_survives_block_cycle() {
+        let temp = tempfile::tempdir().unwrap();
+        let managed = temp.path().join("managed-hosts");
+        let link = temp.path().join("hosts");
+        fs::write(&managed, "127.0.0.1 localhost\n").unwrap();
+        std::os::unix::fs::symlink(&managed, &link).unwrap();
+
+        let hosts = HostsManager::new(&link);
+        hosts.block_domains(&sample_domains()).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        let blocked = fs::read_to_string(&managed).unwrap();
+        assert!(blocked.contains(MARKER_START));
+        assert!(blocked.contains("roblox.com"));
+
+        hosts.unblock_all().unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&managed).unwrap(), "127.0.0.1 localhost\n");
+    }
+
+    #[test]
+    fn test_writes_keep_backup_and_restore() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        let original = "127.0.0.1 localhost\n";
+        fs::write(&path, original).unwrap();
+        let hosts = HostsManager::new(&path);
+
+        hosts.block_domains(&sample_domains()).unwrap();
+        assert_eq!(hosts.backup_path().unwrap(), temp.path().join("hosts.gameblocker.bak"));
+        assert_eq!(fs::read_to_string(hosts.backup_path().unwrap()).unwrap(), original);
+        assert!(!temp.path().join("hosts.parentshield.tmp").exists());
+
+        // A truncated file is not known-good and doesn't replace the backup
+        fs::write(&path, "").unwrap();
+        hosts.unblock_all().unwrap();
+        assert_eq!(fs::read_to_string(hosts.backup_path().unwrap()).unwrap(), original);
+
+        hosts.restore_backup().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_elevated_script_backs_up_only_when_asked() {
+        assert!(elevated_replace_script(true).contains("cp -p \"$1\" \"$1.gameblocker.bak\""));
+        assert!(!elevated_replace_script(false).contains(BACKUP_SUFFIX));
+        assert!(elevated_replace_script(false).ends_with("mv -f \"$1.parentshield.tmp\" \"$1\""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_elevated_script_takes_content_from_stdin() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("hosts");
+        fs::write(&target, "127.0.0.1 localhost\n").unwrap();
+
+        let mut child = Command::new("sh")
+            .args(["-c", &elevated_replace_script(true), "sh"])
+            .arg(&target)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"0.0.0.0 roblox.com\n").unwrap();
+        assert!(child.wait().unwrap().success());
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "0.0.0.0 roblox.com\n");
+        let backup = temp.path().join(format!("hosts{}", BACKUP_SUFFIX));
+        assert_eq!(fs::read_to_string(backup).unwrap(), "127.0.0.1 localhost\n");
+    }
+
+    #[test]
+    fn test_block_cycle_preserves_user_content() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        let before = "127.0.0.1 localhost\n\n\n# my printer\n192.168.1.5 printer\n";
+        let after = "\n# added later\n10.0.0.2 nas  # keep spacing\n\n";
+        fs::write(&path, format!("{}{}", before, after)).unwrap();
+        let hosts = HostsManager::new(&path);
+
+        hosts.block_domains(&sample_domains()).unwrap();
+        let blocked = fs::read_to_string(&path).unwrap();
+        assert!(blocked.starts_with(&format!("{}{}{}\n", before, after, MARKER_START)));
+
+        // Move the section between the user's lines; re-blocking keeps it there
+        let section = &blocked[section_range(&blocked).unwrap()];
+        fs::write(&path, format!("{}{}{}", before, section, after)).unwrap();
+        let fewer: HashSet<String> = ["roblox.com".to_string()].into_iter().collect();
+        hosts.block_domains(&fewer).unwrap();
+        let reblocked = fs::read_to_string(&path).unwrap();
+        assert!(reblocked.starts_with(&format!("{}{}", before, MARKER_START)));
+        assert!(reblocked.ends_with(&format!("{}\n{}", MARKER_END, after)));
+        assert_eq!(hosts.blocked_domains(), fewer);
+
+        hosts.unblock_all().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}{}", before, after));
+    }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_domain() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let hosts = HostsManager::new(&path);
+                std::thread::spawn(move || {
+                    for i in 0..5 {
+                        let domain = format!("game{}-{}.example", worker, i);
+                        hosts
+                            .update_blocked(|mut current| {
+                                current.insert(domain);
+                                current
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let blocked = HostsManager::new(&path).blocked_domains();
+        assert_eq!(blocked.len(), 40);
+        assert!(blocked.contains("game7-4.example"));
+        assert!(fs::read_to_string(&path).unwrap().starts_with("127.0.0.1 localhost\n"));
+    }
+
+    #[test]
+    fn test_lock_times_out_instead_of_hanging() {
+        let temp = tempfile::tempdir().unwrap();
+        let lock_path = temp.path().join("hosts.lock");
+        let _held = lock_hosts(&lock_path, Duration::ZERO).unwrap();
+
+        let started = Instant::now();
+        let err = lock_hosts(&lock_path, Duration::from_millis(200)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_section_appended_after_missing_newline() {
+        let section = format!("{}\n{}\n", MARKER_START, MARKER_END);
+        assert_eq!(with_parentshield_section("127.0.0.1 localhost", &section), format!("127.0.0.1 localhost\n{}", section));
+        assert_eq!(with_parentshield_section("127.0.0.1 localhost\n", &section), format!("127.0.0.1 localhost\n{}", section));
+        assert_eq!(with_parentshield_section("", &section), section);
+        // A section without its end marker runs to the end of the file
+        assert_eq!(remove_parentshield_section(&format!("a\n{}\n127.0.0.1 x", MARKER_START)), "a\n");
+    }
+
+    #[test]
+    fn test_drift_reports_tampered_section() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+        let hosts = HostsManager::new(&path);
+        let expected = sample_domains();
+
+        hosts.block_domains(&expected).unwrap();
+        assert!(hosts.drift(&expected).is_intact());
+
+        // One domain's lines deleted, another added by hand
+        let content = fs::read_to_string(&path).unwrap();
+        let tampered: String = content
+            .lines()
+            .filter(|line| !line.contains("roblox.com"))
+            .map(|line| {
+                if line == MARKER_END {
+                    format!("127.0.0.1 example.org\n{}\n", line)
+                } else {
+                    format!("{}\n", line)
+                }
+            })
+            .collect();
+        fs::write(&path, tampered).unwrap();
+
+        let drift = hosts.drift(&expected);
+        assert!(!drift.section_missing);
+        assert_eq!(drift.added, vec!["example.org".to_string()]);
+        assert_eq!(drift.removed, vec!["roblox.com".to_string()]);
+
+        // Restoring puts it back as it was
+        hosts.block_domains(&expected).unwrap();
+        assert!(hosts.drift(&expected).is_intact());
+    }
+
+    #[test]
+    fn test_drift_reports_removed_section() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+        let hosts = HostsManager::new(&path);
+
+        hosts.block_domains(&sample_domains()).unwrap();
+        let stripped = remove_parentshield_section(&fs::read_to_string(&path).unwrap());
+        fs::write(&path, stripped).unwrap();
+
+        let drift = hosts.drift(&sample_domains());
+        assert!(drift.section_missing);
+        assert_eq!(drift.removed.len(), 2);
+
+        // Nothing expected and nothing there is intact
+        assert!(hosts.drift(&HashSet::new()).is_intact());
+    }
+
+    #[test]
+    fn test_block_parse_unblock_temp_hosts_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("hosts");
+        let original = "127.0.0.1 localhost\n::1 localhost\n";
+        fs::write(&path, original).unwrap();
+        let hosts = HostsManager::new(&path);
+        assert!(!hosts.is_blocking_active());
+
+        hosts.block_domains(&sample_domains()).unwrap();
+        assert!(hosts.is_blocking_active());
+        assert_eq!(hosts.blocked_domains(), sample_domains());
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(original));
+        assert!(content.contains("127.0.0.1 www.roblox.com"));
+
+        // Re-blocking replaces the section rather than appending a second one
+        let fewer: HashSet<String> = ["roblox.com".to_string()].into_iter().collect();
+        hosts.block_domains(&fewer).unwrap();
+        assert_eq!(hosts.blocked_domains(), fewer);
+        assert_eq!(fs::read_to_string(&path).unwrap().matches(MARKER_START).count(), 1);
+
+        hosts.unblock_all().unwrap();
+        assert!(!hosts.is_blocking_active());
+        assert!(hosts.blocked_domains().is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+}
@@ -1,21 +1,34 @@
+pub mod audit;
 pub mod blocklists;
 pub mod browser;
+pub mod capabilities;
+pub mod defaults;
 pub mod hosts;
 pub mod network;
+pub mod notify;
 pub mod process;
+pub mod report;
+pub mod subscriptions;
 
+pub use audit::*;
 pub use blocklists::*;
 pub use browser::*;
+pub use capabilities::*;
 pub use hosts::*;
+pub use report::{ApplyReport, ApplyWarning, WarningCode, WarningSeverity};
 
 // Re-export network blocking functions for Linux
 #[cfg(target_os = "linux")]
 pub use network::linux::{
-    apply_network_blocking, block_doh_providers, is_doh_blocked, remove_network_blocking,
-    unblock_doh_providers,
+    apply_network_blocking, block_doh_providers, is_doh_blocked, list_blocked_doh_ips,
+    remove_network_blocking, unblock_doh_providers,
     // Direct functions for daemon (running as root)
     apply_network_blocking_direct, block_doh_providers_direct, remove_network_blocking_direct,
     unblock_doh_providers_direct,
+    // Safety net against rules outliving the daemon
+    remove_stale_firewall_rules, touch_firewall_heartbeat,
+    // Self-heal after the rules were flushed
+    firewall_rules_present,
 };
 
 // Stub implementations for non-Linux platforms
@@ -24,6 +37,11 @@ pub fn is_doh_blocked() -> bool {
     false // Network firewall blocking not implemented on this platform
 }
 
+#[cfg(not(target_os = "linux"))]
+pub fn list_blocked_doh_ips() -> Vec<String> {
+    Vec::new() // Network firewall blocking not implemented on this platform
+}
+
 #[cfg(not(target_os = "linux"))]
 pub fn apply_network_blocking() -> Result<(), Box<dyn std::error::Error>> {
     Ok(()) // No-op on non-Linux
@@ -0,0 +1,27 @@
+//! Common interface for network-level enforcement backends.
+
+use thiserror::Error;
+
+/// Errors reported by firewall backends
+#[derive(Error, Debug)]
+pub enum BackendError {
+    #[error("Backend unavailable: {0}")]
+    Unavailable(String),
+    #[error("Backend request failed: {0}")]
+    RequestFailed(String),
+}
+
+/// A place network blocking rules can be pushed to (local firewall, router, ...)
+pub trait FirewallBackend: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &'static str;
+
+    /// Install the blocking rules
+    fn apply(&self) -> Result<(), BackendError>;
+
+    /// Remove the blocking rules
+    fn remove(&self) -> Result<(), BackendError>;
+
+    /// Whether the blocking rules are currently in place
+    fn is_active(&self) -> bool;
+}
@@ -0,0 +1,190 @@
+//! Record of DNS queries the proxy refused, for parents to review.
+//! Entries are JSON lines in `blocked-dns.log` under `BLOCK_LOG_DIR`.
+//! The proxy hands entries to a bounded channel and a writer thread appends
+//! them, so a slow disk never holds up DNS answers; when the channel is full
+//! entries are dropped rather than waited on.
+
+use crate::daemon::logs::{LogRetention, LogSettings, RotatingLog};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// Name of the log in `LogSettings::per_log`
+pub const BLOCK_LOG_NAME: &str = "blocked-dns";
+
+/// Size at which the block log rolls over to `.1`
+pub const DEFAULT_BLOCK_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Entries waiting to be written before new ones are dropped
+const BLOCK_LOG_CHANNEL_CAPACITY: usize = 1024;
+
+/// One refused DNS query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockedQuery {
+    pub timestamp: DateTime<Utc>,
+    pub domain: String,
+    pub client: IpAddr,
+}
+
+/// Directory the block log is kept in, next to the daemon's own logs
+pub const BLOCK_LOG_DIR: &str = crate::daemon::logs::LOG_DIR;
+
+/// Where the block log is written
+pub fn block_log_path() -> PathBuf {
+    Path::new(BLOCK_LOG_DIR).join(format!("{}.log", BLOCK_LOG_NAME))
+}
+
+/// Caps for the block log: a per-log override from the config if there is
+/// one, otherwise 5 MB with a single `.1` file kept
+pub fn block_log_retention(settings: &LogSettings) -> LogRetention {
+    settings
+        .per_log
+        .get(BLOCK_LOG_NAME)
+        .copied()
+        .unwrap_or(LogRetention {
+            max_bytes: DEFAULT_BLOCK_LOG_BYTES,
+            max_files: 1,
+            ..settings.default
+        })
+}
+
+/// Handle the DNS proxy logs blocked queries through
+#[derive(Clone)]
+pub struct BlockLogger {
+    sender: mpsc::Sender<BlockedQuery>,
+}
+
+impl BlockLogger {
+    /// Open the log at `path` and start the writer thread
+    pub fn start(path: impl Into<PathBuf>, retention: LogRetention) -> io::Result<Self> {
+        let log = RotatingLog::open(path, retention)?;
+        let (sender, receiver) = mpsc::channel(BLOCK_LOG_CHANNEL_CAPACITY);
+        std::thread::Builder::new()
+            .name("dns-block-log".to_string())
+            .spawn(move || write_entries(receiver, log))?;
+        Ok(Self { sender })
+    }
+
+    /// Queue an entry without waiting; dropped if the writer is behind
+    pub fn log(&self, domain: &str, client: IpAddr) {
+        let entry = BlockedQuery {
+            timestamp: Utc::now(),
+            domain: domain.to_string(),
+            client,
+        };
+        if self.sender.try_send(entry).is_err() {
+            tracing::debug!("Block log is behind, dropped entry for {}", domain);
+        }
+    }
+}
+
+/// Append queued entries until every `BlockLogger` is dropped
+fn write_entries(mut receiver: mpsc::Receiver<BlockedQuery>, mut log: RotatingLog) {
+    while let Some(entry) = receiver.blocking_recv() {
+        let written = serde_json::to_string(&entry)
+            .map_err(io::Error::from)
+            .and_then(|line| writeln!(log, "{}", line))
+            .and_then(|_| log.flush());
+        if let Err(e) = written {
+            tracing::warn!("Failed to write DNS block log: {}", e);
+        }
+    }
+}
+
+/// The last `limit` entries, oldest first, reading back into `.1` if the
+/// active file has fewer. A missing log has no entries.
+pub fn recent_blocks(path: &Path, limit: usize) -> io::Result<Vec<BlockedQuery>> {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+
+    let mut entries = Vec::new();
+    for file in [PathBuf::from(rotated), path.to_path_buf()] {
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        // Skip lines cut short by a crash rather than failing the whole read
+        entries.extend(content.lines().filter_map(|line| serde_json::from_str(line).ok()));
+    }
+
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+    fn entry(domain: &str) -> BlockedQuery {
+        BlockedQuery {
+            timestamp: Utc::now(),
+            domain: domain.to_string(),
+            client: CLIENT,
+        }
+    }
+
+    #[test]
+    fn test_entries_rotate_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocked-dns.log");
+        let line_len = serde_json::to_string(&entry("game0.example")).unwrap().len() as u64 + 1;
+        let retention = LogRetention {
+            max_bytes: line_len * 3,
+            max_age_days: 14,
+            max_files: 1,
+        };
+
+        let (sender, receiver) = mpsc::channel(16);
+        for i in 0..5 {
+            sender.try_send(entry(&format!("game{}.example", i))).unwrap();
+        }
+        drop(sender);
+        write_entries(receiver, RotatingLog::open(&path, retention).unwrap());
+
+        assert!(dir.path().join("blocked-dns.log.1").exists());
+        let recent = recent_blocks(&path, 4).unwrap();
+        let domains: Vec<_> = recent.iter().map(|e| e.domain.as_str()).collect();
+        assert_eq!(domains, ["game1.example", "game2.example", "game3.example", "game4.example"]);
+        assert_eq!(recent[0].client, CLIENT);
+    }
+
+    #[test]
+    fn test_missing_log_and_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocked-dns.log");
+        assert!(recent_blocks(&path, 10).unwrap().is_empty());
+
+        let line = serde_json::to_string(&entry("roblox.com")).unwrap();
+        fs::write(&path, format!("{}\n{{\"timestamp\":", line)).unwrap();
+        assert_eq!(recent_blocks(&path, 10).unwrap(), vec![serde_json::from_str(&line).unwrap()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_block_log_location() {
+        assert_eq!(block_log_path(), Path::new("/var/log/parentshield/blocked-dns.log"));
+    }
+
+    #[test]
+    fn test_retention_defaults_and_override() {
+        let mut settings = LogSettings::default();
+        let retention = block_log_retention(&settings);
+        assert_eq!(retention.max_bytes, DEFAULT_BLOCK_LOG_BYTES);
+        assert_eq!(retention.max_files, 1);
+
+        let custom = LogRetention {
+            max_bytes: 1024,
+            ..LogRetention::default()
+        };
+        settings.per_log.insert(BLOCK_LOG_NAME.to_string(), custom);
+        assert_eq!(block_log_retention(&settings), custom);
+    }
+}
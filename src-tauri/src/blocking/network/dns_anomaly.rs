@@ -0,0 +1,247 @@
+//! DNS query burst detection for the DNS proxy.
+//! A client that suddenly resolves many names under a game CDN or gaming TLD
+//! during a block window is often trying subdomains the blocklist doesn't
+//! cover. Bursts over the configured rate are flagged and the client is cut
+//! off from that suffix for a while. Off by default.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Tracked (client, suffix) pairs above which idle entries are pruned
+const MAX_TRACKED_KEYS: usize = 1024;
+
+/// Suffixes watched for bursts unless the config lists its own
+const DEFAULT_SUSPICIOUS_SUFFIXES: &[&str] = &[
+    "gg",
+    "game",
+    "games",
+    "rbxcdn.com",
+    "roblox.com",
+    "steamcontent.com",
+    "epicgames.com",
+    "fortnite.com",
+    "minecraft.net",
+];
+
+/// Thresholds for burst detection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsAnomalyConfig {
+    pub enabled: bool,
+    /// Length of the sliding window queries are counted over
+    pub window_secs: u64,
+    /// Queries per client and suffix allowed within the window
+    pub max_queries: u32,
+    /// How long a flagged client stays blocked from the suffix
+    pub block_secs: u64,
+    /// Domain suffixes (TLDs or CDN domains) that are watched
+    pub suspicious_suffixes: Vec<String>,
+}
+
+impl Default for DnsAnomalyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 10,
+            max_queries: 30,
+            block_secs: 300,
+            suspicious_suffixes: DEFAULT_SUSPICIOUS_SUFFIXES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// A burst that crossed the threshold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsAnomaly {
+    pub client: IpAddr,
+    pub suffix: String,
+    /// Queries seen within the window when the burst was flagged
+    pub queries: u32,
+    pub blocked_for_secs: u64,
+}
+
+/// What the proxy should do with a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnomalyVerdict {
+    Allow,
+    /// This query tipped the client over the threshold; block it and report
+    Flagged(DnsAnomaly),
+    /// The client is still serving a block for an earlier burst
+    Blocked,
+}
+
+/// Sliding-window query counts per client and suspicious suffix
+#[derive(Debug, Default)]
+pub struct QueryRateTracker {
+    config: DnsAnomalyConfig,
+    windows: HashMap<(IpAddr, String), VecDeque<Instant>>,
+    blocked_until: HashMap<(IpAddr, String), Instant>,
+}
+
+impl QueryRateTracker {
+    pub fn new(config: DnsAnomalyConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Replace the thresholds; counts and active blocks are dropped
+    pub fn set_config(&mut self, config: DnsAnomalyConfig) {
+        *self = Self::new(config);
+    }
+
+    /// Watched suffix `domain` falls under, if any
+    fn suspicious_suffix(&self, domain: &str) -> Option<&str> {
+        let domain = domain.trim_end_matches('.');
+        self.config
+            .suspicious_suffixes
+            .iter()
+            .map(|s| s.trim_start_matches('.'))
+            .find(|suffix| {
+                domain.eq_ignore_ascii_case(suffix)
+                    || domain
+                        .len()
+                        .checked_sub(suffix.len() + 1)
+                        .is_some_and(|dot| {
+                            domain.as_bytes()[dot] == b'.'
+                                && domain[dot + 1..].eq_ignore_ascii_case(suffix)
+                        })
+            })
+    }
+
+    /// Count a query from `client` for `domain` at `now`
+    pub fn record(&mut self, client: IpAddr, domain: &str, now: Instant) -> AnomalyVerdict {
+        if !self.config.enabled {
+            return AnomalyVerdict::Allow;
+        }
+        let Some(suffix) = self.suspicious_suffix(domain) else {
+            return AnomalyVerdict::Allow;
+        };
+        let key = (client, suffix.to_string());
+
+        if self.blocked_until.get(&key).is_some_and(|until| *until > now) {
+            return AnomalyVerdict::Blocked;
+        }
+
+        if self.windows.len() > MAX_TRACKED_KEYS {
+            self.prune(now);
+        }
+
+        let window = Duration::from_secs(self.config.window_secs);
+        let queries = self.windows.entry(key.clone()).or_default();
+        while queries
+            .front()
+            .is_some_and(|seen| now.duration_since(*seen) >= window)
+        {
+            queries.pop_front();
+        }
+        queries.push_back(now);
+
+        let count = queries.len() as u32;
+        if count <= self.config.max_queries {
+            return AnomalyVerdict::Allow;
+        }
+
+        self.windows.remove(&key);
+        self.blocked_until
+            .insert(key.clone(), now + Duration::from_secs(self.config.block_secs));
+        AnomalyVerdict::Flagged(DnsAnomaly {
+            client,
+            suffix: key.1,
+            queries: count,
+            blocked_for_secs: self.config.block_secs,
+        })
+    }
+
+    /// Forget windows with nothing recent and blocks that have run out
+    fn prune(&mut self, now: Instant) {
+        let window = Duration::from_secs(self.config.window_secs);
+        self.windows
+            .retain(|_, queries| queries.back().is_some_and(|last| now.duration_since(*last) < window));
+        self.blocked_until.retain(|_, until| *until > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+    fn tracker(max_queries: u32) -> QueryRateTracker {
+        QueryRateTracker::new(DnsAnomalyConfig {
+            enabled: true,
+            window_secs: 10,
+            max_queries,
+            block_secs: 60,
+            suspicious_suffixes: vec!["rbxcdn.com".to_string(), "gg".to_string()],
+        })
+    }
+
+    #[test]
+    fn test_window_slides() {
+        let mut tracker = tracker(3);
+        let start = Instant::now();
+
+        for i in 0..3 {
+            let domain = format!("c{}.rbxcdn.com", i);
+            assert_eq!(tracker.record(CLIENT, &domain, start), AnomalyVerdict::Allow);
+        }
+        // The first three have aged out, so this starts a fresh window
+        let later = start + Duration::from_secs(10);
+        assert_eq!(tracker.record(CLIENT, "c3.rbxcdn.com", later), AnomalyVerdict::Allow);
+        assert_eq!(tracker.windows[&(CLIENT, "rbxcdn.com".to_string())].len(), 1);
+    }
+
+    #[test]
+    fn test_burst_is_flagged_then_blocked() {
+        let mut tracker = tracker(3);
+        let start = Instant::now();
+
+        for i in 0..3 {
+            tracker.record(CLIENT, &format!("c{}.rbxcdn.com", i), start);
+        }
+        let verdict = tracker.record(CLIENT, "c4.rbxcdn.com", start + Duration::from_secs(1));
+        assert_eq!(
+            verdict,
+            AnomalyVerdict::Flagged(DnsAnomaly {
+                client: CLIENT,
+                suffix: "rbxcdn.com".to_string(),
+                queries: 4,
+                blocked_for_secs: 60,
+            })
+        );
+
+        let during = start + Duration::from_secs(30);
+        assert_eq!(tracker.record(CLIENT, "c5.rbxcdn.com", during), AnomalyVerdict::Blocked);
+        // Other clients and other suffixes are unaffected
+        let other = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 21));
+        assert_eq!(tracker.record(other, "c5.rbxcdn.com", during), AnomalyVerdict::Allow);
+        assert_eq!(tracker.record(CLIENT, "play.gg", during), AnomalyVerdict::Allow);
+
+        let after = start + Duration::from_secs(62);
+        assert_eq!(tracker.record(CLIENT, "c6.rbxcdn.com", after), AnomalyVerdict::Allow);
+    }
+
+    #[test]
+    fn test_unwatched_domains_and_disabled_config() {
+        let mut tracker = tracker(0);
+        let now = Instant::now();
+        assert_eq!(tracker.record(CLIENT, "example.com", now), AnomalyVerdict::Allow);
+        assert_eq!(tracker.record(CLIENT, "notrbxcdn.com", now), AnomalyVerdict::Allow);
+        assert!(matches!(
+            tracker.record(CLIENT, "rbxcdn.com.", now),
+            AnomalyVerdict::Flagged(_)
+        ));
+
+        let mut disabled = QueryRateTracker::new(DnsAnomalyConfig::default());
+        for _ in 0..100 {
+            assert_eq!(disabled.record(CLIENT, "x.rbxcdn.com", now), AnomalyVerdict::Allow);
+        }
+    }
+}
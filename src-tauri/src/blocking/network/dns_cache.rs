@@ -0,0 +1,189 @@
+//! Answer cache for the DNS proxy.
+//! Upstream responses are kept, keyed by the question and its EDNS flags, until the
+//! smallest TTL in their answer section runs out. When the cache is full the
+//! least recently used entry is evicted.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Entries kept unless the proxy is configured otherwise
+pub const DEFAULT_CACHE_ENTRIES: usize = 512;
+
+/// Longest time an answer is served from the cache, whatever its TTL says
+const MAX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// What a cached answer depends on in the query
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Question name, lowercase
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    /// Whether the query carried an OPT record. Answers to EDNS queries can be
+    /// larger and carry an OPT record of their own.
+    pub edns: bool,
+    /// DNSSEC OK bit of that OPT record; answers to it carry signatures
+    pub dnssec_ok: bool,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: Vec<u8>,
+    expires: Instant,
+    last_used: u64,
+}
+
+/// TTL-respecting response cache with LRU eviction
+#[derive(Debug)]
+pub struct DnsCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Incremented on every use; the entry with the lowest value is evicted first
+    clock: u64,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_ENTRIES)
+    }
+}
+
+impl DnsCache {
+    /// A cache holding at most `capacity` answers; 0 disables caching
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Change the entry limit, evicting least recently used entries to fit
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.evict_lru();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Cached response for `key` if it hasn't expired by `now`
+    pub fn get(&mut self, key: &CacheKey, now: Instant) -> Option<Vec<u8>> {
+        let expired = self.entries.get(key)?.expires <= now;
+        if expired {
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(entry.response.clone())
+    }
+
+    /// Store `response` for `ttl` (capped at an hour); a zero TTL isn't cached
+    pub fn insert(&mut self, key: CacheKey, response: Vec<u8>, ttl: Duration, now: Instant) {
+        if self.capacity == 0 || ttl.is_zero() {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.entries.retain(|_, entry| entry.expires > now);
+            if self.entries.len() >= self.capacity {
+                self.evict_lru();
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                expires: now + ttl.min(MAX_CACHE_TTL),
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn evict_lru(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Copy of a cached `response` carrying the transaction ID of `query`
+pub fn with_transaction_id(response: &[u8], query: &[u8]) -> Vec<u8> {
+    let mut response = response.to_vec();
+    if response.len() >= 2 && query.len() >= 2 {
+        response[..2].copy_from_slice(&query[..2]);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> CacheKey {
+        CacheKey {
+            name: name.to_string(),
+            qtype: 1,
+            qclass: 1,
+            edns: false,
+            dnssec_ok: false,
+        }
+    }
+
+    #[test]
+    fn test_entries_expire_with_ttl() {
+        let mut cache = DnsCache::new(4);
+        let now = Instant::now();
+        cache.insert(key("example.com"), vec![1, 2, 3], Duration::from_secs(30), now);
+
+        assert_eq!(cache.get(&key("example.com"), now + Duration::from_secs(29)), Some(vec![1, 2, 3]));
+        assert_eq!(cache.get(&key("example.com"), now + Duration::from_secs(30)), None);
+        assert!(cache.is_empty());
+
+        cache.insert(key("zero.example"), vec![1], Duration::ZERO, now);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let mut cache = DnsCache::new(2);
+        let now = Instant::now();
+        let ttl = Duration::from_secs(300);
+        cache.insert(key("a.example"), vec![1], ttl, now);
+        cache.insert(key("b.example"), vec![2], ttl, now);
+        // Touch a, so b is the least recently used
+        cache.get(&key("a.example"), now);
+        cache.insert(key("c.example"), vec![3], ttl, now);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("b.example"), now).is_none());
+        assert!(cache.get(&key("a.example"), now).is_some());
+
+        cache.set_capacity(1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&key("a.example"), now).is_some());
+    }
+
+    #[test]
+    fn test_transaction_id_is_rewritten() {
+        let cached = vec![0x12, 0x34, 0x81, 0x80, 0, 1];
+        let query = vec![0xAB, 0xCD, 0x01, 0x00, 0, 1];
+        assert_eq!(with_transaction_id(&cached, &query), vec![0xAB, 0xCD, 0x81, 0x80, 0, 1]);
+    }
+}
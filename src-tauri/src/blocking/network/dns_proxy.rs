@@ -2,17 +2,99 @@
 //! Intercepts DNS queries and blocks requests for blocked domains.
 
 use crate::blocking::blocklists;
+use crate::blocking::network::block_log::BlockLogger;
+use crate::blocking::network::dns_anomaly::{AnomalyVerdict, DnsAnomaly, DnsAnomalyConfig, QueryRateTracker};
+use crate::blocking::network::dns_cache::{self, CacheKey, DnsCache};
+use crate::config::ConfigManager;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::net::UdpSocket;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::{broadcast, RwLock};
+
+/// Port the proxy listens on. 5353 belongs to mDNS (avahi, mDNSResponder),
+/// so Linux and macOS use an unassigned port and redirect port 53 to it;
+/// Windows points adapters at 127.0.0.1, which only works on 53.
+#[cfg(not(windows))]
+pub const DNS_PROXY_PORT: u16 = 15353;
+#[cfg(windows)]
+pub const DNS_PROXY_PORT: u16 = 53;
 
 /// DNS proxy configuration
-pub const DNS_PROXY_PORT: u16 = 5353;
 pub const UPSTREAM_DNS: &str = "8.8.8.8:53";
 
+/// DNS record types used by browsers to discover HTTP/3, encrypted DNS and ECH
+const RECORD_TYPE_SVCB: u16 = 64;
+const RECORD_TYPE_HTTPS: u16 = 65;
+
+/// Address record types checked by rebind protection
+const RECORD_TYPE_A: u16 = 1;
+const RECORD_TYPE_AAAA: u16 = 28;
+
+/// EDNS pseudo-record carried in the additional section (RFC 6891)
+const RECORD_TYPE_OPT: u16 = 41;
+
+/// SvcParamKeys that advertise an upgrade path (RFC 9460, RFC 9461, and
+/// `ech` from the TLS ECH SVCB draft)
+const SVC_PARAM_ALPN: u16 = 1;
+const SVC_PARAM_ECH: u16 = 5;
+const SVC_PARAM_DOHPATH: u16 = 7;
+
+/// Largest response a plain UDP client is guaranteed to accept (RFC 1035)
+const MAX_UDP_RESPONSE: usize = 512;
+
+/// How long to wait for the upstream server to answer
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an idle TCP client connection is kept open (RFC 7766 suggests seconds)
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Anomaly events buffered for slow subscribers
+const ANOMALY_EVENT_CAPACITY: usize = 16;
+
+/// Counters the proxy keeps while running
+#[derive(Debug, Default)]
+struct ProxyCounters {
+    queries: AtomicU64,
+    blocked: AtomicU64,
+    timeouts: AtomicU64,
+    parse_errors: AtomicU64,
+    /// When a listener was first started
+    started_at: OnceLock<Instant>,
+}
+
+/// Snapshot of the proxy counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsMetrics {
+    /// Queries received over UDP and TCP
+    pub queries: u64,
+    /// Queries answered with NXDOMAIN by the blocklist, burst detection or rebind protection
+    pub blocked: u64,
+    /// Forwarded queries the upstream server didn't answer in time
+    pub timeouts: u64,
+    /// Queries refused because they couldn't be parsed
+    pub parse_errors: u64,
+    /// Seconds since the proxy started; 0 if it hasn't
+    pub uptime_secs: u64,
+}
+
+/// What the proxy does with a client query
+enum QueryAction {
+    /// Send it on to the upstream server; holds the queried name
+    Forward(String),
+    /// Answer with this response without asking upstream
+    Respond(Vec<u8>),
+    /// Too short to answer; drop it
+    Drop,
+}
+
 /// Errors that can occur during DNS proxy operations
 #[derive(Error, Debug)]
 pub enum DnsProxyError {
@@ -24,31 +106,148 @@ pub enum DnsProxyError {
     ParseError(String),
 }
 
-/// DNS proxy server state
+/// DNS proxy server state.
+/// Clones share the same lists and state, so the UDP and TCP listeners see
+/// every update.
+#[derive(Clone)]
 pub struct DnsProxy {
     blocked_domains: Arc<RwLock<HashSet<String>>>,
+    /// Proxy-only patterns; never written to the hosts file
+    blocked_patterns: Arc<RwLock<Vec<Regex>>>,
     allowed_domains: Arc<RwLock<HashSet<String>>>,
-    upstream_dns: SocketAddr,
+    upstream_dns: Arc<RwLock<SocketAddr>>,
     running: Arc<RwLock<bool>>,
+    prevent_doh: Arc<RwLock<bool>>,
+    /// Refuse upstream answers that point into private, loopback or link-local ranges
+    rebind_protection: Arc<RwLock<bool>>,
+    /// Names (and their subdomains) allowed to resolve to private addresses
+    rebind_allowed: Arc<RwLock<HashSet<String>>>,
+    /// Whether a schedule is blocking right now; burst detection only runs then
+    block_window_active: Arc<RwLock<bool>>,
+    query_rates: Arc<RwLock<QueryRateTracker>>,
+    anomaly_events: broadcast::Sender<DnsAnomaly>,
+    /// Where blocked queries are recorded for parents, if anywhere
+    block_logger: Arc<RwLock<Option<BlockLogger>>>,
+    counters: Arc<ProxyCounters>,
+    /// Upstream answers by question, shared by the UDP and TCP listeners
+    cache: Arc<RwLock<DnsCache>>,
 }
 
 impl DnsProxy {
-    /// Create a new DNS proxy
-    pub fn new(blocked: HashSet<String>, allowed: HashSet<String>) -> Self {
-        Self {
+    /// Create a new DNS proxy forwarding to the default upstream
+    pub fn new(blocked: HashSet<String>, allowed: HashSet<String>) -> Result<Self, DnsProxyError> {
+        Self::with_upstream(blocked, allowed, UPSTREAM_DNS)
+    }
+
+    /// Create a new DNS proxy forwarding to `upstream` (see `parse_upstream`)
+    pub fn with_upstream(
+        blocked: HashSet<String>,
+        allowed: HashSet<String>,
+        upstream: &str,
+    ) -> Result<Self, DnsProxyError> {
+        let upstream_dns = parse_upstream(upstream)?;
+
+        Ok(Self {
             blocked_domains: Arc::new(RwLock::new(blocked)),
+            blocked_patterns: Arc::new(RwLock::new(Vec::new())),
             allowed_domains: Arc::new(RwLock::new(allowed)),
-            upstream_dns: UPSTREAM_DNS.parse().unwrap(),
+            upstream_dns: Arc::new(RwLock::new(upstream_dns)),
             running: Arc::new(RwLock::new(false)),
+            prevent_doh: Arc::new(RwLock::new(false)),
+            rebind_protection: Arc::new(RwLock::new(false)),
+            rebind_allowed: Arc::new(RwLock::new(HashSet::new())),
+            block_window_active: Arc::new(RwLock::new(false)),
+            query_rates: Arc::new(RwLock::new(QueryRateTracker::default())),
+            anomaly_events: broadcast::channel(ANOMALY_EVENT_CAPACITY).0,
+            block_logger: Arc::new(RwLock::new(None)),
+            counters: Arc::new(ProxyCounters::default()),
+            cache: Arc::new(RwLock::new(DnsCache::default())),
+        })
+    }
+
+    /// Forward queries to a different resolver from now on
+    pub async fn set_upstream(&self, upstream: SocketAddr) {
+        let mut upstream_dns = self.upstream_dns.write().await;
+        *upstream_dns = upstream;
+    }
+
+    /// The resolver queries are forwarded to
+    pub async fn upstream(&self) -> SocketAddr {
+        *self.upstream_dns.read().await
+    }
+
+    /// Enable or disable stripping of SVCB/HTTPS records that advertise DoH, HTTP/3 or ECH
+    pub async fn set_prevent_doh(&self, enabled: bool) {
+        let mut prevent_doh = self.prevent_doh.write().await;
+        *prevent_doh = enabled;
+    }
+
+    /// Enable or disable DNS rebinding protection
+    pub async fn set_rebind_protection(&self, enabled: bool) {
+        let mut rebind_protection = self.rebind_protection.write().await;
+        *rebind_protection = enabled;
+    }
+
+    /// Replace the names allowed to resolve to LAN addresses (e.g. the router or a NAS)
+    pub async fn update_rebind_allowed(&self, domains: HashSet<String>) {
+        let mut rebind_allowed = self.rebind_allowed.write().await;
+        *rebind_allowed = domains;
+    }
+
+    /// Replace the burst detection thresholds
+    pub async fn set_anomaly_config(&self, config: DnsAnomalyConfig) {
+        self.query_rates.write().await.set_config(config);
+    }
+
+    /// Tell the proxy whether a block window is in effect
+    pub async fn set_block_window_active(&self, active: bool) {
+        let mut block_window_active = self.block_window_active.write().await;
+        *block_window_active = active;
+    }
+
+    /// Record blocked queries through `logger`, or stop recording with `None`
+    pub async fn set_block_logger(&self, logger: Option<BlockLogger>) {
+        let mut block_logger = self.block_logger.write().await;
+        *block_logger = logger;
+    }
+
+    /// Limit the answer cache to `entries` answers; 0 turns caching off
+    pub async fn set_cache_capacity(&self, entries: usize) {
+        self.cache.write().await.set_capacity(entries);
+    }
+
+    /// Current query counters and uptime
+    pub fn metrics(&self) -> DnsMetrics {
+        let counters = &self.counters;
+        DnsMetrics {
+            queries: counters.queries.load(Ordering::Relaxed),
+            blocked: counters.blocked.load(Ordering::Relaxed),
+            timeouts: counters.timeouts.load(Ordering::Relaxed),
+            parse_errors: counters.parse_errors.load(Ordering::Relaxed),
+            uptime_secs: counters.started_at.get().map_or(0, |at| at.elapsed().as_secs()),
         }
     }
 
+    /// Receive a `DnsAnomaly` each time a client is flagged for a query burst
+    pub fn subscribe_anomalies(&self) -> broadcast::Receiver<DnsAnomaly> {
+        self.anomaly_events.subscribe()
+    }
+
     /// Update blocked domains list
     pub async fn update_blocked(&self, domains: HashSet<String>) {
         let mut blocked = self.blocked_domains.write().await;
         *blocked = domains;
     }
 
+    /// Replace the blocked domain patterns, compiling them up front.
+    /// On error the previous patterns stay in place.
+    pub async fn update_patterns(&self, patterns: &[String]) -> Result<(), DnsProxyError> {
+        let compiled = compile_domain_patterns(patterns)?;
+        let mut blocked_patterns = self.blocked_patterns.write().await;
+        *blocked_patterns = compiled;
+        Ok(())
+    }
+
     /// Update allowed domains list
     pub async fn update_allowed(&self, domains: HashSet<String>) {
         let mut allowed = self.allowed_domains.write().await;
@@ -56,10 +255,80 @@ impl DnsProxy {
     }
 
     /// Check if a domain should be blocked
-    async fn should_block(&self, domain: &str) -> bool {
+    pub(crate) async fn should_block(&self, domain: &str) -> bool {
         let blocked = self.blocked_domains.read().await;
+        let patterns = self.blocked_patterns.read().await;
         let allowed = self.allowed_domains.read().await;
-        blocklists::is_domain_blocked(domain, &blocked, &allowed)
+        domain_matches_blocklist(domain, &blocked, &patterns, &allowed)
+    }
+
+    /// Decide whether a query is answered locally or forwarded
+    async fn check_query(&self, query: &[u8], src: SocketAddr) -> QueryAction {
+        let respond = |response: Option<Vec<u8>>| response.map_or(QueryAction::Drop, QueryAction::Respond);
+        self.counters.queries.fetch_add(1, Ordering::Relaxed);
+
+        // Parse domain from DNS query; refuse anything we can't read
+        let Some(domain) = parse_dns_domain(query) else {
+            tracing::debug!("Refusing malformed DNS query from {}", src);
+            self.counters.parse_errors.fetch_add(1, Ordering::Relaxed);
+            return respond(create_formerr_response(query));
+        };
+
+        if self.should_block(&domain).await {
+            self.counters.blocked.fetch_add(1, Ordering::Relaxed);
+            tracing::info!("Blocked DNS query for: {}", domain);
+            if let Some(logger) = self.block_logger.read().await.as_ref() {
+                logger.log(&domain, src.ip());
+            }
+            return respond(create_nxdomain_response(query));
+        }
+
+        if self.is_query_burst(src, &domain).await {
+            self.counters.blocked.fetch_add(1, Ordering::Relaxed);
+            return respond(create_nxdomain_response(query));
+        }
+
+        QueryAction::Forward(domain)
+    }
+
+    /// Cached answer for a query, carrying the query's transaction ID
+    async fn cached_response(&self, key: Option<&CacheKey>, query: &[u8]) -> Option<Vec<u8>> {
+        let cached = self.cache.write().await.get(key?, Instant::now())?;
+        Some(dns_cache::with_transaction_id(&cached, query))
+    }
+
+    /// Keep an upstream answer until its shortest TTL runs out. Answers too
+    /// big for plain UDP aren't kept, so a TCP answer is never replayed over UDP.
+    async fn cache_response(&self, key: Option<CacheKey>, response: &[u8]) {
+        let (Some(key), Some(ttl)) = (key, min_answer_ttl(response)) else {
+            return;
+        };
+        if response.len() <= MAX_UDP_RESPONSE {
+            let ttl = Duration::from_secs(u64::from(ttl));
+            self.cache.write().await.insert(key, response.to_vec(), ttl, Instant::now());
+        }
+    }
+
+    /// Apply rebind protection and DoH prevention to an upstream response
+    async fn filter_response(&self, domain: &str, query: &[u8], response: Vec<u8>) -> Vec<u8> {
+        if *self.rebind_protection.read().await
+            && answers_private_address(&response)
+            && !blocklists::is_domain_allowed(domain, &*self.rebind_allowed.read().await)
+        {
+            tracing::warn!("Refused DNS answer for {} pointing at a private address", domain);
+            self.counters.blocked.fetch_add(1, Ordering::Relaxed);
+            if let Some(nxdomain) = create_nxdomain_response(query) {
+                return nxdomain;
+            }
+        }
+
+        if *self.prevent_doh.read().await {
+            if let Some(filtered) = filter_upgrade_records(&response) {
+                tracing::info!("Stripped SVCB/HTTPS upgrade records from DNS response");
+                return filtered;
+            }
+        }
+        response
     }
 
     /// Start the DNS proxy server
@@ -74,8 +343,9 @@ impl DnsProxy {
             let mut running = self.running.write().await;
             *running = true;
         }
+        self.counters.started_at.get_or_init(Instant::now);
 
-        let mut buf = [0u8; 512];
+        let mut buf = [0u8; MAX_UDP_RESPONSE];
 
         loop {
             {
@@ -87,36 +357,39 @@ impl DnsProxy {
 
             match socket.recv_from(&mut buf).await {
                 Ok((len, src)) => {
-                    let query = buf[..len].to_vec();
-
-                    // Parse domain from DNS query
-                    if let Some(domain) = parse_dns_domain(&query) {
-                        if self.should_block(&domain).await {
-                            tracing::info!("Blocked DNS query for: {}", domain);
-                            // Send NXDOMAIN response
-                            if let Some(response) = create_nxdomain_response(&query) {
-                                let _ = socket.send_to(&response, src).await;
-                            }
+                    let query = &buf[..len];
+
+                    let domain = match self.check_query(query, src).await {
+                        QueryAction::Respond(response) => {
+                            let _ = socket.send_to(&response, src).await;
                             continue;
                         }
+                        QueryAction::Drop => continue,
+                        QueryAction::Forward(domain) => domain,
+                    };
+
+                    let key = question_key(query, &domain);
+                    if let Some(cached) = self.cached_response(key.as_ref(), query).await {
+                        let response = self.filter_response(&domain, query, cached).await;
+                        let _ = socket.send_to(&response, src).await;
+                        continue;
                     }
 
                     // Forward to upstream DNS
                     let upstream = UdpSocket::bind("0.0.0.0:0").await?;
-                    upstream.send_to(&query, self.upstream_dns).await?;
-
-                    let mut response_buf = [0u8; 512];
-                    match tokio::time::timeout(
-                        std::time::Duration::from_secs(5),
-                        upstream.recv_from(&mut response_buf),
-                    )
-                    .await
-                    {
+                    upstream.send_to(query, self.upstream().await).await?;
+
+                    let mut response_buf = [0u8; MAX_UDP_RESPONSE];
+                    match tokio::time::timeout(UPSTREAM_TIMEOUT, upstream.recv_from(&mut response_buf)).await {
                         Ok(Ok((response_len, _))) => {
-                            let _ = socket.send_to(&response_buf[..response_len], src).await;
+                            let response = response_buf[..response_len].to_vec();
+                            self.cache_response(key, &response).await;
+                            let response = self.filter_response(&domain, query, response).await;
+                            let _ = socket.send_to(&response, src).await;
                         }
                         _ => {
                             tracing::warn!("Upstream DNS timeout");
+                            self.counters.timeouts.fetch_add(1, Ordering::Relaxed);
                         }
                     }
                 }
@@ -129,6 +402,134 @@ impl DnsProxy {
         Ok(())
     }
 
+    /// Start the DNS proxy's TCP listener. Clients fall back to TCP when a
+    /// UDP answer is truncated, and some resolvers use it outright, so run
+    /// this alongside `start` on the same address.
+    pub async fn start_tcp(&self, bind_addr: &str) -> Result<(), DnsProxyError> {
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .map_err(|e| DnsProxyError::BindFailed(e.to_string()))?;
+
+        tracing::info!("DNS proxy listening on {} (TCP)", bind_addr);
+
+        {
+            let mut running = self.running.write().await;
+            *running = true;
+        }
+        self.counters.started_at.get_or_init(Instant::now);
+
+        self.serve_tcp(listener).await
+    }
+
+    /// Accept TCP clients until stopped, one task per connection
+    async fn serve_tcp(&self, listener: TcpListener) -> Result<(), DnsProxyError> {
+        loop {
+            {
+                let running = self.running.read().await;
+                if !*running {
+                    break;
+                }
+            }
+
+            match listener.accept().await {
+                Ok((stream, src)) => {
+                    let proxy = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = proxy.handle_tcp_client(stream, src).await {
+                            tracing::debug!("DNS TCP connection from {} closed: {}", src, e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::error!("DNS proxy accept error: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answer queries on one TCP connection until the client closes it or goes idle
+    async fn handle_tcp_client(&self, mut stream: TcpStream, src: SocketAddr) -> Result<(), DnsProxyError> {
+        loop {
+            let query = match tokio::time::timeout(TCP_IDLE_TIMEOUT, read_tcp_message(&mut stream)).await {
+                Ok(Ok(Some(query))) => query,
+                Ok(Ok(None)) | Err(_) => return Ok(()),
+                Ok(Err(e)) => return Err(e.into()),
+            };
+
+            let response = match self.check_query(&query, src).await {
+                QueryAction::Respond(response) => response,
+                QueryAction::Drop => return Ok(()),
+                QueryAction::Forward(domain) => {
+                    let key = question_key(&query, &domain);
+                    let response = match self.cached_response(key.as_ref(), &query).await {
+                        Some(cached) => cached,
+                        None => {
+                            let response = self.forward_tcp(&query).await?;
+                            self.cache_response(key, &response).await;
+                            response
+                        }
+                    };
+                    self.filter_response(&domain, &query, response).await
+                }
+            };
+
+            let Some(framed) = frame_tcp_message(&response) else {
+                return Err(DnsProxyError::ParseError("DNS response too large for TCP".to_string()));
+            };
+            stream.write_all(&framed).await?;
+        }
+    }
+
+    /// Send a query to the upstream server over TCP and return its answer
+    async fn forward_tcp(&self, query: &[u8]) -> Result<Vec<u8>, DnsProxyError> {
+        let upstream_dns = self.upstream().await;
+        let exchange = async {
+            let mut upstream = TcpStream::connect(upstream_dns).await?;
+            let framed = frame_tcp_message(query)
+                .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "DNS query too large"))?;
+            upstream.write_all(&framed).await?;
+            read_tcp_message(&mut upstream)
+                .await?
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+        };
+
+        match tokio::time::timeout(UPSTREAM_TIMEOUT, exchange).await {
+            Ok(result) => Ok(result?),
+            Err(_) => {
+                tracing::warn!("Upstream DNS timeout");
+                self.counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into())
+            }
+        }
+    }
+
+    /// Run burst detection for a query; true if it should be refused
+    async fn is_query_burst(&self, src: SocketAddr, domain: &str) -> bool {
+        if !*self.block_window_active.read().await {
+            return false;
+        }
+
+        let verdict = self.query_rates.write().await.record(src.ip(), domain, Instant::now());
+        match verdict {
+            AnomalyVerdict::Allow => false,
+            AnomalyVerdict::Blocked => true,
+            AnomalyVerdict::Flagged(anomaly) => {
+                tracing::warn!(
+                    "DNS query burst from {}: {} queries under {}, blocking for {}s",
+                    anomaly.client,
+                    anomaly.queries,
+                    anomaly.suffix,
+                    anomaly.blocked_for_secs
+                );
+                // No subscribers is fine; the warning above is still logged
+                let _ = self.anomaly_events.send(anomaly);
+                true
+            }
+        }
+    }
+
     /// Stop the DNS proxy server
     pub async fn stop(&self) {
         let mut running = self.running.write().await;
@@ -136,42 +537,95 @@ impl DnsProxy {
     }
 }
 
-/// Parse domain name from DNS query packet
-fn parse_dns_domain(query: &[u8]) -> Option<String> {
+/// Longest label and name allowed by RFC 1035
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 253;
+
+/// Compression pointers followed before a name is rejected (stops pointer loops)
+const MAX_POINTER_HOPS: usize = 16;
+
+/// Parse the first question's name from a DNS query packet.
+/// Compression pointers are followed anywhere inside the packet (after the
+/// header); a pointer past the end, back to an offset already visited, or
+/// more than MAX_POINTER_HOPS deep makes the packet invalid.
+pub fn parse_dns_domain(query: &[u8]) -> Option<String> {
     // DNS header is 12 bytes
     if query.len() < 13 {
         return None;
     }
 
     let mut pos = 12;
-    let mut domain_parts = Vec::new();
+    let mut visited = Vec::with_capacity(MAX_POINTER_HOPS);
+    let mut domain = String::new();
 
-    while pos < query.len() {
-        let len = query[pos] as usize;
+    loop {
+        let len = *query.get(pos)? as usize;
         if len == 0 {
             break;
         }
 
+        match len & 0xC0 {
+            0xC0 => {
+                // Compression pointer; stay inside the packet and never revisit an offset
+                let target = ((len & 0x3F) << 8) | *query.get(pos + 1)? as usize;
+                if visited.len() >= MAX_POINTER_HOPS
+                    || target < 12
+                    || target >= query.len()
+                    || visited.contains(&target)
+                {
+                    return None;
+                }
+                visited.push(target);
+                pos = target;
+                continue;
+            }
+            // 0x40 and 0x80 label types are reserved
+            0x40 | 0x80 => return None,
+            _ => {}
+        }
+
         pos += 1;
-        if pos + len > query.len() {
+        let label = query.get(pos..pos + len)?;
+        if len > MAX_LABEL_LEN || !label.iter().all(|b| b.is_ascii_graphic() && *b != b'.') {
             return None;
         }
 
-        if let Ok(part) = std::str::from_utf8(&query[pos..pos + len]) {
-            domain_parts.push(part.to_string());
+        if !domain.is_empty() {
+            domain.push('.');
+        }
+        domain.extend(label.iter().map(|b| *b as char));
+        if domain.len() > MAX_NAME_LEN {
+            return None;
         }
         pos += len;
     }
 
-    if domain_parts.is_empty() {
+    if domain.is_empty() {
         None
     } else {
-        Some(domain_parts.join("."))
+        Some(domain)
     }
 }
 
+/// Create a FORMERR response for a query whose question can't be parsed.
+/// Such queries are refused rather than forwarded, so a malformed name can't
+/// slip past the blocklist.
+pub fn create_formerr_response(query: &[u8]) -> Option<Vec<u8>> {
+    if query.len() < 12 {
+        return None;
+    }
+
+    // Header only: QR=1, RD copied from the query, RCODE=1 (FORMERR), no records
+    let mut response = query[..12].to_vec();
+    response[2] = 0x80 | (query[2] & 0x01);
+    response[3] = 0x81;
+    response[4..12].fill(0);
+
+    Some(response)
+}
+
 /// Create an NXDOMAIN response for a blocked domain
-fn create_nxdomain_response(query: &[u8]) -> Option<Vec<u8>> {
+pub fn create_nxdomain_response(query: &[u8]) -> Option<Vec<u8>> {
     if query.len() < 12 {
         return None;
     }
@@ -190,10 +644,390 @@ fn create_nxdomain_response(query: &[u8]) -> Option<Vec<u8>> {
     Some(response)
 }
 
+/// Parse an upstream resolver address. Accepts `ip:port`, or a bare IP for port 53.
+pub fn parse_upstream(upstream: &str) -> Result<SocketAddr, DnsProxyError> {
+    let upstream = upstream.trim();
+    upstream
+        .parse::<SocketAddr>()
+        .or_else(|_| upstream.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+        .map_err(|e| DnsProxyError::ParseError(format!("Invalid upstream DNS {:?}: {}", upstream, e)))
+}
+
+/// The resolver to forward to: `configured` if it parses, otherwise
+/// `UPSTREAM_DNS`. A DoH provider's address is fine here: the firewall only
+/// drops its port 443, and the daemon's own port 53 queries aren't redirected.
+pub fn choose_upstream(configured: Option<&str>) -> SocketAddr {
+    let default = parse_upstream(UPSTREAM_DNS).expect("default upstream DNS is valid");
+    let Some(configured) = configured else {
+        return default;
+    };
+
+    match parse_upstream(configured) {
+        Ok(addr) => addr,
+        Err(e) => {
+            tracing::warn!("{}; using {}", e, UPSTREAM_DNS);
+            default
+        }
+    }
+}
+
+/// `choose_upstream` for the saved config, or the default if it can't be read
+pub fn configured_upstream() -> SocketAddr {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return choose_upstream(None);
+    };
+    choose_upstream(config.upstream_dns.as_deref())
+}
+
+/// Prefix a DNS message with the 2-byte big-endian length TCP uses (RFC 1035 4.2.2).
+/// Returns `None` if the message is too long to frame.
+pub fn frame_tcp_message(message: &[u8]) -> Option<Vec<u8>> {
+    let len = u16::try_from(message.len()).ok()?;
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&len.to_be_bytes());
+    framed.extend_from_slice(message);
+    Some(framed)
+}
+
+/// Read one length-prefixed DNS message; `None` if the peer closed cleanly first
+async fn read_tcp_message<R: AsyncReadExt + Unpin>(reader: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+    let len = match reader.read_u16().await {
+        Ok(len) => len as usize,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut message = vec![0u8; len];
+    reader.read_exact(&mut message).await?;
+    Ok(Some(message))
+}
+
+/// Skip over a (possibly compressed) domain name, returning the position after it
+fn skip_dns_name(packet: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some(pos + 1);
+        }
+        match len & 0xC0 {
+            0xC0 => {
+                // Compression pointer (2 bytes) always ends the name
+                packet.get(pos + 1)?;
+                return Some(pos + 2);
+            }
+            0x40 | 0x80 => return None,
+            _ => {}
+        }
+        pos += 1 + len;
+    }
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]))
+}
+
+/// Check whether SVCB/HTTPS RDATA advertises an HTTP/3 or encrypted DNS endpoint,
+/// or an ECH config. Plain `h2` is ordinary HTTPS and is left alone.
+fn svcb_advertises_upgrade(rdata: &[u8]) -> bool {
+    // SvcPriority (2 bytes) followed by the uncompressed TargetName
+    let mut pos = match skip_dns_name(rdata, 2) {
+        Some(pos) => pos,
+        None => return false,
+    };
+
+    while pos + 4 <= rdata.len() {
+        let key = read_u16(rdata, pos).unwrap_or(0);
+        let len = read_u16(rdata, pos + 2).unwrap_or(0) as usize;
+        pos += 4;
+        if pos + len > rdata.len() {
+            return false;
+        }
+        let value = &rdata[pos..pos + len];
+
+        match key {
+            SVC_PARAM_DOHPATH | SVC_PARAM_ECH => return true,
+            SVC_PARAM_ALPN => {
+                let mut i = 0;
+                while i < value.len() {
+                    let id_len = value[i] as usize;
+                    let id = value.get(i + 1..i + 1 + id_len).unwrap_or(&[]);
+                    if matches!(id, b"h3" | b"doq" | b"dot") {
+                        return true;
+                    }
+                    i += 1 + id_len;
+                }
+            }
+            _ => {}
+        }
+        pos += len;
+    }
+
+    false
+}
+
+/// Cache key for a query: its first question, and whether it asks for EDNS
+/// and DNSSEC records
+fn question_key(query: &[u8], domain: &str) -> Option<CacheKey> {
+    let pos = skip_dns_name(query, 12)?;
+    let opt = query_opt_record(query);
+    let dnssec_ok = opt.and_then(|record| {
+        // Flags follow the type, payload size, extended RCODE and version
+        let name_end = skip_dns_name(record, 0)?;
+        Some(record.get(name_end + 6)? & 0x80 != 0)
+    });
+    Some(CacheKey {
+        name: domain.to_lowercase(),
+        qtype: read_u16(query, pos)?,
+        qclass: read_u16(query, pos + 2)?,
+        edns: opt.is_some(),
+        dnssec_ok: dnssec_ok.unwrap_or(false),
+    })
+}
+
+/// The OPT record of a query, found after its question and answer sections
+fn query_opt_record(query: &[u8]) -> Option<&[u8]> {
+    let qdcount = read_u16(query, 4)?;
+    let ancount = read_u16(query, 6)?;
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_dns_name(query, pos)? + 4;
+    }
+    for _ in 0..ancount {
+        pos = skip_dns_name(query, pos)?;
+        pos += 10 + read_u16(query, pos + 8)? as usize;
+    }
+    find_opt_record(query, pos)
+}
+
+/// Smallest TTL in the answer section of a successful, untruncated response.
+/// `None` if the response has no answers or isn't worth caching.
+pub fn min_answer_ttl(response: &[u8]) -> Option<u32> {
+    // TC set or RCODE other than NOERROR
+    if response.len() < 12 || response[2] & 0x02 != 0 || response[3] & 0x0F != 0 {
+        return None;
+    }
+
+    let qdcount = read_u16(response, 4)?;
+    let ancount = read_u16(response, 6)?;
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_dns_name(response, pos)? + 4;
+    }
+
+    let mut min_ttl: Option<u32> = None;
+    for _ in 0..ancount {
+        pos = skip_dns_name(response, pos)?;
+        let ttl = u32::from_be_bytes(response.get(pos + 4..pos + 8)?.try_into().ok()?);
+        let rdlength = read_u16(response, pos + 8)? as usize;
+        response.get(pos + 10..pos + 10 + rdlength)?;
+        pos += 10 + rdlength;
+        min_ttl = Some(min_ttl.map_or(ttl, |min| min.min(ttl)));
+    }
+
+    min_ttl
+}
+
+/// Whether an address is in a range a public name should never resolve to:
+/// RFC 1918, loopback, link-local, unspecified, or IPv6 unique local
+fn is_private_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_private_ipv4(v4),
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+                || v6.to_ipv4_mapped().is_some_and(is_private_ipv4)
+        }
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()
+}
+
+/// Check whether any A/AAAA record in the answer section points at a private
+/// address. A response that can't be parsed is reported as not private.
+pub fn answers_private_address(response: &[u8]) -> bool {
+    answer_addresses(response).is_some_and(|addresses| addresses.into_iter().any(is_private_address))
+}
+
+/// Addresses in the A/AAAA records of the answer section
+fn answer_addresses(response: &[u8]) -> Option<Vec<IpAddr>> {
+    if response.len() < 12 {
+        return None;
+    }
+
+    let qdcount = read_u16(response, 4)?;
+    let ancount = read_u16(response, 6)?;
+
+    // Skip the question section
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_dns_name(response, pos)? + 4;
+    }
+
+    let mut addresses = Vec::new();
+    for _ in 0..ancount {
+        pos = skip_dns_name(response, pos)?;
+        let rtype = read_u16(response, pos)?;
+        let rdlength = read_u16(response, pos + 8)? as usize;
+        let rdata = response.get(pos + 10..pos + 10 + rdlength)?;
+        pos += 10 + rdlength;
+
+        match (rtype, rdata.len()) {
+            (RECORD_TYPE_A, 4) => {
+                let octets: [u8; 4] = rdata.try_into().ok()?;
+                addresses.push(IpAddr::V4(Ipv4Addr::from(octets)));
+            }
+            (RECORD_TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = rdata.try_into().ok()?;
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+    }
+
+    Some(addresses)
+}
+
+/// Remove SVCB/HTTPS answers that advertise encrypted DNS, HTTP/3 or ECH.
+/// Returns `None` if the response contains nothing to filter (or can't be parsed),
+/// so the caller can forward the original bytes untouched.
+pub fn filter_upgrade_records(response: &[u8]) -> Option<Vec<u8>> {
+    if response.len() < 12 {
+        return None;
+    }
+
+    let qdcount = read_u16(response, 4)?;
+    let ancount = read_u16(response, 6)?;
+
+    // Skip the question section
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_dns_name(response, pos)? + 4;
+    }
+    let answers_start = pos;
+
+    let mut kept = Vec::new();
+    let mut kept_count: u16 = 0;
+    let mut filtered_any = false;
+
+    for _ in 0..ancount {
+        let record_start = pos;
+        let name_end = skip_dns_name(response, pos)?;
+        let rtype = read_u16(response, name_end)?;
+        let rdlength = read_u16(response, name_end + 8)? as usize;
+        let rdata_start = name_end + 10;
+        let record_end = rdata_start + rdlength;
+        if record_end > response.len() {
+            return None;
+        }
+
+        let is_upgrade = (rtype == RECORD_TYPE_SVCB || rtype == RECORD_TYPE_HTTPS)
+            && svcb_advertises_upgrade(&response[rdata_start..record_end]);
+
+        if is_upgrade {
+            filtered_any = true;
+        } else {
+            kept.extend_from_slice(&response[record_start..record_end]);
+            kept_count += 1;
+        }
+        pos = record_end;
+    }
+
+    if !filtered_any {
+        return None;
+    }
+
+    // Rebuild: header + question + remaining answers. Authority and additional
+    // sections are dropped since they may carry address hints for the stripped
+    // records, except the OPT record, which describes the message itself
+    // (payload size, DO bit, extended RCODE).
+    let opt = find_opt_record(response, pos);
+    let mut filtered = response[..answers_start].to_vec();
+    filtered[6..8].copy_from_slice(&kept_count.to_be_bytes());
+    filtered[8..10].copy_from_slice(&[0, 0]);
+    filtered[10..12].copy_from_slice(&(opt.is_some() as u16).to_be_bytes());
+    filtered.extend_from_slice(&kept);
+    filtered.extend_from_slice(opt.unwrap_or_default());
+
+    Some(filtered)
+}
+
+/// The OPT record of the additional section, with the authority and
+/// additional sections starting at `pos`. `None` if there is none or the
+/// sections can't be parsed.
+fn find_opt_record(response: &[u8], mut pos: usize) -> Option<&[u8]> {
+    let nscount = read_u16(response, 8)?;
+    let arcount = read_u16(response, 10)?;
+
+    for index in 0..nscount as u32 + arcount as u32 {
+        let record_start = pos;
+        let name_end = skip_dns_name(response, pos)?;
+        let rtype = read_u16(response, name_end)?;
+        let rdlength = read_u16(response, name_end + 8)? as usize;
+        let record_end = name_end + 10 + rdlength;
+        let record = response.get(record_start..record_end)?;
+        if index >= nscount as u32 && rtype == RECORD_TYPE_OPT {
+            return Some(record);
+        }
+        pos = record_end;
+    }
+    None
+}
+
+/// Compile blocked domain patterns. `*.example.net` blocks every subdomain of
+/// `example.net`; anything else is a case-insensitive regex matched against
+/// the whole query name.
+pub fn compile_domain_patterns(patterns: &[String]) -> Result<Vec<Regex>, DnsProxyError> {
+    patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .map(|pattern| {
+            let source = match pattern.strip_prefix("*.") {
+                Some(suffix) => format!("(?i)\\.{}$", regex::escape(suffix)),
+                // Anchored, so `roblox` doesn't block every name containing it
+                None => format!("(?i)^(?:{})$", pattern),
+            };
+            Regex::new(&source)
+                .map_err(|e| DnsProxyError::ParseError(format!("Invalid domain pattern {:?}: {}", pattern, e)))
+        })
+        .collect()
+}
+
+/// Literal blocklist first, then patterns; the whitelist overrides both
+fn domain_matches_blocklist(
+    domain: &str,
+    blocked: &HashSet<String>,
+    patterns: &[Regex],
+    allowed: &HashSet<String>,
+) -> bool {
+    if blocklists::is_domain_blocked(domain, blocked, allowed) {
+        return true;
+    }
+    !patterns.is_empty()
+        && !blocklists::is_domain_allowed(domain, allowed)
+        && patterns.iter().any(|pattern| pattern.is_match(domain))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn build_query(domain: &str) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in domain.split('.') {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.extend_from_slice(&[0, 0, 1, 0, 1]);
+        query
+    }
+
     #[test]
     fn test_parse_dns_domain() {
         // Simulated DNS query for "example.com"
@@ -210,6 +1044,120 @@ mod tests {
         assert_eq!(domain, Some("example.com".to_string()));
     }
 
+    #[test]
+    fn test_domain_patterns() {
+        let blocked: HashSet<String> = ["roblox.com".to_string()].into_iter().collect();
+        let allowed: HashSet<String> = ["edu.gaming-cdn.net".to_string()].into_iter().collect();
+        let patterns = compile_domain_patterns(&[
+            r".*\.gaming-cdn\.net$".to_string(),
+            "*.epicgames.dev".to_string(),
+        ])
+        .unwrap();
+
+        // Regex and suffix patterns block subdomains
+        assert!(domain_matches_blocklist("eu.gaming-cdn.net", &blocked, &patterns, &allowed));
+        assert!(domain_matches_blocklist("A.B.GAMING-CDN.NET", &blocked, &patterns, &allowed));
+        assert!(domain_matches_blocklist("api.epicgames.dev", &blocked, &patterns, &allowed));
+        assert!(!domain_matches_blocklist("epicgames.dev.example.org", &blocked, &patterns, &allowed));
+
+        // Literals still work alongside patterns
+        assert!(domain_matches_blocklist("www.roblox.com", &blocked, &patterns, &allowed));
+        assert!(!domain_matches_blocklist("example.org", &blocked, &patterns, &allowed));
+
+        // The whitelist wins over patterns
+        assert!(!domain_matches_blocklist("edu.gaming-cdn.net", &blocked, &patterns, &allowed));
+    }
+
+    #[test]
+    fn test_regex_pattern_matches_the_whole_name() {
+        let none = HashSet::new();
+        let patterns = compile_domain_patterns(&["roblox|fortnite\\.com".to_string()]).unwrap();
+
+        assert!(domain_matches_blocklist("ROBLOX", &none, &patterns, &none));
+        assert!(domain_matches_blocklist("fortnite.com", &none, &patterns, &none));
+        // A partial match is not enough, for either alternative
+        assert!(!domain_matches_blocklist("www.roblox.com", &none, &patterns, &none));
+        assert!(!domain_matches_blocklist("notroblox", &none, &patterns, &none));
+        assert!(!domain_matches_blocklist("fortnite.com.example.org", &none, &patterns, &none));
+    }
+
+    #[test]
+    fn test_invalid_domain_pattern_is_an_error() {
+        let result = compile_domain_patterns(&["(unclosed".to_string()]);
+        assert!(matches!(result, Err(DnsProxyError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_invalid_upstream_is_an_error() {
+        let result = DnsProxy::with_upstream(HashSet::new(), HashSet::new(), "not-an-address");
+        assert!(matches!(result, Err(DnsProxyError::ParseError(_))));
+
+        assert!(DnsProxy::new(HashSet::new(), HashSet::new()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_parse_and_set_upstream() {
+        assert_eq!(parse_upstream("185.228.168.9").unwrap(), "185.228.168.9:53".parse().unwrap());
+        assert_eq!(parse_upstream(" [2606:4700::1111]:5353 ").unwrap().port(), 5353);
+        assert!(matches!(parse_upstream("dns.example:53"), Err(DnsProxyError::ParseError(_))));
+
+        let proxy = DnsProxy::new(HashSet::new(), HashSet::new()).unwrap();
+        assert_eq!(proxy.upstream().await, UPSTREAM_DNS.parse().unwrap());
+        let shared = proxy.clone();
+        proxy.set_upstream("1.1.1.1:53".parse().unwrap()).await;
+        assert_eq!(shared.upstream().await, "1.1.1.1:53".parse().unwrap());
+    }
+
+    #[test]
+    fn test_configured_upstream_kept_unless_invalid() {
+        let default: SocketAddr = UPSTREAM_DNS.parse().unwrap();
+
+        assert_eq!(choose_upstream(None), default);
+        assert_eq!(choose_upstream(Some("not-an-address")), default);
+        assert_eq!(choose_upstream(Some("194.242.2.2")), "194.242.2.2:53".parse().unwrap());
+
+        // DoH providers are blocked on port 443 only, so they still serve plain DNS
+        assert_eq!(choose_upstream(Some("1.1.1.1")), "1.1.1.1:53".parse().unwrap());
+        assert_eq!(choose_upstream(Some("9.9.9.9:5300")), "9.9.9.9:5300".parse().unwrap());
+    }
+
+    #[test]
+    fn test_frame_tcp_message() {
+        let query = build_query("roblox.com");
+        let response = create_nxdomain_response(&query).unwrap();
+        let framed = frame_tcp_message(&response).unwrap();
+        assert_eq!(u16::from_be_bytes([framed[0], framed[1]]) as usize, response.len());
+        assert_eq!(&framed[2..], &response[..]);
+
+        assert!(frame_tcp_message(&vec![0u8; 65536]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_query_for_blocked_domain() {
+        let blocked = HashSet::from(["roblox.com".to_string()]);
+        let proxy = DnsProxy::new(blocked, HashSet::new()).unwrap();
+        *proxy.running.write().await = true;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = proxy.clone();
+        tokio::spawn(async move { server.serve_tcp(listener).await });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        // Two queries on one connection, the second after an update to the shared list
+        let query = build_query("www.roblox.com");
+        client.write_all(&frame_tcp_message(&query).unwrap()).await.unwrap();
+        let response = read_tcp_message(&mut client).await.unwrap().unwrap();
+        assert_eq!(response.len(), query.len());
+        assert_eq!(response[3] & 0x0F, 3);
+
+        proxy.update_blocked(HashSet::from(["minecraft.net".to_string()])).await;
+        let query = build_query("minecraft.net");
+        client.write_all(&frame_tcp_message(&query).unwrap()).await.unwrap();
+        let response = read_tcp_message(&mut client).await.unwrap().unwrap();
+        assert_eq!(response[3] & 0x0F, 3);
+    }
+
     #[test]
     fn test_create_nxdomain_response() {
         let query = vec![0u8; 12];
@@ -218,4 +1166,295 @@ mod tests {
         assert_eq!(response[2], 0x81);
         assert_eq!(response[3], 0x83);
     }
+
+    /// Build a response for "example.com" with a single HTTPS answer
+    fn build_https_response(svc_params: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        packet.extend_from_slice(&[7]);
+        packet.extend_from_slice(b"example");
+        packet.extend_from_slice(&[3]);
+        packet.extend_from_slice(b"com");
+        packet.extend_from_slice(&[0, 0, 65, 0, 1]); // QTYPE=HTTPS, QCLASS=IN
+
+        let mut rdata = vec![0, 1, 0]; // SvcPriority=1, TargetName="."
+        rdata.extend_from_slice(svc_params);
+
+        packet.extend_from_slice(&[0xC0, 12]); // Pointer to question name
+        packet.extend_from_slice(&[0, 65, 0, 1, 0, 0, 0x0E, 0x10]); // HTTPS, IN, TTL
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&rdata);
+        packet
+    }
+
+    /// Response to `query` with a CNAME followed by one address record
+    fn build_address_response(query: &[u8], rtype: u16, address: &[u8]) -> Vec<u8> {
+        let mut packet = query.to_vec();
+        packet[2] = 0x81;
+        packet[3] = 0x80;
+        packet[7] = 2; // ANCOUNT
+
+        let cname = b"\x04edge\xC0\x0C"; // edge.<question name>
+        packet.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60]);
+        packet.extend_from_slice(&(cname.len() as u16).to_be_bytes());
+        packet.extend_from_slice(cname);
+
+        packet.extend_from_slice(&[0xC0, 12]);
+        packet.extend_from_slice(&rtype.to_be_bytes());
+        packet.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        packet.extend_from_slice(&(address.len() as u16).to_be_bytes());
+        packet.extend_from_slice(address);
+        packet
+    }
+
+    #[test]
+    fn test_answers_private_address() {
+        let query = build_query("rebind.example");
+        assert!(answers_private_address(&build_address_response(&query, 1, &[192, 168, 1, 10])));
+        assert!(answers_private_address(&build_address_response(&query, 1, &[127, 0, 0, 1])));
+        assert!(answers_private_address(&build_address_response(&query, 1, &[169, 254, 0, 5])));
+        assert!(!answers_private_address(&build_address_response(&query, 1, &[93, 184, 216, 34])));
+
+        let ula = "fd12::1".parse::<Ipv6Addr>().unwrap().octets();
+        assert!(answers_private_address(&build_address_response(&query, 28, &ula)));
+        let public = "2606:4700::1111".parse::<Ipv6Addr>().unwrap().octets();
+        assert!(!answers_private_address(&build_address_response(&query, 28, &public)));
+
+        // Truncated responses are left to the client
+        let response = build_address_response(&query, 1, &[10, 0, 0, 1]);
+        assert!(!answers_private_address(&response[..response.len() - 2]));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_queries() {
+        let blocked = HashSet::from(["roblox.com".to_string()]);
+        let proxy = DnsProxy::new(blocked, HashSet::new()).unwrap();
+        let src: SocketAddr = "192.168.1.20:5000".parse().unwrap();
+
+        assert!(matches!(proxy.check_query(&build_query("roblox.com"), src).await, QueryAction::Respond(_)));
+        assert!(matches!(proxy.check_query(&build_query("example.com"), src).await, QueryAction::Forward(_)));
+        assert!(matches!(proxy.check_query(&[0u8; 12], src).await, QueryAction::Respond(_)));
+
+        let metrics = proxy.metrics();
+        assert_eq!((metrics.queries, metrics.blocked, metrics.parse_errors), (3, 1, 1));
+        assert_eq!(metrics.uptime_secs, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cached_answer_uses_query_id() {
+        let proxy = DnsProxy::new(HashSet::new(), HashSet::new()).unwrap();
+        let query = build_query("example.com");
+        let mut response = build_address_response(&query, 1, &[93, 184, 216, 34]);
+        // Second answer with a shorter TTL decides the expiry
+        let ttl_byte = response.len() - 7;
+        response[ttl_byte] = 30;
+        assert_eq!(min_answer_ttl(&response), Some(30));
+
+        let key = question_key(&query, "Example.com");
+        assert_eq!(
+            key,
+            Some(CacheKey {
+                name: "example.com".to_string(),
+                qtype: 1,
+                qclass: 1,
+                edns: false,
+                dnssec_ok: false,
+            })
+        );
+        proxy.cache_response(key.clone(), &response).await;
+
+        let mut next_query = query.clone();
+        next_query[..2].copy_from_slice(&[0xBE, 0xEF]);
+        let cached = proxy.cached_response(key.as_ref(), &next_query).await.unwrap();
+        assert_eq!(&cached[..2], &[0xBE, 0xEF]);
+        assert_eq!(&cached[2..], &response[2..]);
+
+        // NXDOMAIN answers carry no TTL to respect and aren't cached
+        assert_eq!(min_answer_ttl(&create_nxdomain_response(&query).unwrap()), None);
+    }
+
+    #[test]
+    fn test_cache_key_separates_edns_and_dnssec_queries() {
+        let plain = build_query("example.com");
+        let with_opt = |flags: u8| {
+            let mut query = plain.clone();
+            query[11] = 1; // ARCOUNT
+            query.extend_from_slice(&[0, 0, 41, 0x04, 0xD0, 0, 0, flags, 0, 0, 0]);
+            query
+        };
+
+        let plain_key = question_key(&plain, "example.com").unwrap();
+        let edns_key = question_key(&with_opt(0), "example.com").unwrap();
+        let dnssec_key = question_key(&with_opt(0x80), "example.com").unwrap();
+        assert!(!plain_key.edns && edns_key.edns && !edns_key.dnssec_ok);
+        assert!(dnssec_key.edns && dnssec_key.dnssec_ok);
+        assert_ne!(plain_key, edns_key);
+        assert_ne!(edns_key, dnssec_key);
+    }
+
+    #[tokio::test]
+    async fn test_rebind_protection() {
+        let proxy = DnsProxy::new(HashSet::new(), HashSet::new()).unwrap();
+        let query = build_query("rebind.example");
+        let response = build_address_response(&query, 1, &[192, 168, 0, 1]);
+
+        // Off by default
+        let forwarded = proxy.filter_response("rebind.example", &query, response.clone()).await;
+        assert_eq!(forwarded, response);
+
+        proxy.set_rebind_protection(true).await;
+        let refused = proxy.filter_response("rebind.example", &query, response.clone()).await;
+        assert_eq!(refused, create_nxdomain_response(&query).unwrap());
+
+        proxy.update_rebind_allowed(HashSet::from(["example".to_string()])).await;
+        let allowed = proxy.filter_response("rebind.example", &query, response.clone()).await;
+        assert_eq!(allowed, response);
+    }
+
+    #[test]
+    fn test_filter_https_record_with_h3() {
+        // alpn = ["h3"]
+        let response = build_https_response(&[0, 1, 0, 3, 2, b'h', b'3']);
+
+        let filtered = filter_upgrade_records(&response).unwrap();
+        assert_eq!(&filtered[6..8], &[0, 0]); // ANCOUNT = 0
+        assert_eq!(parse_dns_domain(&filtered), Some("example.com".to_string()));
+        assert!(filtered.len() < response.len());
+    }
+
+    #[test]
+    fn test_filter_https_record_with_dohpath() {
+        // dohpath = "/dns-query{?dns}"
+        let path = b"/dns-query{?dns}";
+        let mut params = vec![0, 7];
+        params.extend_from_slice(&(path.len() as u16).to_be_bytes());
+        params.extend_from_slice(path);
+        let response = build_https_response(&params);
+
+        assert!(filter_upgrade_records(&response).is_some());
+    }
+
+    #[test]
+    fn test_filter_https_record_with_ech() {
+        // ech = 4 opaque config bytes
+        let response = build_https_response(&[0, 5, 0, 4, 0xFE, 0x0D, 0, 0]);
+
+        assert!(filter_upgrade_records(&response).is_some());
+    }
+
+    #[test]
+    fn test_filtering_keeps_the_opt_record() {
+        let mut response = build_https_response(&[0, 1, 0, 3, 2, b'h', b'3']);
+        response[11] = 1; // ARCOUNT
+        // Root name, OPT, 4096-byte payload, DO bit set, no options
+        let opt = [0, 0, 41, 0x10, 0, 0, 0, 0x80, 0, 0, 0];
+        response.extend_from_slice(&opt);
+
+        let filtered = filter_upgrade_records(&response).unwrap();
+        assert_eq!(&filtered[6..12], &[0, 0, 0, 0, 0, 1]);
+        assert!(filtered.ends_with(&opt));
+        assert_eq!(parse_dns_domain(&filtered), Some("example.com".to_string()));
+    }
+
+    #[test]
+    fn test_https_record_without_upgrade_is_kept() {
+        // port = 8443 only
+        let response = build_https_response(&[0, 3, 0, 2, 0x20, 0xFB]);
+        assert!(filter_upgrade_records(&response).is_none());
+
+        // alpn = ["h2", "http/1.1"] is what most HTTPS sites publish
+        let mut params = vec![0, 1, 0, 12, 2, b'h', b'2', 8];
+        params.extend_from_slice(b"http/1.1");
+        let response = build_https_response(&params);
+        assert!(filter_upgrade_records(&response).is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_pointer_loops() {
+        // Name at offset 12 is a pointer to itself
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        assert_eq!(parse_dns_domain(&query), None);
+
+        // Two labels that point at each other
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[1, b'a', 0xC0, 12]);
+        assert_eq!(parse_dns_domain(&query), None);
+    }
+
+    #[test]
+    fn test_parse_compressed_multi_question_packet() {
+        // Two questions: example.com, then www + pointer back to example.com
+        let mut query = vec![0x12, 0x34, 0x01, 0x00, 0, 2, 0, 0, 0, 0, 0, 0];
+        query.extend_from_slice(&[7]);
+        query.extend_from_slice(b"example");
+        query.extend_from_slice(&[3]);
+        query.extend_from_slice(b"com");
+        query.extend_from_slice(&[0, 0, 1, 0, 1]);
+        query.extend_from_slice(&[3]);
+        query.extend_from_slice(b"www");
+        query.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        assert_eq!(parse_dns_domain(&query), Some("example.com".to_string()));
+
+        // First question is "play" + a forward pointer to a name later in the packet
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[4]);
+        query.extend_from_slice(b"play");
+        query.extend_from_slice(&[0xC0, 23, 0, 1, 0, 1]);
+        assert_eq!(query.len(), 23);
+        query.extend_from_slice(&[6]);
+        query.extend_from_slice(b"roblox");
+        query.extend_from_slice(&[3]);
+        query.extend_from_slice(b"com");
+        query.push(0);
+        assert_eq!(parse_dns_domain(&query), Some("play.roblox.com".to_string()));
+    }
+
+    #[test]
+    fn test_parse_rejects_pointer_past_buffer() {
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[1, b'a', 0xC0, 200, 0, 1, 0, 1]);
+        assert_eq!(parse_dns_domain(&query), None);
+
+        let response = create_formerr_response(&query).unwrap();
+        assert_eq!(response.len(), 12);
+        assert_eq!(response[3] & 0x0F, 1);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_labels() {
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[0x40, b'a', 0]); // reserved label type
+        assert_eq!(parse_dns_domain(&query), None);
+
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[3, b'a', b'.', b'b', 0]); // dot inside a label
+        assert_eq!(parse_dns_domain(&query), None);
+
+        let mut query = vec![0u8; 12];
+        query.extend_from_slice(&[5, b'a']); // truncated label
+        assert_eq!(parse_dns_domain(&query), None);
+    }
+
+    #[test]
+    fn test_random_packets_do_not_panic() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5eed);
+        for _ in 0..20_000 {
+            let len = rng.gen_range(0..600);
+            let mut packet: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            // Bias towards pointer-heavy names
+            if packet.len() > 14 && rng.gen_bool(0.3) {
+                packet[12] = 0xC0 | rng.gen_range(0..4);
+            }
+
+            if let Some(domain) = parse_dns_domain(&packet) {
+                assert!(domain.len() <= MAX_NAME_LEN);
+            }
+            if let Some(response) = create_nxdomain_response(&packet) {
+                assert_eq!(response.len(), packet.len());
+            }
+            let _ = filter_upgrade_records(&packet);
+        }
+    }
 }
@@ -0,0 +1,90 @@
+//! Addresses of DNS-over-HTTPS providers the firewall blocks.
+//! The list lives in the config (`doh_provider_ips`) so it can be extended
+//! when providers add ranges; new configs start from these defaults.
+
+use crate::config::ConfigManager;
+use std::net::IpAddr;
+
+/// Known DNS-over-HTTPS provider IPs that bypass hosts file blocking
+pub const DEFAULT_DOH_PROVIDER_IPS: &[&str] = &[
+    // Cloudflare DNS
+    "1.1.1.1",
+    "1.0.0.1",
+    "2606:4700:4700::1111",
+    "2606:4700:4700::1001",
+    // Google DNS
+    "8.8.8.8",
+    "8.8.4.4",
+    "2001:4860:4860::8888",
+    "2001:4860:4860::8844",
+    // Quad9
+    "9.9.9.9",
+    "149.112.112.112",
+    "2620:fe::fe",
+    "2620:fe::9",
+    // OpenDNS
+    "208.67.222.222",
+    "208.67.220.220",
+    // NextDNS
+    "45.90.28.0",
+    "45.90.30.0",
+    // AdGuard DNS
+    "94.140.14.14",
+    "94.140.15.15",
+    // CleanBrowsing
+    "185.228.168.168",
+    "185.228.169.168",
+    // Comodo Secure DNS
+    "8.26.56.26",
+    "8.20.247.20",
+];
+
+/// The default list as config entries
+pub fn default_doh_provider_ips() -> Vec<String> {
+    DEFAULT_DOH_PROVIDER_IPS.iter().map(|ip| ip.to_string()).collect()
+}
+
+/// Parse config entries, skipping (and warning about) anything that isn't an
+/// IPv4 or IPv6 address. Duplicates are dropped.
+pub fn parse_doh_ips(entries: &[String]) -> Vec<IpAddr> {
+    let mut ips = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match entry.trim().parse::<IpAddr>() {
+            Ok(ip) if !ips.contains(&ip) => ips.push(ip),
+            Ok(_) => {}
+            Err(_) => tracing::warn!("Skipping invalid DoH provider IP {:?}", entry),
+        }
+    }
+    ips
+}
+
+/// DoH provider IPs from the config, or the defaults if it can't be read
+pub fn configured_doh_ips() -> Vec<IpAddr> {
+    match ConfigManager::new().and_then(|m| m.load()) {
+        Ok(config) => parse_doh_ips(&config.doh_provider_ips),
+        Err(_) => parse_doh_ips(&default_doh_provider_ips()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_all_parse() {
+        assert_eq!(parse_doh_ips(&default_doh_provider_ips()).len(), DEFAULT_DOH_PROVIDER_IPS.len());
+    }
+
+    #[test]
+    fn test_invalid_and_duplicate_entries_are_skipped() {
+        let entries = vec![
+            "1.1.1.1".to_string(),
+            " 2606:4700:4700::1111 ".to_string(),
+            "dns.example".to_string(),
+            "45.90.28.0/24".to_string(),
+            "1.1.1.1".to_string(),
+        ];
+        let ips = parse_doh_ips(&entries);
+        assert_eq!(ips, vec!["1.1.1.1".parse::<IpAddr>().unwrap(), "2606:4700:4700::1111".parse().unwrap()]);
+    }
+}
@@ -1,5 +1,8 @@
 //! Linux network configuration using iptables.
 
+use super::doh_ips::configured_doh_ips;
+use super::vpn_ports::{configured_vpn_ports, VpnProtocol, DEFAULT_VPN_PORTS};
+use std::net::IpAddr;
 use std::process::Command;
 use thiserror::Error;
 
@@ -11,43 +14,53 @@ pub enum LinuxNetworkError {
     IptablesNotAvailable,
 }
 
-/// Known DNS-over-HTTPS provider IPs that bypass hosts file blocking
-const DOH_PROVIDER_IPS: &[&str] = &[
-    // Cloudflare DNS
-    "1.1.1.1",
-    "1.0.0.1",
-    "2606:4700:4700::1111",
-    "2606:4700:4700::1001",
-    // Google DNS
-    "8.8.8.8",
-    "8.8.4.4",
-    "2001:4860:4860::8888",
-    "2001:4860:4860::8844",
-    // Quad9
-    "9.9.9.9",
-    "149.112.112.112",
-    "2620:fe::fe",
-    "2620:fe::9",
-    // OpenDNS
-    "208.67.222.222",
-    "208.67.220.220",
-    // NextDNS
-    "45.90.28.0",
-    "45.90.30.0",
-    // AdGuard DNS
-    "94.140.14.14",
-    "94.140.15.15",
-    // CleanBrowsing
-    "185.228.168.168",
-    "185.228.169.168",
-    // Comodo Secure DNS
-    "8.26.56.26",
-    "8.20.247.20",
-];
-
 /// Chain name for ParentShield rules
 const CHAIN_NAME: &str = "PARENTSHIELD";
 
+/// nat chain that sends DNS to the local proxy, and the IPv6 filter chain
+/// that stops DNS going around it
+const DNS_REDIRECT_CHAIN: &str = "PARENTSHIELD_DNS";
+
+/// Redirect DNS on port 53 to the proxy. Root's traffic is let through so
+/// the daemon's own forwarded queries reach the upstream resolver instead of
+/// looping back. The proxy only listens on 127.0.0.1, so other users' DNS
+/// over IPv6 is dropped instead, and resolvers fall back to IPv4.
+fn build_dns_redirect_script(proxy_port: u16) -> String {
+    format!(
+        r#"
+iptables -t nat -N {chain} 2>/dev/null || true
+iptables -t nat -F {chain}
+iptables -t nat -A {chain} -m owner --uid-owner 0 -j RETURN
+iptables -t nat -A {chain} -p udp --dport 53 -j REDIRECT --to-ports {port}
+iptables -t nat -A {chain} -p tcp --dport 53 -j REDIRECT --to-ports {port}
+iptables -t nat -C OUTPUT -j {chain} 2>/dev/null || iptables -t nat -I OUTPUT 1 -j {chain}
+ip6tables -N {chain} 2>/dev/null || true
+ip6tables -F {chain} 2>/dev/null || true
+ip6tables -A {chain} -m owner --uid-owner 0 -j RETURN 2>/dev/null || true
+ip6tables -A {chain} -p udp --dport 53 -j DROP 2>/dev/null || true
+ip6tables -A {chain} -p tcp --dport 53 -j DROP 2>/dev/null || true
+ip6tables -C OUTPUT -j {chain} 2>/dev/null || ip6tables -I OUTPUT 1 -j {chain} 2>/dev/null || true
+"#,
+        chain = DNS_REDIRECT_CHAIN,
+        port = proxy_port
+    )
+}
+
+/// Take the DNS redirect down, leaving queries to go out as they did before
+fn build_dns_redirect_removal_script() -> String {
+    format!(
+        r#"
+iptables -t nat -D OUTPUT -j {chain} 2>/dev/null || true
+iptables -t nat -F {chain} 2>/dev/null || true
+iptables -t nat -X {chain} 2>/dev/null || true
+ip6tables -D OUTPUT -j {chain} 2>/dev/null || true
+ip6tables -F {chain} 2>/dev/null || true
+ip6tables -X {chain} 2>/dev/null || true
+"#,
+        chain = DNS_REDIRECT_CHAIN
+    )
+}
+
 /// Configure DNS redirect to local proxy using iptables
 pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), LinuxNetworkError> {
     // Check if iptables is available
@@ -60,29 +73,17 @@ pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), LinuxNetworkError> {
         return Err(LinuxNetworkError::IptablesNotAvailable);
     }
 
-    // Redirect DNS (port 53) to local proxy
-    let output = Command::new("iptables")
-        .args([
-            "-t",
-            "nat",
-            "-A",
-            "OUTPUT",
-            "-p",
-            "udp",
-            "--dport",
-            "53",
-            "-j",
-            "REDIRECT",
-            "--to-port",
-            &proxy_port.to_string(),
-        ])
+    run_iptables_direct(&build_dns_redirect_script(proxy_port))?;
+
+    let jumped = Command::new("iptables")
+        .args(["-t", "nat", "-C", "OUTPUT", "-j", DNS_REDIRECT_CHAIN])
         .output()
         .map_err(|e| LinuxNetworkError::CommandFailed(e.to_string()))?;
-
-    if !output.status.success() {
-        return Err(LinuxNetworkError::CommandFailed(
-            String::from_utf8_lossy(&output.stderr).to_string(),
-        ));
+    if !jumped.status.success() {
+        return Err(LinuxNetworkError::CommandFailed(format!(
+            "DNS redirect not in place: {}",
+            String::from_utf8_lossy(&jumped.stderr).trim()
+        )));
     }
 
     tracing::info!("DNS redirect configured via iptables");
@@ -90,80 +91,48 @@ pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), LinuxNetworkError> {
 }
 
 /// Remove DNS redirect rules
-pub fn remove_dns_redirect(proxy_port: u16) -> Result<(), LinuxNetworkError> {
-    let output = Command::new("iptables")
-        .args([
-            "-t",
-            "nat",
-            "-D",
-            "OUTPUT",
-            "-p",
-            "udp",
-            "--dport",
-            "53",
-            "-j",
-            "REDIRECT",
-            "--to-port",
-            &proxy_port.to_string(),
-        ])
-        .output()
-        .map_err(|e| LinuxNetworkError::CommandFailed(e.to_string()))?;
-
-    if !output.status.success() {
-        tracing::warn!(
-            "Failed to remove iptables rule: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
-    Ok(())
+pub fn remove_dns_redirect() -> Result<(), LinuxNetworkError> {
+    run_iptables_direct(&build_dns_redirect_removal_script())
 }
 
-/// Block common VPN ports (included in the main blocking script)
-fn build_vpn_block_script() -> String {
-    let vpn_ports = [
-        ("1194", "udp"),  // OpenVPN
-        ("1194", "tcp"),  // OpenVPN
-        ("500", "udp"),   // IKEv2/IPSec
-        ("4500", "udp"),  // IKEv2 NAT-T
-        ("51820", "udp"), // WireGuard
-        ("1701", "udp"),  // L2TP
-    ];
+/// Append `rule` to the chain unless an identical rule is already there
+fn append_rule_once(iptables: &str, rule: &str) -> String {
+    format!(
+        "{iptables} -C {chain} {rule} 2>/dev/null || {iptables} -A {chain} {rule} 2>/dev/null || true\n",
+        iptables = iptables, chain = CHAIN_NAME, rule = rule
+    )
+}
 
-    let mut script = String::new();
-    for (port, protocol) in vpn_ports {
-        script.push_str(&format!(
-            "iptables -A OUTPUT -p {} --dport {} -j DROP 2>/dev/null || true\n",
-            protocol, port
-        ));
-    }
-    script
+/// The DROP rule for one VPN port, as it appears after the chain name
+fn vpn_rule(port: u16, protocol: VpnProtocol) -> String {
+    format!("-p {} --dport {} -j DROP", protocol, port)
 }
 
-/// Build script to unblock VPN ports
-fn build_vpn_unblock_script() -> String {
-    let vpn_ports = [
-        ("1194", "udp"),
-        ("1194", "tcp"),
-        ("500", "udp"),
-        ("4500", "udp"),
-        ("51820", "udp"),
-        ("1701", "udp"),
-    ];
+/// Block common VPN ports and the `extra` ones (included in the main blocking script).
+/// They live in our chain, so flushing it on removal takes them away too.
+fn build_vpn_block_script(extra: &[(u16, VpnProtocol)]) -> String {
+    DEFAULT_VPN_PORTS
+        .iter()
+        .chain(extra)
+        .map(|&(port, protocol)| append_rule_once("iptables", &vpn_rule(port, protocol)))
+        .collect()
+}
 
-    let mut script = String::new();
-    for (port, protocol) in vpn_ports {
-        script.push_str(&format!(
-            "iptables -D OUTPUT -p {} --dport {} -j DROP 2>/dev/null || true\n",
-            protocol, port
-        ));
-    }
-    script
+/// Delete the DROP rules earlier versions appended straight to OUTPUT, every
+/// copy of them, as those aren't caught by flushing the chain
+fn build_legacy_vpn_cleanup_script() -> String {
+    DEFAULT_VPN_PORTS
+        .iter()
+        .map(|&(port, protocol)| {
+            format!("while iptables -D OUTPUT {} 2>/dev/null; do :; done\n", vpn_rule(port, protocol))
+        })
+        .collect()
 }
 
 /// Block common VPN ports
 pub fn block_vpn_ports() -> Result<(), LinuxNetworkError> {
-    let script = build_vpn_block_script();
+    let mut script = build_ensure_chain_script();
+    script.push_str(&build_vpn_block_script(&configured_vpn_ports()));
     run_iptables_batch(&script)?;
     tracing::info!("VPN ports blocked");
     Ok(())
@@ -171,7 +140,15 @@ pub fn block_vpn_ports() -> Result<(), LinuxNetworkError> {
 
 /// Unblock VPN ports
 pub fn unblock_vpn_ports() -> Result<(), LinuxNetworkError> {
-    let script = build_vpn_unblock_script();
+    let mut script = String::new();
+    for (port, protocol) in DEFAULT_VPN_PORTS.iter().chain(&configured_vpn_ports()) {
+        script.push_str(&format!(
+            "iptables -D {} {} 2>/dev/null || true\n",
+            CHAIN_NAME,
+            vpn_rule(*port, *protocol)
+        ));
+    }
+    script.push_str(&build_legacy_vpn_cleanup_script());
     let _ = run_iptables_batch(&script); // Ignore errors on unblock
     Ok(())
 }
@@ -238,60 +215,53 @@ ip6tables -C OUTPUT -j {chain} 2>/dev/null || ip6tables -I OUTPUT 1 -j {chain}
     )
 }
 
-/// Block all known DNS-over-HTTPS providers to enforce hosts file blocking
+/// The whole rule set: chain, jump, DoH (port 443 only) and VPN rules. The
+/// chain is emptied first, so applying again replaces the rules instead of
+/// adding copies, and providers or ports removed from the config stop being
+/// blocked.
+fn build_full_apply_script(doh_ips: &[IpAddr], extra_vpn: &[(u16, VpnProtocol)]) -> String {
+    let mut script = build_ensure_chain_script();
+    script.push_str(&format!(
+        "iptables -F {chain} 2>/dev/null || true\nip6tables -F {chain} 2>/dev/null || true\n",
+        chain = CHAIN_NAME
+    ));
+
+    // Drop HTTPS (443) to the configured DoH providers; plain DNS is left to the redirect
+    script.push_str(&build_doh_block_script(doh_ips));
+
+    // Add VPN blocking rules
+    script.push_str(&build_vpn_block_script(extra_vpn));
+    script
+}
+
+/// Protocol and port dropped for each DoH provider address. Plain DNS on 53
+/// is left alone: these are the resolvers many machines get from DHCP, and
+/// the DNS redirect already sends port 53 through the proxy.
+const DOH_BLOCKED_PORTS: [(&str, u16); 2] = [("tcp", 443), ("udp", 443)];
+
+/// DROP rules for HTTPS (443) traffic to each DoH provider
+fn build_doh_block_script(ips: &[IpAddr]) -> String {
+    let mut script = String::new();
+    for ip in ips {
+        let iptables = if ip.is_ipv6() { "ip6tables" } else { "iptables" };
+        for (proto, port) in DOH_BLOCKED_PORTS {
+            let rule = format!("-d {} -p {} --dport {} -j DROP", ip, proto, port);
+            script.push_str(&append_rule_once(iptables, &rule));
+        }
+    }
+    script
+}
+
+/// Block HTTPS to the known DNS-over-HTTPS providers to enforce hosts file
+/// blocking; plain DNS to them still works
 pub fn block_doh_providers() -> Result<(), LinuxNetworkError> {
     tracing::info!("Blocking DNS-over-HTTPS providers...");
 
     // Build a single script with all iptables commands
     let mut script = build_ensure_chain_script();
 
-    // Add rules to block DoH providers on port 443
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            // IPv6 address
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            // IPv4 address
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        }
-    }
-
-    // Also block DNS (port 53) to these IPs to force local resolver
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        }
-    }
+    // Drop HTTPS (443) to the configured DoH providers; plain DNS is left to the redirect
+    script.push_str(&build_doh_block_script(&configured_doh_ips()));
 
     // Execute all commands with a single pkexec call
     run_iptables_batch(&script)?;
@@ -344,54 +314,121 @@ pub fn is_doh_blocked() -> bool {
     false
 }
 
-/// Apply full network blocking (DoH + VPN) with a single authentication prompt
-pub fn apply_network_blocking() -> Result<(), LinuxNetworkError> {
-    tracing::info!("Applying full network blocking (DoH + VPN)...");
+/// Whether our chain is in place in both families with at least one DROP rule
+/// per configured DoH port and address, plus the VPN ports for IPv4. Rules are
+/// counted, so a flush that only took some of them is caught too.
+pub fn firewall_rules_present() -> bool {
+    let ips = configured_doh_ips();
+    let vpn_rules = DEFAULT_VPN_PORTS.len() + configured_vpn_ports().len();
+    [("iptables", false), ("ip6tables", true)].into_iter().all(|(iptables, ipv6)| {
+        let mut expected = ips.iter().filter(|ip| ip.is_ipv6() == ipv6).count() * DOH_BLOCKED_PORTS.len();
+        if !ipv6 {
+            expected += vpn_rules;
+        }
+        let output = match Command::new(iptables).arg("-S").output() {
+            Ok(output) => output,
+            // Machines without ip6tables never had IPv6 rules
+            Err(_) => return ipv6,
+        };
+        if !output.status.success() {
+            // ip6tables fails outright when IPv6 is off (ipv6.disable=1), and
+            // then there is nothing an apply could restore
+            return ipv6;
+        }
 
-    // Build a combined script for all blocking rules
-    let mut script = build_ensure_chain_script();
+        let rules = parse_chain_rules(&String::from_utf8_lossy(&output.stdout));
+        rules.chain_exists && rules.jumped_to && rules.drop_rules >= expected
+    })
+}
 
-    // Add DoH blocking rules
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
+/// Our part of an `iptables -S` listing
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ChainRules {
+    chain_exists: bool,
+    /// OUTPUT sends traffic through the chain
+    jumped_to: bool,
+    drop_rules: usize,
+}
+
+fn parse_chain_rules(listing: &str) -> ChainRules {
+    let new_chain = format!("-N {}", CHAIN_NAME);
+    let jump = format!("-A OUTPUT -j {}", CHAIN_NAME);
+    let rule_prefix = format!("-A {} ", CHAIN_NAME);
+
+    let mut rules = ChainRules::default();
+    for line in listing.lines().map(str::trim) {
+        if line == new_chain {
+            rules.chain_exists = true;
+        } else if line == jump {
+            rules.jumped_to = true;
+        } else if line.starts_with(&rule_prefix) && line.ends_with("-j DROP") {
+            rules.drop_rules += 1;
         }
     }
+    rules
+}
 
-    // Add VPN blocking rules
-    script.push_str(&build_vpn_block_script());
+/// Destination IPs that have DROP rules on port 443 in the chain, from both
+/// `iptables` and `ip6tables`; port 53 drops left by older versions count too
+pub fn list_blocked_doh_ips() -> Vec<String> {
+    let mut ips = Vec::new();
+    for iptables in ["iptables", "ip6tables"] {
+        let output = Command::new(iptables).args(["-L", CHAIN_NAME, "-n"]).output();
+        let Ok(result) = output else {
+            continue;
+        };
+        if !result.status.success() {
+            continue;
+        }
+        for ip in parse_blocked_doh_ips(&String::from_utf8_lossy(&result.stdout)) {
+            if !ips.contains(&ip) {
+                ips.push(ip);
+            }
+        }
+    }
+    ips
+}
+
+/// Pull DoH destinations out of an `iptables -L <chain> -n` listing
+fn parse_blocked_doh_ips(listing: &str) -> Vec<String> {
+    let mut ips = Vec::new();
+    for line in listing.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.first() != Some(&"DROP") || !fields.iter().any(|f| matches!(*f, "dpt:443" | "dpt:53")) {
+            continue;
+        }
+
+        // The source and destination are the first two address columns.
+        // ip6tables may leave the "opt" column blank, so columns can't be counted.
+        let Some(destination) = fields.iter().filter(|f| is_rule_address(f)).nth(1) else {
+            continue;
+        };
+        let destination = destination
+            .strip_suffix("/32")
+            .or_else(|| destination.strip_suffix("/128"))
+            .unwrap_or(destination);
+        if destination.ends_with("/0") {
+            continue;
+        }
+        if !ips.iter().any(|ip| ip == destination) {
+            ips.push(destination.to_string());
+        }
+    }
+    ips
+}
+
+/// Whether a listing column is an address or network (e.g. `0.0.0.0/0`)
+fn is_rule_address(field: &str) -> bool {
+    let address = field.split_once('/').map_or(field, |(address, _)| address);
+    address.parse::<IpAddr>().is_ok()
+}
+
+/// Apply full network blocking (DoH + VPN) with a single authentication prompt
+pub fn apply_network_blocking() -> Result<(), LinuxNetworkError> {
+    tracing::info!("Applying full network blocking (DoH + VPN)...");
+
+    // Build a combined script for all blocking rules
+    let script = build_full_apply_script(&configured_doh_ips(), &configured_vpn_ports());
 
     // Execute everything with a single pkexec call
     run_iptables_batch(&script)?;
@@ -400,12 +437,10 @@ pub fn apply_network_blocking() -> Result<(), LinuxNetworkError> {
     Ok(())
 }
 
-/// Remove all network blocking rules with a single authentication prompt
-pub fn remove_network_blocking() -> Result<(), LinuxNetworkError> {
-    tracing::info!("Removing all network blocking rules...");
-
-    // Build a combined script to remove all rules
-    let script = format!(
+/// Script removing every rule `build_full_apply_script` and the DNS redirect
+/// put in place, along with the heartbeat that says they're enforced
+fn build_removal_script() -> String {
+    format!(
         r#"
 # Flush the ParentShield chains
 iptables -F {chain} 2>/dev/null || true
@@ -419,15 +454,26 @@ ip6tables -D OUTPUT -j {chain} 2>/dev/null || true
 iptables -X {chain} 2>/dev/null || true
 ip6tables -X {chain} 2>/dev/null || true
 
-# Remove VPN port blocks
-{vpn_unblock}
+# VPN port blocks went with the chain; drop any left in OUTPUT by older versions
+{legacy_vpn}
+# Without the daemon's proxy behind it the DNS redirect would cut off all lookups
+{dns_redirect}
+# Nothing is enforced any more; a stale heartbeat would vouch for rules that are gone
+rm -f {heartbeat}
 "#,
         chain = CHAIN_NAME,
-        vpn_unblock = build_vpn_unblock_script()
-    );
+        legacy_vpn = build_legacy_vpn_cleanup_script(),
+        dns_redirect = build_dns_redirect_removal_script(),
+        heartbeat = FIREWALL_HEARTBEAT_PATH
+    )
+}
+
+/// Remove all network blocking rules with a single authentication prompt
+pub fn remove_network_blocking() -> Result<(), LinuxNetworkError> {
+    tracing::info!("Removing all network blocking rules...");
 
     // Execute with a single pkexec call
-    run_iptables_batch(&script)?;
+    run_iptables_batch(&build_removal_script())?;
 
     tracing::info!("All network blocking rules removed");
     Ok(())
@@ -460,55 +506,14 @@ fn run_iptables_direct(script: &str) -> Result<(), LinuxNetworkError> {
     Ok(())
 }
 
-/// Block DoH providers directly (for daemon running as root)
+/// Block HTTPS to DoH providers directly (for daemon running as root)
 pub fn block_doh_providers_direct() -> Result<(), LinuxNetworkError> {
     tracing::info!("Blocking DNS-over-HTTPS providers (direct)...");
 
     let mut script = build_ensure_chain_script();
 
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        }
-    }
-
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        }
-    }
+    // Drop HTTPS (443) to the configured DoH providers; plain DNS is left to the redirect
+    script.push_str(&build_doh_block_script(&configured_doh_ips()));
 
     run_iptables_direct(&script)?;
 
@@ -542,47 +547,7 @@ ip6tables -X {chain} 2>/dev/null || true
 pub fn apply_network_blocking_direct() -> Result<(), LinuxNetworkError> {
     tracing::info!("Applying full network blocking (direct)...");
 
-    let mut script = build_ensure_chain_script();
-
-    for ip in DOH_PROVIDER_IPS {
-        if ip.contains(':') {
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "ip6tables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        } else {
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 443 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p udp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-            script.push_str(&format!(
-                "iptables -A {chain} -d {ip} -p tcp --dport 53 -j DROP 2>/dev/null || true\n",
-                chain = CHAIN_NAME, ip = ip
-            ));
-        }
-    }
-
-    script.push_str(&build_vpn_block_script());
+    let script = build_full_apply_script(&configured_doh_ips(), &configured_vpn_ports());
 
     run_iptables_direct(&script)?;
 
@@ -594,22 +559,253 @@ pub fn apply_network_blocking_direct() -> Result<(), LinuxNetworkError> {
 pub fn remove_network_blocking_direct() -> Result<(), LinuxNetworkError> {
     tracing::info!("Removing all network blocking rules (direct)...");
 
-    let script = format!(
-        r#"
-iptables -F {chain} 2>/dev/null || true
-ip6tables -F {chain} 2>/dev/null || true
-iptables -D OUTPUT -j {chain} 2>/dev/null || true
-ip6tables -D OUTPUT -j {chain} 2>/dev/null || true
-iptables -X {chain} 2>/dev/null || true
-ip6tables -X {chain} 2>/dev/null || true
-{vpn_unblock}
-"#,
-        chain = CHAIN_NAME,
-        vpn_unblock = build_vpn_unblock_script()
-    );
-
-    run_iptables_direct(&script)?;
+    run_iptables_direct(&build_removal_script())?;
 
     tracing::info!("All network blocking rules removed (direct)");
     Ok(())
 }
+
+/// Timestamp file the daemon refreshes while its firewall rules are in place
+const FIREWALL_HEARTBEAT_PATH: &str = "/var/lib/parentshield/firewall-heartbeat";
+
+/// Record that the daemon is still enforcing the firewall rules
+pub fn touch_firewall_heartbeat() -> std::io::Result<()> {
+    let path = std::path::Path::new(FIREWALL_HEARTBEAT_PATH);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, unix_now().to_string())
+}
+
+/// Read the last heartbeat (Unix seconds), if any
+pub fn read_firewall_heartbeat() -> Option<u64> {
+    std::fs::read_to_string(FIREWALL_HEARTBEAT_PATH)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Decide whether leftover firewall rules should be removed.
+/// Rules are only considered stale when blocking is no longer configured and
+/// nobody has refreshed the heartbeat within `ttl_secs` (or ever).
+pub fn firewall_rules_are_stale(
+    heartbeat: Option<u64>,
+    now: u64,
+    ttl_secs: u64,
+    blocking_configured: bool,
+) -> bool {
+    if blocking_configured {
+        return false;
+    }
+
+    match heartbeat {
+        Some(last) => now.saturating_sub(last) > ttl_secs,
+        None => true,
+    }
+}
+
+/// Remove leftover rules from a crashed or uninstalled daemon if they are stale.
+/// Returns whether anything was removed.
+pub fn remove_stale_firewall_rules(ttl_secs: u64, blocking_configured: bool) -> Result<bool, LinuxNetworkError> {
+    if !is_doh_blocked() {
+        return Ok(false);
+    }
+
+    if !firewall_rules_are_stale(read_firewall_heartbeat(), unix_now(), ttl_secs, blocking_configured) {
+        return Ok(false);
+    }
+
+    tracing::warn!("Removing stale firewall rules left behind by a previous run");
+    remove_network_blocking_direct()?;
+    Ok(true)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::network::DNS_PROXY_PORT;
+
+    #[test]
+    fn test_parse_blocked_doh_ips() {
+        let ipv4 = "\
+Chain PARENTSHIELD (1 references)
+target     prot opt source               destination
+DROP       tcp  --  0.0.0.0/0            1.1.1.1              tcp dpt:443
+DROP       udp  --  0.0.0.0/0            1.1.1.1              udp dpt:443
+DROP       udp  --  0.0.0.0/0            1.1.1.1              udp dpt:53
+DROP       tcp  --  0.0.0.0/0            45.90.28.0/24        tcp dpt:443
+DROP       tcp  --  0.0.0.0/0            0.0.0.0/0            tcp dpt:53
+ACCEPT     tcp  --  0.0.0.0/0            8.8.8.8              tcp dpt:443
+DROP       udp  --  0.0.0.0/0            9.9.9.9              udp dpt:51820
+";
+        assert_eq!(parse_blocked_doh_ips(ipv4), vec!["1.1.1.1", "45.90.28.0/24"]);
+
+        let ipv6 = "\
+Chain PARENTSHIELD (1 references)
+target     prot opt source               destination
+DROP       tcp      ::/0                 2606:4700:4700::1111  tcp dpt:443
+DROP       udp      ::/0                 2620:fe::fe/128      udp dpt:53
+";
+        assert_eq!(parse_blocked_doh_ips(ipv6), vec!["2606:4700:4700::1111", "2620:fe::fe"]);
+    }
+
+    #[test]
+    fn test_parse_chain_rules_counts_drops() {
+        let listing = "\
+-P INPUT ACCEPT
+-P OUTPUT ACCEPT
+-N PARENTSHIELD
+-A OUTPUT -j PARENTSHIELD
+-A OUTPUT -p udp -m udp --dport 1194 -j DROP
+-A PARENTSHIELD -d 1.1.1.1/32 -p tcp -m tcp --dport 443 -j DROP
+-A PARENTSHIELD -d 1.1.1.1/32 -p udp -m udp --dport 443 -j DROP
+-A PARENTSHIELD -d 1.1.1.1/32 -p udp -m udp --dport 53 -j DROP
+-A PARENTSHIELD -d 1.1.1.1/32 -p tcp -m tcp --dport 53 -j DROP
+";
+        let expected = ChainRules {
+            chain_exists: true,
+            jumped_to: true,
+            drop_rules: 4,
+        };
+        assert_eq!(parse_chain_rules(listing), expected);
+
+        // A partial flush leaves the chain and jump but fewer rules
+        let partial: Vec<&str> = listing.lines().filter(|l| !l.contains("--dport 53")).collect();
+        assert_eq!(parse_chain_rules(&partial.join("\n")).drop_rules, 2);
+
+        // `iptables -F` empties the chain; the OUTPUT jump goes with it
+        let flushed = "-P OUTPUT ACCEPT\n-N PARENTSHIELD\n";
+        assert_eq!(
+            parse_chain_rules(flushed),
+            ChainRules {
+                chain_exists: true,
+                ..ChainRules::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_vpn_scripts_include_custom_ports() {
+        let extra = [(41641, VpnProtocol::Udp), (8388, VpnProtocol::Tcp)];
+
+        let block = build_vpn_block_script(&extra);
+        assert_eq!(block.lines().count(), DEFAULT_VPN_PORTS.len() + 2);
+        assert!(block.contains("iptables -A PARENTSHIELD -p udp --dport 51820 -j DROP"));
+        assert!(block.contains("iptables -A PARENTSHIELD -p udp --dport 41641 -j DROP"));
+        assert!(block.contains("iptables -A PARENTSHIELD -p tcp --dport 8388 -j DROP"));
+        // Applying again must not stack a second copy of each rule
+        assert!(block
+            .lines()
+            .all(|line| line.starts_with("iptables -C PARENTSHIELD ") && line.contains(" || iptables -A ")));
+        assert!(!block.contains("OUTPUT"));
+
+        // Removal flushes the chain, so only rules older versions put in OUTPUT need deleting
+        let cleanup = build_legacy_vpn_cleanup_script();
+        assert!(cleanup.contains("while iptables -D OUTPUT -p udp --dport 1194 -j DROP"));
+        assert!(!cleanup.contains("41641"));
+    }
+
+    #[test]
+    fn test_full_apply_replaces_rules() {
+        let ips: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap()];
+        let script = build_full_apply_script(&ips, &[(41641, VpnProtocol::Udp)]);
+        let lines: Vec<&str> = script
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect();
+
+        let flush = lines.iter().position(|l| l.starts_with("iptables -F PARENTSHIELD")).unwrap();
+        let first_rule = lines.iter().position(|l| l.contains(" -A PARENTSHIELD ")).unwrap();
+        assert!(flush < first_rule);
+        assert!(lines.iter().any(|l| l.starts_with("ip6tables -F PARENTSHIELD")));
+        assert!(script.contains("iptables -A PARENTSHIELD -d 1.1.1.1 -p tcp --dport 443 -j DROP"));
+        assert!(script.contains("iptables -A PARENTSHIELD -p udp --dport 41641 -j DROP"));
+
+        // The OUTPUT jump is checked before being inserted, never stacked
+        for line in lines.iter().filter(|l| l.contains("OUTPUT")) {
+            assert!(line.contains("-C OUTPUT -j PARENTSHIELD"), "unguarded: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_doh_block_script_covers_both_families() {
+        let ips: Vec<IpAddr> = vec!["1.1.1.1".parse().unwrap(), "2620:fe::fe".parse().unwrap()];
+        let script = build_doh_block_script(&ips);
+        assert_eq!(script.lines().count(), 4);
+        assert!(script.contains("iptables -A PARENTSHIELD -d 1.1.1.1 -p udp --dport 443 -j DROP"));
+        // Plain DNS to these resolvers must keep working
+        assert!(!script.contains("--dport 53"));
+        assert!(script.contains("ip6tables -A PARENTSHIELD -d 2620:fe::fe -p tcp --dport 443 -j DROP"));
+        assert!(!script.lines().any(|line| line.starts_with("iptables ") && line.contains("2620:")));
+    }
+
+    #[test]
+    fn test_dns_redirect_skips_root_and_is_removed_with_the_rules() {
+        let script = build_dns_redirect_script(DNS_PROXY_PORT);
+        let lines: Vec<&str> = script.lines().filter(|l| !l.is_empty()).collect();
+        // Root's own queries return before the redirects, or the proxy would loop
+        let exempt = lines.iter().position(|l| l.contains("--uid-owner 0 -j RETURN")).unwrap();
+        let redirect = format!("-p udp --dport 53 -j REDIRECT --to-ports {}", DNS_PROXY_PORT);
+        assert!(lines.iter().position(|l| l.ends_with(&redirect)).unwrap() > exempt);
+        assert!(script.contains(&format!("-p tcp --dport 53 -j REDIRECT --to-ports {}", DNS_PROXY_PORT)));
+        // Applying again refills the chain rather than stacking jumps
+        assert!(script.contains("iptables -t nat -F PARENTSHIELD_DNS\n"));
+        assert!(script.contains("iptables -t nat -C OUTPUT -j PARENTSHIELD_DNS 2>/dev/null ||"));
+
+        assert!(build_dns_redirect_removal_script().contains("iptables -t nat -D OUTPUT -j PARENTSHIELD_DNS"));
+    }
+
+    #[test]
+    fn test_ipv6_dns_cannot_go_around_the_redirect() {
+        let script = build_dns_redirect_script(DNS_PROXY_PORT);
+        let lines: Vec<&str> = script.lines().filter(|l| l.starts_with("ip6tables")).collect();
+        let exempt = lines.iter().position(|l| l.contains("--uid-owner 0 -j RETURN")).unwrap();
+        let udp = lines.iter().position(|l| l.contains("-p udp --dport 53 -j DROP")).unwrap();
+        let tcp = lines.iter().position(|l| l.contains("-p tcp --dport 53 -j DROP")).unwrap();
+        assert!(exempt < udp && exempt < tcp);
+        assert!(script.contains("ip6tables -C OUTPUT -j PARENTSHIELD_DNS 2>/dev/null ||"));
+
+        assert!(build_dns_redirect_removal_script().contains("ip6tables -D OUTPUT -j PARENTSHIELD_DNS"));
+        assert!(build_dns_redirect_removal_script().contains("ip6tables -X PARENTSHIELD_DNS"));
+    }
+
+    #[test]
+    fn test_removal_script_clears_rules_and_heartbeat() {
+        let script = build_removal_script();
+        assert!(script.contains(&format!("iptables -X {} 2>/dev/null || true", CHAIN_NAME)));
+        assert!(script.contains(&build_dns_redirect_removal_script()));
+        assert!(script.contains(&format!("rm -f {}\n", FIREWALL_HEARTBEAT_PATH)));
+    }
+
+    #[test]
+    fn test_rules_kept_while_blocking_configured() {
+        assert!(!firewall_rules_are_stale(None, 10_000, 600, true));
+        assert!(!firewall_rules_are_stale(Some(0), 10_000, 600, true));
+    }
+
+    #[test]
+    fn test_rules_stale_after_ttl_without_config() {
+        assert!(firewall_rules_are_stale(Some(1_000), 2_000, 600, false));
+        assert!(!firewall_rules_are_stale(Some(1_500), 2_000, 600, false));
+    }
+
+    #[test]
+    fn test_rules_without_heartbeat_are_stale() {
+        assert!(firewall_rules_are_stale(None, 2_000, 600, false));
+    }
+
+    #[test]
+    fn test_heartbeat_in_future_is_fresh() {
+        // Clock moved backwards since the last refresh
+        assert!(!firewall_rules_are_stale(Some(5_000), 2_000, 600, false));
+    }
+}
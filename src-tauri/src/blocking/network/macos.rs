@@ -1,5 +1,6 @@
 //! macOS network configuration using pf and scutil.
 
+use super::vpn_ports::{configured_vpn_ports, VpnProtocol, DEFAULT_VPN_PORTS};
 use std::fs;
 use std::process::Command;
 use thiserror::Error;
@@ -21,9 +22,10 @@ pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), MacOSNetworkError> {
     // Create pf rules file
     let rules = format!(
         r#"# ParentShield DNS redirect rules
-rdr pass on lo0 proto udp from any to any port 53 -> 127.0.0.1 port {}
+rdr pass on lo0 proto udp from any to any port 53 -> 127.0.0.1 port {port}
+rdr pass on lo0 proto tcp from any to any port 53 -> 127.0.0.1 port {port}
 "#,
-        proxy_port
+        port = proxy_port
     );
 
     fs::write(PF_RULES_PATH, &rules)?;
@@ -71,16 +73,18 @@ pub fn remove_dns_redirect() -> Result<(), MacOSNetworkError> {
     Ok(())
 }
 
+/// pf rules for the common VPN ports and the `extra` ones
+fn build_vpn_pf_rules(extra: &[(u16, VpnProtocol)]) -> String {
+    let mut rules = String::from("# ParentShield VPN blocking rules\n");
+    for (port, protocol) in DEFAULT_VPN_PORTS.iter().chain(extra) {
+        rules.push_str(&format!("block out proto {} to any port {}\n", protocol, port));
+    }
+    rules
+}
+
 /// Block common VPN ports using pf
 pub fn block_vpn_ports() -> Result<(), MacOSNetworkError> {
-    let rules = r#"# ParentShield VPN blocking rules
-block out proto udp to any port 1194   # OpenVPN
-block out proto tcp to any port 1194   # OpenVPN
-block out proto udp to any port 500    # IKEv2
-block out proto udp to any port 4500   # IKEv2 NAT-T
-block out proto udp to any port 51820  # WireGuard
-block out proto udp to any port 1701   # L2TP
-"#;
+    let rules = build_vpn_pf_rules(&configured_vpn_ports());
 
     let vpn_rules_path = "/etc/pf.anchors/parentshield-vpn";
     fs::write(vpn_rules_path, rules)?;
@@ -1,6 +1,13 @@
 //! Network and DNS blocking modules.
 
+pub mod backend;
+pub mod block_log;
+pub mod dns_anomaly;
+pub mod dns_cache;
 pub mod dns_proxy;
+pub mod doh_ips;
+pub mod upnp;
+pub mod vpn_ports;
 
 #[cfg(target_os = "linux")]
 pub mod linux;
@@ -9,4 +16,8 @@ pub mod macos;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
+pub use backend::{BackendError, FirewallBackend};
+pub use block_log::{BlockLogger, BlockedQuery};
+pub use dns_anomaly::{DnsAnomaly, DnsAnomalyConfig};
 pub use dns_proxy::*;
+pub use vpn_ports::VpnProtocol;
@@ -0,0 +1,624 @@
+//! EXPERIMENTAL router backend using UPnP IGD.
+//!
+//! Consoles and game launchers ask the router for UPnP port mappings so other
+//! players can reach them. Removing those mappings for known game ports
+//! degrades online play for every device on the LAN. This cannot stop outbound
+//! traffic and games may re-request mappings, so the daemon re-applies it
+//! periodically. Removed mappings are saved to disk so they can be put back
+//! even after a daemon restart. Opt-in only (`experimental_upnp_blocking`).
+
+use super::backend::{BackendError, FirewallBackend};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const IGD_SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on mappings enumerated from a router
+const MAX_MAPPINGS: u32 = 256;
+
+/// Mappings taken off the router, kept until they are restored
+#[cfg(unix)]
+pub const REMOVED_MAPPINGS_PATH: &str = "/var/lib/parentshield/upnp-removed.json";
+#[cfg(windows)]
+pub const REMOVED_MAPPINGS_PATH: &str = r"C:\ProgramData\ParentShield\upnp-removed.json";
+
+/// Known game ports (start, end, protocol) used for NAT traversal. Games that
+/// map ports out of the ephemeral range (Roblox) aren't listed: matching that
+/// range would take down every device's calls and other apps too.
+const GAME_PORTS: &[(u16, u16, &str)] = &[
+    (3074, 3074, "UDP"),   // Xbox Live
+    (3074, 3074, "TCP"),   // Xbox Live
+    (3478, 3480, "UDP"),   // PlayStation Network
+    (3478, 3480, "TCP"),   // PlayStation Network
+    (27015, 27030, "UDP"), // Steam / Source games
+    (27036, 27037, "TCP"), // Steam in-home streaming
+    (25565, 25565, "TCP"), // Minecraft Java
+    (19132, 19133, "UDP"), // Minecraft Bedrock
+    (5795, 5847, "UDP"),   // Fortnite / Epic
+];
+
+/// Check whether an external port belongs to a known game
+pub fn is_game_port(port: u16, protocol: &str) -> bool {
+    GAME_PORTS
+        .iter()
+        .any(|(start, end, proto)| port >= *start && port <= *end && proto.eq_ignore_ascii_case(protocol))
+}
+
+/// A router port mapping as reported by GetGenericPortMappingEntry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub remote_host: String,
+    pub external_port: u16,
+    pub protocol: String,
+    pub internal_port: u16,
+    pub internal_client: String,
+    pub enabled: bool,
+    pub description: String,
+    pub lease_duration: u32,
+}
+
+/// The WAN connection service of an Internet Gateway Device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpnpGateway {
+    /// Absolute control URL (http://host:port/path)
+    pub control_url: String,
+    pub service_type: String,
+}
+
+/// Sends SOAP actions to the router; mocked in tests
+pub trait UpnpTransport: Send + Sync {
+    fn soap_request(
+        &self,
+        gateway: &UpnpGateway,
+        action: &str,
+        body: &str,
+    ) -> Result<String, BackendError>;
+}
+
+/// Build the SOAP envelope for an action with `(name, value)` arguments
+pub fn build_soap_body(service_type: &str, action: &str, args: &[(&str, String)]) -> String {
+    let args: String = args
+        .iter()
+        .map(|(name, value)| format!("<{name}>{}</{name}>", xml_escape(value)))
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\"?>\r\n\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service_type}\">{args}</u:{action}></s:Body>\
+         </s:Envelope>\r\n"
+    )
+}
+
+/// Build the raw HTTP request carrying a SOAP action
+pub fn build_soap_http_request(gateway: &UpnpGateway, action: &str, body: &str) -> Option<String> {
+    let (host, path) = split_http_url(&gateway.control_url)?;
+    Some(format!(
+        "POST {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: text/xml; charset=\"utf-8\"\r\n\
+         SOAPAction: \"{}#{action}\"\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        gateway.service_type,
+        body.len()
+    ))
+}
+
+/// Split `http://host:port/path` into (`host:port`, `/path`)
+pub fn split_http_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let host = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    Some((host, path.to_string()))
+}
+
+/// Extract the LOCATION header from an SSDP response
+pub fn parse_ssdp_location(response: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("location") {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the WANIPConnection (or WANPPPConnection) service in a device description
+pub fn parse_gateway_description(location: &str, xml: &str) -> Option<UpnpGateway> {
+    let (host, _) = split_http_url(location)?;
+
+    xml.split("<service>").skip(1).find_map(|block| {
+        let block = block.split("</service>").next()?;
+        let service_type = xml_tag(block, "serviceType")?;
+        if !service_type.contains(":WANIPConnection:") && !service_type.contains(":WANPPPConnection:") {
+            return None;
+        }
+        let control = xml_tag(block, "controlURL")?;
+        let control_url = if control.starts_with("http://") {
+            control.to_string()
+        } else if control.starts_with('/') {
+            format!("http://{}{}", host, control)
+        } else {
+            format!("http://{}/{}", host, control)
+        };
+
+        Some(UpnpGateway {
+            control_url,
+            service_type: service_type.to_string(),
+        })
+    })
+}
+
+/// Parse a GetGenericPortMappingEntry response
+pub fn parse_port_mapping(xml: &str) -> Option<PortMapping> {
+    Some(PortMapping {
+        remote_host: xml_tag(xml, "NewRemoteHost").unwrap_or("").to_string(),
+        external_port: xml_tag(xml, "NewExternalPort")?.trim().parse().ok()?,
+        protocol: xml_tag(xml, "NewProtocol")?.trim().to_uppercase(),
+        internal_port: xml_tag(xml, "NewInternalPort")?.trim().parse().ok()?,
+        internal_client: xml_tag(xml, "NewInternalClient")?.trim().to_string(),
+        enabled: xml_tag(xml, "NewEnabled").map(|v| v.trim() == "1").unwrap_or(true),
+        description: xml_tag(xml, "NewPortMappingDescription").unwrap_or("").to_string(),
+        lease_duration: xml_tag(xml, "NewLeaseDuration")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+/// Text content of the first `<tag>...</tag>` (namespace prefixes are not used by IGD)
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(&xml[start..end])
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// SOAP over a plain HTTP/1.1 connection
+pub struct HttpTransport;
+
+impl UpnpTransport for HttpTransport {
+    fn soap_request(
+        &self,
+        gateway: &UpnpGateway,
+        action: &str,
+        body: &str,
+    ) -> Result<String, BackendError> {
+        let request = build_soap_http_request(gateway, action, body)
+            .ok_or_else(|| BackendError::Unavailable(format!("Bad control URL {}", gateway.control_url)))?;
+        let (host, _) = split_http_url(&gateway.control_url)
+            .ok_or_else(|| BackendError::Unavailable(format!("Bad control URL {}", gateway.control_url)))?;
+
+        let (status, body) = http_exchange(&host, &request)?;
+        if status != 200 {
+            return Err(BackendError::RequestFailed(format!("{} returned HTTP {}", action, status)));
+        }
+        Ok(body)
+    }
+}
+
+/// Send a raw HTTP request and return (status, body)
+fn http_exchange(host: &str, request: &str) -> Result<(u16, String), BackendError> {
+    let addr = host
+        .parse()
+        .map_err(|e| BackendError::Unavailable(format!("Bad router address {}: {}", host, e)))?;
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)
+        .map_err(|e| BackendError::Unavailable(e.to_string()))?;
+    let _ = stream.set_read_timeout(Some(HTTP_TIMEOUT));
+
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+    let status = response
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| BackendError::RequestFailed("Malformed HTTP response".to_string()))?;
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+
+    Ok((status, body))
+}
+
+/// Find the router's WAN connection service with SSDP
+pub fn discover_gateway(timeout: Duration) -> Result<UpnpGateway, BackendError> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| BackendError::Unavailable(e.to_string()))?;
+    let _ = socket.set_read_timeout(Some(timeout));
+
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP_ADDR, IGD_SEARCH_TARGET
+    );
+    socket
+        .send_to(search.as_bytes(), SSDP_ADDR)
+        .map_err(|e| BackendError::Unavailable(e.to_string()))?;
+
+    let mut buf = [0u8; 2048];
+    let (len, _) = socket
+        .recv_from(&mut buf)
+        .map_err(|_| BackendError::Unavailable("No UPnP gateway answered".to_string()))?;
+    let location = parse_ssdp_location(&String::from_utf8_lossy(&buf[..len]))
+        .ok_or_else(|| BackendError::Unavailable("Gateway sent no LOCATION".to_string()))?;
+
+    let (host, path) = split_http_url(&location)
+        .ok_or_else(|| BackendError::Unavailable(format!("Unsupported location {}", location)))?;
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host);
+    let (_, description) = http_exchange(&host, &request)?;
+
+    parse_gateway_description(&location, &description)
+        .ok_or_else(|| BackendError::Unavailable("Gateway has no WAN connection service".to_string()))
+}
+
+/// Mappings saved at `path`, or none if there is no readable file
+pub fn load_removed_mappings(path: &Path) -> Vec<PortMapping> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Save the mappings to restore at `path`, deleting the file once there are none
+fn save_removed_mappings(path: &Path, mappings: &[PortMapping]) -> std::io::Result<()> {
+    if mappings.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(mappings).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+/// Removes game port mappings from the router and restores them on removal
+pub struct UpnpBackend {
+    gateway: UpnpGateway,
+    transport: Box<dyn UpnpTransport>,
+    removed: Mutex<Vec<PortMapping>>,
+    /// Where `removed` is saved, so a restarted daemon can still restore it
+    state_file: Option<PathBuf>,
+}
+
+impl UpnpBackend {
+    pub fn new(gateway: UpnpGateway, transport: Box<dyn UpnpTransport>) -> Self {
+        Self {
+            gateway,
+            transport,
+            removed: Mutex::new(Vec::new()),
+            state_file: None,
+        }
+    }
+
+    /// Keep removed mappings in `path`, picking up any an earlier run left there
+    pub fn with_state_file(mut self, path: PathBuf) -> Self {
+        self.removed = Mutex::new(load_removed_mappings(&path));
+        self.state_file = Some(path);
+        self
+    }
+
+    /// Discover the router on the LAN and talk to it over HTTP
+    pub fn discover() -> Result<Self, BackendError> {
+        Ok(Self::new(discover_gateway(Duration::from_secs(3))?, Box::new(HttpTransport))
+            .with_state_file(PathBuf::from(REMOVED_MAPPINGS_PATH)))
+    }
+
+    fn persist(&self, removed: &[PortMapping]) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        if let Err(e) = save_removed_mappings(path, removed) {
+            tracing::warn!("Failed to save removed UPnP mappings to {}: {}", path.display(), e);
+        }
+    }
+
+    fn call(&self, action: &str, args: &[(&str, String)]) -> Result<String, BackendError> {
+        let body = build_soap_body(&self.gateway.service_type, action, args);
+        self.transport.soap_request(&self.gateway, action, &body)
+    }
+
+    /// List the router's port mappings
+    pub fn list_mappings(&self) -> Vec<PortMapping> {
+        let mut mappings = Vec::new();
+        for index in 0..MAX_MAPPINGS {
+            // Routers signal the end of the table with an error (SpecifiedArrayIndexInvalid)
+            let Ok(response) = self.call(
+                "GetGenericPortMappingEntry",
+                &[("NewPortMappingIndex", index.to_string())],
+            ) else {
+                break;
+            };
+            match parse_port_mapping(&response) {
+                Some(mapping) => mappings.push(mapping),
+                None => break,
+            }
+        }
+        mappings
+    }
+
+    fn delete_mapping(&self, mapping: &PortMapping) -> Result<(), BackendError> {
+        self.call(
+            "DeletePortMapping",
+            &[
+                ("NewRemoteHost", mapping.remote_host.clone()),
+                ("NewExternalPort", mapping.external_port.to_string()),
+                ("NewProtocol", mapping.protocol.clone()),
+            ],
+        )
+        .map(|_| ())
+    }
+
+    fn add_mapping(&self, mapping: &PortMapping) -> Result<(), BackendError> {
+        self.call(
+            "AddPortMapping",
+            &[
+                ("NewRemoteHost", mapping.remote_host.clone()),
+                ("NewExternalPort", mapping.external_port.to_string()),
+                ("NewProtocol", mapping.protocol.clone()),
+                ("NewInternalPort", mapping.internal_port.to_string()),
+                ("NewInternalClient", mapping.internal_client.clone()),
+                ("NewEnabled", if mapping.enabled { "1" } else { "0" }.to_string()),
+                ("NewPortMappingDescription", mapping.description.clone()),
+                ("NewLeaseDuration", mapping.lease_duration.to_string()),
+            ],
+        )
+        .map(|_| ())
+    }
+}
+
+impl FirewallBackend for UpnpBackend {
+    fn name(&self) -> &'static str {
+        "upnp"
+    }
+
+    fn apply(&self) -> Result<(), BackendError> {
+        let game_mappings: Vec<PortMapping> = self
+            .list_mappings()
+            .into_iter()
+            .filter(|m| is_game_port(m.external_port, &m.protocol))
+            .collect();
+
+        let mut removed = self
+            .removed
+            .lock()
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+        for mapping in game_mappings {
+            match self.delete_mapping(&mapping) {
+                Ok(()) => {
+                    tracing::info!(
+                        "Removed UPnP mapping {}/{} -> {}",
+                        mapping.external_port,
+                        mapping.protocol,
+                        mapping.internal_client
+                    );
+                    // A game that re-requested its mapping replaces the saved copy
+                    removed.retain(|m| {
+                        (m.external_port, &m.protocol, &m.remote_host)
+                            != (mapping.external_port, &mapping.protocol, &mapping.remote_host)
+                    });
+                    removed.push(mapping);
+                }
+                Err(e) => tracing::warn!("Failed to remove UPnP mapping {}: {}", mapping.external_port, e),
+            }
+        }
+        self.persist(&removed);
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<(), BackendError> {
+        let mut removed = self
+            .removed
+            .lock()
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+        for mapping in removed.drain(..) {
+            if let Err(e) = self.add_mapping(&mapping) {
+                tracing::warn!("Failed to restore UPnP mapping {}: {}", mapping.external_port, e);
+            }
+        }
+        self.persist(&removed);
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.removed.lock().map(|r| !r.is_empty()).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const SERVICE: &str = "urn:schemas-upnp-org:service:WANIPConnection:1";
+
+    fn gateway() -> UpnpGateway {
+        UpnpGateway {
+            control_url: "http://192.168.1.1:5000/ctl/IPConn".to_string(),
+            service_type: SERVICE.to_string(),
+        }
+    }
+
+    fn mapping_response(port: u16, protocol: &str) -> String {
+        format!(
+            "<s:Envelope><s:Body><u:GetGenericPortMappingEntryResponse>\
+             <NewRemoteHost></NewRemoteHost><NewExternalPort>{port}</NewExternalPort>\
+             <NewProtocol>{protocol}</NewProtocol><NewInternalPort>{port}</NewInternalPort>\
+             <NewInternalClient>192.168.1.50</NewInternalClient><NewEnabled>1</NewEnabled>\
+             <NewPortMappingDescription>Xbox</NewPortMappingDescription>\
+             <NewLeaseDuration>0</NewLeaseDuration>\
+             </u:GetGenericPortMappingEntryResponse></s:Body></s:Envelope>"
+        )
+    }
+
+    /// Router with a fixed mapping table that records every action body
+    struct MockDevice {
+        mappings: Vec<(u16, &'static str)>,
+        requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl UpnpTransport for MockDevice {
+        fn soap_request(&self, _gateway: &UpnpGateway, action: &str, body: &str) -> Result<String, BackendError> {
+            self.requests.lock().unwrap().push((action.to_string(), body.to_string()));
+            if action == "GetGenericPortMappingEntry" {
+                let index: usize = xml_tag(body, "NewPortMappingIndex").unwrap().parse().unwrap();
+                return match self.mappings.get(index) {
+                    Some((port, proto)) => Ok(mapping_response(*port, proto)),
+                    None => Err(BackendError::RequestFailed("HTTP 500".to_string())),
+                };
+            }
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn test_build_soap_request() {
+        let body = build_soap_body(
+            SERVICE,
+            "DeletePortMapping",
+            &[("NewExternalPort", "3074".to_string()), ("NewProtocol", "UDP".to_string())],
+        );
+        assert!(body.contains(&format!("<u:DeletePortMapping xmlns:u=\"{}\">", SERVICE)));
+        assert!(body.contains("<NewExternalPort>3074</NewExternalPort><NewProtocol>UDP</NewProtocol>"));
+
+        let request = build_soap_http_request(&gateway(), "DeletePortMapping", &body).unwrap();
+        assert!(request.starts_with("POST /ctl/IPConn HTTP/1.1\r\nHost: 192.168.1.1:5000\r\n"));
+        assert!(request.contains(&format!("SOAPAction: \"{}#DeletePortMapping\"", SERVICE)));
+        assert!(request.contains(&format!("Content-Length: {}", body.len())));
+        assert!(request.ends_with(&body));
+    }
+
+    #[test]
+    fn test_parse_discovery() {
+        let ssdp = "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\n\r\n";
+        let location = parse_ssdp_location(ssdp).unwrap();
+        assert_eq!(location, "http://192.168.1.1:5000/rootDesc.xml");
+
+        let description = "<root><device><serviceList>\
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType><controlURL>/ctl/L3F</controlURL></service>\
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/ctl/IPConn</controlURL></service>\
+            </serviceList></device></root>";
+        assert_eq!(parse_gateway_description(&location, description), Some(gateway()));
+    }
+
+    #[test]
+    fn test_apply_removes_only_game_mappings_and_remove_restores_them() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let device = MockDevice {
+            mappings: vec![(3074, "UDP"), (8080, "TCP"), (27015, "UDP")],
+            requests: Arc::clone(&requests),
+        };
+        let backend = UpnpBackend::new(gateway(), Box::new(device));
+
+        backend.apply().unwrap();
+        assert!(backend.is_active());
+
+        let deleted: Vec<String> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(action, _)| action == "DeletePortMapping")
+            .map(|(_, body)| xml_tag(body, "NewExternalPort").unwrap().to_string())
+            .collect();
+        assert_eq!(deleted, vec!["3074", "27015"]);
+
+        backend.remove().unwrap();
+        assert!(!backend.is_active());
+
+        let restored: Vec<(String, String)> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(action, _)| action == "AddPortMapping")
+            .map(|(_, body)| {
+                (
+                    xml_tag(body, "NewExternalPort").unwrap().to_string(),
+                    xml_tag(body, "NewInternalClient").unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            restored,
+            vec![
+                ("3074".to_string(), "192.168.1.50".to_string()),
+                ("27015".to_string(), "192.168.1.50".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removed_mappings_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upnp-removed.json");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let device = || MockDevice {
+            mappings: vec![(3074, "UDP"), (8080, "TCP")],
+            requests: Arc::clone(&requests),
+        };
+
+        let backend = UpnpBackend::new(gateway(), Box::new(device())).with_state_file(path.clone());
+        backend.apply().unwrap();
+        // A game re-requesting its mapping between applies isn't saved twice
+        backend.apply().unwrap();
+        drop(backend);
+        assert_eq!(load_removed_mappings(&path).len(), 1);
+
+        // A new daemon process restores what the old one removed
+        let backend = UpnpBackend::new(gateway(), Box::new(device())).with_state_file(path.clone());
+        assert!(backend.is_active());
+        backend.remove().unwrap();
+        let restored = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(action, _)| action == "AddPortMapping")
+            .count();
+        assert_eq!(restored, 1);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_is_game_port() {
+        assert!(is_game_port(3074, "udp"));
+        assert!(is_game_port(27020, "UDP"));
+        assert!(!is_game_port(27020, "TCP"));
+        assert!(!is_game_port(443, "TCP"));
+        // Ephemeral ports belong to every app on the LAN
+        assert!(!is_game_port(50000, "UDP"));
+    }
+}
@@ -0,0 +1,107 @@
+//! VPN ports the firewall blocks. Common VPN protocols are always covered;
+//! parents can add ports for anything else a child set up (Tailscale,
+//! Shadowsocks, WireGuard on a custom port) in `extra_vpn_blocks`.
+
+use crate::config::ConfigManager;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Transport protocol of a blocked port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VpnProtocol {
+    Tcp,
+    Udp,
+}
+
+impl VpnProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VpnProtocol::Tcp => "tcp",
+            VpnProtocol::Udp => "udp",
+        }
+    }
+}
+
+impl fmt::Display for VpnProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VpnProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tcp" => Ok(VpnProtocol::Tcp),
+            "udp" => Ok(VpnProtocol::Udp),
+            other => Err(format!("Unsupported protocol {:?} (expected tcp or udp)", other)),
+        }
+    }
+}
+
+/// Ports of common VPN protocols, blocked whenever the firewall is on
+pub const DEFAULT_VPN_PORTS: [(u16, VpnProtocol); 6] = [
+    (1194, VpnProtocol::Udp),  // OpenVPN
+    (1194, VpnProtocol::Tcp),  // OpenVPN
+    (500, VpnProtocol::Udp),   // IKEv2/IPSec
+    (4500, VpnProtocol::Udp),  // IKEv2 NAT-T
+    (51820, VpnProtocol::Udp), // WireGuard
+    (1701, VpnProtocol::Udp),  // L2TP
+];
+
+/// Check a port and protocol from the GUI before storing them
+pub fn parse_vpn_block(port: u16, protocol: &str) -> Result<(u16, VpnProtocol), String> {
+    if port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    Ok((port, protocol.parse()?))
+}
+
+/// Extra ports from the config that the defaults don't already cover
+pub fn extra_vpn_ports(extra: &[(u16, VpnProtocol)]) -> Vec<(u16, VpnProtocol)> {
+    let mut ports: Vec<(u16, VpnProtocol)> = Vec::new();
+    for &block in extra {
+        if block.0 != 0 && !DEFAULT_VPN_PORTS.contains(&block) && !ports.contains(&block) {
+            ports.push(block);
+        }
+    }
+    ports
+}
+
+/// Extra VPN ports from the config, or none if it can't be read
+pub fn configured_vpn_ports() -> Vec<(u16, VpnProtocol)> {
+    ConfigManager::new()
+        .and_then(|m| m.load())
+        .map(|config| extra_vpn_ports(&config.extra_vpn_blocks))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vpn_block() {
+        assert_eq!(parse_vpn_block(41641, "UDP"), Ok((41641, VpnProtocol::Udp)));
+        assert_eq!(parse_vpn_block(8388, " tcp "), Ok((8388, VpnProtocol::Tcp)));
+        assert!(parse_vpn_block(0, "udp").is_err());
+        assert!(parse_vpn_block(41641, "icmp").is_err());
+    }
+
+    #[test]
+    fn test_extra_ports_skip_defaults_and_duplicates() {
+        let extra = [
+            (41641, VpnProtocol::Udp),
+            (51820, VpnProtocol::Udp),
+            (41641, VpnProtocol::Udp),
+            (51820, VpnProtocol::Tcp),
+        ];
+        assert_eq!(
+            extra_vpn_ports(&extra),
+            vec![(41641, VpnProtocol::Udp), (51820, VpnProtocol::Tcp)]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Windows network configuration using netsh and Windows Firewall.
 
+use super::vpn_ports::{configured_vpn_ports, VpnProtocol};
 use std::process::Command;
 use thiserror::Error;
 
@@ -11,8 +12,9 @@ pub enum WindowsNetworkError {
     AdminRequired,
 }
 
-/// Configure DNS settings to use local proxy
-pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), WindowsNetworkError> {
+/// Configure DNS settings to use local proxy. Adapter DNS settings carry no
+/// port, so the proxy listens on 53 here (see `DNS_PROXY_PORT`).
+pub fn setup_dns_redirect() -> Result<(), WindowsNetworkError> {
     // Get active network adapters and set DNS
     let output = Command::new("netsh")
         .args([
@@ -22,7 +24,7 @@ pub fn setup_dns_redirect(proxy_port: u16) -> Result<(), WindowsNetworkError> {
             "dns",
             "name=\"Local Area Connection\"",
             "static",
-            &format!("127.0.0.1:{}", proxy_port),
+            "127.0.0.1",
         ])
         .output()
         .map_err(|e| WindowsNetworkError::CommandFailed(e.to_string()))?;
@@ -74,18 +76,37 @@ pub fn remove_dns_redirect() -> Result<(), WindowsNetworkError> {
     Ok(())
 }
 
+/// Name prefix of the rules for the ports parents add
+const CUSTOM_VPN_RULE_PREFIX: &str = "BlockCustomVPN-";
+
+/// Firewall rule name, protocol and port for the common VPN ports and the `extra` ones
+fn vpn_firewall_rules(extra: &[(u16, VpnProtocol)]) -> Vec<(String, String, u16)> {
+    let mut rules: Vec<(String, String, u16)> = [
+        ("BlockOpenVPN-UDP", "UDP", 1194),
+        ("BlockOpenVPN-TCP", "TCP", 1194),
+        ("BlockIKEv2", "UDP", 500),
+        ("BlockIKEv2-NAT", "UDP", 4500),
+        ("BlockWireGuard", "UDP", 51820),
+        ("BlockL2TP", "UDP", 1701),
+    ]
+    .into_iter()
+    .map(|(name, protocol, port)| (name.to_string(), protocol.to_string(), port))
+    .collect();
+
+    for (port, protocol) in extra {
+        let protocol = protocol.as_str().to_uppercase();
+        rules.push((format!("{}{}-{}", CUSTOM_VPN_RULE_PREFIX, protocol, port), protocol, *port));
+    }
+    rules
+}
+
 /// Block common VPN ports using Windows Firewall
 pub fn block_vpn_ports() -> Result<(), WindowsNetworkError> {
-    let vpn_rules = [
-        ("BlockOpenVPN-UDP", "UDP", "1194"),
-        ("BlockOpenVPN-TCP", "TCP", "1194"),
-        ("BlockIKEv2", "UDP", "500"),
-        ("BlockIKEv2-NAT", "UDP", "4500"),
-        ("BlockWireGuard", "UDP", "51820"),
-        ("BlockL2TP", "UDP", "1701"),
-    ];
-
-    for (name, protocol, port) in vpn_rules {
+    // Ports the parent has since removed shouldn't stay blocked
+    delete_custom_vpn_rules();
+    for (name, protocol, port) in vpn_firewall_rules(&configured_vpn_ports()) {
+        // netsh adds a second rule under the same name, so replace rather than stack
+        delete_firewall_rule(&name);
         let output = Command::new("netsh")
             .args([
                 "advfirewall",
@@ -116,26 +137,41 @@ pub fn block_vpn_ports() -> Result<(), WindowsNetworkError> {
 
 /// Unblock VPN ports
 pub fn unblock_vpn_ports() -> Result<(), WindowsNetworkError> {
-    let rule_names = [
-        "BlockOpenVPN-UDP",
-        "BlockOpenVPN-TCP",
-        "BlockIKEv2",
-        "BlockIKEv2-NAT",
-        "BlockWireGuard",
-        "BlockL2TP",
-    ];
-
-    for name in rule_names {
-        let _ = Command::new("netsh")
-            .args([
-                "advfirewall",
-                "firewall",
-                "delete",
-                "rule",
-                &format!("name={}", name),
-            ])
-            .output();
+    for (name, _, _) in vpn_firewall_rules(&[]) {
+        delete_firewall_rule(&name);
     }
+    // Custom rules go by prefix, so ones for ports no longer in the config are removed too
+    delete_custom_vpn_rules();
 
     Ok(())
 }
+
+/// Delete every custom VPN port rule. netsh only deletes by exact name, so
+/// this matches the prefix through PowerShell (rules added by netsh carry
+/// their name as the display name).
+fn delete_custom_vpn_rules() {
+    let _ = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            &format!(
+                "Remove-NetFirewallRule -DisplayName '{}*' -ErrorAction SilentlyContinue",
+                CUSTOM_VPN_RULE_PREFIX
+            ),
+        ])
+        .output();
+}
+
+/// Delete every firewall rule called `name`
+fn delete_firewall_rule(name: &str) {
+    let _ = Command::new("netsh")
+        .args([
+            "advfirewall",
+            "firewall",
+            "delete",
+            "rule",
+            &format!("name={}", name),
+        ])
+        .output();
+}
@@ -0,0 +1,152 @@
+//! Best-effort desktop notifications shown when a blocked app is closed.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Minimum time between two notifications about the same app, so a game
+/// that restarts itself doesn't flood the desktop
+pub const NOTIFY_COOLDOWN: Duration = Duration::from_secs(60);
+
+static LIMITER: Lazy<Mutex<NotifyLimiter>> = Lazy::new(|| Mutex::new(NotifyLimiter::new(NOTIFY_COOLDOWN)));
+
+/// When each app was last notified about
+#[derive(Debug)]
+pub struct NotifyLimiter {
+    cooldown: Duration,
+    last: HashMap<String, Instant>,
+}
+
+impl NotifyLimiter {
+    pub fn new(cooldown: Duration) -> Self {
+        Self {
+            cooldown,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Whether a notification about `name` may be shown at `now`; records it if so
+    pub fn allow(&mut self, name: &str, now: Instant) -> bool {
+        let cooldown = self.cooldown;
+        self.last.retain(|_, shown| now.duration_since(*shown) < cooldown);
+        let name = name.to_lowercase();
+        if self.last.contains_key(&name) {
+            return false;
+        }
+        self.last.insert(name, now);
+        true
+    }
+}
+
+/// Whether a notification about `name` is due under the shared cooldown
+pub fn notification_due(name: &str) -> bool {
+    LIMITER
+        .lock()
+        .map(|mut limiter| limiter.allow(name, Instant::now()))
+        .unwrap_or(true)
+}
+
+/// Tell the desktop that `name` was blocked ("ParentShield blocked Minecraft"),
+/// at most once per [`NOTIFY_COOLDOWN`] per app.
+pub fn notify_blocked(name: &str) {
+    if notification_due(name) {
+        show_block_message(format!("ParentShield blocked {}", name));
+    }
+}
+
+/// Show a notification without blocking the caller.
+/// Failures are logged and otherwise ignored.
+pub fn show_block_message(message: String) {
+    std::thread::spawn(move || {
+        if let Err(e) = show_notification(&message) {
+            tracing::debug!("Could not show block notification: {}", e);
+        }
+    });
+}
+
+/// Show a notification in a specific user's desktop session.
+/// The daemon runs as root, so on Linux the message is sent over that user's session bus.
+pub fn show_block_message_to_user(message: String, user: String, uid: u32) {
+    std::thread::spawn(move || {
+        if let Err(e) = show_notification_to_user(&message, &user, uid) {
+            tracing::debug!("Could not show block notification to {}: {}", user, e);
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn show_notification_to_user(message: &str, user: &str, uid: u32) -> std::io::Result<()> {
+    let bus = format!("DBUS_SESSION_BUS_ADDRESS=unix:path=/run/user/{}/bus", uid);
+    Command::new("runuser")
+        .args([
+            "-u",
+            user,
+            "--",
+            "env",
+            &bus,
+            "notify-send",
+            "--app-name=ParentShield",
+            "ParentShield",
+            message,
+        ])
+        .output()
+        .map(|_| ())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn show_notification_to_user(message: &str, _user: &str, _uid: u32) -> std::io::Result<()> {
+    show_notification(message)
+}
+
+#[cfg(target_os = "linux")]
+fn show_notification(message: &str) -> std::io::Result<()> {
+    Command::new("notify-send")
+        .args(["--app-name=ParentShield", "ParentShield", message])
+        .output()
+        .map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn show_notification(message: &str) -> std::io::Result<()> {
+    let script = format!(
+        r#"display notification "{}" with title "ParentShield""#,
+        message.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .map(|_| ())
+}
+
+#[cfg(target_os = "windows")]
+fn show_notification(message: &str) -> std::io::Result<()> {
+    // msg.exe reaches interactive sessions even when running as a service
+    Command::new("msg")
+        .args(["*", "/TIME:10", message])
+        .output()
+        .map(|_| ())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn show_notification(_message: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_allows_once_per_cooldown() {
+        let mut limiter = NotifyLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(limiter.allow("Minecraft", start));
+        assert!(!limiter.allow("minecraft", start + Duration::from_secs(10)));
+        // Other apps have their own cooldown
+        assert!(limiter.allow("steam", start + Duration::from_secs(10)));
+        assert!(limiter.allow("minecraft", start + Duration::from_secs(60)));
+    }
+}
@@ -1,15 +1,49 @@
 //! Linux process blocking using procfs.
 
-use super::{ProcessBlocker, ProcessError, ProcessInfo};
+use super::{terminate_with_mode, ProcessBlocker, ProcessError, ProcessInfo, SignalOps, TerminationMode};
 use nix::sys::signal::{kill, Signal};
 use nix::unistd::Pid;
 use procfs::process::all_processes;
+use std::time::Duration;
 
-pub struct LinuxProcessBlocker;
+pub struct LinuxProcessBlocker {
+    /// Time between SIGTERM and the SIGKILL escalation
+    grace: Duration,
+}
 
 impl LinuxProcessBlocker {
-    pub fn new() -> Self {
-        Self
+    pub fn with_grace(grace: Duration) -> Self {
+        Self { grace }
+    }
+}
+
+/// Signal delivery through kill(2)
+struct NixSignals;
+
+impl SignalOps for NixSignals {
+    fn request_exit(&self, pid: u32) -> Result<(), ProcessError> {
+        match kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+            Ok(()) => Ok(()),
+            Err(nix::errno::Errno::ESRCH) => Err(ProcessError::NotFound),
+            Err(nix::errno::Errno::EPERM) => Err(ProcessError::AccessDenied),
+            Err(e) => Err(ProcessError::TerminateFailed(e.to_string())),
+        }
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        // A zombie still answers kill(pid, 0) but has already exited
+        procfs::process::Process::new(pid as i32)
+            .and_then(|process| process.stat())
+            .map(|stat| stat.state != 'Z')
+            .unwrap_or(false)
+    }
+
+    fn force_kill(&self, pid: u32) -> Result<(), ProcessError> {
+        match kill(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+            // Exited between the liveness check and the kill
+            Ok(()) | Err(nix::errno::Errno::ESRCH) => Ok(()),
+            Err(e) => Err(ProcessError::TerminateFailed(e.to_string())),
+        }
     }
 }
 
@@ -28,6 +62,7 @@ impl ProcessBlocker for LinuxProcessBlocker {
                     pid: stat.pid as u32,
                     name: stat.comm.clone(),
                     exe_path,
+                    uid: process.uid().ok(),
                 });
             }
         }
@@ -35,26 +70,22 @@ impl ProcessBlocker for LinuxProcessBlocker {
         Ok(result)
     }
 
-    fn terminate_process(&self, pid: u32) -> Result<(), ProcessError> {
-        let pid = Pid::from_raw(pid as i32);
-
-        // First try SIGTERM for graceful shutdown
-        match kill(pid, Signal::SIGTERM) {
-            Ok(()) => {
-                // Give process time to terminate gracefully
-                std::thread::sleep(std::time::Duration::from_millis(100));
-
-                // Check if still running and force kill
-                if kill(pid, None).is_ok() {
-                    kill(pid, Signal::SIGKILL)
-                        .map_err(|e| ProcessError::TerminateFailed(e.to_string()))?;
-                }
-                Ok(())
-            }
-            Err(nix::errno::Errno::ESRCH) => Err(ProcessError::NotFound),
-            Err(nix::errno::Errno::EPERM) => Err(ProcessError::AccessDenied),
-            Err(e) => Err(ProcessError::TerminateFailed(e.to_string())),
-        }
+    fn terminate_process_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError> {
+        // Graceful: SIGTERM, then SIGKILL once the grace period runs out.
+        // Force: SIGKILL immediately.
+        terminate_with_mode(&NixSignals, pid, self.grace, mode)
+    }
+
+    fn child_pids(&self, pid: u32) -> Result<Vec<u32>, ProcessError> {
+        let processes = all_processes()
+            .map_err(|e| ProcessError::ListFailed(e.to_string()))?;
+
+        Ok(processes
+            .flatten()
+            .filter_map(|process| process.stat().ok())
+            .filter(|stat| stat.ppid as u32 == pid)
+            .map(|stat| stat.pid as u32)
+            .collect())
     }
 }
 
@@ -64,7 +95,7 @@ mod tests {
 
     #[test]
     fn test_list_processes() {
-        let blocker = LinuxProcessBlocker::new();
+        let blocker = LinuxProcessBlocker::with_grace(super::super::DEFAULT_KILL_GRACE);
         let processes = blocker.list_processes().unwrap();
 
         // Should find at least one process (ourselves)
@@ -74,4 +105,39 @@ mod tests {
         let our_pid = std::process::id();
         assert!(processes.iter().any(|p| p.pid == our_pid));
     }
+
+    /// Gone, or a zombie waiting to be reaped
+    fn has_exited(pid: u32) -> bool {
+        procfs::process::Process::new(pid as i32)
+            .and_then(|p| p.stat())
+            .map(|stat| stat.state == 'Z')
+            .unwrap_or(true)
+    }
+
+    #[test]
+    fn test_terminate_process_tree_kills_children() {
+        let blocker = LinuxProcessBlocker::with_grace(super::super::DEFAULT_KILL_GRACE);
+        let mut parent = std::process::Command::new("sh")
+            .args(["-c", "sleep 60 & wait"])
+            .spawn()
+            .unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let child = loop {
+            if let Some(&child) = blocker.child_pids(parent.id()).unwrap().first() {
+                break child;
+            }
+            assert!(std::time::Instant::now() < deadline, "sleep was never started");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        blocker.terminate_process_tree(parent.id()).unwrap();
+
+        assert!(!parent.wait().unwrap().success());
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !has_exited(child) {
+            assert!(std::time::Instant::now() < deadline, "child sleep survived");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
 }
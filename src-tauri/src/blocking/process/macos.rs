@@ -1,13 +1,64 @@
 //! macOS process blocking using sysctl and libproc.
 
-use super::{ProcessBlocker, ProcessError, ProcessInfo};
+use super::{terminate_with_mode, ProcessBlocker, ProcessError, ProcessInfo, SignalOps, TerminationMode};
 use std::process::Command;
+use std::time::Duration;
 
-pub struct MacOSProcessBlocker;
+pub struct MacOSProcessBlocker {
+    /// Time between SIGTERM and the SIGKILL escalation
+    grace: Duration,
+}
 
 impl MacOSProcessBlocker {
-    pub fn new() -> Self {
-        Self
+    pub fn with_grace(grace: Duration) -> Self {
+        Self { grace }
+    }
+}
+
+/// Signal delivery through the kill command
+struct KillCommand;
+
+impl SignalOps for KillCommand {
+    fn request_exit(&self, pid: u32) -> Result<(), ProcessError> {
+        let output = Command::new("kill")
+            .args(["-TERM", &pid.to_string()])
+            .output()
+            .map_err(|e| ProcessError::TerminateFailed(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("No such process") {
+                return Err(ProcessError::NotFound);
+            }
+            if stderr.contains("Operation not permitted") {
+                return Err(ProcessError::AccessDenied);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    fn force_kill(&self, pid: u32) -> Result<(), ProcessError> {
+        let output = Command::new("kill")
+            .args(["-9", &pid.to_string()])
+            .output()
+            .map_err(|e| ProcessError::TerminateFailed(e.to_string()))?;
+
+        if !output.status.success() && self.is_alive(pid) {
+            return Err(ProcessError::TerminateFailed(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
+
+        Ok(())
     }
 }
 
@@ -39,6 +90,7 @@ impl ProcessBlocker for MacOSProcessBlocker {
                         pid,
                         name,
                         exe_path: Some(parts[1].trim().to_string()),
+                        uid: None,
                     });
                 }
             }
@@ -47,36 +99,26 @@ impl ProcessBlocker for MacOSProcessBlocker {
         Ok(processes)
     }
 
-    fn terminate_process(&self, pid: u32) -> Result<(), ProcessError> {
-        // Use kill command
-        let output = Command::new("kill")
-            .args(["-TERM", &pid.to_string()])
-            .output()
-            .map_err(|e| ProcessError::TerminateFailed(e.to_string()))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("No such process") {
-                return Err(ProcessError::NotFound);
-            }
-            if stderr.contains("Operation not permitted") {
-                return Err(ProcessError::AccessDenied);
-            }
+    fn terminate_process_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError> {
+        // Graceful: SIGTERM, then SIGKILL once the grace period runs out.
+        // Force: SIGKILL immediately.
+        terminate_with_mode(&KillCommand, pid, self.grace, mode)
+    }
 
-            // Try SIGKILL
-            let kill_output = Command::new("kill")
-                .args(["-9", &pid.to_string()])
-                .output()
-                .map_err(|e| ProcessError::TerminateFailed(e.to_string()))?;
+    fn child_pids(&self, pid: u32) -> Result<Vec<u32>, ProcessError> {
+        let output = Command::new("pgrep")
+            .args(["-P", &pid.to_string()])
+            .output()
+            .map_err(|e| ProcessError::ListFailed(e.to_string()))?;
 
-            if !kill_output.status.success() {
-                return Err(ProcessError::TerminateFailed(
-                    String::from_utf8_lossy(&kill_output.stderr).to_string(),
-                ));
-            }
+        // pgrep exits 1 when nothing matched
+        match output.status.code() {
+            Some(0) | Some(1) => Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .collect()),
+            _ => Err(ProcessError::ListFailed(String::from_utf8_lossy(&output.stderr).to_string())),
         }
-
-        Ok(())
     }
 }
 
@@ -87,7 +129,7 @@ mod tests {
     #[test]
     #[cfg(target_os = "macos")]
     fn test_list_processes() {
-        let blocker = MacOSProcessBlocker::new();
+        let blocker = MacOSProcessBlocker::with_grace(super::super::DEFAULT_KILL_GRACE);
         let processes = blocker.list_processes().unwrap();
 
         // Should find at least one process
@@ -7,9 +7,68 @@ mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
-use std::collections::HashSet;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Default time a process gets to exit after the polite signal before it is force-killed
+pub const DEFAULT_KILL_GRACE: Duration = Duration::from_millis(100);
+
+/// Longest grace honoured, however large `kill_grace_ms` is set; the blocking
+/// loop waits this long per stubborn process
+pub const MAX_KILL_GRACE: Duration = Duration::from_secs(10);
+
+/// How often to check whether a signalled process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Launchers that respawn games from helper processes, so the whole tree is
+/// terminated rather than the matched PID alone
+const KNOWN_LAUNCHERS: &[&str] = &[
+    "steam",
+    "steamwebhelper",
+    "epicgameslauncher",
+    "battle.net",
+    "origin",
+    "eadesktop",
+    "riotclientservices",
+    "upc",
+    "galaxyclient",
+    "minecraft-launcher",
+    "robloxplayerlauncher",
+    "lutris",
+    "heroic",
+];
+
+/// Whether `name` (with or without `.exe`) is a known game launcher
+pub fn is_known_launcher(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    KNOWN_LAUNCHERS.contains(&name)
+}
+
+/// `root` and its descendants, root-first so each parent is gone before its
+/// children are killed and can't respawn them. `children` lists a PID's
+/// direct children.
+pub(crate) fn process_tree<F>(root: u32, mut children: F) -> Vec<u32>
+where
+    F: FnMut(u32) -> Vec<u32>,
+{
+    let mut tree = vec![root];
+    let mut next = 0;
+    while next < tree.len() {
+        for child in children(tree[next]) {
+            // A PID can't appear twice in a real tree; guard against races
+            if !tree.contains(&child) {
+                tree.push(child);
+            }
+        }
+        next += 1;
+    }
+    tree
+}
+
 /// Errors that can occur during process operations
 #[derive(Error, Debug)]
 pub enum ProcessError {
@@ -29,6 +88,206 @@ pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
     pub exe_path: Option<String>,
+    /// Owning user ID, where the platform reports it
+    pub uid: Option<u32>,
+}
+
+/// Blocklist entries that are globs (`steam*`) or executable paths
+/// (`/opt/games/*`, `C:\Games\*`), compiled once per check. `*` matches any
+/// run of characters and `?` a single one; matching ignores case. Plain names
+/// are kept for [`matches_blocked`]; `matching_rule` leaves them to the exact
+/// and keyword matching in `blocklists`.
+pub struct ProcessPatterns {
+    /// Plain entries, lowercased, matched against the whole process name
+    exact: HashSet<String>,
+    /// (entry, compiled glob) matched against the process name
+    names: Vec<(String, Regex)>,
+    /// (entry, compiled glob) matched against the executable path
+    paths: Vec<(String, Regex)>,
+}
+
+impl ProcessPatterns {
+    pub fn new<'a>(entries: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut exact = HashSet::new();
+        let mut names = Vec::new();
+        let mut paths = Vec::new();
+
+        for entry in entries {
+            let is_path = entry.contains(['/', '\\']);
+            if !is_path && !entry.contains(['*', '?']) {
+                exact.insert(entry.to_lowercase());
+                continue;
+            }
+            let Some(regex) = glob_regex(entry) else {
+                tracing::warn!("Skipping unusable process pattern {:?}", entry);
+                continue;
+            };
+            if is_path {
+                paths.push((entry.clone(), regex));
+            } else {
+                names.push((entry.clone(), regex));
+            }
+        }
+
+        Self { exact, names, paths }
+    }
+
+    /// The entry matching the process name or executable path, if any
+    pub fn matching_rule(&self, info: &ProcessInfo) -> Option<&str> {
+        let by_name = self.names.iter().find(|(_, regex)| regex.is_match(&info.name));
+        let by_path = || {
+            let exe_path = info.exe_path.as_deref()?;
+            self.paths.iter().find(|(_, regex)| regex.is_match(exe_path))
+        };
+        by_name.or_else(by_path).map(|(entry, _)| entry.as_str())
+    }
+}
+
+/// Anchored, case-insensitive regex for a `*`/`?` glob
+fn glob_regex(glob: &str) -> Option<Regex> {
+    let mut source = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => source.push_str(".*"),
+            '?' => source.push('.'),
+            c => source.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    source.push('$');
+    Regex::new(&source).ok()
+}
+
+/// Whether a blocked-process entry matches `info`: a plain name by exact
+/// (case-insensitive) name, a glob by name, and a path by executable path
+pub fn matches_blocked(info: &ProcessInfo, patterns: &ProcessPatterns) -> bool {
+    patterns.exact.contains(&info.name.to_lowercase()) || patterns.matching_rule(info).is_some()
+}
+
+/// Whether `process` is whitelisted by name, or by the absolute path of
+/// its executable
+fn is_whitelisted(process: &ProcessInfo, allowed: &HashSet<String>, allowed_domains: &HashSet<String>) -> bool {
+    super::blocklists::is_exe_path_whitelisted(process.exe_path.as_deref(), allowed)
+        || super::blocklists::is_process_whitelisted(&process.name, allowed, allowed_domains)
+}
+
+/// The blocklist entry that blocks `process`: exact and keyword matches
+/// first, then globs and paths. Whitelisted processes are never blocked, and
+/// a whitelisted executable path wins over any rule matching its name.
+fn blocked_rule(
+    process: &ProcessInfo,
+    blocked: &HashSet<String>,
+    allowed: &HashSet<String>,
+    allowed_domains: &HashSet<String>,
+    patterns: &ProcessPatterns,
+) -> Option<String> {
+    if is_whitelisted(process, allowed, allowed_domains) {
+        return None;
+    }
+    super::blocklists::match_blocked_process(&process.name, blocked, allowed, allowed_domains)
+        .or_else(|| patterns.matching_rule(process).map(str::to_string))
+}
+
+/// A running process annotated with how the blocklist treats it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotatedProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe_path: Option<String>,
+    pub is_blocked: bool,
+    pub matched_rule: Option<String>,
+    pub is_whitelisted: bool,
+}
+
+/// Annotate processes using the same matcher the daemon enforces with
+pub fn annotate_processes(
+    processes: Vec<ProcessInfo>,
+    blocked: &HashSet<String>,
+    allowed: &HashSet<String>,
+    allowed_domains: &HashSet<String>,
+) -> Vec<AnnotatedProcess> {
+    let patterns = ProcessPatterns::new(blocked);
+    processes
+        .into_iter()
+        .map(|p| {
+            let matched_rule = blocked_rule(&p, blocked, allowed, allowed_domains, &patterns);
+            let is_whitelisted = is_whitelisted(&p, allowed, allowed_domains);
+
+            AnnotatedProcess {
+                pid: p.pid,
+                name: p.name,
+                exe_path: p.exe_path,
+                is_blocked: matched_rule.is_some(),
+                matched_rule,
+                is_whitelisted,
+            }
+        })
+        .collect()
+}
+
+/// How a blocked process is terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationMode {
+    /// Ask the process to exit (SIGTERM / close request), force-kill after the grace period
+    #[default]
+    Graceful,
+    /// Ask the process to exit and never force-kill it
+    NoEscalate,
+    /// Force-kill immediately (SIGKILL / TerminateProcess), for processes that respawn
+    Force,
+}
+
+/// Platform operations behind graceful-then-force termination
+#[cfg_attr(not(any(target_os = "linux", target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub(crate) trait SignalOps {
+    /// Ask the process to exit (SIGTERM or equivalent)
+    fn request_exit(&self, pid: u32) -> Result<(), ProcessError>;
+
+    /// Whether the process is still running
+    fn is_alive(&self, pid: u32) -> bool;
+
+    /// Kill the process without giving it a chance to clean up
+    fn force_kill(&self, pid: u32) -> Result<(), ProcessError>;
+}
+
+/// Ask a process to exit, then force-kill it if it is still alive after `grace`
+#[cfg_attr(not(any(target_os = "linux", target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub(crate) fn terminate_with_grace(
+    ops: &impl SignalOps,
+    pid: u32,
+    grace: Duration,
+) -> Result<(), ProcessError> {
+    ops.request_exit(pid)?;
+
+    let deadline = Instant::now() + grace.min(MAX_KILL_GRACE);
+    loop {
+        if !ops.is_alive(pid) {
+            return Ok(());
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        std::thread::sleep(remaining.min(EXIT_POLL_INTERVAL));
+    }
+
+    ops.force_kill(pid)
+}
+
+/// Terminate a process according to `mode`
+#[cfg_attr(not(any(target_os = "linux", target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub(crate) fn terminate_with_mode(
+    ops: &impl SignalOps,
+    pid: u32,
+    grace: Duration,
+    mode: TerminationMode,
+) -> Result<(), ProcessError> {
+    match mode {
+        TerminationMode::Graceful => terminate_with_grace(ops, pid, grace),
+        TerminationMode::NoEscalate => ops.request_exit(pid),
+        TerminationMode::Force => ops.force_kill(pid),
+    }
 }
 
 /// Process blocker trait for cross-platform implementation
@@ -36,8 +295,40 @@ pub trait ProcessBlocker: Send + Sync {
     /// List all running processes
     fn list_processes(&self) -> Result<Vec<ProcessInfo>, ProcessError>;
 
+    /// Terminate a process by PID using the given mode
+    fn terminate_process_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError>;
+
     /// Terminate a process by PID
-    fn terminate_process(&self, pid: u32) -> Result<(), ProcessError>;
+    fn terminate_process(&self, pid: u32) -> Result<(), ProcessError> {
+        self.terminate_process_with_mode(pid, TerminationMode::default())
+    }
+
+    /// Direct children of a process. Platforms that can't enumerate them
+    /// report none, which makes tree termination kill the PID alone.
+    fn child_pids(&self, _pid: u32) -> Result<Vec<u32>, ProcessError> {
+        Ok(Vec::new())
+    }
+
+    /// Terminate a process and all of its descendants, root-first, using the given mode.
+    /// The tree is listed before anything is signalled, so children that get
+    /// reparented when their parent dies are still found.
+    fn terminate_process_tree_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError> {
+        let tree = process_tree(pid, |parent| self.child_pids(parent).unwrap_or_default());
+        let result = self.terminate_process_with_mode(pid, mode);
+        for &descendant in &tree[1..] {
+            match self.terminate_process_with_mode(descendant, mode) {
+                // Already gone, e.g. it exited with its parent
+                Ok(()) | Err(ProcessError::NotFound) => {}
+                Err(e) => tracing::warn!("Failed to terminate child {} of {}: {}", descendant, pid, e),
+            }
+        }
+        result
+    }
+
+    /// Terminate a process and all of its descendants
+    fn terminate_process_tree(&self, pid: u32) -> Result<(), ProcessError> {
+        self.terminate_process_tree_with_mode(pid, TerminationMode::default())
+    }
 
     /// Find and terminate processes matching blocked list
     fn block_processes(
@@ -45,16 +336,51 @@ pub trait ProcessBlocker: Send + Sync {
         blocked: &HashSet<String>,
         allowed: &HashSet<String>,
         allowed_domains: &HashSet<String>,
+    ) -> Result<Vec<ProcessInfo>, ProcessError> {
+        self.block_processes_with_modes(
+            blocked,
+            allowed,
+            allowed_domains,
+            TerminationMode::default(),
+            &HashMap::new(),
+            false,
+        )
+    }
+
+    /// Find and terminate processes matching blocked list, using `overrides`
+    /// (keyed by blocklist entry) or `default_mode` to pick how each is terminated.
+    /// With `dry_run`, matching processes are returned but left running.
+    fn block_processes_with_modes(
+        &self,
+        blocked: &HashSet<String>,
+        allowed: &HashSet<String>,
+        allowed_domains: &HashSet<String>,
+        default_mode: TerminationMode,
+        overrides: &HashMap<String, TerminationMode>,
+        dry_run: bool,
     ) -> Result<Vec<ProcessInfo>, ProcessError> {
         let processes = self.list_processes()?;
+        let patterns = ProcessPatterns::new(blocked);
         let mut blocked_processes = Vec::new();
 
         for process in processes {
-            if super::blocklists::is_process_blocked(&process.name, blocked, allowed, allowed_domains) {
-                if let Err(e) = self.terminate_process(process.pid) {
+            if let Some(rule) = blocked_rule(&process, blocked, allowed, allowed_domains, &patterns) {
+                let mode = overrides.get(&rule).copied().unwrap_or(default_mode);
+                if dry_run {
+                    tracing::info!("Dry run: would terminate {} (PID: {})", process.name, process.pid);
+                    blocked_processes.push(process);
+                    continue;
+                }
+                let terminated = if is_known_launcher(&process.name) {
+                    self.terminate_process_tree_with_mode(process.pid, mode)
+                } else {
+                    self.terminate_process_with_mode(process.pid, mode)
+                };
+                if let Err(e) = terminated {
                     tracing::warn!("Failed to terminate {}: {}", process.name, e);
                 } else {
-                    tracing::info!("Blocked process: {} (PID: {})", process.name, process.pid);
+                    // The daemon logs kills itself so it can quiet respawn loops
+                    tracing::debug!("Blocked process: {} (PID: {})", process.name, process.pid);
                     blocked_processes.push(process);
                 }
             }
@@ -65,23 +391,28 @@ pub trait ProcessBlocker: Send + Sync {
 }
 
 /// Create a platform-specific process blocker
-#[cfg(target_os = "linux")]
 pub fn create_blocker() -> Box<dyn ProcessBlocker> {
-    Box::new(linux::LinuxProcessBlocker::new())
+    create_blocker_with_grace(DEFAULT_KILL_GRACE)
+}
+
+/// Create a platform-specific process blocker with a custom kill grace period
+#[cfg(target_os = "linux")]
+pub fn create_blocker_with_grace(grace: Duration) -> Box<dyn ProcessBlocker> {
+    Box::new(linux::LinuxProcessBlocker::with_grace(grace))
 }
 
 #[cfg(target_os = "windows")]
-pub fn create_blocker() -> Box<dyn ProcessBlocker> {
-    Box::new(windows::WindowsProcessBlocker::new())
+pub fn create_blocker_with_grace(grace: Duration) -> Box<dyn ProcessBlocker> {
+    Box::new(windows::WindowsProcessBlocker::with_grace(grace))
 }
 
 #[cfg(target_os = "macos")]
-pub fn create_blocker() -> Box<dyn ProcessBlocker> {
-    Box::new(macos::MacOSProcessBlocker::new())
+pub fn create_blocker_with_grace(grace: Duration) -> Box<dyn ProcessBlocker> {
+    Box::new(macos::MacOSProcessBlocker::with_grace(grace))
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
-pub fn create_blocker() -> Box<dyn ProcessBlocker> {
+pub fn create_blocker_with_grace(_grace: Duration) -> Box<dyn ProcessBlocker> {
     Box::new(StubProcessBlocker)
 }
 
@@ -95,7 +426,326 @@ impl ProcessBlocker for StubProcessBlocker {
         Ok(Vec::new())
     }
 
-    fn terminate_process(&self, _pid: u32) -> Result<(), ProcessError> {
+    fn terminate_process_with_mode(&self, _pid: u32, _mode: TerminationMode) -> Result<(), ProcessError> {
         Err(ProcessError::TerminateFailed("Unsupported platform".to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            exe_path: None,
+            uid: None,
+        }
+    }
+
+    #[test]
+    fn test_annotate_processes() {
+        let blocked: HashSet<String> = ["steam".to_string(), "discord".to_string()].into();
+        let allowed: HashSet<String> = ["discord".to_string()].into();
+        let allowed_domains = HashSet::new();
+
+        let annotated = annotate_processes(
+            vec![process(1, "steam"), process(2, "discord"), process(3, "bash")],
+            &blocked,
+            &allowed,
+            &allowed_domains,
+        );
+
+        assert!(annotated[0].is_blocked);
+        assert_eq!(annotated[0].matched_rule.as_deref(), Some("steam"));
+        assert!(!annotated[0].is_whitelisted);
+
+        assert!(!annotated[1].is_blocked);
+        assert!(annotated[1].matched_rule.is_none());
+        assert!(annotated[1].is_whitelisted);
+
+        assert!(!annotated[2].is_blocked);
+        assert!(!annotated[2].is_whitelisted);
+    }
+
+    fn process_at(pid: u32, name: &str, exe_path: &str) -> ProcessInfo {
+        ProcessInfo {
+            exe_path: Some(exe_path.to_string()),
+            ..process(pid, name)
+        }
+    }
+
+    #[test]
+    fn test_process_tree_is_root_first() {
+        let children: HashMap<u32, Vec<u32>> = [(1, vec![2, 3]), (2, vec![4]), (4, vec![5])].into();
+        let tree = process_tree(1, |pid| children.get(&pid).cloned().unwrap_or_default());
+
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.first(), Some(&1));
+        let position = |pid| tree.iter().position(|&p| p == pid).unwrap();
+        assert!(position(2) < position(4) && position(4) < position(5));
+        assert!(position(1) < position(3));
+    }
+
+    #[test]
+    fn test_known_launchers() {
+        assert!(is_known_launcher("Steam"));
+        assert!(is_known_launcher("EpicGamesLauncher.exe"));
+        assert!(!is_known_launcher("steamworks-game"));
+    }
+
+    #[test]
+    fn test_exact_names_still_match() {
+        let patterns = ProcessPatterns::new(&["robloxplayer".to_string()]);
+        assert!(matches_blocked(&process(1, "RobloxPlayer"), &patterns));
+        assert!(!matches_blocked(&process(1, "robloxplayer2"), &patterns));
+    }
+
+    #[test]
+    fn test_glob_matches_name() {
+        let patterns = ProcessPatterns::new(&["steam*".to_string(), "mc-?".to_string()]);
+        assert!(matches_blocked(&process(1, "steamwebhelper"), &patterns));
+        assert!(matches_blocked(&process(1, "Steam"), &patterns));
+        assert!(matches_blocked(&process(1, "mc-1"), &patterns));
+        assert!(!matches_blocked(&process(1, "mc-12"), &patterns));
+        assert!(!matches_blocked(&process(1, "notsteam"), &patterns));
+        // Regex metacharacters in an entry are literal
+        assert!(!matches_blocked(&process(1, "battleXnet"), &ProcessPatterns::new(&["battle.net*".to_string()])));
+    }
+
+    #[test]
+    fn test_path_matches_renamed_executable() {
+        let patterns = ProcessPatterns::new(&["/home/*/.local/share/steam/*".to_string(), r"C:\Games\*".to_string()]);
+        assert!(matches_blocked(&process_at(1, "homework", "/home/kid/.local/share/Steam/steam"), &patterns));
+        assert!(matches_blocked(&process_at(1, "notepad.exe", r"c:\games\fortnite.exe"), &patterns));
+        assert!(!matches_blocked(&process_at(1, "steam", "/usr/bin/steam"), &patterns));
+        assert!(!matches_blocked(&process(1, "homework"), &patterns));
+    }
+
+    #[test]
+    fn test_patterns_respect_whitelist() {
+        let blocked: HashSet<String> = ["minecraft*".to_string(), "/opt/games/*".to_string()].into();
+        let allowed: HashSet<String> = ["minecraft-launcher".to_string()].into();
+
+        let annotated = annotate_processes(
+            vec![
+                process(1, "minecraft-launcher"),
+                process_at(2, "tool", "/opt/games/tool"),
+                process(3, "minecraftserver"),
+            ],
+            &blocked,
+            &allowed,
+            &HashSet::new(),
+        );
+
+        assert!(!annotated[0].is_blocked);
+        assert_eq!(annotated[1].matched_rule.as_deref(), Some("/opt/games/*"));
+        assert!(annotated[2].is_blocked);
+    }
+
+    #[test]
+    fn test_path_whitelist_allows_only_that_executable() {
+        let blocked: HashSet<String> = ["python3".to_string(), "/opt/games/*".to_string()].into();
+        let allowed: HashSet<String> = ["/usr/bin/python3".to_string(), "/opt/games/chess".to_string()].into();
+
+        let annotated = annotate_processes(
+            vec![
+                process_at(1, "python3", "/usr/bin/python3"),
+                process_at(2, "python3", "/home/kid/games/python3"),
+                process_at(3, "chess", "/opt/games/chess"),
+                process_at(4, "tool", "/opt/games/tool"),
+            ],
+            &blocked,
+            &allowed,
+            &HashSet::new(),
+        );
+
+        assert!(!annotated[0].is_blocked);
+        assert!(annotated[0].is_whitelisted);
+        assert_eq!(annotated[1].matched_rule.as_deref(), Some("python3"));
+        assert!(!annotated[1].is_whitelisted);
+        // An exact path beats a path glob
+        assert!(!annotated[2].is_blocked);
+        assert!(annotated[3].is_blocked);
+
+        // A name entry still allows the name wherever it runs from
+        let by_name: HashSet<String> = ["python3".to_string()].into();
+        let annotated = annotate_processes(
+            vec![process_at(2, "python3", "/home/kid/games/python3")],
+            &blocked,
+            &by_name,
+            &HashSet::new(),
+        );
+        assert!(!annotated[0].is_blocked);
+    }
+
+    /// Mock signal ops: the process exits after `exits_after` liveness checks
+    struct MockOps {
+        exits_after: Option<usize>,
+        checks: std::cell::Cell<usize>,
+        killed_at: std::cell::Cell<Option<Instant>>,
+    }
+
+    impl MockOps {
+        fn new(exits_after: Option<usize>) -> Self {
+            Self {
+                exits_after,
+                checks: std::cell::Cell::new(0),
+                killed_at: std::cell::Cell::new(None),
+            }
+        }
+    }
+
+    impl SignalOps for MockOps {
+        fn request_exit(&self, _pid: u32) -> Result<(), ProcessError> {
+            Ok(())
+        }
+
+        fn is_alive(&self, _pid: u32) -> bool {
+            self.checks.set(self.checks.get() + 1);
+            self.exits_after.map(|n| self.checks.get() <= n).unwrap_or(true)
+        }
+
+        fn force_kill(&self, _pid: u32) -> Result<(), ProcessError> {
+            self.killed_at.set(Some(Instant::now()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_escalates_after_grace_when_still_alive() {
+        let ops = MockOps::new(None);
+        let grace = Duration::from_millis(50);
+
+        let start = Instant::now();
+        terminate_with_grace(&ops, 42, grace).unwrap();
+
+        let killed_at = ops.killed_at.get().expect("process should be force-killed");
+        assert!(killed_at.duration_since(start) >= grace);
+    }
+
+    #[test]
+    fn test_huge_grace_is_capped() {
+        // A process that exits straight away must not wait for (or overflow on) the grace
+        let ops = MockOps::new(Some(1));
+        terminate_with_grace(&ops, 42, Duration::from_millis(u64::MAX)).unwrap();
+        terminate_with_grace(&ops, 42, Duration::MAX).unwrap();
+        assert!(ops.killed_at.get().is_none());
+    }
+
+    #[test]
+    fn test_no_escalation_when_process_exits() {
+        let ops = MockOps::new(Some(2));
+
+        terminate_with_grace(&ops, 42, Duration::from_millis(500)).unwrap();
+
+        assert!(ops.killed_at.get().is_none());
+    }
+
+    /// Mock ops that record the order of calls
+    #[derive(Default)]
+    struct RecordingOps {
+        calls: std::cell::RefCell<Vec<&'static str>>,
+    }
+
+    impl SignalOps for RecordingOps {
+        fn request_exit(&self, _pid: u32) -> Result<(), ProcessError> {
+            self.calls.borrow_mut().push("term");
+            Ok(())
+        }
+
+        fn is_alive(&self, _pid: u32) -> bool {
+            true
+        }
+
+        fn force_kill(&self, _pid: u32) -> Result<(), ProcessError> {
+            self.calls.borrow_mut().push("kill");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_force_mode_kills_without_grace() {
+        let ops = RecordingOps::default();
+
+        let start = Instant::now();
+        terminate_with_mode(&ops, 42, Duration::from_secs(5), TerminationMode::Force).unwrap();
+
+        assert_eq!(*ops.calls.borrow(), vec!["kill"]);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_no_escalate_mode_only_requests_exit() {
+        let ops = RecordingOps::default();
+        terminate_with_mode(&ops, 42, Duration::from_millis(20), TerminationMode::NoEscalate).unwrap();
+        assert_eq!(*ops.calls.borrow(), vec!["term"]);
+    }
+
+    /// Blocker that records the mode used for each PID
+    struct ModeRecordingBlocker {
+        modes: std::sync::Mutex<Vec<(u32, TerminationMode)>>,
+    }
+
+    impl ProcessBlocker for ModeRecordingBlocker {
+        fn list_processes(&self) -> Result<Vec<ProcessInfo>, ProcessError> {
+            Ok(vec![process(1, "steam"), process(2, "robloxplayer"), process(3, "bash")])
+        }
+
+        fn terminate_process_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError> {
+            self.modes.lock().unwrap().push((pid, mode));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_block_processes_uses_per_entry_mode() {
+        let blocker = ModeRecordingBlocker {
+            modes: std::sync::Mutex::new(Vec::new()),
+        };
+        let blocked: HashSet<String> = ["steam".to_string(), "robloxplayer".to_string()].into();
+        let overrides: HashMap<String, TerminationMode> =
+            [("robloxplayer".to_string(), TerminationMode::Force)].into();
+
+        let killed = blocker
+            .block_processes_with_modes(
+                &blocked,
+                &HashSet::new(),
+                &HashSet::new(),
+                TerminationMode::Graceful,
+                &overrides,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(killed.len(), 2);
+        assert_eq!(
+            *blocker.modes.lock().unwrap(),
+            vec![(1, TerminationMode::Graceful), (2, TerminationMode::Force)]
+        );
+    }
+
+    #[test]
+    fn test_dry_run_kills_nothing() {
+        let blocker = ModeRecordingBlocker {
+            modes: std::sync::Mutex::new(Vec::new()),
+        };
+        let blocked: HashSet<String> = ["steam".to_string(), "robloxplayer".to_string()].into();
+
+        let matched = blocker
+            .block_processes_with_modes(
+                &blocked,
+                &HashSet::new(),
+                &HashSet::new(),
+                TerminationMode::Force,
+                &HashMap::new(),
+                true,
+            )
+            .unwrap();
+
+        let pids: Vec<u32> = matched.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![1, 2]);
+        assert!(blocker.modes.lock().unwrap().is_empty());
+    }
+}
@@ -1,7 +1,15 @@
 //! Windows process blocking using Windows API.
 
-use super::{ProcessBlocker, ProcessError, ProcessInfo};
+use super::{ProcessBlocker, ProcessError, ProcessInfo, TerminationMode};
+use std::time::Duration;
 
+#[cfg(target_os = "windows")]
+use super::{terminate_with_mode, SignalOps};
+#[cfg(target_os = "windows")]
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use windows::core::PWSTR;
 #[cfg(target_os = "windows")]
 use windows::Win32::{
     Foundation::CloseHandle,
@@ -10,15 +18,90 @@ use windows::Win32::{
             CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32,
             TH32CS_SNAPPROCESS,
         },
-        Threading::{OpenProcess, TerminateProcess, PROCESS_QUERY_INFORMATION, PROCESS_TERMINATE},
+        Threading::{
+            GetExitCodeProcess, OpenProcess, QueryFullProcessImageNameW, TerminateProcess,
+            PROCESS_NAME_WIN32, PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION,
+            PROCESS_TERMINATE,
+        },
     },
 };
 
-pub struct WindowsProcessBlocker;
+/// Exit code reported by GetExitCodeProcess while a process is running
+#[cfg(target_os = "windows")]
+const STILL_ACTIVE: u32 = 259;
+
+pub struct WindowsProcessBlocker {
+    /// Time between the close request and TerminateProcess
+    grace: Duration,
+}
 
 impl WindowsProcessBlocker {
-    pub fn new() -> Self {
-        Self
+    pub fn with_grace(grace: Duration) -> Self {
+        Self { grace }
+    }
+}
+
+/// Close requests through taskkill, forced termination through TerminateProcess
+#[cfg(target_os = "windows")]
+struct WindowsSignals;
+
+#[cfg(target_os = "windows")]
+impl SignalOps for WindowsSignals {
+    fn request_exit(&self, pid: u32) -> Result<(), ProcessError> {
+        // Without /F, taskkill asks the process's windows to close. Console
+        // processes can't be asked politely, so a failure here just means we
+        // fall through to TerminateProcess after the grace period.
+        let _ = Command::new("taskkill")
+            .args(["/PID", &pid.to_string()])
+            .output();
+        Ok(())
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        unsafe {
+            let Ok(handle) = OpenProcess(PROCESS_QUERY_INFORMATION, false, pid) else {
+                return false;
+            };
+
+            let mut code = 0u32;
+            let alive = GetExitCodeProcess(handle, &mut code).is_ok() && code == STILL_ACTIVE;
+            let _ = CloseHandle(handle);
+            alive
+        }
+    }
+
+    fn force_kill(&self, pid: u32) -> Result<(), ProcessError> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE | PROCESS_QUERY_INFORMATION, false, pid)
+                .map_err(|e| {
+                    if e.code().0 as u32 == 5 {
+                        // ERROR_ACCESS_DENIED
+                        ProcessError::AccessDenied
+                    } else {
+                        ProcessError::TerminateFailed(e.to_string())
+                    }
+                })?;
+
+            let result = TerminateProcess(handle, 1);
+            let _ = CloseHandle(handle);
+
+            result.map_err(|e| ProcessError::TerminateFailed(e.to_string()))
+        }
+    }
+}
+
+/// Full path of a process's executable. `None` for processes we may not
+/// query (protected and some system processes); path rules skip those.
+#[cfg(target_os = "windows")]
+fn process_image_path(pid: u32) -> Option<String> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(handle);
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
     }
 }
 
@@ -48,7 +131,8 @@ impl ProcessBlocker for WindowsProcessBlocker {
                     processes.push(ProcessInfo {
                         pid: entry.th32ProcessID,
                         name,
-                        exe_path: None, // Would need additional API calls
+                        exe_path: process_image_path(entry.th32ProcessID),
+                        uid: None,
                     });
 
                     if Process32Next(snapshot, &mut entry).is_err() {
@@ -63,23 +147,42 @@ impl ProcessBlocker for WindowsProcessBlocker {
         Ok(processes)
     }
 
-    fn terminate_process(&self, pid: u32) -> Result<(), ProcessError> {
+    fn terminate_process_with_mode(&self, pid: u32, mode: TerminationMode) -> Result<(), ProcessError> {
+        // Graceful: close request, then TerminateProcess once the grace period runs out.
+        // Force: TerminateProcess immediately.
+        terminate_with_mode(&WindowsSignals, pid, self.grace, mode)
+    }
+
+    fn child_pids(&self, pid: u32) -> Result<Vec<u32>, ProcessError> {
+        let mut children = Vec::new();
+
         unsafe {
-            let handle = OpenProcess(PROCESS_TERMINATE | PROCESS_QUERY_INFORMATION, false, pid)
-                .map_err(|e| {
-                    if e.code().0 as u32 == 5 {
-                        // ERROR_ACCESS_DENIED
-                        ProcessError::AccessDenied
-                    } else {
-                        ProcessError::TerminateFailed(e.to_string())
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+                .map_err(|e| ProcessError::ListFailed(e.to_string()))?;
+
+            let mut entry = PROCESSENTRY32 {
+                dwSize: std::mem::size_of::<PROCESSENTRY32>() as u32,
+                ..Default::default()
+            };
+
+            if Process32First(snapshot, &mut entry).is_ok() {
+                loop {
+                    // The parent PID isn't cleared when the parent exits, so
+                    // this only means something while `pid` is still running
+                    if entry.th32ParentProcessID == pid && entry.th32ProcessID != pid {
+                        children.push(entry.th32ProcessID);
                     }
-                })?;
 
-            let result = TerminateProcess(handle, 1);
-            let _ = CloseHandle(handle);
+                    if Process32Next(snapshot, &mut entry).is_err() {
+                        break;
+                    }
+                }
+            }
 
-            result.map_err(|e| ProcessError::TerminateFailed(e.to_string()))
+            let _ = CloseHandle(snapshot);
         }
+
+        Ok(children)
     }
 }
 
@@ -89,7 +192,7 @@ impl ProcessBlocker for WindowsProcessBlocker {
         Err(ProcessError::ListFailed("Not on Windows".to_string()))
     }
 
-    fn terminate_process(&self, _pid: u32) -> Result<(), ProcessError> {
+    fn terminate_process_with_mode(&self, _pid: u32, _mode: TerminationMode) -> Result<(), ProcessError> {
         Err(ProcessError::TerminateFailed("Not on Windows".to_string()))
     }
 }
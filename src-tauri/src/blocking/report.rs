@@ -0,0 +1,137 @@
+//! Outcome of applying blocking, with the problems that used to be swallowed.
+
+use serde::{Deserialize, Serialize};
+
+/// How much a warning affects enforcement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WarningSeverity {
+    /// Worth knowing, blocking still works
+    Info,
+    /// Blocking works but can be bypassed in some way
+    Degraded,
+    /// A blocking layer is not in effect
+    Critical,
+}
+
+/// Machine-readable reason for a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// Firewall rules could not be installed
+    FirewallUnavailable,
+    /// Old firewall rules could not be removed
+    FirewallCleanupFailed,
+    /// The firewall heartbeat could not be written
+    HeartbeatFailed,
+    /// A Snap/Flatpak browser ignores system policies
+    BrowserSandboxed,
+    /// A browser can still resolve names over DoH, bypassing the hosts file
+    DohStillEnabled,
+    /// The experimental router backend could not be reached
+    RouterUnavailable,
+    /// DNS could not be sent through the proxy, so patterns and the block log miss it
+    DnsRedirectFailed,
+}
+
+impl WarningCode {
+    pub fn severity(self) -> WarningSeverity {
+        match self {
+            WarningCode::FirewallUnavailable => WarningSeverity::Critical,
+            WarningCode::BrowserSandboxed
+            | WarningCode::DohStillEnabled
+            | WarningCode::RouterUnavailable
+            | WarningCode::DnsRedirectFailed => WarningSeverity::Degraded,
+            WarningCode::FirewallCleanupFailed | WarningCode::HeartbeatFailed => WarningSeverity::Info,
+        }
+    }
+}
+
+/// A single problem found while applying blocking
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyWarning {
+    pub code: WarningCode,
+    pub severity: WarningSeverity,
+    pub message: String,
+}
+
+/// Result of an apply operation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub warnings: Vec<ApplyWarning>,
+    /// Domains written to the hosts file
+    #[serde(default)]
+    pub domains_blocked: usize,
+    /// Names of processes terminated while applying
+    #[serde(default)]
+    pub processes_terminated: Vec<String>,
+    /// Browsers with DoH turned off by policy, as "Browser for user"
+    #[serde(default)]
+    pub browsers_configured: Vec<String>,
+    /// Whether the firewall rules went in
+    #[serde(default)]
+    pub firewall_applied: bool,
+}
+
+impl ApplyReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a warning; its severity comes from the code
+    pub fn warn(&mut self, code: WarningCode, message: impl Into<String>) {
+        let message = message.into();
+        tracing::warn!("{:?}: {}", code, message);
+        self.warnings.push(ApplyWarning {
+            code,
+            severity: code.severity(),
+            message,
+        });
+    }
+
+    /// Worst severity reported, if any
+    pub fn max_severity(&self) -> Option<WarningSeverity> {
+        self.warnings.iter().map(|w| w.severity).max()
+    }
+
+    /// Blocking is fully in effect when nothing critical was reported
+    pub fn is_effective(&self) -> bool {
+        self.max_severity() != Some(WarningSeverity::Critical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_firewall_unavailable_is_critical() {
+        let mut report = ApplyReport::new();
+        assert!(report.is_effective());
+
+        report.warn(WarningCode::FirewallUnavailable, "iptables not found");
+        assert_eq!(report.warnings[0].code, WarningCode::FirewallUnavailable);
+        assert_eq!(report.warnings[0].severity, WarningSeverity::Critical);
+        assert!(!report.is_effective());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["warnings"][0]["code"], "FIREWALL_UNAVAILABLE");
+        assert_eq!(json["warnings"][0]["severity"], "Critical");
+    }
+
+    #[test]
+    fn test_max_severity() {
+        let mut report = ApplyReport::new();
+        report.warn(WarningCode::HeartbeatFailed, "read-only filesystem");
+        report.warn(WarningCode::BrowserSandboxed, "Firefox (Snap) for alice");
+        assert_eq!(report.max_severity(), Some(WarningSeverity::Degraded));
+        assert!(report.is_effective());
+    }
+
+    #[test]
+    fn test_report_from_older_daemon_still_parses() {
+        let report: ApplyReport = serde_json::from_str(r#"{"warnings":[]}"#).unwrap();
+        assert_eq!(report, ApplyReport::new());
+        assert_eq!(report.domains_blocked, 0);
+        assert!(!report.firewall_applied);
+    }
+}
@@ -0,0 +1,187 @@
+//! Blocklist subscriptions: community lists the daemon re-fetches from a URL
+//! to keep them current. Each subscription keeps the domains of its last
+//! successful fetch, so a failed download changes nothing, and their union
+//! lives in `subscribed_domains` apart from the domains the parent added.
+
+use crate::blocking::blocklists::{self, ImportError, MAX_IMPORT_BYTES};
+use crate::config::AppConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long a blocklist download may take
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Refresh period for subscriptions added without one
+pub const DEFAULT_REFRESH_HOURS: u32 = 24;
+
+#[derive(Error, Debug)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("Only http and https blocklist URLs are supported")]
+    UnsupportedScheme,
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("Download failed: HTTP {0}")]
+    Status(reqwest::StatusCode),
+    #[error(transparent)]
+    Import(#[from] ImportError),
+}
+
+/// A blocklist fetched from `url` every `refresh_hours`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlocklistSubscription {
+    pub url: String,
+    pub refresh_hours: u32,
+    /// When the list was last fetched successfully
+    #[serde(default)]
+    pub last_refreshed: Option<DateTime<Utc>>,
+    /// Domains from the last successful fetch
+    #[serde(default)]
+    pub domains: HashSet<String>,
+}
+
+impl BlocklistSubscription {
+    pub fn new(url: String, refresh_hours: u32) -> Self {
+        Self {
+            url,
+            refresh_hours,
+            last_refreshed: None,
+            domains: HashSet::new(),
+        }
+    }
+
+    /// Whether the list should be fetched again at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        let period = chrono::Duration::hours(i64::from(self.refresh_hours.max(1)));
+        self.last_refreshed.is_none_or(|at| now - at >= period)
+    }
+}
+
+/// Check that `url` is something `fetch_blocklist` will download
+pub fn validate_url(url: &str) -> Result<reqwest::Url, FetchError> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| FetchError::InvalidUrl(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(FetchError::UnsupportedScheme);
+    }
+    Ok(parsed)
+}
+
+/// Download a blocklist over HTTP(S), refusing anything over the import size cap
+pub async fn fetch_blocklist(url: &str) -> Result<String, FetchError> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    let mut response = client.get(validate_url(url)?).send().await?;
+    if !response.status().is_success() {
+        return Err(FetchError::Status(response.status()));
+    }
+
+    if response.content_length().is_some_and(|len| len > MAX_IMPORT_BYTES as u64) {
+        return Err(ImportError::TooLarge.into());
+    }
+    // Servers can omit or misstate the length, so count as it streams in
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_IMPORT_BYTES {
+            return Err(ImportError::TooLarge.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Download and parse a subscribed list
+pub async fn fetch_domains(url: &str) -> Result<Vec<String>, FetchError> {
+    let content = fetch_blocklist(url).await?;
+    Ok(blocklists::parse_import_limited(&content)?)
+}
+
+/// Store freshly fetched domains for the subscription at `url` and rebuild
+/// `subscribed_domains`. Returns false if the subscription is gone.
+pub fn record_refresh(config: &mut AppConfig, url: &str, domains: Vec<String>, now: DateTime<Utc>) -> bool {
+    let Some(subscription) = config.blocklist_subscriptions.iter_mut().find(|s| s.url == url) else {
+        return false;
+    };
+    subscription.domains = domains.into_iter().collect();
+    subscription.last_refreshed = Some(now);
+    rebuild_subscribed_domains(config);
+    true
+}
+
+/// Drop the subscription at `url` along with the domains only it supplied
+pub fn remove_subscription(config: &mut AppConfig, url: &str) -> bool {
+    let before = config.blocklist_subscriptions.len();
+    config.blocklist_subscriptions.retain(|s| s.url != url);
+    rebuild_subscribed_domains(config);
+    config.blocklist_subscriptions.len() != before
+}
+
+/// Recompute `subscribed_domains` as the union of every subscription's last good list
+pub fn rebuild_subscribed_domains(config: &mut AppConfig) {
+    config.subscribed_domains = config
+        .blocklist_subscriptions
+        .iter()
+        .flat_map(|s| s.domains.iter().cloned())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domains(list: &[&str]) -> Vec<String> {
+        list.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_subscription_domains_stay_apart_from_user_domains() {
+        let mut config = AppConfig {
+            dns_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("mine.example".to_string());
+        config.blocklist_subscriptions = vec![
+            BlocklistSubscription::new("https://lists.example/a.txt".to_string(), 24),
+            BlocklistSubscription::new("https://lists.example/b.txt".to_string(), 24),
+        ];
+        let now = Utc::now();
+
+        let list_a = domains(&["a.example", "mine.example"]);
+        assert!(record_refresh(&mut config, "https://lists.example/a.txt", list_a, now));
+        assert!(record_refresh(&mut config, "https://lists.example/b.txt", domains(&["b.example"]), now));
+        assert!(!record_refresh(&mut config, "https://lists.example/gone.txt", domains(&["x.example"]), now));
+
+        assert_eq!(config.blocked_domains, ["mine.example".to_string()].into());
+        assert_eq!(config.subscribed_domains.len(), 3);
+        let effective = blocklists::get_effective_blocked_domains(&config);
+        assert!(effective.contains("a.example") && effective.contains("b.example"));
+
+        // A later fetch replaces only that subscription's share
+        record_refresh(&mut config, "https://lists.example/b.txt", domains(&["b2.example"]), now);
+        assert!(config.subscribed_domains.contains("a.example"));
+        assert!(!config.subscribed_domains.contains("b.example"));
+
+        // Unsubscribing keeps the parent's own copy of a shared domain
+        assert!(remove_subscription(&mut config, "https://lists.example/a.txt"));
+        assert_eq!(config.subscribed_domains, ["b2.example".to_string()].into());
+        assert!(blocklists::get_effective_blocked_domains(&config).contains("mine.example"));
+    }
+
+    #[test]
+    fn test_refresh_due_after_period() {
+        let now = Utc::now();
+        let mut subscription = BlocklistSubscription::new("https://lists.example/a.txt".to_string(), 6);
+        assert!(subscription.is_due(now));
+
+        subscription.last_refreshed = Some(now - chrono::Duration::hours(5));
+        assert!(!subscription.is_due(now));
+        subscription.last_refreshed = Some(now - chrono::Duration::hours(6));
+        assert!(subscription.is_due(now));
+
+        assert!(validate_url("ftp://lists.example/a.txt").is_err());
+        assert!(validate_url("not a url").is_err());
+    }
+}
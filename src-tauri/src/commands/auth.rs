@@ -1,7 +1,12 @@
 //! Authentication Tauri commands.
 
 use crate::config::{ConfigError, ConfigManager};
+use crate::daemon::client;
+use crate::security::master_password::{self, MachineFingerprint, MasterPasswordFormat};
+use crate::security::session::AUTH_SESSION;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use zeroize::Zeroizing;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,15 +27,41 @@ pub struct SetupResult {
 pub async fn get_auth_status() -> Result<AuthStatus, String> {
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
 
+    let is_authenticated = AUTH_SESSION
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_active(Instant::now());
+
     Ok(AuthStatus {
         is_configured: manager.config_exists(),
-        is_authenticated: false, // Will be managed by app state
+        is_authenticated,
     })
 }
 
+/// Authorize a sensitive operation with either a live session token or the password
+pub(crate) fn authorize(password: Option<String>, token: Option<&str>) -> Result<bool, String> {
+    let password = password.map(Zeroizing::new);
+
+    if let Some(token) = token {
+        let mut session = AUTH_SESSION.lock().map_err(|e| e.to_string())?;
+        if session.validate(token, Instant::now()) {
+            return Ok(true);
+        }
+    }
+
+    match password {
+        Some(password) => {
+            let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+            manager.verify_password(&password).map_err(|e| e.to_string())
+        }
+        None => Ok(false),
+    }
+}
+
 /// Initialize the app with a password (first run)
 #[tauri::command]
 pub async fn setup_password(password: String) -> Result<SetupResult, String> {
+    let password = Zeroizing::new(password);
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
 
     if manager.config_exists() {
@@ -58,20 +89,55 @@ pub async fn setup_password(password: String) -> Result<SetupResult, String> {
     }
 }
 
-/// Verify the password
+/// Verify the password and open an authenticated session.
+/// Returns the session token, or `None` if the password is wrong.
 #[tauri::command]
-pub async fn verify_password(password: String) -> Result<bool, String> {
+pub async fn verify_password(password: String) -> Result<Option<String>, String> {
+    let password = Zeroizing::new(password);
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
-    manager.verify_password(&password).map_err(|e| e.to_string())
+    if !manager.verify_password(&password).map_err(|e| e.to_string())? {
+        return Ok(None);
+    }
+
+    // Configs from before the parent account was recorded learn it here
+    #[cfg(unix)]
+    if let Err(e) = manager.record_parent_uid(unsafe { libc::getuid() }) {
+        tracing::warn!("Failed to record the parent account: {}", e);
+    }
+
+    // Lets the daemon take privileged requests from this GUI
+    if client::is_daemon_running() {
+        if let Err(e) = client::authenticate(&password) {
+            tracing::warn!("Daemon did not issue an IPC token: {}", e);
+        }
+    }
+
+    let mut session = AUTH_SESSION.lock().map_err(|e| e.to_string())?;
+    Ok(Some(session.establish(Instant::now())))
+}
+
+/// End the authenticated session
+#[tauri::command]
+pub async fn logout() -> Result<bool, String> {
+    AUTH_SESSION.lock().map_err(|e| e.to_string())?.clear();
+    client::forget_token();
+    Ok(true)
 }
 
 /// Change the password
 #[tauri::command]
 pub async fn change_password(old_password: String, new_password: String) -> Result<bool, String> {
+    let (old_password, new_password) = (Zeroizing::new(old_password), Zeroizing::new(new_password));
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
 
     match manager.change_password(&old_password, &new_password) {
-        Ok(()) => Ok(true),
+        Ok(()) => {
+            // The old IPC token died with the old password hash
+            if client::is_daemon_running() {
+                let _ = client::authenticate(&new_password);
+            }
+            Ok(true)
+        }
         Err(ConfigError::InvalidPassword) => Ok(false),
         Err(e) => Err(e.to_string()),
     }
@@ -80,6 +146,7 @@ pub async fn change_password(old_password: String, new_password: String) -> Resu
 /// Reset password using master recovery password
 #[tauri::command]
 pub async fn reset_with_master(master_password: String, new_password: String) -> Result<bool, String> {
+    let (master_password, new_password) = (Zeroizing::new(master_password), Zeroizing::new(new_password));
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
 
     match manager.reset_with_master_password(&master_password, &new_password) {
@@ -89,50 +156,107 @@ pub async fn reset_with_master(master_password: String, new_password: String) ->
     }
 }
 
-/// Get the master recovery password (requires authentication)
+/// Get the master recovery password (requires a session token or the password)
 #[tauri::command]
-pub async fn get_master_password(password: String) -> Result<Option<String>, String> {
+pub async fn get_master_password(
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<Option<String>, String> {
+    // Verify authentication first
+    if !authorize(password, token.as_deref())? {
+        return Ok(None);
+    }
+
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.get_master_password().map(Some).map_err(|e| e.to_string())
+}
 
-    // Verify password first
-    if !manager.verify_password(&password).map_err(|e| e.to_string())? {
+/// Replace the master recovery password with a new one (requires the password).
+/// Returns the new master password, or `None` if the password is wrong.
+#[tauri::command]
+pub async fn rotate_master_password(password: String) -> Result<Option<String>, String> {
+    let password = Zeroizing::new(password);
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+
+    match manager.rotate_master_password(&password) {
+        Ok(master) => Ok(Some(master)),
+        Err(ConfigError::InvalidPassword) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Change the master recovery password's format and return the new master
+/// password (requires a session token or the password)
+#[tauri::command]
+pub async fn set_master_password_format(
+    format: MasterPasswordFormat,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<Option<String>, String> {
+    if !authorize(password, token.as_deref())? {
         return Ok(None);
     }
 
-    manager.get_master_password().map(Some).map_err(|e| e.to_string())
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.set_master_password_format(format).map(Some).map_err(|e| e.to_string())
 }
 
 /// Enable uninstall protection (called after setup)
 #[tauri::command]
 pub async fn enable_uninstall_protection() -> Result<bool, String> {
-    crate::security::uninstall_protection::enable_protection()
-        .map(|_| true)
-        .map_err(|e| e.to_string())
+    crate::security::uninstall_protection::enable_protection().map_err(|e| e.to_string())?;
+    remember_uninstall_protection(true)?;
+    Ok(true)
 }
 
-/// Disable uninstall protection (requires password)
+/// Disable uninstall protection (requires a session token or the password)
 #[tauri::command]
-pub async fn disable_uninstall_protection(password: String) -> Result<bool, String> {
-    // Verify password first
-    crate::security::uninstall_protection::verify_uninstall_password(&password)
-        .map_err(|e| e.to_string())?;
+pub async fn disable_uninstall_protection(
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<bool, String> {
+    // Verify authentication first
+    if !authorize(password, token.as_deref())? {
+        return Err(crate::security::uninstall_protection::ProtectionError::AccessDenied.to_string());
+    }
 
+    // Stop the daemon from putting it back first
+    remember_uninstall_protection(false)?;
     crate::security::uninstall_protection::disable_protection()
         .map(|_| true)
         .map_err(|e| e.to_string())
 }
 
-/// Uninstall the application (requires password)
+/// Record whether the daemon should keep uninstall protection in place
+fn remember_uninstall_protection(enabled: bool) -> Result<(), String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+    if config.uninstall_protection != enabled {
+        config.uninstall_protection = enabled;
+        manager.save(&config).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Uninstall the application (requires a session token or the password)
 #[tauri::command]
-pub async fn uninstall_app(password: String) -> Result<bool, String> {
-    crate::security::uninstall_protection::uninstall_with_password(&password)
+pub async fn uninstall_app(password: Option<String>, token: Option<String>) -> Result<bool, String> {
+    if !authorize(password, token.as_deref())? {
+        return Err(crate::security::uninstall_protection::ProtectionError::AccessDenied.to_string());
+    }
+
+    crate::security::uninstall_protection::uninstall_authorized()
         .map(|_| true)
         .map_err(|e| e.to_string())
 }
 
-/// Quit the application (requires password, or no password if not configured)
+/// Quit the application (requires a session token or the password, or nothing if not configured)
 #[tauri::command]
-pub async fn quit_with_password(password: String, app: tauri::AppHandle) -> Result<bool, String> {
+pub async fn quit_with_password(
+    password: Option<String>,
+    token: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<bool, String> {
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
 
     // If app is not configured yet, allow quitting without password
@@ -142,12 +266,12 @@ pub async fn quit_with_password(password: String, app: tauri::AppHandle) -> Resu
         return Ok(true);
     }
 
-    // Verify password first
-    if !manager.verify_password(&password).map_err(|e| e.to_string())? {
+    // Verify authentication first
+    if !authorize(password, token.as_deref())? {
         return Ok(false);
     }
 
-    // Password correct, exit the app
+    // Authenticated, exit the app
     app.exit(0);
     Ok(true)
 }
@@ -166,3 +290,28 @@ pub async fn force_quit_unconfigured(app: tauri::AppHandle) -> Result<bool, Stri
     app.exit(0);
     Ok(true)
 }
+
+/// Show which machine id the app is using (hashed), to diagnose a master
+/// password that stopped working after a hardware change
+#[tauri::command]
+pub async fn get_machine_fingerprint() -> Result<MachineFingerprint, String> {
+    master_password::machine_fingerprint().ok_or_else(|| ConfigError::NoMachineId.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensitive_ops_rejected_without_session() {
+        AUTH_SESSION.lock().unwrap().clear();
+        assert!(!authorize(None, None).unwrap());
+        assert!(!authorize(None, Some("stale-token")).unwrap());
+
+        let token = AUTH_SESSION.lock().unwrap().establish(Instant::now());
+        assert!(authorize(None, Some(&token)).unwrap());
+
+        AUTH_SESSION.lock().unwrap().clear();
+        assert!(!authorize(None, Some(&token)).unwrap());
+    }
+}
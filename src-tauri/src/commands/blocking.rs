@@ -2,8 +2,9 @@
 
 use crate::blocking::{self, process};
 use crate::commands::license::LICENSE_STATE;
-use crate::config::ConfigManager;
+use crate::config::{AppConfig, ConfigManager};
 use crate::daemon::client;
+use crate::scheduler;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
@@ -31,6 +32,9 @@ pub struct BlockingStatus {
 pub struct BlockedProcess {
     pub pid: u32,
     pub name: String,
+    /// Times the daemon has killed this process name today (blocking checks only)
+    #[serde(default)]
+    pub kills_today: u32,
 }
 
 /// Get current blocking status
@@ -40,7 +44,7 @@ pub async fn get_blocking_status() -> Result<BlockingStatus, String> {
     let config = manager.load().map_err(|e| e.to_string())?;
 
     // Check if we're currently in an active blocking period
-    let currently_blocking = crate::scheduler::should_block_now(&config.schedules);
+    let currently_blocking = crate::scheduler::should_block_with_budget(&config);
 
     // Check if firewall blocking is active
     #[cfg(target_os = "linux")]
@@ -163,6 +167,195 @@ pub async fn set_dns_blocking(enabled: bool) -> Result<bool, String> {
     Ok(enabled)
 }
 
+/// Desired state of every blocking toggle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockingToggles {
+    pub game: bool,
+    pub ai: bool,
+    pub dns: bool,
+    pub browser: bool,
+}
+
+impl BlockingToggles {
+    /// Write the toggles into `config`, returning how many changed
+    fn apply_to(self, config: &mut AppConfig) -> usize {
+        let mut changed = 0;
+        for (flag, value) in [
+            (&mut config.game_blocking_enabled, self.game),
+            (&mut config.ai_blocking_enabled, self.ai),
+            (&mut config.dns_blocking_enabled, self.dns),
+            (&mut config.browser_blocking_enabled, self.browser),
+        ] {
+            if *flag != value {
+                *flag = value;
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+/// Update every toggle, then save and apply once if anything changed
+fn update_toggles<S, A>(config: &mut AppConfig, toggles: BlockingToggles, save: S, apply: A) -> Result<usize, String>
+where
+    S: FnOnce(&AppConfig) -> Result<(), String>,
+    A: FnOnce() -> Result<(), String>,
+{
+    let changed = toggles.apply_to(config);
+    if changed > 0 {
+        save(config)?;
+        apply()?;
+    }
+    Ok(changed)
+}
+
+/// Set all blocking toggles at once with a single apply (one password prompt
+/// when the daemon isn't running, instead of one per toggle)
+#[tauri::command]
+pub async fn set_all_blocking(game: bool, ai: bool, dns: bool, browser: bool) -> Result<BlockingStatus, String> {
+    check_license_active()?;
+    info!(
+        "set_all_blocking called with game={}, ai={}, dns={}, browser={}",
+        game, ai, dns, browser
+    );
+
+    if client::is_daemon_running() {
+        // The daemon saves and applies once per update
+        info!("Using daemon for bulk blocking toggle");
+        client::update_config(Some(game), Some(ai), Some(dns), Some(browser))
+            .map_err(|e| e.to_string())?;
+        return get_blocking_status().await;
+    }
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+    let toggles = BlockingToggles { game, ai, dns, browser };
+    update_toggles(
+        &mut config,
+        toggles,
+        |config| manager.save(config).map_err(|e| e.to_string()),
+        || apply_blocking_with_pkexec().map(|_| ()).map_err(|e| e.to_string()),
+    )?;
+
+    get_blocking_status().await
+}
+
+/// Set the message shown when a blocked app is closed (None restores the default)
+#[tauri::command]
+pub async fn set_block_message(message: Option<String>) -> Result<bool, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    config.block_message = message.filter(|m| !m.trim().is_empty());
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Longest a category can be lifted in one go
+const MAX_CATEGORY_ALLOWANCE_MINUTES: u32 = 24 * 60;
+
+/// Temporarily allow a whole category ("games" or "ai") for some minutes (requires a session token or the password).
+/// Returns when blocking resumes; the daemon re-applies blocking once the window ends.
+#[tauri::command]
+pub async fn allow_category_temporarily(
+    category: String,
+    minutes: u32,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    let category: blocking::BlockCategory = category.parse()?;
+    if minutes == 0 || minutes > MAX_CATEGORY_ALLOWANCE_MINUTES {
+        return Err(format!(
+            "Duration must be between 1 and {} minutes",
+            MAX_CATEGORY_ALLOWANCE_MINUTES
+        ));
+    }
+
+    if !crate::commands::auth::authorize(password, token.as_deref())? {
+        return Err("Invalid password".to_string());
+    }
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+    // The trusted clock, so turning the system clock back doesn't extend it
+    let now = scheduler::trusted_now();
+    let until = now + chrono::Duration::minutes(minutes as i64);
+
+    config.category_allowances.retain(|_, expiry| *expiry > now);
+    config
+        .category_allowances
+        .insert(category.as_str().to_string(), until);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    info!("Category {} allowed until {}", category.as_str(), until);
+
+    if client::is_daemon_running() {
+        client::apply_blocking().map_err(|e| e.to_string())?;
+    } else {
+        apply_blocking_with_pkexec().map_err(|e| e.to_string())?;
+    }
+
+    Ok(until)
+}
+
+/// Lift all blocking for some minutes (requires a session token or the
+/// password). Returns when blocking resumes; the daemon re-applies blocking
+/// once the unlock expires.
+#[tauri::command]
+pub async fn grant_temporary_unlock(
+    minutes: u32,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<chrono::DateTime<chrono::Utc>, String> {
+    if minutes == 0 || minutes > scheduler::MAX_UNLOCK_MINUTES {
+        return Err(format!(
+            "Duration must be between 1 and {} minutes",
+            scheduler::MAX_UNLOCK_MINUTES
+        ));
+    }
+
+    if !crate::commands::auth::authorize(password, token.as_deref())? {
+        return Err("Invalid password".to_string());
+    }
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+    let unlock = scheduler::TemporaryUnlock::for_minutes(minutes, scheduler::trusted_now());
+    config.temporary_unlock = Some(unlock);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    info!("Blocking unlocked until {}", unlock.until);
+
+    if client::is_daemon_running() {
+        client::apply_blocking().map_err(|e| e.to_string())?;
+    } else {
+        apply_blocking_with_pkexec().map_err(|e| e.to_string())?;
+    }
+
+    Ok(unlock.until)
+}
+
+/// When the running temporary unlock ends, for a countdown; `None` if there is none
+#[tauri::command]
+pub async fn get_active_unlock() -> Result<Option<chrono::DateTime<chrono::Utc>>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(scheduler::active_unlock_at(
+        config.temporary_unlock.as_ref(),
+        scheduler::trusted_now(),
+    ))
+}
+
+/// Check for ways the current blocking could be bypassed
+#[tauri::command]
+pub async fn audit_bypass_risks() -> Result<Vec<blocking::BypassRisk>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(blocking::audit_bypass_risks(&config))
+}
 
 /// Run a blocking check now (scan and terminate blocked processes)
 #[tauri::command]
@@ -170,11 +363,12 @@ pub async fn run_blocking_check() -> Result<Vec<BlockedProcess>, String> {
     // Try to use daemon first (it runs continuously with root)
     if client::is_daemon_running() {
         info!("Using daemon for blocking check");
-        let processes = client::run_blocking_check().map_err(|e| e.to_string())?;
+        let (processes, kills_today) = client::run_blocking_check().map_err(|e| e.to_string())?;
         return Ok(processes
             .into_iter()
             .map(|p| BlockedProcess {
                 pid: p.pid,
+                kills_today: kills_today.get(&p.name.to_lowercase()).copied().unwrap_or(0),
                 name: p.name,
             })
             .collect());
@@ -197,29 +391,51 @@ pub async fn list_processes() -> Result<Vec<BlockedProcess>, String> {
         .map(|p| BlockedProcess {
             pid: p.pid,
             name: p.name,
+            kills_today: 0,
         })
         .collect())
 }
 
+/// List running processes annotated with whether the daemon would block them
+#[tauri::command]
+pub async fn list_processes_annotated() -> Result<Vec<process::AnnotatedProcess>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    let blocker = process::create_blocker();
+    let processes = blocker.list_processes().map_err(|e| e.to_string())?;
+
+    let blocked = blocking::get_effective_blocked_processes(&config);
+    Ok(process::annotate_processes(
+        processes,
+        &blocked,
+        &config.allowed_processes,
+        &config.allowed_domains,
+    ))
+}
+
 /// Apply current blocking settings (call on app start/login)
 #[tauri::command]
-pub async fn apply_blocking() -> Result<(), String> {
+pub async fn apply_blocking() -> Result<blocking::ApplyReport, String> {
     check_license_active()?;
     // Try to use daemon first (runs as root, no password prompt)
     if client::is_daemon_running() {
         info!("Using daemon for apply_blocking");
-        client::apply_blocking().map_err(|e| e.to_string())?;
-        return Ok(());
+        return client::apply_blocking().map_err(|e| e.to_string());
     }
 
     // Daemon not running - apply blocking with single pkexec call
     info!("Daemon not running, applying blocking with pkexec");
-    apply_blocking_with_pkexec().map_err(|e| e.to_string())?;
-    Ok(())
+    let domains_blocked = apply_blocking_with_pkexec().map_err(|e| e.to_string())?;
+    Ok(blocking::ApplyReport {
+        domains_blocked,
+        ..blocking::ApplyReport::new()
+    })
 }
 
-/// Apply all blocking with a single pkexec call (for when daemon isn't running)
-fn apply_blocking_with_pkexec() -> std::io::Result<()> {
+/// Apply all blocking with a single pkexec call (for when daemon isn't running),
+/// returning how many domains went into the hosts file
+fn apply_blocking_with_pkexec() -> std::io::Result<usize> {
     let manager = ConfigManager::new().map_err(|e| {
         std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
     })?;
@@ -227,53 +443,30 @@ fn apply_blocking_with_pkexec() -> std::io::Result<()> {
         std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
     })?;
 
-    let should_block = crate::scheduler::should_block_now(&config.schedules);
+    let should_block = crate::scheduler::should_block_with_budget(&config);
     let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
 
     if !should_block || !any_blocking {
         info!("Blocking not active, clearing hosts file");
-        return blocking::unblock_all_domains();
+        return blocking::unblock_all_domains().map(|_| 0);
     }
 
     // Build domains to block
-    let mut domains: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    if config.game_blocking_enabled {
-        domains.extend(blocking::get_default_gaming_domains());
-    }
-    if config.ai_blocking_enabled {
-        domains.extend(blocking::get_default_ai_domains());
-    }
-    if config.dns_blocking_enabled {
-        // Custom Websites blocks all domains in the blocklist (defaults + user-added)
-        domains.extend(blocking::get_default_gaming_domains());
-        domains.extend(blocking::get_default_ai_domains());
-        domains.extend(config.blocked_domains.clone());
-    }
-
-    // Remove allowed domains (including subdomains)
-    let domains_to_remove: Vec<String> = domains
-        .iter()
-        .filter(|domain| {
-            for allowed in &config.allowed_domains {
-                if *domain == allowed || domain.ends_with(&format!(".{}", allowed)) {
-                    return true;
-                }
-            }
-            false
-        })
-        .cloned()
-        .collect();
-    for domain in domains_to_remove {
-        domains.remove(&domain);
-    }
+    let domains = blocking::get_effective_blocked_domains(&config);
 
     if domains.is_empty() {
-        return blocking::unblock_all_domains();
+        return blocking::unblock_all_domains().map(|_| 0);
     }
 
     info!("Blocking {} domains via hosts file", domains.len());
-    blocking::block_domains(&domains)
+    blocking::block_domains(&domains)?;
+    Ok(domains.len())
+}
+
+/// Put the last known-good hosts file back if a write left it broken
+#[tauri::command]
+pub async fn restore_hosts_backup() -> Result<(), String> {
+    blocking::restore_hosts_backup().map_err(|e| e.to_string())
 }
 
 /// Disable DNS-over-HTTPS in all browsers for effective blocking
@@ -282,7 +475,10 @@ pub async fn disable_browser_doh() -> Result<Vec<String>, String> {
     use tracing::info;
     info!("Disabling DNS-over-HTTPS in browsers...");
 
-    blocking::disable_doh_all_browsers().map_err(|e| e.to_string())
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    blocking::disable_doh_all_browsers(config.block_private_browsing).map_err(|e| e.to_string())
 }
 
 /// Re-enable DNS-over-HTTPS in all browsers
@@ -300,6 +496,12 @@ pub async fn is_doh_disabled() -> Result<bool, String> {
     Ok(blocking::is_doh_disabled())
 }
 
+/// Check if private browsing is disabled by browser policy
+#[tauri::command]
+pub async fn is_private_browsing_disabled() -> Result<bool, String> {
+    Ok(blocking::is_private_browsing_disabled())
+}
+
 /// Enable firewall-level blocking (blocks DoH providers to enforce hosts file)
 #[tauri::command]
 pub async fn enable_firewall_blocking() -> Result<bool, String> {
@@ -348,3 +550,71 @@ pub async fn is_firewall_blocking_active() -> Result<bool, String> {
         Ok(false)
     }
 }
+
+/// DoH provider IPs that currently have firewall DROP rules, to compare
+/// against the configured list
+#[tauri::command]
+pub async fn get_blocked_doh_ips() -> Result<Vec<String>, String> {
+    Ok(blocking::list_blocked_doh_ips())
+}
+
+/// Report which blocking features are implemented on this platform
+#[tauri::command]
+pub async fn get_capabilities() -> Result<blocking::Capabilities, String> {
+    Ok(blocking::get_capabilities())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn run_update(config: &mut AppConfig, toggles: BlockingToggles) -> (usize, u32, u32) {
+        let saves = Cell::new(0);
+        let applies = Cell::new(0);
+        let changed = update_toggles(
+            config,
+            toggles,
+            |_| {
+                saves.set(saves.get() + 1);
+                Ok(())
+            },
+            || {
+                applies.set(applies.get() + 1);
+                Ok(())
+            },
+        )
+        .unwrap();
+        (changed, saves.get(), applies.get())
+    }
+
+    #[test]
+    fn test_bulk_toggle_applies_once() {
+        let mut config = AppConfig::default();
+        let all_on = BlockingToggles {
+            game: true,
+            ai: true,
+            dns: true,
+            browser: true,
+        };
+        assert_eq!(run_update(&mut config, all_on), (4, 1, 1));
+        assert!(config.game_blocking_enabled && config.ai_blocking_enabled);
+        assert!(config.dns_blocking_enabled && config.browser_blocking_enabled);
+
+        let one_off = BlockingToggles { ai: false, ..all_on };
+        assert_eq!(run_update(&mut config, one_off), (1, 1, 1));
+        assert!(!config.ai_blocking_enabled);
+    }
+
+    #[test]
+    fn test_bulk_toggle_without_changes_skips_apply() {
+        let mut config = AppConfig::default();
+        let unchanged = BlockingToggles {
+            game: config.game_blocking_enabled,
+            ai: config.ai_blocking_enabled,
+            dns: config.dns_blocking_enabled,
+            browser: config.browser_blocking_enabled,
+        };
+        assert_eq!(run_update(&mut config, unchanged), (0, 0, 0));
+    }
+}
@@ -1,8 +1,16 @@
 //! Blocklist management Tauri commands.
 
-use crate::blocking::blocklists;
+use crate::blocking::network::doh_ips::parse_doh_ips;
+use crate::blocking::network::vpn_ports::parse_vpn_block;
+use crate::blocking::subscriptions::{self, BlocklistSubscription};
+use crate::blocking::{self, blocklists, blocklists::BlocklistGroup, process::TerminationMode};
 use crate::config::ConfigManager;
+use crate::daemon::client;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Source tag for lists pasted into the app
+const PASTED_SOURCE: &str = "pasted";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlocklistItem {
@@ -13,7 +21,11 @@ pub struct BlocklistItem {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlocklistCategory {
+    /// Stable identifier for `set_category_blocking`
+    pub id: String,
     pub name: String,
+    /// Whether the category's default entries are enforced
+    pub enabled: bool,
     pub items: Vec<BlocklistItem>,
 }
 
@@ -50,7 +62,9 @@ pub async fn get_blocklists() -> Result<Vec<BlocklistCategory>, String> {
     }
 
     categories.push(BlocklistCategory {
-        name: "Gaming Processes".to_string(),
+        id: BlocklistGroup::GamingProcesses.id().to_string(),
+        name: BlocklistGroup::GamingProcesses.display_name().to_string(),
+        enabled: blocklists::is_group_enabled(&config, BlocklistGroup::GamingProcesses),
         items: process_items,
     });
 
@@ -65,7 +79,9 @@ pub async fn get_blocklists() -> Result<Vec<BlocklistCategory>, String> {
         .collect();
 
     categories.push(BlocklistCategory {
-        name: "AI Services".to_string(),
+        id: BlocklistGroup::AiServices.id().to_string(),
+        name: BlocklistGroup::AiServices.display_name().to_string(),
+        enabled: blocklists::is_group_enabled(&config, BlocklistGroup::AiServices),
         items: ai_items,
     });
 
@@ -90,13 +106,30 @@ pub async fn get_blocklists() -> Result<Vec<BlocklistCategory>, String> {
     }
 
     categories.push(BlocklistCategory {
-        name: "Gaming Websites".to_string(),
+        id: BlocklistGroup::GamingWebsites.id().to_string(),
+        name: BlocklistGroup::GamingWebsites.display_name().to_string(),
+        enabled: blocklists::is_group_enabled(&config, BlocklistGroup::GamingWebsites),
         items: gaming_items,
     });
 
     Ok(categories)
 }
 
+/// Block or allow every default entry of a category (by its stable id, such
+/// as "ai_services") at once, returning whether anything changed
+#[tauri::command]
+pub async fn set_category_blocking(category: String, enabled: bool) -> Result<bool, String> {
+    let group: BlocklistGroup = category.parse()?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let changed = blocklists::set_group_blocking(&mut config, group, enabled);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(changed)
+}
+
 /// Add a custom blocked process
 #[tauri::command]
 pub async fn add_blocked_process(process: String) -> Result<bool, String> {
@@ -123,6 +156,35 @@ pub async fn remove_blocked_process(process: String) -> Result<bool, String> {
     Ok(removed)
 }
 
+/// Set how blocked processes are terminated. With `process`, sets the mode for that
+/// blocklist entry (`None` mode clears the override); without it, sets the global default.
+#[tauri::command]
+pub async fn set_termination_mode(
+    process: Option<String>,
+    mode: Option<TerminationMode>,
+) -> Result<bool, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    match process {
+        Some(process) => {
+            let process_lower = process.to_lowercase();
+            match mode {
+                Some(mode) => {
+                    config.process_termination_modes.insert(process_lower, mode);
+                }
+                None => {
+                    config.process_termination_modes.remove(&process_lower);
+                }
+            }
+        }
+        None => config.termination_mode = mode.unwrap_or_default(),
+    }
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
 /// Add a custom blocked domain
 #[tauri::command]
 pub async fn add_blocked_domain(domain: String) -> Result<bool, String> {
@@ -130,6 +192,8 @@ pub async fn add_blocked_domain(domain: String) -> Result<bool, String> {
     let mut config = manager.load().map_err(|e| e.to_string())?;
 
     let domain_lower = domain.to_lowercase();
+    // Adding by hand makes an imported entry a custom one
+    config.imported_domain_sources.remove(&domain_lower);
     config.blocked_domains.insert(domain_lower);
     manager.save(&config).map_err(|e| e.to_string())?;
 
@@ -144,6 +208,78 @@ pub async fn remove_blocked_domain(domain: String) -> Result<bool, String> {
 
     let domain_lower = domain.to_lowercase();
     let removed = config.blocked_domains.remove(&domain_lower);
+    config.imported_domain_sources.remove(&domain_lower);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(removed)
+}
+
+/// Add an address to the DoH provider IPs the firewall blocks.
+/// Takes effect the next time blocking is applied.
+#[tauri::command]
+pub async fn add_doh_block_ip(ip: String) -> Result<bool, String> {
+    let ip: IpAddr = ip.trim().parse().map_err(|_| format!("Invalid IP address: {}", ip))?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    if parse_doh_ips(&config.doh_provider_ips).contains(&ip) {
+        return Ok(false);
+    }
+    config.doh_provider_ips.push(ip.to_string());
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Stop blocking a DoH provider IP
+#[tauri::command]
+pub async fn remove_doh_block_ip(ip: String) -> Result<bool, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    // Match by address so "2620:fe::fe" removes "2620:00fe::00fe" too
+    let target = ip.trim();
+    let parsed = target.parse::<IpAddr>().ok();
+    let before = config.doh_provider_ips.len();
+    config
+        .doh_provider_ips
+        .retain(|entry| entry.trim() != target && (parsed.is_none() || entry.trim().parse().ok() != parsed));
+    let removed = config.doh_provider_ips.len() != before;
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(removed)
+}
+
+/// Block another VPN port (e.g. 41641/udp for Tailscale).
+/// Takes effect the next time blocking is applied.
+#[tauri::command]
+pub async fn add_vpn_block(port: u16, protocol: String) -> Result<bool, String> {
+    let block = parse_vpn_block(port, &protocol)?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    if config.extra_vpn_blocks.contains(&block) {
+        return Ok(false);
+    }
+    config.extra_vpn_blocks.push(block);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+/// Stop blocking a VPN port added with `add_vpn_block`
+#[tauri::command]
+pub async fn remove_vpn_block(port: u16, protocol: String) -> Result<bool, String> {
+    let block = parse_vpn_block(port, &protocol)?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let before = config.extra_vpn_blocks.len();
+    config.extra_vpn_blocks.retain(|entry| *entry != block);
+    let removed = config.extra_vpn_blocks.len() != before;
     manager.save(&config).map_err(|e| e.to_string())?;
 
     Ok(removed)
@@ -159,6 +295,10 @@ pub async fn add_to_whitelist(item: String, item_type: String) -> Result<bool, S
 
     match item_type.as_str() {
         "process" => {
+            // Paths must be absolute to be matched against the executable
+            if item_lower.contains(['/', '\\']) && !blocklists::is_exe_path_entry(&item_lower) {
+                return Err("Whitelisted executable paths must be absolute".to_string());
+            }
             config.allowed_processes.insert(item_lower);
         }
         "domain" => {
@@ -188,3 +328,141 @@ pub async fn remove_from_whitelist(item: String, item_type: String) -> Result<bo
     manager.save(&config).map_err(|e| e.to_string())?;
     Ok(removed)
 }
+
+/// Export the currently effective blocklist as a hosts or dnsmasq file
+#[tauri::command]
+pub async fn export_hosts(format: String) -> Result<String, String> {
+    let format: blocking::ExportFormat = format.parse()?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    let domains = blocklists::get_effective_blocked_domains(&config);
+    Ok(blocking::export_blocklist(&domains, format))
+}
+
+/// Import a community blocklist (hosts or plain format), tagging entries with `source`
+#[tauri::command]
+pub async fn import_blocklist(source: String, content: String) -> Result<usize, String> {
+    let source = source.trim().to_string();
+    if source.is_empty() {
+        return Err("Source name is required".to_string());
+    }
+
+    import_into_config(&source, &content).await
+}
+
+/// Import a hosts-format or plain domain list downloaded from `url`,
+/// tagging entries with the URL so they can be cleared together
+#[tauri::command]
+pub async fn import_blocklist_from_url(url: String) -> Result<usize, String> {
+    let url = url.trim().to_string();
+    let content = subscriptions::fetch_blocklist(&url).await.map_err(|e| e.to_string())?;
+    import_into_config(&url, &content).await
+}
+
+/// Import a pasted hosts-format or plain domain list
+#[tauri::command]
+pub async fn import_blocklist_from_text(text: String) -> Result<usize, String> {
+    import_into_config(PASTED_SOURCE, &text).await
+}
+
+/// Parse `content`, add its new domains to the blocklist and re-apply
+/// blocking, returning how many were added
+async fn import_into_config(source: &str, content: &str) -> Result<usize, String> {
+    let domains = blocklists::parse_import_limited(content).map_err(|e| e.to_string())?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let added = blocklists::import_domains(&mut config, source, &domains);
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    crate::commands::blocking::apply_blocking().await?;
+    Ok(added)
+}
+
+/// Subscribe to a blocklist URL; the daemon fetches it now and then every
+/// `refresh_hours`. Returns false if already subscribed.
+#[tauri::command]
+pub async fn add_blocklist_subscription(url: String, refresh_hours: Option<u32>) -> Result<bool, String> {
+    let url = url.trim().to_string();
+    subscriptions::validate_url(&url).map_err(|e| e.to_string())?;
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    if config.blocklist_subscriptions.iter().any(|s| s.url == url) {
+        return Ok(false);
+    }
+    let refresh_hours = refresh_hours.unwrap_or(subscriptions::DEFAULT_REFRESH_HOURS).max(1);
+    config
+        .blocklist_subscriptions
+        .push(BlocklistSubscription::new(url, refresh_hours));
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    if client::is_daemon_running() {
+        client::refresh_subscriptions_now().map_err(|e| e.to_string())?;
+    }
+    Ok(true)
+}
+
+/// Unsubscribe from a blocklist URL, dropping the domains only it supplied
+#[tauri::command]
+pub async fn remove_blocklist_subscription(url: String) -> Result<bool, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let removed = subscriptions::remove_subscription(&mut config, url.trim());
+    manager.save(&config).map_err(|e| e.to_string())?;
+
+    Ok(removed)
+}
+
+/// Have the daemon re-fetch every subscription in the background
+#[tauri::command]
+pub async fn refresh_subscriptions_now() -> Result<(), String> {
+    client::refresh_subscriptions_now().map_err(|e| e.to_string())
+}
+
+/// Remove the entries imported from `source`, keeping custom and default entries
+#[tauri::command]
+pub async fn clear_imported_blocklist(source: String) -> Result<usize, String> {
+    clear_imported(Some(&source)).await
+}
+
+/// Remove every imported entry, keeping custom and default entries
+#[tauri::command]
+pub async fn clear_all_imported() -> Result<usize, String> {
+    clear_imported(None).await
+}
+
+async fn clear_imported(source: Option<&str>) -> Result<usize, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let removed = blocklists::clear_imported_domains(&mut config, source);
+    if removed > 0 {
+        manager.save(&config).map_err(|e| e.to_string())?;
+        crate::commands::blocking::apply_blocking().await?;
+    }
+    Ok(removed)
+}
+
+/// Check how each domain is handled right now, and why
+#[tauri::command]
+pub async fn test_coverage(domains: Vec<String>) -> Result<Vec<(String, blocklists::BlockDecision)>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    // The blocking loop's clock, so a changed system clock gives the same answer
+    let now = crate::scheduler::trusted_now();
+    Ok(domains
+        .into_iter()
+        .filter(|d| !d.trim().is_empty())
+        .map(|domain| {
+            let decision = blocklists::check_domain(&config, &domain, now);
+            (domain, decision)
+        })
+        .collect())
+}
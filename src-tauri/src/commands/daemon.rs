@@ -1,7 +1,17 @@
 //! Tauri commands for daemon management.
 
+use crate::blocking::network::{BlockedQuery, DnsMetrics};
+use crate::commands::auth::authorize;
 use crate::daemon::{client, service};
+use crate::security::audit::{AuditEntry, AuditLog};
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Blocked queries returned when the frontend doesn't ask for a number
+const DEFAULT_RECENT_BLOCKS: usize = 100;
+
+/// Tamper log entries returned when the frontend doesn't ask for a number
+const DEFAULT_TAMPER_ENTRIES: usize = 50;
 
 /// Daemon status returned to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +27,19 @@ pub struct DaemonStatus {
     pub firewall_active: bool,
     pub blocked_count: u32,
     pub uptime_secs: u64,
+    /// When the daemon last ran a blocking check (Unix seconds, 0 if unknown)
+    pub last_check_unix: u64,
+    pub check_interval_secs: u32,
+    /// The daemon answers but its monitoring loop hasn't checked in for a while
+    pub check_stale: bool,
+}
+
+/// Result of re-applying browser policies via daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserPolicyResult {
+    pub applied: Vec<String>,
+    pub users: Vec<crate::blocking::UserDohStatus>,
 }
 
 /// Check if daemon is installed
@@ -50,6 +73,9 @@ pub async fn get_daemon_status() -> Result<DaemonStatus, String> {
             firewall_active: false,
             blocked_count: 0,
             uptime_secs: 0,
+            last_check_unix: 0,
+            check_interval_secs: 0,
+            check_stale: false,
         });
     }
 
@@ -65,6 +91,9 @@ pub async fn get_daemon_status() -> Result<DaemonStatus, String> {
             firewall_active: status.firewall_active,
             blocked_count: status.blocked_count,
             uptime_secs: status.uptime_secs,
+            last_check_unix: status.last_check_unix,
+            check_interval_secs: status.check_interval_secs,
+            check_stale: status.check_is_stale(chrono::Utc::now().timestamp().max(0) as u64),
         }),
         Err(_) => {
             // Daemon installed but not responding - might be stopped
@@ -80,6 +109,9 @@ pub async fn get_daemon_status() -> Result<DaemonStatus, String> {
                 firewall_active: false,
                 blocked_count: 0,
                 uptime_secs: 0,
+                last_check_unix: 0,
+                check_interval_secs: 0,
+                check_stale: false,
             })
         }
     }
@@ -87,7 +119,7 @@ pub async fn get_daemon_status() -> Result<DaemonStatus, String> {
 
 /// Install the daemon service (requires root)
 #[tauri::command]
-pub async fn install_daemon() -> Result<(), String> {
+pub async fn install_daemon() -> Result<service::InstallOutcome, String> {
     let manager = service::get_service_manager();
     manager.install().map_err(|e| e.to_string())
 }
@@ -128,12 +160,13 @@ pub async fn daemon_update_config(
 /// Run blocking check via daemon
 #[tauri::command]
 pub async fn daemon_run_blocking_check() -> Result<Vec<crate::commands::blocking::BlockedProcess>, String> {
-    let processes = client::run_blocking_check().map_err(|e| e.to_string())?;
+    let (processes, kills_today) = client::run_blocking_check().map_err(|e| e.to_string())?;
 
     Ok(processes
         .into_iter()
         .map(|p| crate::commands::blocking::BlockedProcess {
             pid: p.pid,
+            kills_today: kills_today.get(&p.name.to_lowercase()).copied().unwrap_or(0),
             name: p.name,
         })
         .collect())
@@ -141,10 +174,22 @@ pub async fn daemon_run_blocking_check() -> Result<Vec<crate::commands::blocking
 
 /// Apply blocking settings via daemon
 #[tauri::command]
-pub async fn daemon_apply_blocking() -> Result<(), String> {
+pub async fn daemon_apply_blocking() -> Result<crate::blocking::ApplyReport, String> {
     client::apply_blocking().map_err(|e| e.to_string())
 }
 
+/// Processes and domains the daemon would block right now, without blocking them
+#[tauri::command]
+pub async fn daemon_preview_blocking() -> Result<crate::daemon::ipc::BlockingPreview, String> {
+    client::preview_blocking().map_err(|e| e.to_string())
+}
+
+/// Have the daemon pick up blocklist and schedule edits from the config file
+#[tauri::command]
+pub async fn daemon_reload_config() -> Result<(), String> {
+    client::reload_config().map_err(|e| e.to_string())
+}
+
 /// Enable firewall blocking via daemon
 #[tauri::command]
 pub async fn daemon_enable_firewall() -> Result<(), String> {
@@ -156,3 +201,96 @@ pub async fn daemon_enable_firewall() -> Result<(), String> {
 pub async fn daemon_disable_firewall() -> Result<(), String> {
     client::disable_firewall().map_err(|e| e.to_string())
 }
+
+/// Re-apply managed browser DoH policies via daemon
+#[tauri::command]
+pub async fn daemon_apply_browser_policies() -> Result<BrowserPolicyResult, String> {
+    let (applied, users) = client::apply_browser_policies().map_err(|e| e.to_string())?;
+    Ok(BrowserPolicyResult { applied, users })
+}
+
+/// Pause daemon self-heal so ParentShield can be updated (requires a session
+/// token or the password)
+#[tauri::command]
+pub async fn daemon_enter_maintenance(
+    minutes: Option<u64>,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<u64, String> {
+    authorize_with_daemon(password, token.as_deref())?;
+    client::enter_maintenance(minutes).map_err(|e| e.to_string())
+}
+
+/// End maintenance mode and re-enable protection
+#[tauri::command]
+pub async fn daemon_exit_maintenance() -> Result<(), String> {
+    client::exit_maintenance().map_err(|e| e.to_string())
+}
+
+/// Delete rotated daemon logs and empty the active ones (requires a session
+/// token or the password)
+#[tauri::command]
+pub async fn purge_logs(password: Option<String>, token: Option<String>) -> Result<usize, String> {
+    authorize_with_daemon(password, token.as_deref())?;
+    client::purge_logs().map_err(|e| e.to_string())
+}
+
+/// Check the password or session token for a privileged daemon request. A
+/// password also renews the daemon's IPC token, which the request carries.
+fn authorize_with_daemon(password: Option<String>, token: Option<&str>) -> Result<(), String> {
+    if !authorize(password.clone(), token)? {
+        return Err("Invalid password".to_string());
+    }
+    if let Some(password) = password.map(Zeroizing::new) {
+        client::authenticate(&password).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// DNS proxy counters for the dashboard
+#[tauri::command]
+pub async fn get_dns_metrics() -> Result<DnsMetrics, String> {
+    client::get_dns_metrics().map_err(|e| e.to_string())
+}
+
+/// Recently blocked DNS queries for the parent to review (newest last)
+#[tauri::command]
+pub async fn get_recent_blocks(limit: Option<usize>) -> Result<Vec<BlockedQuery>, String> {
+    client::get_recent_blocks(limit.unwrap_or(DEFAULT_RECENT_BLOCKS)).map_err(|e| e.to_string())
+}
+
+/// Recent tamper log entries, once the whole log has verified
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TamperLog {
+    pub entries: Vec<AuditEntry>,
+    /// Why the log failed verification (no entries are returned then)
+    pub integrity_error: Option<String>,
+}
+
+/// Protection the daemon saw removed outside the app (newest last)
+#[tauri::command]
+pub async fn get_tamper_log(limit: Option<usize>) -> Result<TamperLog, String> {
+    let log = AuditLog::system().map_err(|e| e.to_string())?;
+
+    Ok(match log.verify() {
+        Ok(mut entries) => {
+            let keep = limit.unwrap_or(DEFAULT_TAMPER_ENTRIES);
+            entries.drain(..entries.len().saturating_sub(keep));
+            TamperLog {
+                entries,
+                integrity_error: None,
+            }
+        }
+        Err(e) => TamperLog {
+            entries: Vec::new(),
+            integrity_error: Some(e.to_string()),
+        },
+    })
+}
+
+/// Report why the daemon can or can't be reached
+#[tauri::command]
+pub async fn diagnose_daemon_connection() -> client::DaemonConnDiag {
+    client::diagnose_daemon_connection()
+}
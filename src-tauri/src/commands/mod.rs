@@ -4,6 +4,7 @@ pub mod blocklist;
 pub mod daemon;
 pub mod license;
 pub mod schedule;
+pub mod settings;
 
 pub use auth::*;
 pub use blocking::*;
@@ -11,3 +12,4 @@ pub use blocklist::*;
 pub use daemon::*;
 pub use license::*;
 pub use schedule::*;
+pub use settings::*;
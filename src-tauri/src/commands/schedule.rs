@@ -1,7 +1,8 @@
 //! Schedule management Tauri commands.
 
 use crate::config::{ConfigManager, ScheduleEntry};
-use crate::scheduler;
+use crate::scheduler::{self, DateException, ExceptionMode, ScheduleWarning};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -14,6 +15,16 @@ pub struct ScheduleInfo {
     pub start_minutes: u16,
     pub end_minutes: u16,
     pub blocking_enabled: bool,
+    #[serde(default)]
+    pub warn_minutes: Option<u32>,
+    #[serde(default)]
+    pub warn_message: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub cron: Option<String>,
 }
 
 impl From<ScheduleEntry> for ScheduleInfo {
@@ -26,20 +37,44 @@ impl From<ScheduleEntry> for ScheduleInfo {
             start_minutes: entry.start_minutes,
             end_minutes: entry.end_minutes,
             blocking_enabled: entry.blocking_enabled,
+            warn_minutes: entry.warn_minutes,
+            warn_message: entry.warn_message,
+            timezone: entry.timezone,
+            priority: entry.priority,
+            cron: entry.cron,
         }
     }
 }
 
-impl From<ScheduleInfo> for ScheduleEntry {
-    fn from(info: ScheduleInfo) -> Self {
-        Self {
-            id: Uuid::parse_str(&info.id).unwrap_or_else(|_| Uuid::new_v4()),
-            name: info.name,
-            enabled: info.enabled,
-            days: info.days,
-            start_minutes: info.start_minutes,
-            end_minutes: info.end_minutes,
-            blocking_enabled: info.blocking_enabled,
+impl TryFrom<ScheduleInfo> for ScheduleEntry {
+    type Error = String;
+
+    /// Convert a schedule from the frontend, rejecting out-of-range or empty fields
+    fn try_from(info: ScheduleInfo) -> Result<Self, String> {
+        let mut entry = info.into_entry_unchecked();
+        entry.days.sort_unstable();
+        entry.days.dedup();
+        scheduler::check_schedule_fields(&entry)?;
+        Ok(entry)
+    }
+}
+
+impl ScheduleInfo {
+    /// Convert without checking, for reporting problems in a proposed set
+    fn into_entry_unchecked(self) -> ScheduleEntry {
+        ScheduleEntry {
+            id: Uuid::parse_str(&self.id).unwrap_or_else(|_| Uuid::new_v4()),
+            name: self.name,
+            enabled: self.enabled,
+            days: self.days,
+            start_minutes: self.start_minutes,
+            end_minutes: self.end_minutes,
+            blocking_enabled: self.blocking_enabled,
+            warn_minutes: self.warn_minutes,
+            warn_message: self.warn_message,
+            timezone: self.timezone,
+            priority: self.priority,
+            cron: self.cron,
         }
     }
 }
@@ -56,10 +91,11 @@ pub async fn get_schedules() -> Result<Vec<ScheduleInfo>, String> {
 /// Add a new schedule
 #[tauri::command]
 pub async fn add_schedule(schedule: ScheduleInfo) -> Result<ScheduleInfo, String> {
+    let mut entry = ScheduleEntry::try_from(schedule)?;
+
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
     let mut config = manager.load().map_err(|e| e.to_string())?;
 
-    let mut entry: ScheduleEntry = schedule.into();
     entry.id = Uuid::new_v4(); // Generate new ID
 
     config.schedules.push(entry.clone());
@@ -71,19 +107,13 @@ pub async fn add_schedule(schedule: ScheduleInfo) -> Result<ScheduleInfo, String
 /// Update an existing schedule
 #[tauri::command]
 pub async fn update_schedule(schedule: ScheduleInfo) -> Result<bool, String> {
-    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
-    let mut config = manager.load().map_err(|e| e.to_string())?;
-
     let id = Uuid::parse_str(&schedule.id).map_err(|e| e.to_string())?;
+    let schedule = ScheduleEntry::try_from(schedule)?;
 
-    if let Some(entry) = config.schedules.iter_mut().find(|s| s.id == id) {
-        entry.name = schedule.name;
-        entry.enabled = schedule.enabled;
-        entry.days = schedule.days;
-        entry.start_minutes = schedule.start_minutes;
-        entry.end_minutes = schedule.end_minutes;
-        entry.blocking_enabled = schedule.blocking_enabled;
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
 
+    if replace_schedule(&mut config.schedules, id, schedule) {
         manager.save(&config).map_err(|e| e.to_string())?;
         Ok(true)
     } else {
@@ -91,6 +121,17 @@ pub async fn update_schedule(schedule: ScheduleInfo) -> Result<bool, String> {
     }
 }
 
+/// Replace the schedule with the given id wholesale, keeping its id
+fn replace_schedule(schedules: &mut [ScheduleEntry], id: Uuid, schedule: ScheduleEntry) -> bool {
+    match schedules.iter_mut().find(|s| s.id == id) {
+        Some(entry) => {
+            *entry = ScheduleEntry { id, ..schedule };
+            true
+        }
+        None => false,
+    }
+}
+
 /// Delete a schedule
 #[tauri::command]
 pub async fn delete_schedule(id: String) -> Result<bool, String> {
@@ -110,23 +151,61 @@ pub async fn delete_schedule(id: String) -> Result<bool, String> {
     }
 }
 
-/// Add a preset schedule template
+/// Add a preset schedule template, returning the schedules created. Presets
+/// already present as enabled schedules of the same name are skipped.
 #[tauri::command]
-pub async fn add_preset_schedule(preset: String) -> Result<ScheduleInfo, String> {
-    let entry = match preset.as_str() {
-        "school" => scheduler::create_school_hours_schedule(),
-        "bedtime" => scheduler::create_bedtime_schedule(),
-        "weekend" => scheduler::create_weekend_gaming_schedule(),
-        _ => return Err("Unknown preset".to_string()),
-    };
+pub async fn add_preset_schedule(preset: String) -> Result<Vec<ScheduleInfo>, String> {
+    let presets = scheduler::preset_schedules(&preset).ok_or("Unknown preset")?;
 
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
     let mut config = manager.load().map_err(|e| e.to_string())?;
 
-    config.schedules.push(entry.clone());
+    let added = scheduler::add_presets(&mut config.schedules, presets);
+    if !added.is_empty() {
+        manager.save(&config).map_err(|e| e.to_string())?;
+    }
+
+    Ok(added.into_iter().map(ScheduleInfo::from).collect())
+}
+
+/// Get the holiday and sick-day overrides, in date order
+#[tauri::command]
+pub async fn list_date_exceptions() -> Result<Vec<DateException>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(config.date_exceptions)
+}
+
+/// Block or allow a whole day regardless of the schedules, replacing any
+/// exception already set for that date. Past exceptions are dropped.
+#[tauri::command]
+pub async fn add_date_exception(date: NaiveDate, mode: ExceptionMode) -> Result<Vec<DateException>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let today = scheduler::household_date(config.household_timezone.as_deref(), scheduler::trusted_now());
+    scheduler::set_exception(&mut config.date_exceptions, DateException { date, mode }, today);
     manager.save(&config).map_err(|e| e.to_string())?;
 
-    Ok(ScheduleInfo::from(entry))
+    Ok(config.date_exceptions)
+}
+
+/// Remove the exception for a date
+#[tauri::command]
+pub async fn remove_date_exception(date: NaiveDate) -> Result<bool, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = manager.load().map_err(|e| e.to_string())?;
+
+    let original_len = config.date_exceptions.len();
+    config.date_exceptions.retain(|e| e.date != date);
+
+    if config.date_exceptions.len() != original_len {
+        manager.save(&config).map_err(|e| e.to_string())?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
 }
 
 /// Check if blocking should be active now
@@ -135,5 +214,111 @@ pub async fn should_block_now() -> Result<bool, String> {
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
     let config = manager.load().map_err(|e| e.to_string())?;
 
-    Ok(scheduler::should_block_now(&config.schedules))
+    Ok(scheduler::should_block_with_budget(&config))
+}
+
+/// Minutes of the daily time budget left today, or `None` without a budget
+#[tauri::command]
+pub async fn get_remaining_budget() -> Result<Option<u16>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(scheduler::get_remaining_budget(&config))
+}
+
+/// Whether blocking is on right now, and which schedule, budget or override decided it
+#[tauri::command]
+pub async fn why_blocking() -> Result<scheduler::BlockDecision, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(scheduler::explain_now(&config))
+}
+
+/// Check a proposed schedule set for problems without saving it
+#[tauri::command]
+pub async fn validate_schedules(schedules: Vec<ScheduleInfo>) -> Vec<ScheduleWarning> {
+    let original_ids: Vec<String> = schedules.iter().map(|s| s.id.clone()).collect();
+    let entries: Vec<ScheduleEntry> = schedules.into_iter().map(ScheduleInfo::into_entry_unchecked).collect();
+
+    // Report warnings against the IDs the caller sent, even unsaved or unparsable ones
+    let mut warnings = scheduler::validate_schedules(&entries);
+    for warning in &mut warnings {
+        for id in &mut warning.schedule_ids {
+            if let Some(index) = entries.iter().position(|e| e.id.to_string() == *id) {
+                *id = original_ids[index].clone();
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(days: Vec<u8>, start_minutes: u16, end_minutes: u16) -> ScheduleInfo {
+        ScheduleInfo {
+            id: String::new(),
+            name: "Homework".to_string(),
+            enabled: true,
+            days,
+            start_minutes,
+            end_minutes,
+            blocking_enabled: true,
+            warn_minutes: None,
+            warn_message: None,
+            timezone: None,
+            priority: 0,
+            cron: None,
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_minutes_rejected() {
+        let err = ScheduleEntry::try_from(info(vec![1], 5000, 18 * 60)).unwrap_err();
+        assert!(err.contains("start time 5000"), "{}", err);
+        assert!(ScheduleEntry::try_from(info(vec![1], 16 * 60, 1440)).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range_days_rejected_and_duplicates_dropped() {
+        let err = ScheduleEntry::try_from(info(vec![1, 7], 16 * 60, 18 * 60)).unwrap_err();
+        assert!(err.contains("day 7"), "{}", err);
+
+        let entry = ScheduleEntry::try_from(info(vec![3, 1, 3, 1], 16 * 60, 18 * 60)).unwrap();
+        assert_eq!(entry.days, vec![1, 3]);
+
+        let unnamed = ScheduleInfo {
+            name: " ".to_string(),
+            ..info(vec![1], 16 * 60, 18 * 60)
+        };
+        assert!(ScheduleEntry::try_from(unnamed).is_err());
+    }
+
+    #[test]
+    fn test_update_keeps_warning_fields() {
+        let original = ScheduleEntry::try_from(info(vec![1], 16 * 60, 18 * 60)).unwrap();
+        let id = original.id;
+        let mut schedules = vec![original];
+
+        let updated = ScheduleEntry::try_from(ScheduleInfo {
+            id: id.to_string(),
+            warn_minutes: Some(10),
+            warn_message: Some("Save your game".to_string()),
+            ..info(vec![1, 2], 17 * 60, 19 * 60)
+        })
+        .unwrap();
+        assert!(replace_schedule(&mut schedules, id, updated));
+
+        let read_back = ScheduleInfo::from(schedules[0].clone());
+        assert_eq!(read_back.id, id.to_string());
+        assert_eq!(read_back.days, vec![1, 2]);
+        assert_eq!(read_back.warn_minutes, Some(10));
+        assert_eq!(read_back.warn_message.as_deref(), Some("Save your game"));
+
+        let other = ScheduleEntry::try_from(info(vec![1], 16 * 60, 18 * 60)).unwrap();
+        assert!(!replace_schedule(&mut schedules, Uuid::new_v4(), other));
+    }
 }
@@ -0,0 +1,60 @@
+//! Configuration review Tauri commands.
+
+use crate::commands::auth::authorize;
+use crate::config::{self, AppConfig, ConfigDiff, ConfigManager};
+use crate::daemon::client;
+
+/// Show what would change if the proposed config replaced the current one (nothing is saved)
+#[tauri::command]
+pub async fn diff_config(proposed: AppConfig) -> Result<ConfigDiff, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let current = manager.load().map_err(|e| e.to_string())?;
+
+    Ok(config::diff_config(&current, &proposed))
+}
+
+/// Export the settings as a bundle encrypted with `bundle_password`
+/// (requires a session token or the password)
+#[tauri::command]
+pub async fn export_config(
+    bundle_password: String,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<Vec<u8>, String> {
+    if !authorize(password, token.as_deref())? {
+        return Err("Invalid password".to_string());
+    }
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let current = manager.load().map_err(|e| e.to_string())?;
+
+    config::export_config(&current, &bundle_password).map_err(|e| e.to_string())
+}
+
+/// Replace the settings with those in an exported bundle
+/// (requires a session token or the password)
+#[tauri::command]
+pub async fn import_config(
+    bundle: Vec<u8>,
+    bundle_password: String,
+    password: Option<String>,
+    token: Option<String>,
+) -> Result<(), String> {
+    if !authorize(password, token.as_deref())? {
+        return Err("Invalid password".to_string());
+    }
+
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let current = manager.load().map_err(|e| e.to_string())?;
+    let imported = config::import_config(&bundle, &bundle_password, &current).map_err(|e| e.to_string())?;
+    manager.save(&imported).map_err(|e| e.to_string())?;
+
+    // Let a running daemon pick up the new lists without a restart
+    if client::is_daemon_running() {
+        if let Err(e) = client::reload_config() {
+            tracing::warn!("Daemon did not reload the imported config: {}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Portable settings bundles, for copying a known-good configuration to
+//! another machine. The bundle is encrypted and authenticated with a key
+//! derived from a password the parent picks rather than the machine id, so
+//! it opens anywhere with that password. What identifies this install (its
+//! id, the parent password hash, the master password's format and epoch, the
+//! parent account), running state like a temporary unlock or category
+//! allowance, and the daemon's event hooks, which run commands as root,
+//! stay behind.
+
+use crate::config::AppConfig;
+use crate::scheduler::{validate_schedules, ScheduleWarningKind};
+use crate::security::crypto::{self, CryptoError};
+use aes_gcm::aead::OsRng;
+use argon2::password_hash::rand_core::RngCore;
+use thiserror::Error;
+
+/// Marks a settings bundle, and its format version
+const BUNDLE_MAGIC: &[u8; 4] = b"PSB1";
+
+/// Random salt mixed into the bundle key
+const SALT_LEN: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("A bundle password is required")]
+    EmptyPassword,
+    #[error("Not a ParentShield settings bundle")]
+    NotABundle,
+    #[error("Wrong bundle password, or the bundle is damaged")]
+    WrongPassword,
+    #[error("Bundle was made by a newer version (config version {0})")]
+    UnsupportedVersion(u32),
+    #[error("Bundle has an invalid schedule: {0}")]
+    InvalidSchedule(String),
+    #[error("Bundle contents are invalid: {0}")]
+    InvalidContents(#[from] serde_json::Error),
+    #[error("Crypto error: {0}")]
+    Crypto(#[from] CryptoError),
+}
+
+/// Encrypt the portable part of `config` with `password`
+pub fn export_config(config: &AppConfig, password: &str) -> Result<Vec<u8>, BundleError> {
+    if password.is_empty() {
+        return Err(BundleError::EmptyPassword);
+    }
+
+    let portable = AppConfig {
+        password_hash: String::new(),
+        parent_uid: None,
+        temporary_unlock: None,
+        category_allowances: Default::default(),
+        hooks: Default::default(),
+        failed_attempts: Default::default(),
+        ..config.clone()
+    };
+    let json = serde_json::to_vec(&portable)?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = bundle_key(&salt, password);
+
+    let mut bundle = Vec::with_capacity(BUNDLE_MAGIC.len() + SALT_LEN + json.len() + 32);
+    bundle.extend_from_slice(BUNDLE_MAGIC);
+    bundle.extend_from_slice(&salt);
+    bundle.extend_from_slice(&crypto::encrypt(&json, &key)?);
+    Ok(bundle)
+}
+
+/// Open `bundle` with `password` and check it, returning `current` with the
+/// bundle's settings in place of its own. Nothing is saved.
+pub fn import_config(bundle: &[u8], password: &str, current: &AppConfig) -> Result<AppConfig, BundleError> {
+    let header = BUNDLE_MAGIC.len() + SALT_LEN;
+    if bundle.len() < header || !bundle.starts_with(BUNDLE_MAGIC) {
+        return Err(BundleError::NotABundle);
+    }
+
+    let salt = &bundle[BUNDLE_MAGIC.len()..header];
+    let key = bundle_key(salt, password);
+    let json = crypto::decrypt(&bundle[header..], &key).map_err(|_| BundleError::WrongPassword)?;
+    let imported: AppConfig = serde_json::from_slice(&json)?;
+
+    if imported.version > current.version {
+        return Err(BundleError::UnsupportedVersion(imported.version));
+    }
+    if let Some(warning) = validate_schedules(&imported.schedules)
+        .into_iter()
+        .find(|w| w.kind == ScheduleWarningKind::InvalidField)
+    {
+        return Err(BundleError::InvalidSchedule(warning.message));
+    }
+
+    Ok(AppConfig {
+        version: current.version,
+        installation_timestamp: current.installation_timestamp,
+        installation_id: current.installation_id.clone(),
+        password_hash: current.password_hash.clone(),
+        parent_uid: current.parent_uid,
+        temporary_unlock: current.temporary_unlock,
+        category_allowances: current.category_allowances.clone(),
+        hooks: current.hooks.clone(),
+        master_password_format: current.master_password_format,
+        master_epoch: current.master_epoch,
+        failed_attempts: current.failed_attempts.clone(),
+        ..imported
+    })
+}
+
+fn bundle_key(salt: &[u8], password: &str) -> zeroize::Zeroizing<[u8; 32]> {
+    let salt: String = salt.iter().map(|b| format!("{:02x}", b)).collect();
+    crypto::derive_key(&salt, password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScheduleEntry;
+
+    fn source_config() -> AppConfig {
+        let mut config = AppConfig {
+            password_hash: "source-hash".to_string(),
+            parent_uid: Some(1000),
+            game_blocking_enabled: true,
+            ..AppConfig::default()
+        };
+        config.blocked_domains.insert("games.example".to_string());
+        config.blocked_processes.insert("steam".to_string());
+        config.schedules.push(ScheduleEntry {
+            id: uuid::Uuid::new_v4(),
+            name: "School nights".to_string(),
+            enabled: true,
+            days: vec![0, 1, 2, 3, 4],
+            start_minutes: 20 * 60,
+            end_minutes: 22 * 60,
+            blocking_enabled: true,
+            warn_minutes: None,
+            warn_message: None,
+            timezone: None,
+            priority: 0,
+            cron: None,
+        });
+        config
+    }
+
+    #[test]
+    fn test_bundle_round_trip_keeps_local_identity() {
+        let source = source_config();
+        let bundle = export_config(&source, "copy-me").unwrap();
+
+        let target = AppConfig {
+            password_hash: "target-hash".to_string(),
+            parent_uid: Some(501),
+            ..AppConfig::default()
+        };
+        let imported = import_config(&bundle, "copy-me", &target).unwrap();
+
+        assert!(imported.game_blocking_enabled);
+        assert_eq!(imported.blocked_domains, source.blocked_domains);
+        assert_eq!(imported.blocked_processes, source.blocked_processes);
+        assert_eq!(imported.schedules, source.schedules);
+
+        assert_eq!(imported.password_hash, "target-hash");
+        assert_eq!(imported.parent_uid, Some(501));
+        assert_eq!(imported.installation_id, target.installation_id);
+    }
+
+    #[test]
+    fn test_bundle_leaves_hooks_and_allowances_behind() {
+        let mut source = source_config();
+        source.hooks.insert("block".to_string(), "/tmp/payload".to_string());
+        source
+            .category_allowances
+            .insert("games".to_string(), chrono::Utc::now() + chrono::Duration::days(365));
+        let bundle = export_config(&source, "copy-me").unwrap();
+
+        let mut target = AppConfig::default();
+        target.hooks.insert("block".to_string(), "/usr/local/bin/notify".to_string());
+        let imported = import_config(&bundle, "copy-me", &target).unwrap();
+
+        assert_eq!(imported.hooks, target.hooks);
+        assert!(imported.category_allowances.is_empty());
+    }
+
+    #[test]
+    fn test_bundle_rejects_wrong_password_and_tampering() {
+        let bundle = export_config(&source_config(), "copy-me").unwrap();
+        let target = AppConfig::default();
+
+        assert!(matches!(import_config(&bundle, "guess", &target), Err(BundleError::WrongPassword)));
+        assert!(matches!(import_config(b"PK\x03\x04", "copy-me", &target), Err(BundleError::NotABundle)));
+
+        let mut tampered = bundle.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(import_config(&tampered, "copy-me", &target), Err(BundleError::WrongPassword)));
+
+        assert!(matches!(export_config(&target, ""), Err(BundleError::EmptyPassword)));
+    }
+
+    #[test]
+    fn test_bundle_with_invalid_schedule_is_rejected() {
+        let mut source = source_config();
+        source.schedules[0].days = vec![9];
+        let bundle = export_config(&source, "copy-me").unwrap();
+
+        assert!(matches!(
+            import_config(&bundle, "copy-me", &AppConfig::default()),
+            Err(BundleError::InvalidSchedule(_))
+        ));
+    }
+}
@@ -0,0 +1,273 @@
+//! Read-only comparison of two configurations for change review.
+
+use super::{AppConfig, ScheduleEntry};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Entries added to and removed from a list
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ListDiff {
+    fn between(current: &HashSet<String>, proposed: &HashSet<String>) -> Self {
+        let mut added: Vec<String> = proposed.difference(current).cloned().collect();
+        let mut removed: Vec<String> = current.difference(proposed).cloned().collect();
+        added.sort();
+        removed.sort();
+        Self { added, removed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A schedule present in both configs with different settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleChange {
+    pub before: ScheduleEntry,
+    pub after: ScheduleEntry,
+}
+
+/// A scalar setting whose value differs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub name: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// Everything that would change if the proposed config replaced the current one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    pub blocked_processes: ListDiff,
+    pub blocked_domains: ListDiff,
+    pub allowed_processes: ListDiff,
+    pub allowed_domains: ListDiff,
+    pub schedules_added: Vec<ScheduleEntry>,
+    pub schedules_removed: Vec<ScheduleEntry>,
+    pub schedules_changed: Vec<ScheduleChange>,
+    pub settings: Vec<SettingChange>,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.blocked_processes.is_empty()
+            && self.blocked_domains.is_empty()
+            && self.allowed_processes.is_empty()
+            && self.allowed_domains.is_empty()
+            && self.schedules_added.is_empty()
+            && self.schedules_removed.is_empty()
+            && self.schedules_changed.is_empty()
+            && self.settings.is_empty()
+    }
+}
+
+/// Config keys with their own section of the diff
+const LISTED_KEYS: &[&str] = &[
+    "blocked_processes",
+    "blocked_domains",
+    "allowed_processes",
+    "allowed_domains",
+    "schedules",
+];
+
+/// Config keys never reported: secrets, what identifies this install, and
+/// state the app or daemon keeps for itself
+const HIDDEN_KEYS: &[&str] = &[
+    "version",
+    "installation_timestamp",
+    "installation_id",
+    "password_hash",
+    "parent_uid",
+    "master_epoch",
+    "failed_attempts",
+    "temporary_unlock",
+    "category_allowances",
+    "subscribed_domains",
+    "last_modified",
+];
+
+/// Compare two configs without saving anything. Every setting is compared,
+/// apart from the keys in `HIDDEN_KEYS`.
+pub fn diff_config(current: &AppConfig, proposed: &AppConfig) -> ConfigDiff {
+    let mut diff = ConfigDiff {
+        blocked_processes: ListDiff::between(&current.blocked_processes, &proposed.blocked_processes),
+        blocked_domains: ListDiff::between(&current.blocked_domains, &proposed.blocked_domains),
+        allowed_processes: ListDiff::between(&current.allowed_processes, &proposed.allowed_processes),
+        allowed_domains: ListDiff::between(&current.allowed_domains, &proposed.allowed_domains),
+        ..Default::default()
+    };
+
+    for after in &proposed.schedules {
+        match current.schedules.iter().find(|s| s.id == after.id) {
+            None => diff.schedules_added.push(after.clone()),
+            Some(before) if before != after => diff.schedules_changed.push(ScheduleChange {
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.schedules_removed = current
+        .schedules
+        .iter()
+        .filter(|s| !proposed.schedules.iter().any(|p| p.id == s.id))
+        .cloned()
+        .collect();
+
+    let (Value::Object(before), Value::Object(after)) = (to_value(current), to_value(proposed)) else {
+        return diff;
+    };
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        if LISTED_KEYS.contains(&name.as_str()) || HIDDEN_KEYS.contains(&name.as_str()) {
+            continue;
+        }
+        let from = before.get(name).cloned().unwrap_or(Value::Null);
+        let to = after.get(name).cloned().unwrap_or(Value::Null);
+        if !same_value(&from, &to) {
+            diff.settings.push(SettingChange {
+                name: name.clone(),
+                from,
+                to,
+            });
+        }
+    }
+
+    diff
+}
+
+fn to_value(config: &AppConfig) -> Value {
+    serde_json::to_value(config).unwrap_or(Value::Null)
+}
+
+/// Compare two setting values, ignoring the order of list entries since sets
+/// serialize in no particular order
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            let mut a: Vec<String> = a.iter().map(Value::to_string).collect();
+            let mut b: Vec<String> = b.iter().map(Value::to_string).collect();
+            a.sort();
+            b.sort();
+            a == b
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn schedule(name: &str) -> ScheduleEntry {
+        ScheduleEntry {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            enabled: true,
+            days: vec![1, 2, 3, 4, 5],
+            start_minutes: 8 * 60,
+            end_minutes: 15 * 60,
+            blocking_enabled: true,
+            warn_minutes: None,
+            warn_message: None,
+            timezone: None,
+            priority: 0,
+            cron: None,
+        }
+    }
+
+    #[test]
+    fn test_identical_configs_have_empty_diff() {
+        let config = AppConfig::default();
+        assert!(diff_config(&config, &config.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_domains_schedules_and_settings() {
+        let school = schedule("School");
+        let bedtime = schedule("Bedtime");
+
+        let current = AppConfig {
+            blocked_domains: ["roblox.com".to_string()].into(),
+            schedules: vec![school.clone(), bedtime.clone()],
+            ..Default::default()
+        };
+
+        let mut proposed = current.clone();
+        proposed.blocked_domains.insert("twitch.tv".to_string());
+        proposed.blocked_domains.insert("discord.com".to_string());
+        proposed.schedules.retain(|s| s.id != bedtime.id);
+        proposed.game_blocking_enabled = true;
+
+        let diff = diff_config(&current, &proposed);
+
+        assert_eq!(diff.blocked_domains.added, vec!["discord.com", "twitch.tv"]);
+        assert!(diff.blocked_domains.removed.is_empty());
+        assert!(diff.blocked_processes.is_empty());
+
+        assert_eq!(diff.schedules_removed.len(), 1);
+        assert_eq!(diff.schedules_removed[0].id, bedtime.id);
+        assert!(diff.schedules_added.is_empty());
+        assert!(diff.schedules_changed.is_empty());
+
+        assert_eq!(
+            diff.settings,
+            vec![SettingChange {
+                name: "game_blocking_enabled".to_string(),
+                from: Value::Bool(false),
+                to: Value::Bool(true),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_every_setting_compared_except_hidden_ones() {
+        let current = AppConfig::default();
+        let mut proposed = current.clone();
+        proposed.hooks.insert("block".to_string(), "/usr/local/bin/notify".to_string());
+        proposed.upstream_dns = Some("9.9.9.9".to_string());
+        proposed.password_hash = "changed".to_string();
+        proposed.failed_attempts.record_failure(1_700_000_000);
+        proposed.last_modified = chrono::Utc::now() + chrono::Duration::hours(1);
+
+        let names: Vec<String> = diff_config(&current, &proposed)
+            .settings
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["hooks", "upstream_dns"]);
+    }
+
+    #[test]
+    fn test_set_order_is_not_a_change() {
+        assert!(same_value(&serde_json::json!(["a", "b", "c"]), &serde_json::json!(["c", "a", "b"])));
+        assert!(!same_value(&serde_json::json!(["a", "b"]), &serde_json::json!(["a", "c"])));
+    }
+
+    #[test]
+    fn test_changed_schedule() {
+        let school = schedule("School");
+        let current = AppConfig {
+            schedules: vec![school.clone()],
+            ..Default::default()
+        };
+
+        let mut proposed = current.clone();
+        proposed.schedules[0].end_minutes = 16 * 60;
+
+        let diff = diff_config(&current, &proposed);
+        assert_eq!(diff.schedules_changed.len(), 1);
+        assert_eq!(diff.schedules_changed[0].before.end_minutes, 15 * 60);
+        assert_eq!(diff.schedules_changed[0].after.end_minutes, 16 * 60);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod bundle;
+pub mod diff;
 pub mod storage;
 
+pub use bundle::*;
+pub use diff::*;
 pub use storage::*;
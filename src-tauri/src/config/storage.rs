@@ -1,15 +1,27 @@
 //! Encrypted configuration storage system.
 //! Stores all settings in an encrypted file that can only be read on the same machine.
 
+use crate::blocking::blocklists::BlocklistGroup;
+use crate::blocking::network::doh_ips::default_doh_provider_ips;
+use crate::blocking::network::VpnProtocol;
+use crate::blocking::network::DnsAnomalyConfig;
+use crate::blocking::process::TerminationMode;
+use crate::blocking::subscriptions::BlocklistSubscription;
+use crate::daemon::kill_tracker::KillDebounceConfig;
+use crate::daemon::logs::LogSettings;
+use crate::scheduler::{DateException, TemporaryUnlock, TimeBudget};
+use crate::security::master_password::MasterPasswordFormat;
+use crate::security::ratelimit::AttemptTracker;
 use crate::security::{crypto, master_password};
 use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use thiserror::Error;
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
 /// Errors that can occur during configuration operations
 #[derive(Error, Debug)]
@@ -28,10 +40,18 @@ pub enum ConfigError {
     InvalidPassword,
     #[error("Machine ID not available")]
     NoMachineId,
+    #[error("Too many failed attempts, try again in {retry_after} seconds")]
+    TooManyAttempts { retry_after: u64 },
+    #[error(
+        "A master password needs {} to {} words, not {words}",
+        master_password::MIN_MASTER_WORDS,
+        master_password::MAX_MASTER_WORDS
+    )]
+    UnsupportedMasterFormat { words: u8 },
 }
 
 /// Schedule entry for time-based blocking
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScheduleEntry {
     pub id: Uuid,
     pub name: String,
@@ -44,6 +64,25 @@ pub struct ScheduleEntry {
     pub end_minutes: u16,
     /// Whether blocking is enabled during this time window
     pub blocking_enabled: bool,
+    /// Minutes before this schedule starts blocking to warn the child
+    #[serde(default)]
+    pub warn_minutes: Option<u32>,
+    /// Warning text (supports {schedule} and {minutes})
+    #[serde(default)]
+    pub warn_message: Option<String>,
+    /// Zone the window is read in (a UTC offset such as "+01:00" or an IANA
+    /// name such as "Europe/Berlin");
+    /// unset uses the household zone, then local time
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Decides between overlapping windows: the highest priority wins, and
+    /// on a tie blocking beats allowing
+    #[serde(default)]
+    pub priority: i32,
+    /// Cron expression for the active minutes (see `scheduler::cron`);
+    /// replaces `days`, `start_minutes` and `end_minutes` when set
+    #[serde(default)]
+    pub cron: Option<String>,
 }
 
 /// Main application configuration
@@ -57,6 +96,11 @@ pub struct AppConfig {
     pub installation_id: String,
     /// Hashed parent password
     pub password_hash: String,
+    /// Local account of the parent (Unix UID); the daemon only takes
+    /// privileged requests from it or root. Unset rejects every other peer
+    /// until the parent's UID is recorded.
+    #[serde(default)]
+    pub parent_uid: Option<u32>,
     /// Whether game blocking is enabled
     pub game_blocking_enabled: bool,
     /// Whether AI service blocking is enabled
@@ -70,14 +114,121 @@ pub struct AppConfig {
     pub blocked_processes: HashSet<String>,
     /// Custom blocked domains
     pub blocked_domains: HashSet<String>,
+    /// Where imported entries in `blocked_domains` came from (domain -> every
+    /// source listing it); hand-added domains have no entry
+    #[serde(default, deserialize_with = "deserialize_domain_sources")]
+    pub imported_domain_sources: HashMap<String, HashSet<String>>,
+    /// Blocklists the daemon keeps fetching from a URL
+    #[serde(default)]
+    pub blocklist_subscriptions: Vec<BlocklistSubscription>,
+    /// Union of the subscriptions' latest domains, kept apart from `blocked_domains`
+    #[serde(default)]
+    pub subscribed_domains: HashSet<String>,
+    /// Regex or `*.suffix` domain patterns, enforced by the DNS proxy only
+    /// (the hosts file can't express patterns)
+    #[serde(default)]
+    pub blocked_domain_patterns: Vec<String>,
+    /// DNS proxy burst detection for game CDN lookups (off by default)
+    #[serde(default)]
+    pub dns_anomaly: DnsAnomalyConfig,
+    /// Have the DNS proxy strip SVCB/HTTPS records advertising HTTP/3, DoH or
+    /// ECH, so browsers stay on connections the blocking can see (off by default)
+    #[serde(default)]
+    pub dns_prevent_doh: bool,
+    /// DNS-over-HTTPS provider addresses the firewall blocks; configs from
+    /// before this was configurable get the built-in list
+    #[serde(default = "default_doh_provider_ips")]
+    pub doh_provider_ips: Vec<String>,
+    /// Ports the firewall blocks on top of the common VPN ports
+    #[serde(default)]
+    pub extra_vpn_blocks: Vec<(u16, VpnProtocol)>,
+    /// Resolver the DNS proxy forwards to (`ip:port` or bare IP); None uses the built-in default
+    #[serde(default)]
+    pub upstream_dns: Option<String>,
+    /// Write `::1` entries to the hosts file alongside the `127.0.0.1` ones
+    #[serde(default = "default_true")]
+    pub hosts_ipv6_entries: bool,
+    /// Also block the `www.` form of each domain in the hosts file
+    #[serde(default = "default_true")]
+    pub hosts_www_variant: bool,
     /// Custom allowed processes (whitelist)
     pub allowed_processes: HashSet<String>,
     /// Custom allowed domains (whitelist)
     pub allowed_domains: HashSet<String>,
     /// Schedule entries
     pub schedules: Vec<ScheduleEntry>,
-    /// Whether to show notifications on block
+    /// Holidays and sick days that override the schedules for a whole day
+    #[serde(default)]
+    pub date_exceptions: Vec<DateException>,
+    /// Whether to show a desktop notification when a blocked app is closed
+    /// (rate-limited per app, see `blocking::notify::NOTIFY_COOLDOWN`)
+    #[serde(alias = "notify_on_block")]
     pub show_notifications: bool,
+    /// Message shown when a blocked app is closed (supports {process}, {schedule}, {until})
+    #[serde(default)]
+    pub block_message: Option<String>,
+    /// Categories lifted temporarily ("games", "ai"), mapped to when blocking resumes
+    #[serde(default)]
+    pub category_allowances: HashMap<String, DateTime<Utc>>,
+    /// Default blocklist groups switched off as a whole in the blocklist editor
+    #[serde(default)]
+    pub disabled_blocklist_groups: HashSet<BlocklistGroup>,
+    /// Blocking lifted entirely until this expires
+    #[serde(default)]
+    pub temporary_unlock: Option<TemporaryUnlock>,
+    /// Minutes after which firewall rules without a daemon heartbeat are removed
+    /// on startup if blocking is no longer configured (None disables this)
+    #[serde(default)]
+    pub firewall_rule_ttl_minutes: Option<u64>,
+    /// Milliseconds a blocked process gets to exit before it is force-killed
+    #[serde(default)]
+    pub kill_grace_ms: Option<u64>,
+    /// EXPERIMENTAL: also remove game port mappings from the router via UPnP
+    #[serde(default)]
+    pub experimental_upnp_blocking: bool,
+    /// Also disable incognito/private windows through browser policies
+    #[serde(default)]
+    pub block_private_browsing: bool,
+    /// Keep uninstall protection in place; the daemon restores flags and holds
+    /// that go missing until the parent turns it off
+    #[serde(default = "default_true")]
+    pub uninstall_protection: bool,
+    /// Commands the daemon runs on events (event type -> command line)
+    #[serde(default)]
+    pub hooks: HashMap<String, String>,
+    /// Size/age caps for the daemon's log files
+    #[serde(default)]
+    pub log_settings: LogSettings,
+    /// How blocked processes are terminated unless overridden per entry
+    #[serde(default)]
+    pub termination_mode: TerminationMode,
+    /// Per-blocklist-entry termination overrides (e.g. "force" for respawning anti-cheat)
+    #[serde(default)]
+    pub process_termination_modes: HashMap<String, TerminationMode>,
+    /// When a process killed over and over stops being logged and is escalated
+    #[serde(default)]
+    pub kill_debounce: KillDebounceConfig,
+    /// Minutes of games allowed per day; `None` leaves it to the schedules
+    #[serde(default)]
+    pub time_budget: Option<TimeBudget>,
+    /// Zone all schedules without their own are read in, so changing the
+    /// system timezone doesn't move them; unset uses local time
+    #[serde(default)]
+    pub household_timezone: Option<String>,
+    /// NTP server ("host" or "host:port") the daemon checks the system clock
+    /// against; schedules follow its time when the two disagree
+    #[serde(default)]
+    pub trusted_time_server: Option<String>,
+    /// Shape of the master recovery password; older installs have the original format
+    #[serde(default)]
+    pub master_password_format: MasterPasswordFormat,
+    /// Times the master recovery password has been rotated; only ever goes up
+    #[serde(default)]
+    pub master_epoch: u64,
+    /// Recent wrong passwords and any lockout; kept in the encrypted config
+    /// so deleting a file can't clear a lockout
+    #[serde(default)]
+    pub failed_attempts: AttemptTracker,
     /// Whether to start minimized to tray
     pub start_minimized: bool,
     /// Whether to start at system boot
@@ -88,6 +239,33 @@ pub struct AppConfig {
     pub last_modified: DateTime<Utc>,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+/// Read `imported_domain_sources`, including configs that kept a single
+/// source per domain
+fn deserialize_domain_sources<'de, D>(deserializer: D) -> Result<HashMap<String, HashSet<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sources {
+        One(String),
+        Many(HashSet<String>),
+    }
+
+    let sources = HashMap::<String, Sources>::deserialize(deserializer)?;
+    Ok(sources
+        .into_iter()
+        .map(|(domain, sources)| match sources {
+            Sources::One(source) => (domain, HashSet::from([source])),
+            Sources::Many(sources) => (domain, sources),
+        })
+        .collect())
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -95,6 +273,7 @@ impl Default for AppConfig {
             installation_timestamp: Utc::now().timestamp() as u64,
             installation_id: Uuid::new_v4().to_string(),
             password_hash: String::new(),
+            parent_uid: None,
             // Game blocking disabled by default - user must enable it
             game_blocking_enabled: false,
             ai_blocking_enabled: false,
@@ -102,10 +281,42 @@ impl Default for AppConfig {
             browser_blocking_enabled: false,
             blocked_processes: HashSet::new(),
             blocked_domains: HashSet::new(),
+            imported_domain_sources: HashMap::new(),
+            blocklist_subscriptions: Vec::new(),
+            subscribed_domains: HashSet::new(),
+            blocked_domain_patterns: Vec::new(),
+            dns_anomaly: DnsAnomalyConfig::default(),
+            dns_prevent_doh: false,
+            doh_provider_ips: default_doh_provider_ips(),
+            extra_vpn_blocks: Vec::new(),
+            upstream_dns: None,
+            hosts_ipv6_entries: true,
+            hosts_www_variant: true,
             allowed_processes: HashSet::new(),
             allowed_domains: HashSet::new(),
             schedules: Vec::new(),
+            date_exceptions: Vec::new(),
             show_notifications: true,
+            block_message: None,
+            category_allowances: HashMap::new(),
+            disabled_blocklist_groups: HashSet::new(),
+            temporary_unlock: None,
+            firewall_rule_ttl_minutes: None,
+            kill_grace_ms: None,
+            experimental_upnp_blocking: false,
+            block_private_browsing: false,
+            uninstall_protection: true,
+            hooks: HashMap::new(),
+            log_settings: LogSettings::default(),
+            termination_mode: TerminationMode::default(),
+            process_termination_modes: HashMap::new(),
+            kill_debounce: KillDebounceConfig::default(),
+            time_budget: None,
+            household_timezone: None,
+            trusted_time_server: None,
+            master_password_format: MasterPasswordFormat::default(),
+            master_epoch: 0,
+            failed_attempts: AttemptTracker::default(),
             start_minimized: false,
             start_at_boot: true,
             theme: "system".to_string(),
@@ -144,6 +355,11 @@ impl ConfigManager {
         })
     }
 
+    /// Path of the encrypted config; unlike `new`, works without a machine id
+    pub fn default_config_path() -> Result<PathBuf, ConfigError> {
+        Ok(Self::get_config_dir()?.join("config.enc"))
+    }
+
     /// Get the config directory - uses a fixed path that works for both user and root
     fn get_config_dir() -> Result<PathBuf, ConfigError> {
         // First try the standard user config directory
@@ -188,17 +404,34 @@ impl ConfigManager {
         crypto::derive_key(&self.machine_id, &self.secret)
     }
 
+    /// Record `uid` as the parent account if none is recorded yet, for
+    /// configs from before it was kept. Returns whether it was recorded.
+    pub fn record_parent_uid(&self, uid: u32) -> Result<bool, ConfigError> {
+        let mut config = self.load()?;
+        if config.parent_uid.is_some() {
+            return Ok(false);
+        }
+        config.parent_uid = Some(uid);
+        self.save(&config)?;
+        Ok(true)
+    }
+
     /// Check if configuration exists
     pub fn config_exists(&self) -> bool {
         self.config_path.exists()
     }
 
     /// Initialize a new configuration with a password
-    pub fn initialize(&self, password: &str) -> Result<AppConfig, ConfigError> {
+    pub fn initialize(&self, password: &Zeroizing<String>) -> Result<AppConfig, ConfigError> {
         let password_hash = crypto::hash_password(password)?;
 
         let mut config = AppConfig::default();
         config.password_hash = password_hash;
+        // Setup runs in the parent's session
+        #[cfg(unix)]
+        {
+            config.parent_uid = Some(unsafe { libc::getuid() });
+        }
 
         self.save(&config)?;
 
@@ -240,14 +473,58 @@ impl ConfigManager {
         Ok(())
     }
 
-    /// Verify the password against stored hash
-    pub fn verify_password(&self, password: &str) -> Result<bool, ConfigError> {
-        let config = self.load()?;
-        Ok(crypto::verify_password(password, &config.password_hash)?)
+    /// Verify the password against stored hash.
+    /// Refused without checking while locked out by too many failures.
+    pub fn verify_password(&self, password: &Zeroizing<String>) -> Result<bool, ConfigError> {
+        let mut config = self.load()?;
+        self.check_with_attempt_limit(&mut config, |config| {
+            let outcome = crypto::verify_password_outcome(password, &config.password_hash)?;
+
+            // Bring a hash from an older release up to the current parameters
+            if outcome.needs_rehash {
+                config.password_hash = crypto::hash_password(password)?;
+                self.save(config)?;
+            }
+
+            Ok(outcome.valid)
+        })
+    }
+
+    /// Run a password check under the shared failed-attempt limit, so the
+    /// parent and master passwords lock out together. The outcome is saved
+    /// with `config` before returning; if it can't be, the check fails.
+    fn check_with_attempt_limit(
+        &self,
+        config: &mut AppConfig,
+        check: impl FnOnce(&mut AppConfig) -> Result<bool, ConfigError>,
+    ) -> Result<bool, ConfigError> {
+        let now = Utc::now().timestamp().max(0) as u64;
+        if let Some(wait) = config.failed_attempts.retry_after(now) {
+            return Err(ConfigError::TooManyAttempts {
+                retry_after: wait.as_secs(),
+            });
+        }
+
+        let valid = check(config)?;
+
+        if !valid {
+            config.failed_attempts.record_failure(now);
+        } else if config.failed_attempts.is_clear() {
+            return Ok(true);
+        } else {
+            config.failed_attempts.record_success();
+        }
+        self.save(config)?;
+
+        Ok(valid)
     }
 
     /// Change the password
-    pub fn change_password(&self, old_password: &str, new_password: &str) -> Result<(), ConfigError> {
+    pub fn change_password(
+        &self,
+        old_password: &Zeroizing<String>,
+        new_password: &Zeroizing<String>,
+    ) -> Result<(), ConfigError> {
         if !self.verify_password(old_password)? {
             return Err(ConfigError::InvalidPassword);
         }
@@ -265,26 +542,59 @@ impl ConfigManager {
         Ok(master_password::generate_master_password(
             &self.machine_id,
             config.installation_timestamp,
+            config.master_epoch,
+            config.master_password_format,
         ))
     }
 
-    /// Verify master password and reset to new password
+    /// Replace the master recovery password with a new one, after checking the
+    /// parent password; the previous master password stops working
+    pub fn rotate_master_password(&self, current_parent_password: &Zeroizing<String>) -> Result<String, ConfigError> {
+        if !self.verify_password(current_parent_password)? {
+            return Err(ConfigError::InvalidPassword);
+        }
+
+        let mut config = self.load()?;
+        config.master_epoch = config.master_epoch.saturating_add(1);
+        self.save(&config)?;
+        self.get_master_password()
+    }
+
+    /// Switch the master recovery password to another format and return the new one;
+    /// the old master password stops working. Word counts the generator would
+    /// have to clamp are refused rather than stored.
+    pub fn set_master_password_format(&self, format: MasterPasswordFormat) -> Result<String, ConfigError> {
+        if !format.is_supported() {
+            return Err(ConfigError::UnsupportedMasterFormat { words: format.words });
+        }
+        let mut config = self.load()?;
+        config.master_password_format = format;
+        self.save(&config)?;
+        self.get_master_password()
+    }
+
+    /// Verify master password and reset to new password.
+    /// Shares the parent password's lockout, so it can't be guessed either.
     pub fn reset_with_master_password(
         &self,
         master: &str,
-        new_password: &str,
+        new_password: &Zeroizing<String>,
     ) -> Result<(), ConfigError> {
-        let config = self.load()?;
+        let mut config = self.load()?;
 
-        if !master_password::verify_master_password(
-            master,
-            &self.machine_id,
-            config.installation_timestamp,
-        ) {
+        let valid = self.check_with_attempt_limit(&mut config, |config| {
+            Ok(master_password::verify_master_password(
+                master,
+                &self.machine_id,
+                config.installation_timestamp,
+                config.master_epoch,
+                config.master_password_format,
+            ))
+        })?;
+        if !valid {
             return Err(ConfigError::InvalidPassword);
         }
 
-        let mut config = config;
         config.password_hash = crypto::hash_password(new_password)?;
         self.save(&config)?;
 
@@ -295,6 +605,16 @@ impl ConfigManager {
     pub fn config_path(&self) -> &PathBuf {
         &self.config_path
     }
+
+    /// Manager for a config in `dir` with a fixed key, for tests elsewhere in the crate
+    #[cfg(test)]
+    pub(crate) fn in_dir(dir: &std::path::Path) -> Self {
+        Self {
+            config_path: dir.join("config.enc"),
+            machine_id: "test-machine-id".to_string(),
+            secret: "test-secret".to_string(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -302,16 +622,13 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    fn secret(password: &str) -> Zeroizing<String> {
+        Zeroizing::new(password.to_string())
+    }
+
     fn create_test_manager() -> (ConfigManager, tempfile::TempDir) {
         let temp_dir = tempdir().unwrap();
-        let config_path = temp_dir.path().join("config.enc");
-
-        let manager = ConfigManager {
-            config_path,
-            machine_id: "test-machine-id".to_string(),
-            secret: "test-secret".to_string(),
-        };
-
+        let manager = ConfigManager::in_dir(temp_dir.path());
         (manager, temp_dir)
     }
 
@@ -319,7 +636,7 @@ mod tests {
     fn test_initialize_and_load() {
         let (manager, _temp) = create_test_manager();
 
-        let config = manager.initialize("test_password").unwrap();
+        let config = manager.initialize(&secret("test_password")).unwrap();
         // Game blocking is disabled by default
         assert!(!config.game_blocking_enabled);
         assert!(!config.password_hash.is_empty());
@@ -328,24 +645,178 @@ mod tests {
         assert_eq!(loaded.installation_id, config.installation_id);
     }
 
+    #[test]
+    fn test_older_config_gets_default_doh_ips() {
+        let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("doh_provider_ips");
+
+        let config: AppConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.doh_provider_ips, default_doh_provider_ips());
+    }
+
+    #[test]
+    fn test_older_config_keeps_all_hosts_variants() {
+        let mut value = serde_json::to_value(AppConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("hosts_ipv6_entries");
+        value.as_object_mut().unwrap().remove("hosts_www_variant");
+
+        let config: AppConfig = serde_json::from_value(value).unwrap();
+        assert!(config.hosts_ipv6_entries);
+        assert!(config.hosts_www_variant);
+    }
+
     #[test]
     fn test_password_verification() {
         let (manager, _temp) = create_test_manager();
 
-        manager.initialize("correct_password").unwrap();
+        manager.initialize(&secret("correct_password")).unwrap();
+
+        assert!(manager.verify_password(&secret("correct_password")).unwrap());
+        assert!(!manager.verify_password(&secret("wrong_password")).unwrap());
+    }
+
+    #[test]
+    fn test_weak_hash_is_upgraded_on_verify() {
+        let (manager, _temp) = create_test_manager();
+        let mut config = manager.initialize(&secret("correct_password")).unwrap();
 
-        assert!(manager.verify_password("correct_password").unwrap());
-        assert!(!manager.verify_password("wrong_password").unwrap());
+        let weak_params = argon2::Params::new(8 * 1024, 1, 1, Some(32)).unwrap();
+        config.password_hash = crypto::hash_password_with(&secret("correct_password"), weak_params).unwrap();
+        manager.save(&config).unwrap();
+
+        assert!(manager.verify_password(&secret("correct_password")).unwrap());
+
+        let upgraded = manager.load().unwrap().password_hash;
+        assert_ne!(upgraded, config.password_hash);
+        let outcome = crypto::verify_password_outcome(&secret("correct_password"), &upgraded).unwrap();
+        assert!(outcome.valid && !outcome.needs_rehash);
+    }
+
+    #[test]
+    fn test_repeated_wrong_passwords_lock_verification() {
+        let (manager, temp) = create_test_manager();
+        manager.initialize(&secret("correct_password")).unwrap();
+
+        for _ in 0..crate::security::ratelimit::MAX_FAILURES {
+            assert!(!manager.verify_password(&secret("guess")).unwrap());
+        }
+
+        // Even the right password is refused while locked, and a fresh
+        // manager (as after a restart) sees the same lockout
+        let restarted = ConfigManager::in_dir(temp.path());
+        for manager in [&manager, &restarted] {
+            assert!(matches!(
+                manager.verify_password(&secret("correct_password")),
+                Err(ConfigError::TooManyAttempts { retry_after }) if retry_after > 0
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unreadable_config_refuses_verification() {
+        let (manager, temp) = create_test_manager();
+        manager.initialize(&secret("correct_password")).unwrap();
+
+        std::fs::write(temp.path().join("config.enc"), b"garbage").unwrap();
+        assert!(manager.verify_password(&secret("correct_password")).is_err());
+    }
+
+    #[test]
+    fn test_repeated_wrong_master_passwords_lock_reset() {
+        let (manager, _temp) = create_test_manager();
+        manager.initialize(&secret("parent_password")).unwrap();
+        let master = manager.get_master_password().unwrap();
+
+        for _ in 0..crate::security::ratelimit::MAX_FAILURES {
+            assert!(matches!(
+                manager.reset_with_master_password("ALPHA-ALPHA-0000-ALPHA", &secret("new_password")),
+                Err(ConfigError::InvalidPassword)
+            ));
+        }
+
+        // The right master password and the parent password are both refused now
+        assert!(matches!(
+            manager.reset_with_master_password(&master, &secret("new_password")),
+            Err(ConfigError::TooManyAttempts { .. })
+        ));
+        assert!(matches!(
+            manager.verify_password(&secret("parent_password")),
+            Err(ConfigError::TooManyAttempts { .. })
+        ));
+    }
+
+    #[test]
+    fn test_master_password_follows_stored_format() {
+        let (manager, _temp) = create_test_manager();
+        manager.initialize(&secret("old_password")).unwrap();
+        let original = manager.get_master_password().unwrap();
+
+        let format = MasterPasswordFormat {
+            words: 4,
+            include_number: false,
+        };
+        let master = manager.set_master_password_format(format).unwrap();
+        assert_eq!(master.split('-').count(), 4);
+        assert_eq!(manager.get_master_password().unwrap(), master);
+
+        assert!(matches!(
+            manager.reset_with_master_password(&original, &secret("new_password")),
+            Err(ConfigError::InvalidPassword)
+        ));
+        manager.reset_with_master_password(&master, &secret("new_password")).unwrap();
+        assert!(manager.verify_password(&secret("new_password")).unwrap());
+    }
+
+    #[test]
+    fn test_unsupported_master_format_is_refused() {
+        let (manager, _temp) = create_test_manager();
+        manager.initialize(&secret("parent_password")).unwrap();
+        let original = manager.get_master_password().unwrap();
+
+        let digits_only = MasterPasswordFormat {
+            words: 0,
+            include_number: true,
+        };
+        assert!(matches!(
+            manager.set_master_password_format(digits_only),
+            Err(ConfigError::UnsupportedMasterFormat { words: 0 })
+        ));
+        assert_eq!(manager.load().unwrap().master_password_format, MasterPasswordFormat::default());
+        assert_eq!(manager.get_master_password().unwrap(), original);
+    }
+
+    #[test]
+    fn test_rotation_invalidates_previous_master() {
+        let (manager, _temp) = create_test_manager();
+        manager.initialize(&secret("parent_password")).unwrap();
+        let original = manager.get_master_password().unwrap();
+
+        assert!(matches!(
+            manager.rotate_master_password(&secret("wrong_password")),
+            Err(ConfigError::InvalidPassword)
+        ));
+        assert_eq!(manager.get_master_password().unwrap(), original);
+
+        let rotated = manager.rotate_master_password(&secret("parent_password")).unwrap();
+        assert_ne!(rotated, original);
+        assert_eq!(manager.load().unwrap().master_epoch, 1);
+
+        assert!(matches!(
+            manager.reset_with_master_password(&original, &secret("new_password")),
+            Err(ConfigError::InvalidPassword)
+        ));
+        manager.reset_with_master_password(&rotated, &secret("new_password")).unwrap();
+        assert!(manager.verify_password(&secret("new_password")).unwrap());
     }
 
     #[test]
     fn test_change_password() {
         let (manager, _temp) = create_test_manager();
 
-        manager.initialize("old_password").unwrap();
-        manager.change_password("old_password", "new_password").unwrap();
+        manager.initialize(&secret("old_password")).unwrap();
+        manager.change_password(&secret("old_password"), &secret("new_password")).unwrap();
 
-        assert!(!manager.verify_password("old_password").unwrap());
-        assert!(manager.verify_password("new_password").unwrap());
+        assert!(!manager.verify_password(&secret("old_password")).unwrap());
+        assert!(manager.verify_password(&secret("new_password")).unwrap());
     }
 }
@@ -1,134 +1,144 @@
 //! Client for communicating with the ParentShield daemon from the GUI.
 
-use crate::daemon::ipc::{read_message, write_message, DaemonRequest, DaemonResponse};
+use crate::blocking::network::{BlockedQuery, DnsMetrics};
+use crate::blocking::{ApplyReport, UserDohStatus};
+use crate::daemon::ipc::{
+    read_message, write_message, BlockedProcessInfo, BlockingPreview, DaemonRequest, DaemonResponse,
+};
+use crate::daemon::events::DaemonEvent;
+use crate::daemon::transport;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::io::{BufReader, BufWriter};
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
 
 #[cfg(unix)]
 use crate::daemon::ipc::SOCKET_PATH;
 #[cfg(unix)]
 use std::os::unix::net::UnixStream;
 #[cfg(unix)]
-use std::time::Duration;
+use std::path::Path;
 
 #[cfg(windows)]
 use crate::daemon::ipc::PIPE_NAME;
 #[cfg(windows)]
 use std::fs::OpenOptions;
 
-/// Check if the daemon is running by attempting to connect
+/// Backoff delays (ms) between connection attempts while the daemon socket
+/// hasn't been created yet, e.g. when the GUI starts before the daemon on boot
 #[cfg(unix)]
-pub fn is_daemon_running() -> bool {
-    match UnixStream::connect(SOCKET_PATH) {
-        Ok(stream) => {
-            // Try to ping
-            if let Ok(response) = send_request_internal_unix(stream, DaemonRequest::Ping) {
-                matches!(response, DaemonResponse::Pong)
-            } else {
-                false
-            }
-        }
-        Err(_) => false,
-    }
-}
+const SOCKET_RETRY_DELAYS_MS: &[u64] = &[50, 100, 200, 400];
 
+/// Backoff delays (ms) while every pipe instance is busy with another client
 #[cfg(windows)]
+const PIPE_BUSY_RETRY_DELAYS_MS: &[u64] = &[50, 100, 200];
+
+/// IPC token from the last successful [`authenticate`], attached to privileged requests
+static IPC_TOKEN: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Check if the daemon is running by pinging it
 pub fn is_daemon_running() -> bool {
-    match OpenOptions::new().read(true).write(true).open(PIPE_NAME) {
-        Ok(pipe) => {
-            if let Ok(response) = send_request_internal_windows(pipe, DaemonRequest::Ping) {
-                matches!(response, DaemonResponse::Pong)
-            } else {
-                false
-            }
+    matches!(send_request(DaemonRequest::Ping), Ok(DaemonResponse::Pong))
+}
+
+/// Send a request to the daemon and get a response. Privileged requests
+/// carry the IPC token if the parent has signed in.
+pub fn send_request(request: DaemonRequest) -> Result<DaemonResponse, DaemonClientError> {
+    let token = IPC_TOKEN.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    let request = match token {
+        Some(token) if request.is_privileged() => DaemonRequest::Authorized {
+            token,
+            request: Box::new(request),
+        },
+        _ => request,
+    };
+    transport::round_trip(&daemon_transport(), &request)
+}
+
+/// Trade the parent password for an IPC token and keep it for privileged requests
+pub fn authenticate(password: &str) -> Result<(), DaemonClientError> {
+    match send_request(DaemonRequest::Authenticate {
+        password: password.to_string(),
+    })? {
+        DaemonResponse::Authenticated { token } => {
+            *IPC_TOKEN.lock().unwrap_or_else(PoisonError::into_inner) = Some(token);
+            Ok(())
         }
-        Err(_) => false,
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
     }
 }
 
-/// Send a request to the daemon and get a response
-#[cfg(unix)]
-pub fn send_request(request: DaemonRequest) -> Result<DaemonResponse, DaemonClientError> {
-    let stream = UnixStream::connect(SOCKET_PATH).map_err(|e| {
-        if e.kind() == std::io::ErrorKind::NotFound
-            || e.kind() == std::io::ErrorKind::ConnectionRefused
-        {
-            DaemonClientError::DaemonNotRunning
-        } else {
-            DaemonClientError::ConnectionFailed(e.to_string())
+/// Pass daemon events to `callback` as they happen, until it returns false
+/// or the daemon goes away. Blocks the calling thread.
+pub fn subscribe(mut callback: impl FnMut(DaemonEvent) -> bool) -> Result<(), DaemonClientError> {
+    let mut result = Ok(());
+    transport::stream(&daemon_transport(), &DaemonRequest::Subscribe, |response| match response {
+        DaemonResponse::Ok => true,
+        DaemonResponse::Event { event } => callback(event),
+        DaemonResponse::Error { message } => {
+            result = Err(DaemonClientError::DaemonError(message));
+            false
+        }
+        _ => {
+            result = Err(DaemonClientError::UnexpectedResponse);
+            false
         }
     })?;
-
-    send_request_internal_unix(stream, request)
+    result
 }
 
-#[cfg(windows)]
-pub fn send_request(request: DaemonRequest) -> Result<DaemonResponse, DaemonClientError> {
-    let pipe = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(PIPE_NAME)
-        .map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                DaemonClientError::DaemonNotRunning
-            } else {
-                DaemonClientError::ConnectionFailed(e.to_string())
-            }
-        })?;
-
-    send_request_internal_windows(pipe, request)
+/// Drop the IPC token, e.g. when the parent signs out
+pub fn forget_token() {
+    *IPC_TOKEN.lock().unwrap_or_else(PoisonError::into_inner) = None;
 }
 
+/// The daemon's socket
 #[cfg(unix)]
-fn send_request_internal_unix(
-    stream: UnixStream,
-    request: DaemonRequest,
-) -> Result<DaemonResponse, DaemonClientError> {
-    stream
-        .set_read_timeout(Some(Duration::from_secs(10)))
-        .ok();
-    stream
-        .set_write_timeout(Some(Duration::from_secs(5)))
-        .ok();
-
-    let mut reader = BufReader::new(stream.try_clone().map_err(|e| {
-        DaemonClientError::ConnectionFailed(e.to_string())
-    })?);
-    let mut writer = BufWriter::new(stream);
-
-    // Send request
-    write_message(&mut writer, &request).map_err(|e| {
-        DaemonClientError::SendFailed(e.to_string())
-    })?;
-
-    // Read response
-    let response: DaemonResponse = read_message(&mut reader).map_err(|e| {
-        DaemonClientError::ReceiveFailed(e.to_string())
-    })?;
-
-    Ok(response)
+fn daemon_transport() -> transport::UnixSocket {
+    transport::UnixSocket {
+        path: SOCKET_PATH.into(),
+        retry_delays: socket_retry_delays(),
+    }
 }
 
+/// The daemon's pipe
 #[cfg(windows)]
-fn send_request_internal_windows(
-    pipe: std::fs::File,
-    request: DaemonRequest,
-) -> Result<DaemonResponse, DaemonClientError> {
-    let mut reader = BufReader::new(pipe.try_clone().map_err(|e| {
-        DaemonClientError::ConnectionFailed(e.to_string())
-    })?);
-    let mut writer = BufWriter::new(pipe);
-
-    // Send request
-    write_message(&mut writer, &request).map_err(|e| {
-        DaemonClientError::SendFailed(e.to_string())
-    })?;
+fn daemon_transport() -> transport::NamedPipe {
+    transport::NamedPipe {
+        name: PIPE_NAME.to_string(),
+        retry_delays: PIPE_BUSY_RETRY_DELAYS_MS,
+    }
+}
 
-    // Read response
-    let response: DaemonResponse = read_message(&mut reader).map_err(|e| {
-        DaemonClientError::ReceiveFailed(e.to_string())
-    })?;
+/// Only wait for a missing socket if the service is installed and expected to appear
+#[cfg(unix)]
+fn socket_retry_delays() -> &'static [u64] {
+    if crate::daemon::service::get_service_manager().is_installed() {
+        SOCKET_RETRY_DELAYS_MS
+    } else {
+        &[]
+    }
+}
 
-    Ok(response)
+/// Have the daemon re-read the config file and apply it
+pub fn reload_config() -> Result<(), DaemonClientError> {
+    match send_request(DaemonRequest::ReloadConfig)? {
+        DaemonResponse::Ok => Ok(()),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// Ask the daemon to re-fetch blocklist subscriptions now
+pub fn refresh_subscriptions_now() -> Result<(), DaemonClientError> {
+    match send_request(DaemonRequest::RefreshSubscriptions)? {
+        DaemonResponse::Ok => Ok(()),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
 }
 
 /// Get daemon status
@@ -144,6 +154,8 @@ pub fn get_status() -> Result<DaemonStatus, DaemonClientError> {
             firewall_active,
             blocked_count,
             uptime_secs,
+            last_check_unix,
+            check_interval_secs,
         } => Ok(DaemonStatus {
             running,
             blocking_active,
@@ -154,6 +166,8 @@ pub fn get_status() -> Result<DaemonStatus, DaemonClientError> {
             firewall_active,
             blocked_count,
             uptime_secs,
+            last_check_unix,
+            check_interval_secs,
         }),
         DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
         _ => Err(DaemonClientError::UnexpectedResponse),
@@ -179,19 +193,28 @@ pub fn update_config(
     }
 }
 
-/// Run blocking check via daemon
-pub fn run_blocking_check() -> Result<Vec<crate::daemon::ipc::BlockedProcessInfo>, DaemonClientError> {
+/// Run blocking check via daemon; also returns today's kills per process name
+pub fn run_blocking_check() -> Result<(Vec<BlockedProcessInfo>, HashMap<String, u32>), DaemonClientError> {
     match send_request(DaemonRequest::RunBlockingCheck)? {
-        DaemonResponse::BlockedProcesses { processes } => Ok(processes),
+        DaemonResponse::BlockedProcesses { processes, kills_today } => Ok((processes, kills_today)),
         DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
         _ => Err(DaemonClientError::UnexpectedResponse),
     }
 }
 
 /// Apply blocking settings via daemon
-pub fn apply_blocking() -> Result<(), DaemonClientError> {
+pub fn apply_blocking() -> Result<ApplyReport, DaemonClientError> {
     match send_request(DaemonRequest::ApplyBlocking)? {
-        DaemonResponse::Ok => Ok(()),
+        DaemonResponse::Applied { report } => Ok(report),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// Ask the daemon what applying blocking would do, without doing it
+pub fn preview_blocking() -> Result<BlockingPreview, DaemonClientError> {
+    match send_request(DaemonRequest::PreviewBlocking)? {
+        DaemonResponse::BlockingPreview { preview } => Ok(preview),
         DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
         _ => Err(DaemonClientError::UnexpectedResponse),
     }
@@ -215,6 +238,72 @@ pub fn disable_firewall() -> Result<(), DaemonClientError> {
     }
 }
 
+/// Re-apply managed browser DoH policies via daemon
+pub fn apply_browser_policies() -> Result<(Vec<String>, Vec<UserDohStatus>), DaemonClientError> {
+    match send_request(DaemonRequest::ApplyBrowserPolicies)? {
+        DaemonResponse::BrowserPolicies { applied, users } => Ok((applied, users)),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// Put the daemon in maintenance mode; returns the seconds until it ends
+pub fn enter_maintenance(minutes: Option<u64>) -> Result<u64, DaemonClientError> {
+    match send_request(DaemonRequest::EnterMaintenance { minutes })? {
+        DaemonResponse::Maintenance { remaining_secs, .. } => Ok(remaining_secs),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// End maintenance mode early
+pub fn exit_maintenance() -> Result<(), DaemonClientError> {
+    match send_request(DaemonRequest::ExitMaintenance)? {
+        DaemonResponse::Maintenance { .. } => Ok(()),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// Delete the daemon's rotated logs; returns how many files were removed
+pub fn purge_logs() -> Result<usize, DaemonClientError> {
+    match send_request(DaemonRequest::PurgeLogs)? {
+        DaemonResponse::LogsPurged { removed } => Ok(removed),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// Counters from the daemon's DNS proxy
+pub fn get_dns_metrics() -> Result<DnsMetrics, DaemonClientError> {
+    match send_request(DaemonRequest::GetDnsMetrics)? {
+        DaemonResponse::DnsMetrics {
+            queries,
+            blocked,
+            timeouts,
+            parse_errors,
+            uptime_secs,
+        } => Ok(DnsMetrics {
+            queries,
+            blocked,
+            timeouts,
+            parse_errors,
+            uptime_secs,
+        }),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
+/// The last `limit` blocked DNS queries, oldest first
+pub fn get_recent_blocks(limit: usize) -> Result<Vec<BlockedQuery>, DaemonClientError> {
+    match send_request(DaemonRequest::GetRecentBlocks { limit })? {
+        DaemonResponse::RecentBlocks { entries } => Ok(entries),
+        DaemonResponse::Error { message } => Err(DaemonClientError::DaemonError(message)),
+        _ => Err(DaemonClientError::UnexpectedResponse),
+    }
+}
+
 /// Daemon status information
 #[derive(Debug, Clone)]
 pub struct DaemonStatus {
@@ -227,6 +316,240 @@ pub struct DaemonStatus {
     pub firewall_active: bool,
     pub blocked_count: u32,
     pub uptime_secs: u64,
+    pub last_check_unix: u64,
+    pub check_interval_secs: u32,
+}
+
+/// Missed check intervals after which the monitoring loop counts as stuck
+const STALE_CHECK_INTERVALS: u64 = 3;
+
+impl DaemonStatus {
+    /// Whether the monitoring loop has gone quiet for several intervals at
+    /// `now_unix`. Daemons that don't report a check interval never count.
+    pub fn check_is_stale(&self, now_unix: u64) -> bool {
+        let limit = self.check_interval_secs as u64 * STALE_CHECK_INTERVALS;
+        if limit == 0 {
+            return false;
+        }
+        if self.last_check_unix == 0 {
+            // No check yet: only suspicious once the daemon has been up a while
+            return self.uptime_secs > limit;
+        }
+        now_unix.saturating_sub(self.last_check_unix) > limit
+    }
+}
+
+/// How long a diagnostic ping waits before calling the daemon hung
+const DIAGNOSTIC_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Result of opening the daemon socket/pipe
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConnectOutcome {
+    Connected,
+    /// Socket file or runtime directory doesn't exist
+    SocketMissing,
+    /// Socket exists but this user may not open it
+    PermissionDenied,
+    /// Socket exists but no daemon is listening
+    NothingListening,
+    Failed { message: String },
+}
+
+/// Result of sending a ping over an open connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PingOutcome {
+    Pong,
+    /// Connected, but no answer in time: the daemon is likely hung
+    TimedOut,
+    UnexpectedResponse,
+    Failed { message: String },
+    /// No connection to ping over
+    NotAttempted,
+}
+
+/// Who is on the other end of the socket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PeerCheck {
+    /// Owned by root, as the real daemon is
+    Root,
+    /// Owned by an unprivileged user: something else holds the socket
+    Untrusted { uid: u32 },
+    Unavailable { message: String },
+    NotChecked,
+}
+
+/// Step-by-step view of why the daemon can or can't be reached
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonConnDiag {
+    pub socket_path: String,
+    pub socket_exists: bool,
+    pub connect: ConnectOutcome,
+    pub peer: PeerCheck,
+    pub ping: PingOutcome,
+    pub ping_latency_ms: Option<u64>,
+}
+
+impl DaemonConnDiag {
+    /// Connected to a trusted daemon that answered the ping
+    pub fn is_healthy(&self) -> bool {
+        self.connect == ConnectOutcome::Connected
+            && self.ping == PingOutcome::Pong
+            && !matches!(self.peer, PeerCheck::Untrusted { .. })
+    }
+}
+
+/// Map an error from opening the socket/pipe to a diagnostic outcome
+fn connect_outcome(e: &std::io::Error) -> ConnectOutcome {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => ConnectOutcome::SocketMissing,
+        std::io::ErrorKind::PermissionDenied => ConnectOutcome::PermissionDenied,
+        std::io::ErrorKind::ConnectionRefused => ConnectOutcome::NothingListening,
+        _ => ConnectOutcome::Failed {
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Map an error from the ping exchange to a diagnostic outcome
+fn ping_failure(e: &std::io::Error) -> PingOutcome {
+    match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => PingOutcome::TimedOut,
+        _ => PingOutcome::Failed {
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Send a ping and time the answer
+fn timed_ping(
+    reader: &mut impl std::io::Read,
+    writer: &mut impl std::io::Write,
+) -> (PingOutcome, Option<u64>) {
+    let started = Instant::now();
+    if let Err(e) = write_message(writer, &DaemonRequest::Ping).and_then(|_| writer.flush()) {
+        return (ping_failure(&e), None);
+    }
+
+    match read_message::<DaemonResponse>(reader) {
+        Ok(DaemonResponse::Pong) => (PingOutcome::Pong, Some(started.elapsed().as_millis() as u64)),
+        Ok(_) => (PingOutcome::UnexpectedResponse, None),
+        Err(e) => (ping_failure(&e), None),
+    }
+}
+
+/// Diagnose the connection to the daemon without retrying or starting anything
+#[cfg(unix)]
+pub fn diagnose_daemon_connection() -> DaemonConnDiag {
+    diagnose_socket(Path::new(SOCKET_PATH), DIAGNOSTIC_PING_TIMEOUT)
+}
+
+#[cfg(unix)]
+fn diagnose_socket(path: &Path, ping_timeout: std::time::Duration) -> DaemonConnDiag {
+    let mut diag = DaemonConnDiag {
+        socket_path: path.display().to_string(),
+        socket_exists: path.exists(),
+        connect: ConnectOutcome::Connected,
+        peer: PeerCheck::NotChecked,
+        ping: PingOutcome::NotAttempted,
+        ping_latency_ms: None,
+    };
+
+    let stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) => {
+            diag.connect = connect_outcome(&e);
+            return diag;
+        }
+    };
+
+    diag.peer = peer_check(&stream, path);
+
+    let _ = stream.set_read_timeout(Some(ping_timeout));
+    let _ = stream.set_write_timeout(Some(ping_timeout));
+    let Ok(read_half) = stream.try_clone() else {
+        diag.ping = PingOutcome::Failed {
+            message: "Could not clone socket".to_string(),
+        };
+        return diag;
+    };
+    let (ping, latency) = timed_ping(&mut BufReader::new(read_half), &mut BufWriter::new(stream));
+    diag.ping = ping;
+    diag.ping_latency_ms = latency;
+    diag
+}
+
+/// Check the uid of the process holding the socket
+#[cfg(target_os = "linux")]
+fn peer_check(stream: &UnixStream, _path: &Path) -> PeerCheck {
+    use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+
+    match getsockopt(stream, PeerCredentials) {
+        Ok(cred) => uid_check(cred.uid()),
+        Err(e) => PeerCheck::Unavailable {
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Without SO_PEERCRED, trust whoever owns the socket file
+#[cfg(all(unix, not(target_os = "linux")))]
+fn peer_check(_stream: &UnixStream, path: &Path) -> PeerCheck {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::fs::metadata(path) {
+        Ok(meta) => uid_check(meta.uid()),
+        Err(e) => PeerCheck::Unavailable {
+            message: e.to_string(),
+        },
+    }
+}
+
+#[cfg(unix)]
+fn uid_check(uid: u32) -> PeerCheck {
+    if uid == 0 {
+        PeerCheck::Root
+    } else {
+        PeerCheck::Untrusted { uid }
+    }
+}
+
+/// Diagnose the connection to the daemon without retrying or starting anything
+#[cfg(windows)]
+pub fn diagnose_daemon_connection() -> DaemonConnDiag {
+    let mut diag = DaemonConnDiag {
+        socket_path: PIPE_NAME.to_string(),
+        socket_exists: false,
+        connect: ConnectOutcome::Connected,
+        // Named pipes are created with the daemon's ACL; nothing to compare here
+        peer: PeerCheck::NotChecked,
+        ping: PingOutcome::NotAttempted,
+        ping_latency_ms: None,
+    };
+
+    let pipe = match OpenOptions::new().read(true).write(true).open(PIPE_NAME) {
+        Ok(pipe) => pipe,
+        Err(e) => {
+            diag.connect = connect_outcome(&e);
+            diag.socket_exists = diag.connect != ConnectOutcome::SocketMissing;
+            return diag;
+        }
+    };
+    diag.socket_exists = true;
+
+    let Ok(read_half) = pipe.try_clone() else {
+        diag.ping = PingOutcome::Failed {
+            message: "Could not clone pipe handle".to_string(),
+        };
+        return diag;
+    };
+    let (ping, latency) = timed_ping(&mut BufReader::new(read_half), &mut BufWriter::new(pipe));
+    diag.ping = ping;
+    diag.ping_latency_ms = latency;
+    diag
 }
 
 /// Client errors
@@ -234,6 +557,8 @@ pub struct DaemonStatus {
 pub enum DaemonClientError {
     #[error("Daemon is not running")]
     DaemonNotRunning,
+    #[error("Daemon socket is not ready yet")]
+    SocketNotReady,
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
     #[error("Failed to send request: {0}")]
@@ -245,3 +570,133 @@ pub enum DaemonClientError {
     #[error("Unexpected response from daemon")]
     UnexpectedResponse,
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_socket_is_not_ready() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("missing").join("parentshield.sock");
+
+        let socket = transport::UnixSocket { path, retry_delays: &[] };
+        let result = transport::Transport::connect(&socket);
+        assert!(matches!(result, Err(DaemonClientError::SocketNotReady)));
+    }
+
+    #[test]
+    fn test_retries_until_late_socket_binds() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("parentshield.sock");
+
+        let server_path = path.clone();
+        let server = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(120));
+            let listener = UnixListener::bind(&server_path).unwrap();
+            let (mut stream, _) = listener.accept().unwrap();
+            let _: DaemonRequest = read_message(&mut stream).unwrap();
+            write_message(&mut stream, &DaemonResponse::Pong).unwrap();
+        });
+
+        let socket = transport::UnixSocket {
+            path,
+            retry_delays: SOCKET_RETRY_DELAYS_MS,
+        };
+        let response = transport::round_trip(&socket, &DaemonRequest::Ping).unwrap();
+        assert!(matches!(response, DaemonResponse::Pong));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_stale_check_detection() {
+        let status = DaemonStatus {
+            running: true,
+            blocking_active: true,
+            game_blocking: true,
+            ai_blocking: false,
+            dns_blocking: false,
+            browser_blocking: false,
+            firewall_active: false,
+            blocked_count: 0,
+            uptime_secs: 600,
+            last_check_unix: 1_000,
+            check_interval_secs: 5,
+        };
+        assert!(!status.check_is_stale(1_010));
+        assert!(status.check_is_stale(1_016));
+
+        let starting = DaemonStatus {
+            last_check_unix: 0,
+            uptime_secs: 2,
+            ..status.clone()
+        };
+        assert!(!starting.check_is_stale(1_016));
+
+        let old_daemon = DaemonStatus {
+            check_interval_secs: 0,
+            ..status
+        };
+        assert!(!old_daemon.check_is_stale(99_999));
+    }
+
+    #[test]
+    fn test_connect_errors_map_to_diagnostics() {
+        use std::io::{Error, ErrorKind};
+
+        assert_eq!(connect_outcome(&Error::from(ErrorKind::NotFound)), ConnectOutcome::SocketMissing);
+        assert_eq!(
+            connect_outcome(&Error::from(ErrorKind::PermissionDenied)),
+            ConnectOutcome::PermissionDenied
+        );
+        assert_eq!(
+            connect_outcome(&Error::from(ErrorKind::ConnectionRefused)),
+            ConnectOutcome::NothingListening
+        );
+        assert!(matches!(
+            connect_outcome(&Error::from(ErrorKind::AddrNotAvailable)),
+            ConnectOutcome::Failed { .. }
+        ));
+
+        assert_eq!(ping_failure(&Error::from(ErrorKind::WouldBlock)), PingOutcome::TimedOut);
+        assert!(matches!(
+            ping_failure(&Error::from(ErrorKind::UnexpectedEof)),
+            PingOutcome::Failed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_diagnose_missing_socket() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("parentshield.sock");
+
+        let diag = diagnose_socket(&path, Duration::from_millis(100));
+        assert!(!diag.socket_exists);
+        assert_eq!(diag.connect, ConnectOutcome::SocketMissing);
+        assert_eq!(diag.ping, PingOutcome::NotAttempted);
+        assert!(!diag.is_healthy());
+    }
+
+    #[test]
+    fn test_diagnose_stale_socket_and_hung_daemon() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("parentshield.sock");
+
+        // Listener that accepts but never answers
+        let listener = UnixListener::bind(&path).unwrap();
+        let diag = diagnose_socket(&path, Duration::from_millis(100));
+        assert_eq!(diag.connect, ConnectOutcome::Connected);
+        assert_eq!(diag.ping, PingOutcome::TimedOut);
+        assert_eq!(diag.ping_latency_ms, None);
+
+        // Socket file left behind with nobody listening
+        drop(listener);
+        let diag = diagnose_socket(&path, Duration::from_millis(100));
+        assert!(diag.socket_exists);
+        assert_eq!(diag.connect, ConnectOutcome::NothingListening);
+    }
+}
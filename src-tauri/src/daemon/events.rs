@@ -0,0 +1,73 @@
+//! Live daemon events for GUI subscribers.
+//! A `Subscribe` connection stays open and receives every event published
+//! after it subscribed, so the GUI doesn't have to poll the status.
+
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, PoisonError};
+
+/// Something the GUI may want to show as it happens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    /// A blocked process was terminated
+    ProcessBlocked { pid: u32, name: String },
+    /// Firewall-level DoH blocking was turned on or off
+    FirewallToggled { active: bool },
+    /// Schedules started or stopped blocking
+    ScheduleChanged { blocking: bool },
+}
+
+/// Fans each published event out to every live subscriber
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<DaemonEvent>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> Receiver<DaemonEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap_or_else(PoisonError::into_inner).push(tx);
+        rx
+    }
+
+    /// Send `event` to all subscribers, dropping those that went away
+    pub fn publish(&self, event: DaemonEvent) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_reaches_every_subscriber() {
+        let bus = EventBus::new();
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+
+        let event = DaemonEvent::FirewallToggled { active: true };
+        bus.publish(event.clone());
+        assert_eq!(first.try_recv(), Ok(event.clone()));
+        assert_eq!(second.try_recv(), Ok(event));
+
+        // A subscriber that disconnected is forgotten on the next publish
+        drop(first);
+        bus.publish(DaemonEvent::ScheduleChanged { blocking: false });
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(second.try_recv(), Ok(DaemonEvent::ScheduleChanged { blocking: false }));
+    }
+}
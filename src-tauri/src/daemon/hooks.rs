@@ -0,0 +1,254 @@
+//! User-defined commands run when the daemon enforces something.
+//! The config `hooks` map goes from event type (`process_blocked`,
+//! `process_respawning`, `schedule_changed`, `tamper_detected`) to a command
+//! line. Hooks get the
+//! event as `PARENTSHIELD_*` environment variables and as JSON on stdin.
+//!
+//! Hooks are not run through a shell, start with an empty environment, run as
+//! `nobody` when the daemon is root, and are killed after HOOK_TIMEOUT.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Longest a hook may run before it is killed
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PATH given to hooks; nothing else is inherited from the daemon
+const HOOK_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Unprivileged account hooks run as when the daemon is root
+#[cfg(unix)]
+const HOOK_UID: u32 = 65534;
+
+/// Something the daemon did or noticed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    ProcessBlocked {
+        pid: u32,
+        name: String,
+        user: Option<String>,
+    },
+    /// A blocked process keeps restarting after being killed
+    ProcessRespawning {
+        name: String,
+        kills: u32,
+        window_secs: u64,
+    },
+    ScheduleChanged {
+        blocking: bool,
+    },
+    TamperDetected {
+        detail: String,
+    },
+}
+
+impl HookEvent {
+    /// Key in the `hooks` config map
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HookEvent::ProcessBlocked { .. } => "process_blocked",
+            HookEvent::ProcessRespawning { .. } => "process_respawning",
+            HookEvent::ScheduleChanged { .. } => "schedule_changed",
+            HookEvent::TamperDetected { .. } => "tamper_detected",
+        }
+    }
+}
+
+/// Everything needed to start a hook process
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookInvocation {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub stdin: String,
+}
+
+#[derive(Serialize)]
+struct HookPayload<'a> {
+    #[serde(flatten)]
+    event: &'a HookEvent,
+    timestamp: DateTime<Utc>,
+}
+
+/// Build the process invocation for `command` handling `event`
+pub fn build_invocation(command: &str, event: &HookEvent, now: DateTime<Utc>) -> Option<HookInvocation> {
+    let mut parts = command.split_whitespace().map(str::to_string);
+    let program = parts.next()?;
+    let args = parts.collect();
+
+    let mut env = vec![
+        ("PATH".to_string(), HOOK_PATH.to_string()),
+        ("PARENTSHIELD_EVENT".to_string(), event.kind().to_string()),
+        ("PARENTSHIELD_TIMESTAMP".to_string(), now.to_rfc3339()),
+    ];
+    match event {
+        HookEvent::ProcessBlocked { pid, name, user } => {
+            env.push(("PARENTSHIELD_PID".to_string(), pid.to_string()));
+            env.push(("PARENTSHIELD_PROCESS".to_string(), name.clone()));
+            if let Some(user) = user {
+                env.push(("PARENTSHIELD_USER".to_string(), user.clone()));
+            }
+        }
+        HookEvent::ProcessRespawning { name, kills, window_secs } => {
+            env.push(("PARENTSHIELD_PROCESS".to_string(), name.clone()));
+            env.push(("PARENTSHIELD_KILLS".to_string(), kills.to_string()));
+            env.push(("PARENTSHIELD_WINDOW_SECS".to_string(), window_secs.to_string()));
+        }
+        HookEvent::ScheduleChanged { blocking } => {
+            env.push(("PARENTSHIELD_BLOCKING".to_string(), blocking.to_string()));
+        }
+        HookEvent::TamperDetected { detail } => {
+            env.push(("PARENTSHIELD_DETAIL".to_string(), detail.clone()));
+        }
+    }
+
+    let stdin = serde_json::to_string(&HookPayload { event, timestamp: now }).ok()?;
+
+    Some(HookInvocation {
+        program,
+        args,
+        env,
+        stdin,
+    })
+}
+
+/// Run the hook configured for `event`, if any, on a background thread
+pub fn dispatch(hooks: &HashMap<String, String>, event: HookEvent) {
+    let Some(command) = hooks.get(event.kind()) else {
+        return;
+    };
+    let Some(invocation) = build_invocation(command, &event, Utc::now()) else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        if let Err(e) = run_invocation(&invocation, HOOK_TIMEOUT) {
+            tracing::warn!("Hook {} for {} failed: {}", invocation.program, event.kind(), e);
+        }
+    });
+}
+
+/// Start the hook in its sandbox and wait up to `timeout` for it to finish
+fn run_invocation(invocation: &HookInvocation, timeout: Duration) -> std::io::Result<()> {
+    let mut command = Command::new(&invocation.program);
+    command
+        .args(&invocation.args)
+        .env_clear()
+        .envs(invocation.env.iter().map(|(k, v)| (k, v)))
+        .current_dir("/")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    #[cfg(unix)]
+    if unsafe { libc::geteuid() } == 0 {
+        use std::os::unix::process::CommandExt;
+        command.uid(HOOK_UID).gid(HOOK_UID);
+    }
+
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin closes the pipe early; that's fine
+        let _ = stdin.write_all(invocation.stdin.as_bytes());
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                tracing::debug!("Hook {} exited with {}", invocation.program, status);
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("killed after {}s", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn env_value<'a>(invocation: &'a HookInvocation, key: &str) -> Option<&'a str> {
+        invocation
+            .env
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_process_blocked_invocation() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 18, 30, 0).unwrap();
+        let event = HookEvent::ProcessBlocked {
+            pid: 4242,
+            name: "steam".to_string(),
+            user: Some("kid".to_string()),
+        };
+
+        let invocation = build_invocation("/opt/hooks/notify.sh --light on", &event, now).unwrap();
+        assert_eq!(invocation.program, "/opt/hooks/notify.sh");
+        assert_eq!(invocation.args, vec!["--light", "on"]);
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_EVENT"), Some("process_blocked"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_PID"), Some("4242"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_PROCESS"), Some("steam"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_USER"), Some("kid"));
+        assert_eq!(env_value(&invocation, "PATH"), Some(HOOK_PATH));
+
+        let payload: serde_json::Value = serde_json::from_str(&invocation.stdin).unwrap();
+        assert_eq!(payload["event"], "process_blocked");
+        assert_eq!(payload["pid"], 4242);
+        assert_eq!(payload["name"], "steam");
+        assert_eq!(payload["timestamp"], "2024-05-01T18:30:00Z");
+    }
+
+    #[test]
+    fn test_schedule_and_tamper_invocations() {
+        let now = Utc::now();
+
+        let invocation =
+            build_invocation("hook", &HookEvent::ScheduleChanged { blocking: true }, now).unwrap();
+        assert!(invocation.args.is_empty());
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_BLOCKING"), Some("true"));
+
+        let event = HookEvent::TamperDetected {
+            detail: "browser_policy".to_string(),
+        };
+        let invocation = build_invocation("hook", &event, now).unwrap();
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_EVENT"), Some("tamper_detected"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_DETAIL"), Some("browser_policy"));
+        assert!(invocation.stdin.contains("\"detail\":\"browser_policy\""));
+    }
+
+    #[test]
+    fn test_process_respawning_invocation() {
+        let event = HookEvent::ProcessRespawning {
+            name: "steam".to_string(),
+            kills: 6,
+            window_secs: 60,
+        };
+        let invocation = build_invocation("hook", &event, Utc::now()).unwrap();
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_EVENT"), Some("process_respawning"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_KILLS"), Some("6"));
+        assert_eq!(env_value(&invocation, "PARENTSHIELD_WINDOW_SECS"), Some("60"));
+    }
+
+    #[test]
+    fn test_empty_command_is_ignored() {
+        let event = HookEvent::ScheduleChanged { blocking: false };
+        assert!(build_invocation("   ", &event, Utc::now()).is_none());
+    }
+}
@@ -1,12 +1,37 @@
 //! IPC protocol for daemon-GUI communication.
 //! Uses Unix domain sockets on Linux/macOS and named pipes on Windows.
 
+use crate::blocking::network::BlockedQuery;
+use crate::blocking::{ApplyReport, UserDohStatus};
+use crate::daemon::events::DaemonEvent;
+use crate::security::session::constant_time_eq;
+use aes_gcm::aead::OsRng;
+use argon2::password_hash::rand_core::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Directory holding the daemon socket. systemd creates it through
+/// RuntimeDirectory, but the daemon recreates it when started any other way.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub const RUNTIME_DIR: &str = "/run/parentshield";
+
+/// macOS has no /run, and launchd doesn't create runtime directories
+#[cfg(target_os = "macos")]
+pub const RUNTIME_DIR: &str = "/var/run/parentshield";
 
 /// Socket path for Unix domain socket
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos")))]
 pub const SOCKET_PATH: &str = "/run/parentshield/parentshield.sock";
 
+#[cfg(target_os = "macos")]
+pub const SOCKET_PATH: &str = "/var/run/parentshield/parentshield.sock";
+
+/// Permissions for the runtime directory (matches RuntimeDirectoryMode)
+#[cfg(unix)]
+const RUNTIME_DIR_MODE: u32 = 0o755;
+
 /// Named pipe path for Windows
 #[cfg(windows)]
 pub const PIPE_NAME: &str = r"\\.\pipe\parentshield";
@@ -28,14 +53,75 @@ pub enum DaemonRequest {
     RunBlockingCheck,
     /// Apply current blocking settings (hosts file, firewall)
     ApplyBlocking,
+    /// Report what `ApplyBlocking` and a blocking check would do, without
+    /// touching the system
+    PreviewBlocking,
     /// Enable firewall-level DoH blocking
     EnableFirewall,
     /// Disable firewall-level DoH blocking
     DisableFirewall,
+    /// Re-apply managed browser policies that disable DoH
+    ApplyBrowserPolicies,
+    /// Re-read the config file and push it into the running DNS proxy and
+    /// the hosts file/firewall, keeping the socket and proxy up
+    ReloadConfig,
+    /// Re-fetch every blocklist subscription in the background and re-apply
+    /// blocking; the daemon answers `Ok` without waiting for the downloads
+    RefreshSubscriptions,
+    /// Pause self-heal and clear immutable flags so ParentShield can be updated.
+    /// Privileged; ends on its own after `minutes`.
+    EnterMaintenance {
+        minutes: Option<u64>,
+    },
+    /// End maintenance mode early and re-enable protection
+    ExitMaintenance,
+    /// Delete rotated logs and empty the active ones (privileged)
+    PurgeLogs,
+    /// Counters from the running DNS proxy
+    GetDnsMetrics,
+    /// The most recent blocked DNS queries, oldest first
+    GetRecentBlocks {
+        limit: usize,
+    },
     /// Graceful shutdown (only for development/testing)
     Shutdown,
     /// Ping to check if daemon is alive
     Ping,
+    /// Keep the connection open and stream `Event` responses until the
+    /// client disconnects; the daemon acknowledges with `Ok` first
+    Subscribe,
+    /// Exchange the parent password for an IPC token
+    Authenticate {
+        password: String,
+    },
+    /// A privileged request with the token from `Authenticate`
+    Authorized {
+        token: String,
+        request: Box<DaemonRequest>,
+    },
+}
+
+impl DaemonRequest {
+    /// Requests that can weaken protection; they need an IPC token and a
+    /// trusted peer
+    pub fn is_privileged(&self) -> bool {
+        matches!(
+            self,
+            Self::UpdateConfig { .. }
+                | Self::ApplyBlocking
+                | Self::DisableFirewall
+                | Self::ReloadConfig
+                | Self::RefreshSubscriptions
+                | Self::EnterMaintenance { .. }
+                | Self::PurgeLogs
+                | Self::Shutdown
+        )
+    }
+
+    /// Whether only the parent account (or root) may send this request
+    pub fn needs_trusted_peer(&self) -> bool {
+        self.is_privileged() || matches!(self, Self::Authenticate { .. } | Self::Authorized { .. })
+    }
 }
 
 /// Response messages from daemon to GUI
@@ -53,12 +139,56 @@ pub enum DaemonResponse {
         firewall_active: bool,
         blocked_count: u32,
         uptime_secs: u64,
+        /// When the monitoring loop last finished a blocking check (Unix
+        /// seconds, 0 before the first one)
+        #[serde(default)]
+        last_check_unix: u64,
+        /// How often the monitoring loop runs a check
+        #[serde(default)]
+        check_interval_secs: u32,
     },
     /// Operation succeeded
     Ok,
+    /// Blocking applied, with anything that kept it from being fully effective
+    Applied {
+        report: ApplyReport,
+    },
+    /// What applying blocking would change
+    BlockingPreview {
+        preview: BlockingPreview,
+    },
     /// Blocked processes list from blocking check
     BlockedProcesses {
         processes: Vec<BlockedProcessInfo>,
+        /// Kills per process name since midnight
+        #[serde(default)]
+        kills_today: HashMap<String, u32>,
+    },
+    /// Result of re-applying managed browser policies
+    BrowserPolicies {
+        applied: Vec<String>,
+        users: Vec<UserDohStatus>,
+    },
+    /// Maintenance mode state
+    Maintenance {
+        active: bool,
+        remaining_secs: u64,
+    },
+    /// Logs purged, with the number of rotated files removed
+    LogsPurged {
+        removed: usize,
+    },
+    /// DNS proxy counters, with the proxy's uptime for computing rates
+    DnsMetrics {
+        queries: u64,
+        blocked: u64,
+        timeouts: u64,
+        parse_errors: u64,
+        uptime_secs: u64,
+    },
+    /// Blocked DNS queries from the block log
+    RecentBlocks {
+        entries: Vec<BlockedQuery>,
     },
     /// Error occurred
     Error {
@@ -66,6 +196,15 @@ pub enum DaemonResponse {
     },
     /// Pong response to ping
     Pong,
+    /// Something happened, sent to subscribed connections
+    Event {
+        event: DaemonEvent,
+    },
+    /// Token for privileged requests, valid until the daemon restarts or
+    /// the password changes
+    Authenticated {
+        token: String,
+    },
 }
 
 /// Information about a blocked process
@@ -75,6 +214,133 @@ pub struct BlockedProcessInfo {
     pub name: String,
 }
 
+/// Actions a blocking check and apply would take under the current config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockingPreview {
+    /// Running processes that would be terminated
+    pub processes: Vec<BlockedProcessInfo>,
+    /// Domains that would be added to the hosts file
+    pub domains_added: Vec<String>,
+    /// Domains that would be taken out of the hosts file
+    pub domains_removed: Vec<String>,
+    /// Whether firewall rules would be in place
+    pub firewall: bool,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum IpcAuthError {
+    #[error("Sign in as the parent to change protection")]
+    NotAuthenticated,
+    #[error("Invalid or expired IPC token")]
+    InvalidToken,
+    #[error("Requests from user {0} are not accepted")]
+    PeerNotAllowed(u32),
+    #[error("Sign in with the parent password first so this account can be recognized")]
+    ParentNotRecorded,
+    #[error("Nested authorized requests are not accepted")]
+    Nested,
+}
+
+/// Issues and checks IPC tokens. A token is a hash of a secret drawn when
+/// the daemon starts and the stored parent password hash, so it stops
+/// working on restart and when the password changes.
+pub struct IpcAuth {
+    secret: [u8; 32],
+}
+
+impl IpcAuth {
+    pub fn new() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self { secret }
+    }
+
+    /// Token for the parent whose password hashes to `password_hash`
+    pub fn token_for(&self, password_hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret);
+        hasher.update(password_hash.as_bytes());
+        hasher.update(b"parentshield-ipc-token-v1");
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Whether `token` was issued for `password_hash` by this daemon
+    pub fn verify(&self, token: &str, password_hash: &str) -> bool {
+        !password_hash.is_empty() && constant_time_eq(self.token_for(password_hash).as_bytes(), token.as_bytes())
+    }
+
+    /// Let `request` through if it needs no authority, or unwrap it if its
+    /// token is valid and `peer_uid` may act for the parent. `peer_uid` is
+    /// `None` where the transport can't tell who connected (named pipes).
+    pub fn check(
+        &self,
+        request: DaemonRequest,
+        peer_uid: Option<u32>,
+        password_hash: &str,
+        parent_uid: Option<u32>,
+    ) -> Result<DaemonRequest, IpcAuthError> {
+        // Until the parent account is known only Authenticate gets through,
+        // so the parent signing in can record it
+        let enrolling = parent_uid.is_none() && matches!(request, DaemonRequest::Authenticate { .. });
+        if request.needs_trusted_peer() && !enrolling {
+            check_peer(peer_uid, parent_uid)?;
+        }
+
+        match request {
+            DaemonRequest::Authorized { token, request } => {
+                if !self.verify(&token, password_hash) {
+                    return Err(IpcAuthError::InvalidToken);
+                }
+                match *request {
+                    DaemonRequest::Authorized { .. } => Err(IpcAuthError::Nested),
+                    request => Ok(request),
+                }
+            }
+            request if request.is_privileged() => Err(IpcAuthError::NotAuthenticated),
+            request => Ok(request),
+        }
+    }
+}
+
+impl Default for IpcAuth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Root and the parent account are trusted. With no parent account recorded
+/// nobody else is, rather than everyone.
+fn check_peer(peer_uid: Option<u32>, parent_uid: Option<u32>) -> Result<(), IpcAuthError> {
+    match (peer_uid, parent_uid) {
+        (Some(0), _) | (None, _) => Ok(()),
+        (Some(uid), Some(parent)) if uid == parent => Ok(()),
+        (Some(uid), Some(_)) => Err(IpcAuthError::PeerNotAllowed(uid)),
+        (Some(_), None) => Err(IpcAuthError::ParentNotRecorded),
+    }
+}
+
+/// UID of the process on the other end of `stream`
+#[cfg(target_os = "linux")]
+pub fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+
+    getsockopt(stream, PeerCredentials)
+        .map(|cred| cred.uid())
+        .map_err(std::io::Error::from)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn peer_uid(stream: &std::os::unix::net::UnixStream) -> std::io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
 /// Read a message from a stream (length-prefixed JSON)
 pub fn read_message<T: for<'de> Deserialize<'de>>(
     reader: &mut impl std::io::Read,
@@ -122,11 +388,210 @@ pub fn write_message<T: Serialize>(
     Ok(())
 }
 
+/// Make sure the socket's directory exists, is a real directory, and is
+/// owned by root (when we are root) with RUNTIME_DIR_MODE
+#[cfg(unix)]
+pub fn ensure_runtime_dir(dir: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::symlink_metadata(dir) {
+        Ok(meta) if meta.is_dir() => {}
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a directory", dir.display()),
+            ));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::info!("Creating runtime directory {}", dir.display());
+            std::fs::create_dir_all(dir)?;
+        }
+        Err(e) => return Err(e),
+    }
+
+    if unsafe { libc::geteuid() } == 0 {
+        std::os::unix::fs::chown(dir, Some(0), Some(0))?;
+    }
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(RUNTIME_DIR_MODE))
+}
+
+/// Bind the daemon socket at `path`, creating its directory and replacing a
+/// stale socket file. The socket is left world-writable so the GUI can connect.
+#[cfg(unix)]
+pub fn bind_socket(path: &std::path::Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Some(dir) = path.parent() {
+        ensure_runtime_dir(dir)?;
+    }
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = std::os::unix::net::UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o666))?;
+    Ok(listener)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Cursor;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_creates_missing_runtime_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("run").join("parentshield");
+        let socket = dir.join("parentshield.sock");
+
+        let listener = bind_socket(&socket).unwrap();
+        assert!(socket.exists());
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, RUNTIME_DIR_MODE);
+
+        // A stale socket from a previous run is replaced
+        drop(listener);
+        let _listener = bind_socket(&socket).unwrap();
+        assert!(std::os::unix::net::UnixStream::connect(&socket).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_over_stale_socket_after_crash() {
+        let temp = tempfile::tempdir().unwrap();
+        let socket = temp.path().join("parentshield.sock");
+
+        // A daemon killed without cleaning up leaves a socket nobody answers
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        assert!(std::os::unix::net::UnixStream::connect(&socket).is_err());
+
+        let _listener = bind_socket(&socket).unwrap();
+        assert!(std::os::unix::net::UnixStream::connect(&socket).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_runtime_dir_must_be_a_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("parentshield");
+        std::fs::write(&file, "").unwrap();
+
+        assert!(ensure_runtime_dir(&file).is_err());
+    }
+
+    fn disable_firewall(token: &str) -> DaemonRequest {
+        DaemonRequest::Authorized {
+            token: token.to_string(),
+            request: Box::new(DaemonRequest::DisableFirewall),
+        }
+    }
+
+    #[test]
+    fn test_unauthenticated_privileged_requests_are_rejected() {
+        let auth = IpcAuth::new();
+        let hash = "$argon2id$parent";
+
+        let unauthenticated = [
+            DaemonRequest::DisableFirewall,
+            DaemonRequest::ApplyBlocking,
+            DaemonRequest::ReloadConfig,
+            DaemonRequest::RefreshSubscriptions,
+            DaemonRequest::EnterMaintenance { minutes: None },
+            DaemonRequest::PurgeLogs,
+            DaemonRequest::UpdateConfig {
+                game_blocking: Some(false),
+                ai_blocking: None,
+                dns_blocking: None,
+                browser_blocking: None,
+            },
+        ];
+        for request in unauthenticated {
+            assert_eq!(
+                auth.check(request, Some(1000), hash, Some(1000)).unwrap_err(),
+                IpcAuthError::NotAuthenticated
+            );
+        }
+
+        // A forged token, or one from another daemon run
+        assert_eq!(
+            auth.check(disable_firewall("0000"), Some(1000), hash, Some(1000)).unwrap_err(),
+            IpcAuthError::InvalidToken
+        );
+        let other = IpcAuth::new().token_for(hash);
+        assert_eq!(
+            auth.check(disable_firewall(&other), Some(1000), hash, Some(1000)).unwrap_err(),
+            IpcAuthError::InvalidToken
+        );
+        // A token from before the password changed
+        assert_eq!(
+            auth.check(disable_firewall(&auth.token_for(hash)), Some(1000), "$argon2id$new", Some(1000))
+                .unwrap_err(),
+            IpcAuthError::InvalidToken
+        );
+    }
+
+    #[test]
+    fn test_only_parent_and_root_are_honored() {
+        let auth = IpcAuth::new();
+        let hash = "$argon2id$parent";
+        let token = auth.token_for(hash);
+
+        assert_eq!(
+            auth.check(disable_firewall(&token), Some(1001), hash, Some(1000)).unwrap_err(),
+            IpcAuthError::PeerNotAllowed(1001)
+        );
+        assert!(matches!(
+            auth.check(DaemonRequest::Authenticate { password: "guess".into() }, Some(1001), hash, Some(1000)),
+            Err(IpcAuthError::PeerNotAllowed(1001))
+        ));
+        assert!(matches!(
+            auth.check(disable_firewall(&token), Some(1000), hash, Some(1000)),
+            Ok(DaemonRequest::DisableFirewall)
+        ));
+        assert!(matches!(
+            auth.check(disable_firewall(&token), Some(0), hash, Some(1000)),
+            Ok(DaemonRequest::DisableFirewall)
+        ));
+
+        // Read-only requests stay open to everyone
+        assert!(matches!(
+            auth.check(DaemonRequest::GetStatus, Some(1001), hash, Some(1000)),
+            Ok(DaemonRequest::GetStatus)
+        ));
+        assert!(matches!(auth.check(DaemonRequest::Ping, Some(1001), hash, Some(1000)), Ok(DaemonRequest::Ping)));
+    }
+
+    #[test]
+    fn test_unrecorded_parent_fails_closed() {
+        let auth = IpcAuth::new();
+        let hash = "$argon2id$parent";
+        let token = auth.token_for(hash);
+
+        // Installs from before the parent uid was kept must not trust everyone
+        assert_eq!(
+            auth.check(disable_firewall(&token), Some(1001), hash, None).unwrap_err(),
+            IpcAuthError::ParentNotRecorded
+        );
+        assert_eq!(
+            auth.check(DaemonRequest::ApplyBlocking, Some(1001), hash, None).unwrap_err(),
+            IpcAuthError::ParentNotRecorded
+        );
+        // Signing in is how the parent account gets recorded
+        assert!(matches!(
+            auth.check(DaemonRequest::Authenticate { password: "pw".into() }, Some(1001), hash, None),
+            Ok(DaemonRequest::Authenticate { .. })
+        ));
+        assert!(matches!(
+            auth.check(disable_firewall(&token), Some(0), hash, None),
+            Ok(DaemonRequest::DisableFirewall)
+        ));
+    }
+
     #[test]
     fn test_roundtrip() {
         let request = DaemonRequest::UpdateConfig {
@@ -0,0 +1,170 @@
+//! Debouncing for processes that keep coming back after being killed.
+//! A game that auto-restarts gets killed on every blocking check; once a name
+//! has been killed more than `threshold` times within `window_secs` the daemon
+//! escalates once and stops logging and notifying for it until the restarts
+//! calm down. Kills are also counted per calendar day for the GUI.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// When repeated kills of the same process count as a respawn loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KillDebounceConfig {
+    /// Length of the sliding window kills are counted over
+    pub window_secs: u64,
+    /// Kills of one process name allowed within the window before escalating
+    pub threshold: u32,
+}
+
+impl Default for KillDebounceConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            threshold: 5,
+        }
+    }
+}
+
+/// What to do about a kill that was just recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillVerdict {
+    /// Log and notify as usual
+    Report,
+    /// The process just crossed the threshold; escalate once
+    Escalate { kills: u32 },
+    /// Still respawning after escalation; stay quiet
+    Suppress,
+}
+
+/// Recent kills per process name
+#[derive(Debug)]
+pub struct KillTracker {
+    config: KillDebounceConfig,
+    recent: HashMap<String, VecDeque<Instant>>,
+    /// Names over the threshold whose kills are no longer reported
+    escalated: HashSet<String>,
+    day: Option<NaiveDate>,
+    kills_today: HashMap<String, u32>,
+}
+
+impl Default for KillTracker {
+    fn default() -> Self {
+        Self::new(KillDebounceConfig::default())
+    }
+}
+
+impl KillTracker {
+    pub fn new(config: KillDebounceConfig) -> Self {
+        Self {
+            config,
+            recent: HashMap::new(),
+            escalated: HashSet::new(),
+            day: None,
+            kills_today: HashMap::new(),
+        }
+    }
+
+    /// Change the window and threshold; today's counts are kept
+    pub fn set_config(&mut self, config: KillDebounceConfig) {
+        self.config = config;
+    }
+
+    /// Count a kill of `name` at `now` on `today`
+    pub fn record(&mut self, name: &str, now: Instant, today: NaiveDate) -> KillVerdict {
+        let name = name.to_lowercase();
+
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.kills_today.clear();
+        }
+        *self.kills_today.entry(name.clone()).or_default() += 1;
+
+        let window = Duration::from_secs(self.config.window_secs);
+        self.recent
+            .retain(|_, kills| kills.back().is_some_and(|last| now.duration_since(*last) < window));
+        self.escalated.retain(|name| self.recent.contains_key(name));
+
+        let kills = self.recent.entry(name.clone()).or_default();
+        while kills.front().is_some_and(|seen| now.duration_since(*seen) >= window) {
+            kills.pop_front();
+        }
+        kills.push_back(now);
+
+        let count = kills.len() as u32;
+        if count <= self.config.threshold {
+            self.escalated.remove(&name);
+            KillVerdict::Report
+        } else if self.escalated.insert(name) {
+            KillVerdict::Escalate { kills: count }
+        } else {
+            KillVerdict::Suppress
+        }
+    }
+
+    /// Kills per (lowercase) process name since midnight
+    pub fn kills_today(&self) -> &HashMap<String, u32> {
+        &self.kills_today
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(threshold: u32) -> KillTracker {
+        KillTracker::new(KillDebounceConfig {
+            window_secs: 60,
+            threshold,
+        })
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_escalates_once_then_suppresses() {
+        let mut tracker = tracker(2);
+        let start = Instant::now();
+
+        assert_eq!(tracker.record("Steam", start, day(1)), KillVerdict::Report);
+        assert_eq!(tracker.record("steam", start + Duration::from_secs(5), day(1)), KillVerdict::Report);
+        assert_eq!(
+            tracker.record("steam", start + Duration::from_secs(10), day(1)),
+            KillVerdict::Escalate { kills: 3 }
+        );
+        assert_eq!(tracker.record("steam", start + Duration::from_secs(15), day(1)), KillVerdict::Suppress);
+        // Other names are counted separately
+        assert_eq!(tracker.record("roblox", start + Duration::from_secs(15), day(1)), KillVerdict::Report);
+    }
+
+    #[test]
+    fn test_quiet_period_resets_escalation() {
+        let mut tracker = tracker(1);
+        let start = Instant::now();
+
+        tracker.record("steam", start, day(1));
+        assert_eq!(tracker.record("steam", start, day(1)), KillVerdict::Escalate { kills: 2 });
+
+        let later = start + Duration::from_secs(120);
+        assert_eq!(tracker.record("steam", later, day(1)), KillVerdict::Report);
+        assert_eq!(tracker.record("steam", later, day(1)), KillVerdict::Escalate { kills: 2 });
+    }
+
+    #[test]
+    fn test_daily_counts_reset_at_midnight() {
+        let mut tracker = tracker(100);
+        let start = Instant::now();
+
+        for _ in 0..12 {
+            tracker.record("steam", start, day(1));
+        }
+        assert_eq!(tracker.kills_today().get("steam"), Some(&12));
+
+        tracker.record("steam", start, day(2));
+        assert_eq!(tracker.kills_today().get("steam"), Some(&1));
+    }
+}
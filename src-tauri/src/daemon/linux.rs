@@ -1,7 +1,8 @@
 //! Linux service management using systemd.
 
-use super::{ServiceError, ServiceManager, ServiceStatus};
-use std::process::Command;
+use super::{InstallOutcome, ServiceError, ServiceManager, ServiceStatus};
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 const SERVICE_NAME: &str = "parentshield";
 const SERVICE_FILE: &str = "/etc/systemd/system/parentshield.service";
@@ -24,9 +25,26 @@ impl LinuxServiceManager {
 
     /// Run a command with pkexec for privilege escalation
     fn run_privileged(&self, args: &[&str]) -> Result<(), ServiceError> {
-        let output = Command::new("pkexec")
+        self.run_privileged_with_input(args, None)
+    }
+
+    /// Run a command with pkexec, feeding `input` to its stdin if given
+    fn run_privileged_with_input(&self, args: &[&str], input: Option<&[u8]>) -> Result<(), ServiceError> {
+        let mut child = Command::new("pkexec")
             .args(args)
-            .output()
+            .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| ServiceError::ControlFailed(format!("Failed to run pkexec: {}", e)))?;
+
+        // Closed at the end of this statement, so the command sees EOF
+        let written = match (input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => stdin.write_all(input),
+            _ => Ok(()),
+        };
+        let output = child
+            .wait_with_output()
             .map_err(|e| ServiceError::ControlFailed(format!("Failed to run pkexec: {}", e)))?;
 
         if !output.status.success() {
@@ -39,15 +57,79 @@ impl LinuxServiceManager {
             }
             return Err(ServiceError::ControlFailed(stderr.to_string()));
         }
+        written.map_err(|e| ServiceError::ControlFailed(format!("Failed to pass input to pkexec: {}", e)))?;
 
         Ok(())
     }
+
+    /// Write `content` over the unit file as root and reload systemd. The unit
+    /// goes through `tee`'s stdin rather than a file another account could
+    /// swap out before it is copied into place.
+    fn write_unit_file(&self, content: &str) -> Result<(), ServiceError> {
+        self.run_privileged_with_input(&["tee", SERVICE_FILE], Some(content.as_bytes()))?;
+        self.run_privileged(&["systemctl", "daemon-reload"])
+    }
+
+    /// Stop the service for removal, lifting RefuseManualStop from the unit if it
+    /// turns the stop down
+    fn stop_for_uninstall(&self) -> Result<(), ServiceError> {
+        match self.stop() {
+            Err(ServiceError::ManualStopRefused) => {
+                let unit = build_unit_file(&self.daemon_path)
+                    .replace("RefuseManualStop=true", "RefuseManualStop=false");
+                self.write_unit_file(&unit)?;
+                self.stop()
+            }
+            other => other,
+        }
+    }
 }
 
-impl ServiceManager for LinuxServiceManager {
-    fn install(&self) -> Result<(), ServiceError> {
-        let service_content = format!(
-            r#"[Unit]
+/// Whether systemctl turned a stop down because of RefuseManualStop
+fn is_manual_stop_refusal(stderr: &str) -> bool {
+    stderr.contains("Operation refused") || stderr.contains("refuse manual start/stop")
+}
+
+/// Sandboxing for the `[Service]` section. The daemon still needs:
+/// - /etc writable: the hosts file is replaced by a rename in /etc, and
+///   browser policies live under /etc/<browser>/policies
+/// - Firefox's distribution directories and our binaries, for policies and
+///   immutable flags; `-` skips the ones a system doesn't have
+/// - /var/lib/dpkg, to hold the package
+/// - /home and /root writable (ProtectSystem=strict covers them too, and there
+///   is no ProtectHome): the config lives in the users'
+///   ~/.config/parentshield, and Firefox DoH prefs go into each profile's user.js
+/// - capabilities for the firewall, DNS proxy on port 53, killing and
+///   inspecting other users' processes, chattr and runuser notifications
+const HARDENING_DIRECTIVES: &str = "\
+NoNewPrivileges=true
+ProtectSystem=strict
+ReadWritePaths=/etc -/var/lib/dpkg
+ReadWritePaths=-/home -/root
+ReadWritePaths=-/usr/lib/firefox/distribution -/usr/lib64/firefox/distribution
+ReadWritePaths=-/usr/lib/firefox-esr/distribution -/opt/firefox/distribution
+ReadWritePaths=-/usr/bin/parentshield -/usr/bin/parentshield-daemon
+StateDirectory=parentshield
+LogsDirectory=parentshield
+PrivateTmp=true
+ProtectClock=true
+ProtectKernelLogs=true
+ProtectControlGroups=true
+RestrictSUIDSGID=true
+RestrictRealtime=true
+RestrictNamespaces=true
+LockPersonality=true
+SystemCallArchitectures=native
+RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6 AF_NETLINK
+CapabilityBoundingSet=CAP_NET_ADMIN CAP_NET_RAW CAP_NET_BIND_SERVICE CAP_KILL CAP_SYS_PTRACE
+CapabilityBoundingSet=CAP_DAC_OVERRIDE CAP_DAC_READ_SEARCH CAP_FOWNER CAP_CHOWN CAP_LINUX_IMMUTABLE
+CapabilityBoundingSet=CAP_SETUID CAP_SETGID CAP_AUDIT_WRITE
+";
+
+/// Build the systemd unit file for the daemon binary
+fn build_unit_file(daemon_path: &str) -> String {
+    format!(
+        r#"[Unit]
 Description=ParentShield Parental Control Daemon
 After=network.target
 
@@ -65,44 +147,79 @@ RuntimeDirectoryMode=0755
 # Prevent manual stop (parental control)
 RefuseManualStop=true
 
+# Sandboxing
+{}
 [Install]
 WantedBy=multi-user.target
 "#,
-            self.daemon_path
-        );
-
-        // Write service content to a temp file first, then use pkexec to copy it
-        let temp_path = "/tmp/parentshield.service.tmp";
-        std::fs::write(temp_path, &service_content)
-            .map_err(|e| ServiceError::InstallFailed(format!("Failed to write temp file: {}", e)))?;
+        daemon_path, HARDENING_DIRECTIVES
+    )
+}
 
-        // Use pkexec with cp to copy the service file as root
-        let result = self.run_privileged(&["cp", temp_path, SERVICE_FILE]);
+/// Map `systemctl is-active` output for an installed unit to a status
+fn parse_is_active(output: &str) -> ServiceStatus {
+    match output.trim() {
+        "active" | "reloading" => ServiceStatus::Running,
+        "inactive" | "failed" => ServiceStatus::Stopped,
+        // Starting, stopping, or something newer systemd added
+        _ => ServiceStatus::Unknown,
+    }
+}
 
-        // Clean up temp file
-        let _ = std::fs::remove_file(temp_path);
+/// Decide what installing means given the unit file currently on disk
+fn plan_install(existing: Option<&str>, desired: &str) -> InstallOutcome {
+    match existing {
+        None => InstallOutcome::Installed,
+        Some(current) if current == desired => InstallOutcome::Unchanged,
+        Some(_) => InstallOutcome::Upgraded,
+    }
+}
 
-        result.map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+/// systemctl calls that bring the service up once the unit file is in place
+fn activation_commands(outcome: InstallOutcome) -> Vec<Vec<&'static str>> {
+    // Enabling is a no-op if already enabled
+    let mut commands = vec![vec!["systemctl", "enable", SERVICE_NAME]];
+    if outcome == InstallOutcome::Upgraded {
+        // RefuseManualStop turns `restart` down too, so signal the old daemon
+        // and let Restart=always bring up the binary the reloaded unit names
+        commands.push(vec!["systemctl", "kill", "--signal=SIGTERM", SERVICE_NAME]);
+    }
+    // Covers a daemon that wasn't running; harmless if it already is
+    commands.push(vec!["systemctl", "start", SERVICE_NAME]);
+    commands
+}
 
-        // Reload systemd with pkexec
-        self.run_privileged(&["systemctl", "daemon-reload"])
-            .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+impl ServiceManager for LinuxServiceManager {
+    fn install(&self) -> Result<InstallOutcome, ServiceError> {
+        let service_content = build_unit_file(&self.daemon_path);
+        let existing = std::fs::read_to_string(SERVICE_FILE).ok();
+        let outcome = plan_install(existing.as_deref(), &service_content);
 
-        // Enable service with pkexec
-        self.run_privileged(&["systemctl", "enable", SERVICE_NAME])
-            .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+        if outcome != InstallOutcome::Unchanged {
+            self.write_unit_file(&service_content)
+                .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+        }
 
-        // Start the service
-        self.run_privileged(&["systemctl", "start", SERVICE_NAME])
-            .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+        for command in activation_commands(outcome) {
+            match self.run_privileged(&command) {
+                Ok(()) => {}
+                // Nothing to kill when the old daemon wasn't running; start follows
+                Err(e) if command[1] == "kill" => {
+                    tracing::warn!("Failed to signal the old daemon: {}", e);
+                }
+                Err(e) => return Err(ServiceError::InstallFailed(e.to_string())),
+            }
+        }
 
-        tracing::info!("ParentShield service installed and started");
-        Ok(())
+        tracing::info!("ParentShield service install finished: {:?}", outcome);
+        Ok(outcome)
     }
 
     fn uninstall(&self) -> Result<(), ServiceError> {
-        // Stop service first (ignore errors)
-        let _ = self.stop();
+        // Stop service first; removal goes ahead even if this fails
+        if let Err(e) = self.stop_for_uninstall() {
+            tracing::warn!("Failed to stop service before removal: {}", e);
+        }
 
         // Disable service with pkexec
         let _ = self.run_privileged(&["systemctl", "disable", SERVICE_NAME]);
@@ -124,25 +241,23 @@ WantedBy=multi-user.target
 
     fn stop(&self) -> Result<(), ServiceError> {
         self.run_privileged(&["systemctl", "stop", SERVICE_NAME])
+            .map_err(|e| match e {
+                ServiceError::ControlFailed(stderr) if is_manual_stop_refusal(&stderr) => {
+                    ServiceError::ManualStopRefused
+                }
+                other => other,
+            })
     }
 
     fn status(&self) -> ServiceStatus {
-        let output = Command::new("systemctl")
-            .args(["is-active", SERVICE_NAME])
-            .output();
-
-        match output {
-            Ok(out) => {
-                let status = String::from_utf8_lossy(&out.stdout);
-                if status.trim() == "active" {
-                    ServiceStatus::Running
-                } else if status.trim() == "inactive" {
-                    ServiceStatus::Stopped
-                } else {
-                    ServiceStatus::Unknown
-                }
-            }
-            Err(_) => ServiceStatus::NotInstalled,
+        // is-active reports "inactive" for units that don't exist, so check the file first
+        if !self.is_installed() {
+            return ServiceStatus::NotInstalled;
+        }
+
+        match Command::new("systemctl").args(["is-active", SERVICE_NAME]).output() {
+            Ok(out) => parse_is_active(&String::from_utf8_lossy(&out.stdout)),
+            Err(_) => ServiceStatus::Unknown,
         }
     }
 
@@ -150,3 +265,91 @@ WantedBy=multi-user.target
         std::path::Path::new(SERVICE_FILE).exists()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_fresh_install() {
+        let desired = build_unit_file("/opt/parentshield/parentshield-daemon");
+        assert_eq!(plan_install(None, &desired), InstallOutcome::Installed);
+    }
+
+    #[test]
+    fn test_plan_upgrade_with_stale_binary_path() {
+        let existing = build_unit_file("/tmp/.mount_old/parentshield-daemon");
+        let desired = build_unit_file("/opt/parentshield/parentshield-daemon");
+        assert_eq!(plan_install(Some(&existing), &desired), InstallOutcome::Upgraded);
+    }
+
+    #[test]
+    fn test_upgrade_never_asks_for_a_manual_restart() {
+        let actions = |outcome| -> Vec<&str> {
+            activation_commands(outcome).into_iter().map(|command| command[1]).collect()
+        };
+        assert_eq!(actions(InstallOutcome::Installed), ["enable", "start"]);
+        assert_eq!(actions(InstallOutcome::Unchanged), ["enable", "start"]);
+        // restart and stop are refused under RefuseManualStop=true
+        assert_eq!(actions(InstallOutcome::Upgraded), ["enable", "kill", "start"]);
+
+        let unit = build_unit_file("/opt/parentshield/parentshield-daemon");
+        assert!(unit.lines().any(|l| l == "RefuseManualStop=true"));
+        assert!(unit.lines().any(|l| l == "Restart=always"));
+    }
+
+    #[test]
+    fn test_unit_keeps_hardening_directives() {
+        let unit = build_unit_file("/opt/parentshield/parentshield-daemon");
+        let service = unit.split("[Install]").next().unwrap();
+        for line in [
+            "ProtectSystem=strict",
+            "NoNewPrivileges=true",
+            "ReadWritePaths=/etc -/var/lib/dpkg",
+            // Config, lockout attempts and Firefox user.js are written under homes
+            "ReadWritePaths=-/home -/root",
+            "StateDirectory=parentshield",
+            "LogsDirectory=parentshield",
+            "RuntimeDirectory=parentshield",
+            "RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6 AF_NETLINK",
+        ] {
+            assert!(service.lines().any(|l| l == line), "missing {}", line);
+        }
+
+        // The firewall, hosts writes and process killing depend on these
+        let capabilities: Vec<&str> = service
+            .lines()
+            .filter_map(|l| l.strip_prefix("CapabilityBoundingSet="))
+            .flat_map(str::split_whitespace)
+            .collect();
+        for cap in ["CAP_NET_ADMIN", "CAP_NET_RAW", "CAP_NET_BIND_SERVICE", "CAP_KILL", "CAP_DAC_OVERRIDE"] {
+            assert!(capabilities.contains(&cap), "missing {}", cap);
+        }
+        assert!(!service.contains("ProtectHome"));
+    }
+
+    #[test]
+    fn test_parse_is_active_states() {
+        assert_eq!(parse_is_active("active\n"), ServiceStatus::Running);
+        assert_eq!(parse_is_active("inactive\n"), ServiceStatus::Stopped);
+        assert_eq!(parse_is_active("failed\n"), ServiceStatus::Stopped);
+        assert_eq!(parse_is_active("activating\n"), ServiceStatus::Unknown);
+        assert_eq!(parse_is_active(""), ServiceStatus::Unknown);
+    }
+
+    #[test]
+    fn test_manual_stop_refusal_detected() {
+        let stderr = "Failed to stop parentshield.service: Operation refused, unit parentshield.service \
+            may be requested by dependency only (it is configured to refuse manual start/stop).\n";
+        assert!(is_manual_stop_refusal(stderr));
+        let not_loaded = "Failed to stop parentshield.service: Unit parentshield.service not loaded.\n";
+        assert!(!is_manual_stop_refusal(not_loaded));
+        assert!(!is_manual_stop_refusal("Authentication cancelled by user"));
+    }
+
+    #[test]
+    fn test_plan_unchanged_install() {
+        let desired = build_unit_file("/opt/parentshield/parentshield-daemon");
+        assert_eq!(plan_install(Some(&desired), &desired), InstallOutcome::Unchanged);
+    }
+}
@@ -0,0 +1,320 @@
+//! Size and age based rotation for the daemon's log files.
+//! `<name>.log` rolls over to `<name>.log.1` (older files shift up) once a
+//! write would take it past its size cap. Rotated files beyond the count or
+//! age cap are deleted by `prune_rotated`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory all daemon logs are written to
+#[cfg(unix)]
+pub const LOG_DIR: &str = "/var/log/parentshield";
+#[cfg(windows)]
+pub const LOG_DIR: &str = r"C:\ProgramData\ParentShield\logs";
+
+pub const DEFAULT_MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_MAX_LOG_AGE_DAYS: u32 = 14;
+pub const DEFAULT_MAX_ROTATED_FILES: u32 = 5;
+
+/// Caps for a single log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogRetention {
+    /// Size at which the active file is rotated
+    pub max_bytes: u64,
+    /// Rotated files older than this are deleted
+    pub max_age_days: u32,
+    /// Number of rotated files kept
+    pub max_files: u32,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_LOG_BYTES,
+            max_age_days: DEFAULT_MAX_LOG_AGE_DAYS,
+            max_files: DEFAULT_MAX_ROTATED_FILES,
+        }
+    }
+}
+
+/// Retention for every log, with optional per-log overrides keyed by log
+/// name (e.g. "daemon" for daemon.log)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSettings {
+    #[serde(default)]
+    pub default: LogRetention,
+    #[serde(default)]
+    pub per_log: HashMap<String, LogRetention>,
+}
+
+impl LogSettings {
+    pub fn retention_for(&self, name: &str) -> LogRetention {
+        self.per_log.get(name).copied().unwrap_or(self.default)
+    }
+}
+
+/// Whether writing `incoming` bytes to a file of `current_len` should rotate it
+/// first. An empty file is never rotated, so one oversized line still lands.
+pub fn needs_rotation(current_len: u64, incoming: u64, max_bytes: u64) -> bool {
+    current_len > 0 && current_len.saturating_add(incoming) > max_bytes
+}
+
+/// `<path>.<n>`
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Split `daemon.log.3` into ("daemon", 3)
+fn parse_rotated_name(file_name: &str) -> Option<(&str, u32)> {
+    let (stem, n) = file_name.rsplit_once('.')?;
+    let n = n.parse().ok()?;
+    Some((stem.strip_suffix(".log")?, n))
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Move `path` to `<path>.1`, replacing an older one, once it has grown
+/// past `max_bytes`. For files another process writes to, such as launchd's
+/// capture of the daemon's stdout, which can't go through `RotatingLog`.
+pub fn rotate_if_larger(path: &Path, max_bytes: u64) -> io::Result<bool> {
+    match fs::metadata(path) {
+        Ok(meta) if meta.len() > max_bytes => {
+            let rotated = rotated_path(path, 1);
+            remove_if_exists(&rotated)?;
+            fs::rename(path, rotated)?;
+            Ok(true)
+        }
+        Ok(_) => Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Append-only log file that rotates itself when it reaches its size cap
+pub struct RotatingLog {
+    path: PathBuf,
+    retention: LogRetention,
+    file: Option<File>,
+    len: u64,
+}
+
+impl RotatingLog {
+    pub fn open(path: impl Into<PathBuf>, retention: LogRetention) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path,
+            retention,
+            file: Some(file),
+            len,
+        })
+    }
+
+    /// Move the active file to `.1`, shifting older files up and dropping the
+    /// oldest past `max_files`
+    pub fn rotate(&mut self) -> io::Result<()> {
+        // Close first; Windows can't rename an open file
+        self.file = None;
+
+        let max_files = self.retention.max_files;
+        if max_files == 0 {
+            remove_if_exists(&self.path)?;
+        } else {
+            remove_if_exists(&rotated_path(&self.path, max_files))?;
+            for n in (1..max_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            if self.path.exists() {
+                fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            }
+        }
+
+        self.file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if needs_rotation(self.len, buf.len() as u64, self.retention.max_bytes) {
+            self.rotate()?;
+        }
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("log file could not be reopened after rotation"))?;
+        let written = file.write(buf)?;
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Delete rotated files in `dir` that are past their log's count or age cap.
+/// Returns how many files were removed.
+pub fn prune_rotated(dir: &Path, settings: &LogSettings, now: SystemTime) -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some((log, n)) = file_name.to_str().and_then(parse_rotated_name) else {
+            continue;
+        };
+
+        let retention = settings.retention_for(log);
+        let max_age = Duration::from_secs(u64::from(retention.max_age_days) * 24 * 60 * 60);
+        let too_old = entry
+            .metadata()?
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > max_age);
+
+        if (n > retention.max_files || too_old) && remove_if_exists(&entry.path())? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Delete every rotated file in `dir` and empty the active logs.
+/// Returns how many rotated files were removed.
+pub fn purge_logs(dir: &Path) -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let Some(name) = file_name.to_str() else {
+            continue;
+        };
+
+        if parse_rotated_name(name).is_some() {
+            if remove_if_exists(&path)? {
+                removed += 1;
+            }
+        } else if name.ends_with(".log") {
+            OpenOptions::new().write(true).open(&path)?.set_len(0)?;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_retention(max_files: u32) -> LogRetention {
+        LogRetention {
+            max_bytes: 32,
+            max_age_days: DEFAULT_MAX_LOG_AGE_DAYS,
+            max_files,
+        }
+    }
+
+    #[test]
+    fn test_rotation_trigger() {
+        assert!(!needs_rotation(0, 100, 32));
+        assert!(!needs_rotation(20, 12, 32));
+        assert!(needs_rotation(20, 13, 32));
+    }
+
+    #[test]
+    fn test_write_past_cap_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.log");
+        let mut log = RotatingLog::open(&path, small_retention(2)).unwrap();
+
+        for line in ["first line\n", "second line\n", "third line\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+        log.write_all(b"fourth line overflows it\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth line overflows it\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third line\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "first line\nsecond line\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_rotate_if_larger() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("launchd.log");
+        assert!(!rotate_if_larger(&path, 32).unwrap());
+
+        fs::write(&path, "short\n").unwrap();
+        assert!(!rotate_if_larger(&path, 32).unwrap());
+
+        fs::write(rotated_path(&path, 1), "older run\n").unwrap();
+        fs::write(&path, "a crash loop's worth of output\n".repeat(4)).unwrap();
+        assert!(rotate_if_larger(&path, 32).unwrap());
+        assert!(!path.exists());
+        assert!(fs::read_to_string(rotated_path(&path, 1)).unwrap().starts_with("a crash loop"));
+    }
+
+    #[test]
+    fn test_prune_drops_files_past_count() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["daemon.log", "daemon.log.1", "daemon.log.2", "daemon.log.3", "notes.txt"] {
+            fs::write(dir.path().join(name), "x").unwrap();
+        }
+        let mut settings = LogSettings::default();
+        settings.per_log.insert("daemon".to_string(), small_retention(1));
+
+        let removed = prune_rotated(dir.path(), &settings, SystemTime::now()).unwrap();
+        assert_eq!(removed, 2);
+        assert!(dir.path().join("daemon.log.1").exists());
+        assert!(!dir.path().join("daemon.log.2").exists());
+        assert!(dir.path().join("notes.txt").exists());
+
+        let later = SystemTime::now() + Duration::from_secs(30 * 24 * 60 * 60);
+        assert_eq!(prune_rotated(dir.path(), &settings, later).unwrap(), 1);
+        assert!(dir.path().join("daemon.log").exists());
+    }
+
+    #[test]
+    fn test_purge_removes_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.log");
+        let mut log = RotatingLog::open(&path, small_retention(3)).unwrap();
+        for _ in 0..5 {
+            log.write_all(b"a line that fills the cap\n").unwrap();
+        }
+        assert!(rotated_path(&path, 3).exists());
+
+        let removed = purge_logs(dir.path()).unwrap();
+        assert_eq!(removed, 3);
+        assert!(!rotated_path(&path, 1).exists());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+    }
+}
@@ -1,12 +1,23 @@
 //! macOS service management using launchd.
 
-use super::{ServiceError, ServiceManager, ServiceStatus};
+use super::logs::LOG_DIR;
+use super::{InstallOutcome, ServiceError, ServiceManager, ServiceStatus};
 use std::fs;
 use std::process::Command;
 
 const SERVICE_LABEL: &str = "com.parentshield.daemon";
 const PLIST_PATH: &str = "/Library/LaunchDaemons/com.parentshield.daemon.plist";
 
+/// Where launchd appends the daemon's stdout and stderr
+pub const LAUNCHD_LOG_PATHS: [&str; 2] = [
+    "/var/log/parentshield/launchd.log",
+    "/var/log/parentshield/launchd.error.log",
+];
+
+/// Seconds launchd waits before relaunching the daemon, so one that crashes
+/// on startup doesn't spin in a tight loop
+const THROTTLE_INTERVAL_SECS: u32 = 30;
+
 pub struct MacOSServiceManager {
     exe_path: String,
 }
@@ -21,10 +32,10 @@ impl MacOSServiceManager {
     }
 }
 
-impl ServiceManager for MacOSServiceManager {
-    fn install(&self) -> Result<(), ServiceError> {
-        let plist_content = format!(
-            r#"<?xml version="1.0" encoding="UTF-8"?>
+/// Build the launchd plist for the daemon executable
+fn build_plist(exe_path: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
 <plist version="1.0">
 <dict>
@@ -39,19 +50,48 @@ impl ServiceManager for MacOSServiceManager {
     <true/>
     <key>KeepAlive</key>
     <true/>
+    <key>ThrottleInterval</key>
+    <integer>{}</integer>
     <key>StandardOutPath</key>
-    <string>/var/log/parentshield.log</string>
+    <string>{}</string>
     <key>StandardErrorPath</key>
-    <string>/var/log/parentshield.error.log</string>
+    <string>{}</string>
 </dict>
 </plist>
 "#,
-            SERVICE_LABEL, self.exe_path
-        );
+        SERVICE_LABEL, exe_path, THROTTLE_INTERVAL_SECS, LAUNCHD_LOG_PATHS[0], LAUNCHD_LOG_PATHS[1]
+    )
+}
+
+impl ServiceManager for MacOSServiceManager {
+    fn install(&self) -> Result<InstallOutcome, ServiceError> {
+        let plist_content = build_plist(&self.exe_path);
+
+        let outcome = match fs::read_to_string(PLIST_PATH) {
+            Ok(existing) if existing == plist_content => InstallOutcome::Unchanged,
+            Ok(_) => InstallOutcome::Upgraded,
+            Err(_) => InstallOutcome::Installed,
+        };
+
+        if outcome == InstallOutcome::Unchanged {
+            tracing::info!("ParentShield launchd daemon already installed");
+            return Ok(outcome);
+        }
+
+        if outcome == InstallOutcome::Upgraded {
+            // Unload the stale definition before replacing it
+            let _ = Command::new("launchctl")
+                .args(["unload", PLIST_PATH])
+                .output();
+        }
 
         fs::write(PLIST_PATH, plist_content)
             .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
 
+        // launchd won't create the directory for StandardOutPath, and the
+        // load fails without it
+        fs::create_dir_all(LOG_DIR).map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+
         // Load the daemon
         let output = Command::new("launchctl")
             .args(["load", "-w", PLIST_PATH])
@@ -59,13 +99,16 @@ impl ServiceManager for MacOSServiceManager {
             .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
 
         if !output.status.success() {
-            return Err(ServiceError::InstallFailed(
-                String::from_utf8_lossy(&output.stderr).to_string(),
-            ));
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // A definition loaded from somewhere other than our plist
+            if stderr.contains("already loaded") {
+                return Err(ServiceError::AlreadyInstalled);
+            }
+            return Err(ServiceError::InstallFailed(stderr.to_string()));
         }
 
-        tracing::info!("ParentShield launchd daemon installed");
-        Ok(())
+        tracing::info!("ParentShield launchd daemon install finished: {:?}", outcome);
+        Ok(outcome)
     }
 
     fn uninstall(&self) -> Result<(), ServiceError> {
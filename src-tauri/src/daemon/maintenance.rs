@@ -0,0 +1,118 @@
+//! Maintenance window for updating ParentShield itself.
+//! While active the daemon stops re-applying its protections so an installer
+//! can replace binaries and rules; protection comes back on its own when the
+//! window expires.
+
+use std::time::{Duration, Instant};
+
+/// Window length when the caller doesn't ask for one
+pub const DEFAULT_MAINTENANCE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Longest window a parent can request
+pub const MAX_MAINTENANCE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+struct ActiveWindow {
+    until: Instant,
+    /// File protection flags were set when the window opened and must be restored
+    restore_protection: bool,
+}
+
+/// Tracks whether the daemon is in maintenance mode
+#[derive(Default)]
+pub struct MaintenanceWindow {
+    active: Option<ActiveWindow>,
+}
+
+impl MaintenanceWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open (or extend) the window; the duration is capped at MAX_MAINTENANCE_WINDOW
+    pub fn enter(&mut self, now: Instant, duration: Duration, restore_protection: bool) {
+        let until = now + duration.min(MAX_MAINTENANCE_WINDOW);
+        // Re-entering keeps the pending restore from the first entry
+        let restore_protection =
+            restore_protection || self.active.as_ref().is_some_and(|w| w.restore_protection);
+        self.active = Some(ActiveWindow {
+            until,
+            restore_protection,
+        });
+    }
+
+    /// Close the window early; returns whether file protection must be restored
+    pub fn exit(&mut self) -> bool {
+        self.active.take().is_some_and(|w| w.restore_protection)
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        self.active.as_ref().is_some_and(|w| now < w.until)
+    }
+
+    /// Self-heal (policy refresh, flag re-application) only runs outside the window
+    pub fn allows_self_heal(&self, now: Instant) -> bool {
+        !self.is_active(now)
+    }
+
+    /// Time left in the window
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.active
+            .as_ref()
+            .map(|w| w.until.saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+
+    /// If the window has run out, close it. Returns `Some(restore_protection)`
+    /// exactly once per expiry.
+    pub fn take_expired(&mut self, now: Instant) -> Option<bool> {
+        match &self.active {
+            Some(w) if now >= w.until => self.active.take().map(|w| w.restore_protection),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_suppresses_self_heal() {
+        let mut window = MaintenanceWindow::new();
+        let now = Instant::now();
+        assert!(window.allows_self_heal(now));
+
+        window.enter(now, Duration::from_secs(60), true);
+        assert!(window.is_active(now));
+        assert!(!window.allows_self_heal(now + Duration::from_secs(30)));
+        assert_eq!(window.remaining(now + Duration::from_secs(30)), Duration::from_secs(30));
+        assert_eq!(window.take_expired(now + Duration::from_secs(30)), None);
+    }
+
+    #[test]
+    fn test_maintenance_auto_expires() {
+        let mut window = MaintenanceWindow::new();
+        let now = Instant::now();
+        window.enter(now, Duration::from_secs(60), true);
+
+        let later = now + Duration::from_secs(61);
+        assert!(window.allows_self_heal(later));
+        // Expiry re-enables protection once
+        assert_eq!(window.take_expired(later), Some(true));
+        assert_eq!(window.take_expired(later), None);
+        assert!(!window.is_active(later));
+    }
+
+    #[test]
+    fn test_window_is_capped_and_exit_reports_restore() {
+        let mut window = MaintenanceWindow::new();
+        let now = Instant::now();
+        window.enter(now, Duration::from_secs(24 * 60 * 60), true);
+        assert_eq!(window.remaining(now), MAX_MAINTENANCE_WINDOW);
+
+        // Extending keeps the restore flag from the first entry
+        window.enter(now, Duration::from_secs(60), false);
+        assert!(window.exit());
+        assert!(!window.exit());
+    }
+}
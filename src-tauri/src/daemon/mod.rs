@@ -2,6 +2,13 @@ pub mod service;
 pub mod ipc;
 pub mod runner;
 pub mod client;
+pub mod events;
+pub mod transport;
+pub mod hooks;
+pub mod kill_tracker;
+pub mod logs;
+pub mod maintenance;
+pub mod sessions;
 
 #[cfg(target_os = "linux")]
 pub mod linux;
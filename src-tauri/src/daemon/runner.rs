@@ -1,34 +1,127 @@
 //! Daemon runner - main loop and client connection handling.
 
-use crate::blocking::{self, process};
-use crate::config::ConfigManager;
+use crate::blocking::network::upnp::{self, UpnpBackend};
+use crate::blocking::network::{block_log, BlockLogger, DnsProxy, FirewallBackend};
+use crate::blocking::{self, process, subscriptions, ApplyReport, WarningCode};
+use crate::config::{AppConfig, ConfigManager};
 use crate::daemon::ipc::{
-    read_message, write_message, BlockedProcessInfo, DaemonRequest, DaemonResponse,
+    read_message, write_message, BlockedProcessInfo, BlockingPreview, DaemonRequest, DaemonResponse,
+    IpcAuth,
 };
-use std::collections::HashSet;
+use crate::daemon::events::{DaemonEvent, EventBus};
+use crate::daemon::hooks::{self, HookEvent};
+use crate::daemon::kill_tracker::{KillTracker, KillVerdict};
+use crate::daemon::logs::{self, LOG_DIR};
+use crate::daemon::maintenance::{MaintenanceWindow, DEFAULT_MAINTENANCE_WINDOW};
+use crate::daemon::sessions;
+use crate::scheduler;
+use crate::security::audit::{self, TamperEvent};
+use crate::security::recovery::{self, AuditRecord, RecoveryStep};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufReader, BufWriter};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{error, info, warn};
+use zeroize::Zeroizing;
 
 #[cfg(unix)]
 use crate::daemon::ipc::SOCKET_PATH;
 #[cfg(unix)]
 use std::fs;
 #[cfg(unix)]
-use std::os::unix::net::{UnixListener, UnixStream};
-#[cfg(unix)]
-use std::path::Path;
+use std::os::unix::net::UnixStream;
 
 #[cfg(windows)]
 use crate::daemon::ipc::PIPE_NAME;
+#[cfg(windows)]
+use crate::daemon::transport::accept_pipe_client;
+
+/// How often the monitoring loop checks for blocked processes
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the daemon re-applies managed browser DoH policies
+const BROWSER_POLICY_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often rotated logs past their retention are deleted
+const LOG_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the system clock is checked against the trusted time server
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(15 * 60);
 
-/// Daemon state shared across threads
+/// How long to wait for the trusted time server
+const CLOCK_SYNC_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// System clock offset treated as someone having changed the clock
+const CLOCK_SKEW_ALERT_MINUTES: i64 = 5;
+
+/// Most time charged against the daily budget for one check, so a suspended
+/// machine or a stalled loop doesn't eat the budget in one go
+const MAX_BUDGET_CHARGE: Duration = Duration::from_secs(30);
+
+/// How often an idle subscriber connection checks whether the daemon is stopping
+const SUBSCRIBER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often to look for blocklist subscriptions due a refresh
+const SUBSCRIPTION_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How often game port mappings that games re-requested are taken off the router again
+const ROUTER_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Daemon state shared between the IPC handler threads and the blocking loop.
+///
+/// Counters and flags are atomics, so status reads never take a lock and never
+/// wait on an apply in progress. Anything that changes the system (hosts file,
+/// firewall, router mappings, browser policies, saved config) runs under
+/// `apply_lock` so IPC requests and the loop can't interleave their writes.
+///
+/// Lock order, outermost first: `apply_lock`, `maintenance`, `ROUTER_BACKEND`.
+/// Never take `apply_lock` while holding `maintenance`.
 pub struct DaemonState {
     pub running: AtomicBool,
     pub blocked_count: AtomicU32,
     pub start_time: Instant,
+    /// Unix time the monitoring loop last finished a check, 0 before the first
+    last_check_unix: AtomicU64,
+    pub maintenance: Mutex<MaintenanceWindow>,
+    /// Resolver the DNS proxy forwards to, read from the config at startup
+    pub upstream_dns: SocketAddr,
+    /// The DNS proxy serving clients, once one has been started
+    dns_proxy: Mutex<Option<DnsProxy>>,
+    /// Recent kills per process name, for debouncing respawn loops
+    kill_tracker: Mutex<KillTracker>,
+    /// When time was last charged against the daily budget
+    last_budget_charge: Mutex<Option<Instant>>,
+    /// Issues and checks the tokens privileged IPC requests carry
+    ipc_auth: IpcAuth,
+    /// Live events for subscribed GUI connections
+    pub events: EventBus,
+    applying: AtomicBool,
+    apply_lock: Mutex<()>,
+}
+
+/// Point-in-time view of the daemon counters, taken without locking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DaemonSnapshot {
+    pub blocked_count: u32,
+    pub uptime_secs: u64,
+    pub last_check_unix: u64,
+    pub applying: bool,
+}
+
+/// Held while changing the system; see [`DaemonState`] for the lock order
+pub struct ApplyGuard<'a> {
+    state: &'a DaemonState,
+    _lock: MutexGuard<'a, ()>,
+}
+
+impl Drop for ApplyGuard<'_> {
+    fn drop(&mut self) {
+        self.state.applying.store(false, Ordering::Release);
+    }
 }
 
 impl DaemonState {
@@ -37,6 +130,108 @@ impl DaemonState {
             running: AtomicBool::new(true),
             blocked_count: AtomicU32::new(0),
             start_time: Instant::now(),
+            last_check_unix: AtomicU64::new(0),
+            maintenance: Mutex::new(MaintenanceWindow::new()),
+            upstream_dns: configured_upstream_dns(),
+            dns_proxy: Mutex::new(None),
+            kill_tracker: Mutex::new(KillTracker::default()),
+            last_budget_charge: Mutex::new(None),
+            ipc_auth: IpcAuth::new(),
+            events: EventBus::new(),
+            applying: AtomicBool::new(false),
+            apply_lock: Mutex::new(()),
+        }
+    }
+
+    /// Kills per process name since midnight
+    pub fn kills_today(&self) -> HashMap<String, u32> {
+        self.kill_tracker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .kills_today()
+            .clone()
+    }
+
+    /// Register the running DNS proxy so IPC requests can reach it
+    pub fn attach_dns_proxy(&self, proxy: DnsProxy) {
+        *self.dns_proxy.lock().unwrap_or_else(PoisonError::into_inner) = Some(proxy);
+    }
+
+    /// Whether a DNS proxy is attached; one that failed to start is detached
+    pub fn dns_proxy_attached(&self) -> bool {
+        self.dns_proxy.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+    }
+
+    /// Detach the DNS proxy and take down the redirect pointing at it. Holds
+    /// the apply lock so an apply can't put the redirect back in between.
+    fn detach_dns_proxy(&self) -> Option<DnsProxy> {
+        let _apply = self.begin_apply();
+        let proxy = self.dns_proxy.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if let Err(e) = apply_dns_redirect(false) {
+            warn!("Failed to remove DNS redirect: {}", e);
+        }
+        proxy
+    }
+
+    /// Stop the DNS proxy's listeners, if one is running
+    pub fn stop_dns_proxy(&self) {
+        let Some(proxy) = self.detach_dns_proxy() else {
+            return;
+        };
+        match tokio::runtime::Builder::new_current_thread().build() {
+            Ok(runtime) => runtime.block_on(proxy.stop()),
+            Err(e) => warn!("Failed to stop DNS proxy: {}", e),
+        }
+    }
+
+    /// Wait for any other apply to finish and take the apply lock
+    pub fn begin_apply(&self) -> ApplyGuard<'_> {
+        // The lock guards no data, so a panicked apply leaves nothing to recover
+        let lock = self.apply_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.applying.store(true, Ordering::Release);
+        ApplyGuard { state: self, _lock: lock }
+    }
+
+    /// Apply the current config, serialized with every other apply
+    pub fn apply_blocking(&self) -> Result<ApplyReport, Box<dyn std::error::Error>> {
+        let _apply = self.begin_apply();
+        apply_blocking_now(self, false)
+    }
+
+    /// Heartbeat from the monitoring loop, so status can tell a wedged loop apart
+    pub fn record_check(&self, at: SystemTime) {
+        let unix = at.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.last_check_unix.store(unix, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> DaemonSnapshot {
+        DaemonSnapshot {
+            blocked_count: self.blocked_count.load(Ordering::Relaxed),
+            uptime_secs: self.start_time.elapsed().as_secs(),
+            last_check_unix: self.last_check_unix.load(Ordering::Relaxed),
+            applying: self.applying.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Set from the SIGTERM/SIGINT handler; the accept loop turns it into a shutdown
+#[cfg(unix)]
+static TERMINATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_termination_signal(_signal: libc::c_int) {
+    // Storing to an atomic is all a signal handler can safely do
+    TERMINATION_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Shut down cleanly when systemd or launchd stops the service, instead of dying
+/// with the socket still on disk
+#[cfg(unix)]
+fn install_termination_handlers() {
+    let handler = on_termination_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            warn!("Failed to install handler for signal {}", signal);
         }
     }
 }
@@ -47,38 +242,38 @@ pub fn run_daemon() -> std::io::Result<()> {
     info!("Starting ParentShield daemon...");
 
     let state = Arc::new(DaemonState::new());
+    install_termination_handlers();
 
-    // Create socket directory
-    let socket_dir = Path::new(SOCKET_PATH).parent().unwrap();
-    fs::create_dir_all(socket_dir)?;
-
-    // Remove stale socket file
-    if Path::new(SOCKET_PATH).exists() {
-        fs::remove_file(SOCKET_PATH)?;
-    }
-
-    // Create Unix socket listener
-    let listener = UnixListener::bind(SOCKET_PATH)?;
-
-    // Set socket permissions (world readable/writable so GUI can connect)
-    {
-        use std::os::unix::fs::PermissionsExt;
-        fs::set_permissions(SOCKET_PATH, fs::Permissions::from_mode(0o666))?;
-    }
+    // Create the runtime directory if nothing else did, then bind
+    let listener = crate::daemon::ipc::bind_socket(Path::new(SOCKET_PATH))?;
 
     info!("Daemon listening on {}", SOCKET_PATH);
 
+    start_dns_proxy(&state);
+
     // Spawn blocking check thread
     let state_clone = Arc::clone(&state);
     let blocking_thread = std::thread::spawn(move || {
         run_blocking_loop(state_clone);
     });
 
+    // Downloads can be slow, so subscriptions refresh on their own thread
+    let state_clone = Arc::clone(&state);
+    let subscription_thread = std::thread::spawn(move || {
+        run_subscription_loop(state_clone);
+    });
+
     // Set non-blocking mode for accept with timeout
     listener.set_nonblocking(true)?;
 
     // Main accept loop
     while state.running.load(Ordering::Relaxed) {
+        if TERMINATION_REQUESTED.load(Ordering::Relaxed) {
+            info!("Termination signal received");
+            state.running.store(false, Ordering::Relaxed);
+            break;
+        }
+
         match listener.accept() {
             Ok((stream, _)) => {
                 let state_clone = Arc::clone(&state);
@@ -99,12 +294,16 @@ pub fn run_daemon() -> std::io::Result<()> {
     }
 
     info!("Daemon shutting down...");
+    audit::record(TamperEvent::ServiceStopped, None);
+    state.stop_dns_proxy();
 
     // Clean up socket
+    drop(listener);
     let _ = fs::remove_file(SOCKET_PATH);
 
-    // Wait for blocking thread
+    // Wait for blocking and subscription threads
     let _ = blocking_thread.join();
+    let _ = subscription_thread.join();
 
     Ok(())
 }
@@ -112,16 +311,10 @@ pub fn run_daemon() -> std::io::Result<()> {
 /// Run the daemon main loop (Windows version using named pipes)
 #[cfg(windows)]
 pub fn run_daemon() -> std::io::Result<()> {
-    use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
-    use windows::Win32::System::Pipes::{
-        ConnectNamedPipe, CreateNamedPipeW,
-        PIPE_READMODE_MESSAGE, PIPE_TYPE_MESSAGE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
-    };
-    use windows::core::PCWSTR;
-
     info!("Starting ParentShield daemon...");
 
     let state = Arc::new(DaemonState::new());
+    start_dns_proxy(&state);
 
     // Spawn blocking check thread
     let state_clone = Arc::clone(&state);
@@ -129,60 +322,39 @@ pub fn run_daemon() -> std::io::Result<()> {
         run_blocking_loop(state_clone);
     });
 
+    // Downloads can be slow, so subscriptions refresh on their own thread
+    let state_clone = Arc::clone(&state);
+    let subscription_thread = std::thread::spawn(move || {
+        run_subscription_loop(state_clone);
+    });
+
     info!("Daemon listening on {}", PIPE_NAME);
 
-    // Main loop - create named pipe instances and wait for clients
+    // Main loop - create a pipe instance per client and serve it on its own thread
     while state.running.load(Ordering::Relaxed) {
-        // Create a named pipe instance
-        let pipe_name: Vec<u16> = PIPE_NAME.encode_utf16().chain(std::iter::once(0)).collect();
-
-        let pipe = unsafe {
-            CreateNamedPipeW(
-                PCWSTR(pipe_name.as_ptr()),
-                PIPE_ACCESS_DUPLEX,
-                PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE | PIPE_WAIT,
-                PIPE_UNLIMITED_INSTANCES,
-                4096,
-                4096,
-                0,
-                None,
-            )
-        };
-
-        // Check if pipe creation failed (returns INVALID_HANDLE_VALUE on error)
-        if pipe.is_invalid() {
-            let err = unsafe { windows::Win32::Foundation::GetLastError() };
-            error!("Failed to create named pipe: {:?}", err);
-            std::thread::sleep(Duration::from_secs(1));
-            continue;
-        }
-
-        // Wait for a client to connect (with timeout via polling)
-        let connected = unsafe { ConnectNamedPipe(pipe, None) };
-
-        if connected.is_ok() || unsafe { windows::Win32::Foundation::GetLastError() } == windows::Win32::Foundation::ERROR_PIPE_CONNECTED {
-            let state_clone = Arc::clone(&state);
-            // Extract raw handle value before moving into thread (raw pointer is not Send)
-            let raw_handle = pipe.0 as usize;
-            std::thread::spawn(move || {
-                // Convert raw handle back to a file for reading/writing
-                use std::os::windows::io::FromRawHandle;
-                let file = unsafe { std::fs::File::from_raw_handle(raw_handle as *mut std::ffi::c_void) };
-
-                if let Err(e) = handle_client_windows(file, state_clone) {
-                    warn!("Client handler error: {}", e);
-                }
-            });
-        } else {
-            error!("Failed to connect named pipe");
-            std::thread::sleep(Duration::from_millis(100));
+        match accept_pipe_client(PIPE_NAME) {
+            Ok(pipe) => {
+                let state_clone = Arc::clone(&state);
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_client_windows(pipe, state_clone) {
+                        warn!("Client handler error: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                error!("Failed to accept pipe client: {}", e);
+                std::thread::sleep(Duration::from_millis(100));
+            }
         }
     }
 
     info!("Daemon shutting down...");
+    audit::record(TamperEvent::ServiceStopped, None);
+    state.stop_dns_proxy();
 
-    // Wait for blocking thread
+    // Wait for blocking and subscription threads
     let _ = blocking_thread.join();
+    let _ = subscription_thread.join();
 
     Ok(())
 }
@@ -192,6 +364,7 @@ pub fn run_daemon() -> std::io::Result<()> {
 fn handle_client_unix(stream: UnixStream, state: Arc<DaemonState>) -> std::io::Result<()> {
     stream.set_read_timeout(Some(Duration::from_secs(30)))?;
     stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+    let peer_uid = crate::daemon::ipc::peer_uid(&stream)?;
 
     let mut reader = BufReader::new(stream.try_clone()?);
     let mut writer = BufWriter::new(stream);
@@ -210,8 +383,19 @@ fn handle_client_unix(stream: UnixStream, state: Arc<DaemonState>) -> std::io::R
             }
         };
 
+        // The connection turns into an event stream for good
+        if let DaemonRequest::Subscribe = request {
+            return stream_events(&mut writer, &state);
+        }
+
         // Process request
-        let response = process_request(request, &state);
+        let response = match authorize_request(&state, request, Some(peer_uid)) {
+            Ok(request) => process_request(request, &state, Some(peer_uid)),
+            Err(message) => {
+                warn!("Rejected IPC request from uid {}: {}", peer_uid, message);
+                DaemonResponse::Error { message }
+            }
+        };
 
         // Send response
         if let Err(e) = write_message(&mut writer, &response) {
@@ -253,7 +437,15 @@ fn handle_client_windows(pipe: std::fs::File, state: Arc<DaemonState>) -> std::i
         };
 
         // Process request
-        let response = process_request(request, &state);
+        if let DaemonRequest::Subscribe = request {
+            return stream_events(&mut writer, &state);
+        }
+
+        // Pipes don't say who connected; the token alone decides
+        let response = match authorize_request(&state, request, None) {
+            Ok(request) => process_request(request, &state, None),
+            Err(message) => DaemonResponse::Error { message },
+        };
 
         // Send response
         if let Err(e) = write_message(&mut writer, &response) {
@@ -270,8 +462,72 @@ fn handle_client_windows(pipe: std::fs::File, state: Arc<DaemonState>) -> std::i
     Ok(())
 }
 
+/// Acknowledge a subscription, then forward events until the client goes
+/// away or the daemon stops
+fn stream_events(writer: &mut impl std::io::Write, state: &DaemonState) -> std::io::Result<()> {
+    let events = state.events.subscribe();
+    write_message(writer, &DaemonResponse::Ok)?;
+
+    while state.running.load(Ordering::Relaxed) {
+        match events.recv_timeout(SUBSCRIBER_POLL_INTERVAL) {
+            Ok(event) => write_message(writer, &DaemonResponse::Event { event })?,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Check the token and peer of requests that need them, unwrapping
+/// `Authorized` requests
+fn authorize_request(
+    state: &DaemonState,
+    request: DaemonRequest,
+    peer_uid: Option<u32>,
+) -> Result<DaemonRequest, String> {
+    if !request.needs_trusted_peer() {
+        return Ok(request);
+    }
+
+    // Without a readable config no token can be valid
+    let config = ConfigManager::new().and_then(|m| m.load()).ok();
+    let (password_hash, parent_uid) = config
+        .as_ref()
+        .map_or(("", None), |c| (c.password_hash.as_str(), c.parent_uid));
+    state
+        .ipc_auth
+        .check(request, peer_uid, password_hash, parent_uid)
+        .map_err(|e| e.to_string())
+}
+
+/// Verify the parent password and issue an IPC token
+fn authenticate(
+    state: &DaemonState,
+    manager: &ConfigManager,
+    password: &Zeroizing<String>,
+    peer_uid: Option<u32>,
+) -> Result<DaemonResponse, Box<dyn std::error::Error>> {
+    // Goes through the manager so failures count towards the lockout
+    if !manager.verify_password(password)? {
+        return Err("Incorrect password".into());
+    }
+
+    // Configs from before the parent account was recorded learn it from
+    // whoever first signs in with the parent password
+    if let Some(uid) = peer_uid.filter(|&uid| uid != 0) {
+        if manager.record_parent_uid(uid)? {
+            info!("Recorded uid {} as the parent account", uid);
+        }
+    }
+    // Verifying may have upgraded the stored hash, so read it afterwards
+    let config = manager.load()?;
+    Ok(DaemonResponse::Authenticated {
+        token: state.ipc_auth.token_for(&config.password_hash),
+    })
+}
+
 /// Process a daemon request and return a response
-fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonResponse {
+fn process_request(request: DaemonRequest, state: &Arc<DaemonState>, peer_uid: Option<u32>) -> DaemonResponse {
     match request {
         DaemonRequest::Ping => DaemonResponse::Pong,
 
@@ -290,7 +546,7 @@ fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonRe
             dns_blocking,
             browser_blocking,
         } => {
-            match update_config(game_blocking, ai_blocking, dns_blocking, browser_blocking) {
+            match update_config(state, game_blocking, ai_blocking, dns_blocking, browser_blocking) {
                 Ok(_) => DaemonResponse::Ok,
                 Err(e) => DaemonResponse::Error {
                     message: e.to_string(),
@@ -300,7 +556,10 @@ fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonRe
 
         DaemonRequest::RunBlockingCheck => {
             match run_blocking_check_now(state) {
-                Ok(processes) => DaemonResponse::BlockedProcesses { processes },
+                Ok(processes) => DaemonResponse::BlockedProcesses {
+                    processes,
+                    kills_today: state.kills_today(),
+                },
                 Err(e) => DaemonResponse::Error {
                     message: e.to_string(),
                 },
@@ -308,8 +567,26 @@ fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonRe
         }
 
         DaemonRequest::ApplyBlocking => {
-            match apply_blocking_now() {
-                Ok(_) => DaemonResponse::Ok,
+            match state.apply_blocking() {
+                Ok(mut report) => {
+                    // Enforce process blocking now rather than on the next check
+                    match run_blocking_check_now(state) {
+                        Ok(processes) => {
+                            report.processes_terminated = processes.into_iter().map(|p| p.name).collect();
+                        }
+                        Err(e) => warn!("Blocking check after apply failed: {}", e),
+                    }
+                    DaemonResponse::Applied { report }
+                }
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        DaemonRequest::PreviewBlocking => {
+            match preview_blocking_now() {
+                Ok(preview) => DaemonResponse::BlockingPreview { preview },
                 Err(e) => DaemonResponse::Error {
                     message: e.to_string(),
                 },
@@ -317,8 +594,12 @@ fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonRe
         }
 
         DaemonRequest::EnableFirewall => {
+            let _apply = state.begin_apply();
             match enable_firewall_blocking() {
-                Ok(_) => DaemonResponse::Ok,
+                Ok(_) => {
+                    state.events.publish(DaemonEvent::FirewallToggled { active: true });
+                    DaemonResponse::Ok
+                }
                 Err(e) => DaemonResponse::Error {
                     message: e.to_string(),
                 },
@@ -326,19 +607,367 @@ fn process_request(request: DaemonRequest, state: &Arc<DaemonState>) -> DaemonRe
         }
 
         DaemonRequest::DisableFirewall => {
+            let _apply = state.begin_apply();
             match disable_firewall_blocking() {
-                Ok(_) => DaemonResponse::Ok,
+                Ok(_) => {
+                    state.events.publish(DaemonEvent::FirewallToggled { active: false });
+                    DaemonResponse::Ok
+                }
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        DaemonRequest::ApplyBrowserPolicies => {
+            let block_private_browsing = ConfigManager::new()
+                .and_then(|m| m.load())
+                .map(|c| c.block_private_browsing)
+                .unwrap_or(false);
+            let _apply = state.begin_apply();
+            match blocking::apply_managed_browser_policies(block_private_browsing) {
+                Ok(applied) => DaemonResponse::BrowserPolicies {
+                    applied,
+                    users: blocking::get_user_doh_status(),
+                },
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        DaemonRequest::ReloadConfig => {
+            match reload_and_apply(state) {
+                Ok(()) => DaemonResponse::Ok,
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        DaemonRequest::RefreshSubscriptions => {
+            let state = Arc::clone(state);
+            std::thread::spawn(move || {
+                if let Err(e) = refresh_subscriptions(&state, true) {
+                    warn!("Subscription refresh error: {}", e);
+                }
+            });
+            DaemonResponse::Ok
+        }
+
+        DaemonRequest::EnterMaintenance { minutes } => {
+            match enter_maintenance(state, minutes) {
+                Ok(response) => response,
                 Err(e) => DaemonResponse::Error {
                     message: e.to_string(),
                 },
             }
         }
 
+        DaemonRequest::ExitMaintenance => {
+            end_maintenance(state);
+            DaemonResponse::Maintenance {
+                active: false,
+                remaining_secs: 0,
+            }
+        }
+
+        DaemonRequest::PurgeLogs => {
+            match logs::purge_logs(Path::new(LOG_DIR)).map_err(|e| e.to_string()) {
+                Ok(removed) => {
+                    info!("Purged {} rotated log files", removed);
+                    DaemonResponse::LogsPurged { removed }
+                }
+                Err(message) => DaemonResponse::Error { message },
+            }
+        }
+
+        DaemonRequest::GetDnsMetrics => {
+            let proxy = state.dns_proxy.lock().unwrap_or_else(PoisonError::into_inner);
+            match proxy.as_ref().map(DnsProxy::metrics) {
+                Some(metrics) => DaemonResponse::DnsMetrics {
+                    queries: metrics.queries,
+                    blocked: metrics.blocked,
+                    timeouts: metrics.timeouts,
+                    parse_errors: metrics.parse_errors,
+                    uptime_secs: metrics.uptime_secs,
+                },
+                None => DaemonResponse::Error {
+                    message: "DNS proxy is not running".to_string(),
+                },
+            }
+        }
+
+        DaemonRequest::GetRecentBlocks { limit } => {
+            match block_log::recent_blocks(&block_log::block_log_path(), limit) {
+                Ok(entries) => DaemonResponse::RecentBlocks { entries },
+                Err(e) => DaemonResponse::Error {
+                    message: format!("Failed to read block log: {}", e),
+                },
+            }
+        }
+
+        // Handled by the connection before it gets here
+        DaemonRequest::Subscribe => DaemonResponse::Error {
+            message: "Subscribe must be the first request on its connection".to_string(),
+        },
+
         DaemonRequest::Shutdown => {
             info!("Shutdown requested");
             state.running.store(false, Ordering::Relaxed);
             DaemonResponse::Ok
         }
+
+        DaemonRequest::Authenticate { password } => {
+            let authenticated = ConfigManager::new()
+                .map_err(Into::into)
+                .and_then(|manager| authenticate(state, &manager, &Zeroizing::new(password), peer_uid));
+            match authenticated {
+                Ok(response) => response,
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        // Unwrapped by authorize_request before getting here
+        DaemonRequest::Authorized { .. } => DaemonResponse::Error {
+            message: "Authorized requests must be checked first".to_string(),
+        },
+    }
+}
+
+/// Pick up config edits made since the daemon last looked
+fn reload_and_apply(state: &DaemonState) -> Result<(), Box<dyn std::error::Error>> {
+    reload_config(state, &ConfigManager::new()?)?;
+    state.apply_blocking()?;
+    Ok(())
+}
+
+/// Serializes subscription refreshes from the timer and from IPC
+static SUBSCRIPTION_REFRESH: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Refresh blocklist subscriptions as they come due
+fn run_subscription_loop(state: Arc<DaemonState>) {
+    let mut last_check: Option<Instant> = None;
+    while state.running.load(Ordering::Relaxed) {
+        if last_check.is_none_or(|at| at.elapsed() >= SUBSCRIPTION_CHECK_INTERVAL) {
+            last_check = Some(Instant::now());
+            if let Err(e) = refresh_subscriptions(&state, false) {
+                warn!("Subscription refresh error: {}", e);
+            }
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+/// Re-fetch the subscriptions that are due (all of them with `force`) and
+/// re-apply blocking if any came back. A list that fails to download keeps
+/// its last good domains. Returns how many lists were refreshed.
+fn refresh_subscriptions(state: &DaemonState, force: bool) -> Result<usize, Box<dyn std::error::Error>> {
+    let _refresh = SUBSCRIPTION_REFRESH.lock().unwrap_or_else(PoisonError::into_inner);
+    let manager = ConfigManager::new()?;
+    let now = chrono::Utc::now();
+    let due: Vec<String> = manager
+        .load()?
+        .blocklist_subscriptions
+        .iter()
+        .filter(|s| force || s.is_due(now))
+        .map(|s| s.url.clone())
+        .collect();
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let mut fetched = Vec::new();
+    for url in due {
+        match runtime.block_on(subscriptions::fetch_domains(&url)) {
+            Ok(domains) => {
+                info!("Fetched {} domains from {}", domains.len(), url);
+                fetched.push((url, domains));
+            }
+            Err(e) => warn!("Failed to refresh {}, keeping its last list: {}", url, e),
+        }
+    }
+    if fetched.is_empty() {
+        return Ok(0);
+    }
+
+    // Load again so changes made during the downloads aren't overwritten
+    let mut config = manager.load()?;
+    let mut refreshed = 0;
+    for (url, domains) in fetched {
+        // Skip lists unsubscribed from while they downloaded
+        if subscriptions::record_refresh(&mut config, &url, domains, now) {
+            refreshed += 1;
+        }
+    }
+    manager.save(&config)?;
+
+    reload_and_apply(state)?;
+    Ok(refreshed)
+}
+
+/// Load the config from `manager` and swap its lists into the running DNS
+/// proxy, if there is one. Schedules need nothing here: every check reads
+/// them from the file.
+fn reload_config(state: &DaemonState, manager: &ConfigManager) -> Result<(), Box<dyn std::error::Error>> {
+    let config = manager.load()?;
+
+    // Clones share the proxy's lists, so the lock isn't held while updating
+    let proxy = state.dns_proxy.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    if let Some(proxy) = proxy {
+        let blocking = crate::scheduler::should_block_with_budget(&config);
+        let blocked = if blocking {
+            blocking::get_effective_blocked_domains(&config)
+        } else {
+            Default::default()
+        };
+        info!("Reloading DNS proxy: {} blocked domains", blocked.len());
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            proxy.update_blocked(blocked).await;
+            proxy.update_allowed(config.allowed_domains.clone()).await;
+            proxy.set_anomaly_config(config.dns_anomaly.clone()).await;
+            proxy.set_prevent_doh(config.dns_prevent_doh).await;
+            proxy.set_block_window_active(blocking).await;
+            proxy.update_patterns(&config.blocked_domain_patterns).await
+        })?;
+    }
+
+    info!("Config reloaded from {}", manager.config_path().display());
+    Ok(())
+}
+
+/// Modification time of the config file, if it can be read
+fn config_modified(manager: &ConfigManager) -> Option<SystemTime> {
+    std::fs::metadata(manager.config_path()).and_then(|m| m.modified()).ok()
+}
+
+/// Reload the DNS proxy's lists if the config file changed since `seen`.
+/// The GUI saves blocklist edits straight to the file, so this is how they
+/// reach the proxy without an explicit `ReloadConfig`.
+fn reload_if_config_changed(state: &DaemonState, manager: &ConfigManager, seen: &mut Option<SystemTime>) {
+    let modified = config_modified(manager);
+    if modified == *seen {
+        return;
+    }
+    *seen = modified;
+    if let Err(e) = reload_config(state, manager) {
+        warn!("Failed to reload changed config: {}", e);
+    }
+}
+
+/// Create the DNS proxy forwarding to the configured upstream and recording
+/// blocked queries in `block_log`, attach it and load the config's lists into it
+fn create_dns_proxy(
+    state: &DaemonState,
+    manager: &ConfigManager,
+    block_log: &Path,
+) -> Result<DnsProxy, Box<dyn std::error::Error>> {
+    let proxy = DnsProxy::with_upstream(HashSet::new(), HashSet::new(), &state.upstream_dns.to_string())?;
+
+    // Queries are still answered without a log, so a bad log directory isn't fatal
+    let retention = block_log::block_log_retention(&manager.load()?.log_settings);
+    match BlockLogger::start(block_log, retention) {
+        Ok(logger) => {
+            let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+            runtime.block_on(proxy.set_block_logger(Some(logger)));
+        }
+        Err(e) => warn!("Failed to open DNS block log {}: {}", block_log.display(), e),
+    }
+
+    state.attach_dns_proxy(proxy.clone());
+    reload_config(state, manager)?;
+    Ok(proxy)
+}
+
+/// Start the DNS proxy on its own thread, listening on UDP and TCP. If it
+/// can't be set up or its port can't be bound it is detached again and the
+/// DNS redirect taken down, so lookups keep working and `GetDnsMetrics`
+/// reports it as not running.
+fn start_dns_proxy(state: &Arc<DaemonState>) {
+    let proxy = match ConfigManager::new()
+        .map_err(Into::into)
+        .and_then(|manager| create_dns_proxy(state, &manager, &block_log::block_log_path()))
+    {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            error!("Failed to set up DNS proxy: {}", e);
+            state.detach_dns_proxy();
+            return;
+        }
+    };
+
+    let thread_state = Arc::clone(state);
+    let spawned = std::thread::Builder::new().name("dns-proxy".to_string()).spawn(move || {
+        let bind_addr = format!("127.0.0.1:{}", blocking::network::DNS_PROXY_PORT);
+        let served = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(Into::into)
+            .and_then(|runtime| {
+                runtime.block_on(async { tokio::try_join!(proxy.start(&bind_addr), proxy.start_tcp(&bind_addr)) })
+            });
+        if let Err(e) = served {
+            error!("DNS proxy stopped: {}", e);
+            thread_state.detach_dns_proxy();
+        }
+    });
+    if let Err(e) = spawned {
+        error!("Failed to start DNS proxy thread: {}", e);
+        state.detach_dns_proxy();
+    }
+}
+
+/// Open a maintenance window; the request was checked for a valid IPC token
+fn enter_maintenance(
+    state: &Arc<DaemonState>,
+    minutes: Option<u64>,
+) -> Result<DaemonResponse, Box<dyn std::error::Error>> {
+    let duration = minutes
+        .map(|m| Duration::from_secs(m.saturating_mul(60)))
+        .unwrap_or(DEFAULT_MAINTENANCE_WINDOW);
+    let restore_protection = crate::security::file_protection_active();
+    crate::security::suspend_file_protection()?;
+
+    let now = Instant::now();
+    let mut window = state.maintenance.lock().map_err(|e| e.to_string())?;
+    window.enter(now, duration, restore_protection);
+    let remaining_secs = window.remaining(now).as_secs();
+    info!("Maintenance mode entered for {}s", remaining_secs);
+
+    Ok(DaemonResponse::Maintenance {
+        active: true,
+        remaining_secs,
+    })
+}
+
+/// Leave maintenance mode and bring protection back
+fn end_maintenance(state: &Arc<DaemonState>) {
+    let restore_protection = match state.maintenance.lock() {
+        Ok(mut window) => window.exit(),
+        Err(_) => return,
+    };
+    resume_protection(state, restore_protection);
+}
+
+/// Re-apply everything maintenance mode let slide
+fn resume_protection(state: &DaemonState, restore_file_flags: bool) {
+    info!("Maintenance mode ended, re-enabling protection");
+    if restore_file_flags {
+        if let Err(e) = crate::security::restore_file_protection() {
+            warn!("Failed to restore file protection: {}", e);
+        }
+    }
+    let _apply = state.begin_apply();
+    if let Err(e) = apply_blocking_now(state, false) {
+        error!("Failed to re-apply blocking: {}", e);
+    }
+    if let Err(e) = refresh_browser_policies() {
+        warn!("Failed to refresh browser DoH policies: {}", e);
     }
 }
 
@@ -347,8 +976,9 @@ fn get_daemon_status(state: &Arc<DaemonState>) -> Result<DaemonResponse, Box<dyn
     let manager = ConfigManager::new()?;
     let config = manager.load()?;
 
-    let should_block = crate::scheduler::should_block_now(&config.schedules);
+    let should_block = crate::scheduler::should_block_with_budget(&config);
     let firewall_active = blocking::is_doh_blocked();
+    let snapshot = state.snapshot();
 
     Ok(DaemonResponse::Status {
         running: true,
@@ -358,18 +988,22 @@ fn get_daemon_status(state: &Arc<DaemonState>) -> Result<DaemonResponse, Box<dyn
         dns_blocking: config.dns_blocking_enabled,
         browser_blocking: config.browser_blocking_enabled,
         firewall_active,
-        blocked_count: state.blocked_count.load(Ordering::Relaxed),
-        uptime_secs: state.start_time.elapsed().as_secs(),
+        blocked_count: snapshot.blocked_count,
+        uptime_secs: snapshot.uptime_secs,
+        last_check_unix: snapshot.last_check_unix,
+        check_interval_secs: CHECK_INTERVAL.as_secs() as u32,
     })
 }
 
 /// Update configuration
 fn update_config(
+    state: &DaemonState,
     game_blocking: Option<bool>,
     ai_blocking: Option<bool>,
     dns_blocking: Option<bool>,
     browser_blocking: Option<bool>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let _apply = state.begin_apply();
     let manager = ConfigManager::new()?;
     let mut config = manager.load()?;
 
@@ -389,7 +1023,7 @@ fn update_config(
     manager.save(&config)?;
 
     // Apply the new blocking settings
-    apply_blocking_now()?;
+    apply_blocking_now(state, false)?;
 
     Ok(())
 }
@@ -399,26 +1033,84 @@ fn run_blocking_check_now(state: &Arc<DaemonState>) -> Result<Vec<BlockedProcess
     let manager = ConfigManager::new()?;
     let config = manager.load()?;
 
-    if !crate::scheduler::should_block_now(&config.schedules) {
+    if !crate::scheduler::should_block_with_budget(&config) {
+        charge_budget(state, &config);
         return Ok(Vec::new());
     }
 
-    let blocker = process::create_blocker();
-
-    let mut blocked_set = HashSet::new();
-    if config.game_blocking_enabled {
-        blocked_set.extend(blocking::get_default_gaming_processes());
-    }
-    if config.ai_blocking_enabled {
-        blocked_set.extend(blocking::get_default_ai_processes());
+    let grace = config
+        .kill_grace_ms
+        .map(Duration::from_millis)
+        .unwrap_or(process::DEFAULT_KILL_GRACE);
+    let blocker = process::create_blocker_with_grace(grace);
+
+    let blocked_set = blocking::get_effective_blocked_processes(&config);
+
+    let blocked = blocker.block_processes_with_modes(
+        &blocked_set,
+        &config.allowed_processes,
+        &config.allowed_domains,
+        config.termination_mode,
+        &config.process_termination_modes,
+        false,
+    )?;
+
+    // Games a temporary allowance lets through still use up the budget
+    charge_budget(state, &config);
+
+    let verdicts = record_kills(state, &config, &blocked);
+
+    if !config.hooks.is_empty() && !blocked.is_empty() {
+        let sessions = sessions::list_user_sessions();
+        for process in &blocked {
+            let user = process
+                .uid
+                .and_then(|uid| sessions.iter().find(|s| s.uid == uid))
+                .map(|s| s.user.clone());
+            hooks::dispatch(
+                &config.hooks,
+                HookEvent::ProcessBlocked {
+                    pid: process.pid,
+                    name: process.name.clone(),
+                    user,
+                },
+            );
+        }
     }
-    blocked_set.extend(config.blocked_processes.clone());
 
-    let blocked = blocker.block_processes(&blocked_set, &config.allowed_processes, &config.allowed_domains)?;
-
-    // Update blocked count
-    let count = blocked.len() as u32;
-    state.blocked_count.fetch_add(count, Ordering::Relaxed);
+    // Tell the user why the app disappeared, in the session that was running it
+    if config.show_notifications && !blocked.is_empty() {
+        let sessions = sessions::list_user_sessions();
+        // "Blocking ends at" is read on the household clock, like the schedules
+        let now = crate::scheduler::trusted_now();
+        let schedule_state = crate::scheduler::schedule_state_at(&config, now);
+        let zone = crate::scheduler::zone_offset(None, config.household_timezone.as_deref(), now);
+        let now = now.with_timezone(&zone).time();
+        let reported = blocked
+            .iter()
+            .zip(&verdicts)
+            .filter(|(_, verdict)| **verdict != KillVerdict::Suppress)
+            .map(|(process, _)| process)
+            .filter(|process| blocking::notify::notification_due(&process.name));
+        for process in reported {
+            let message = crate::scheduler::compose_block_message(
+                config.block_message.as_deref(),
+                &process.name,
+                &schedule_state,
+                now,
+            );
+            let owner = process
+                .uid
+                .and_then(|uid| sessions.iter().find(|s| s.uid == uid));
+            match owner {
+                Some(session) => {
+                    info!("Blocked {} in {}'s session", process.name, session.user);
+                    blocking::notify::show_block_message_to_user(message, session.user.clone(), session.uid);
+                }
+                None => blocking::notify::show_block_message(message),
+            }
+        }
+    }
 
     Ok(blocked
         .into_iter()
@@ -429,8 +1121,111 @@ fn run_blocking_check_now(state: &Arc<DaemonState>) -> Result<Vec<BlockedProcess
         .collect())
 }
 
-/// Apply current blocking settings (hosts file, firewall)
-fn apply_blocking_now() -> Result<(), Box<dyn std::error::Error>> {
+/// Count the kills of one check, decide which are worth reporting and let
+/// hooks and subscribers know about them
+fn record_kills(state: &DaemonState, config: &AppConfig, blocked: &[process::ProcessInfo]) -> Vec<KillVerdict> {
+    // Update blocked count
+    let count = blocked.len() as u32;
+    state.blocked_count.fetch_add(count, Ordering::Relaxed);
+
+    // A game that restarts itself is escalated once, then killed quietly
+    let verdicts: Vec<KillVerdict> = {
+        let mut tracker = state.kill_tracker.lock().unwrap_or_else(PoisonError::into_inner);
+        tracker.set_config(config.kill_debounce);
+        let now = Instant::now();
+        // Days are counted on the household clock, like the schedules
+        let today =
+            crate::scheduler::household_date(config.household_timezone.as_deref(), crate::scheduler::trusted_now());
+        blocked.iter().map(|p| tracker.record(&p.name, now, today)).collect()
+    };
+    for (process, verdict) in blocked.iter().zip(&verdicts) {
+        if *verdict != KillVerdict::Suppress {
+            state.events.publish(DaemonEvent::ProcessBlocked {
+                pid: process.pid,
+                name: process.name.clone(),
+            });
+        }
+        match *verdict {
+            KillVerdict::Report => info!("Blocked process: {} (PID: {})", process.name, process.pid),
+            KillVerdict::Escalate { kills } => {
+                warn!(
+                    "{} keeps restarting: killed {} times in {}s, no longer logging each kill",
+                    process.name, kills, config.kill_debounce.window_secs
+                );
+                hooks::dispatch(
+                    &config.hooks,
+                    HookEvent::ProcessRespawning {
+                        name: process.name.clone(),
+                        kills,
+                        window_secs: config.kill_debounce.window_secs,
+                    },
+                );
+            }
+            KillVerdict::Suppress => {}
+        }
+    }
+    verdicts
+}
+
+/// Charge the time since the last check against the daily budget if a
+/// blocked process is running anyway (free schedule window or temporary
+/// allowance). Nothing is charged while no such process runs.
+fn charge_budget(state: &DaemonState, config: &AppConfig) {
+    let Some(budget) = config.time_budget.as_ref() else {
+        return;
+    };
+
+    let now = Instant::now();
+    let elapsed = state
+        .last_budget_charge
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(now)
+        .map(|last| now.duration_since(last).min(MAX_BUDGET_CHARGE));
+    let Some(elapsed) = elapsed else {
+        return;
+    };
+
+    // What would be blocked without allowances is what the budget covers
+    let mut unrestricted = config.clone();
+    unrestricted.category_allowances.clear();
+    let tracked = blocking::get_effective_blocked_processes(&unrestricted);
+    let processes = match process::create_blocker().list_processes() {
+        Ok(processes) => processes,
+        Err(e) => {
+            warn!("Failed to list processes for the time budget: {}", e);
+            return;
+        }
+    };
+    let playing = process::annotate_processes(
+        processes,
+        &tracked,
+        &config.allowed_processes,
+        &config.allowed_domains,
+    )
+    .iter()
+    .any(|p| p.is_blocked);
+    if !playing {
+        return;
+    }
+
+    let path = scheduler::usage_path();
+    let today = scheduler::household_date(config.household_timezone.as_deref(), scheduler::trusted_now());
+    let mut usage = scheduler::load_usage(&path, today);
+    let before = usage.remaining_minutes(budget, today);
+    usage.consume(today, elapsed.as_secs());
+    if let Err(e) = scheduler::save_usage(&path, &usage) {
+        warn!("Failed to save time budget usage: {}", e);
+    }
+    if before > 0 && usage.remaining_minutes(budget, today) == 0 {
+        info!("Daily time budget used up, blocking until midnight");
+    }
+}
+
+/// Apply current blocking settings (hosts file, firewall), reporting layers
+/// that could not be put in place. With `dry_run`, the changes are only logged.
+fn apply_blocking_now(state: &DaemonState, dry_run: bool) -> Result<ApplyReport, Box<dyn std::error::Error>> {
+    let mut report = ApplyReport::new();
     let manager = ConfigManager::new()?;
     let config = manager.load()?;
 
@@ -442,75 +1237,235 @@ fn apply_blocking_now() -> Result<(), Box<dyn std::error::Error>> {
         config.browser_blocking_enabled
     );
 
-    let should_block = crate::scheduler::should_block_now(&config.schedules);
+    let should_block = crate::scheduler::should_block_with_budget(&config);
     info!("Schedule check: should_block={}", should_block);
 
     // Update hosts file
     if !should_block {
-        blocking::unblock_all_domains()?;
-    } else {
-        let mut domains_to_block: HashSet<String> = HashSet::new();
-
-        if config.ai_blocking_enabled {
-            domains_to_block.extend(blocking::get_default_ai_domains());
-        }
-        if config.game_blocking_enabled {
-            domains_to_block.extend(blocking::get_default_gaming_domains());
-        }
-        if config.dns_blocking_enabled {
-            // Custom Websites blocks all domains in the blocklist (defaults + user-added)
-            domains_to_block.extend(blocking::get_default_gaming_domains());
-            domains_to_block.extend(blocking::get_default_ai_domains());
-            domains_to_block.extend(config.blocked_domains.clone());
-        }
-
-        // Remove allowed domains (including subdomains)
-        let domains_to_remove: Vec<String> = domains_to_block
-            .iter()
-            .filter(|domain| {
-                for allowed in &config.allowed_domains {
-                    if *domain == allowed || domain.ends_with(&format!(".{}", allowed)) {
-                        return true;
-                    }
-                }
-                false
-            })
-            .cloned()
-            .collect();
-        for domain in domains_to_remove {
-            domains_to_block.remove(&domain);
+        if dry_run {
+            info!("Dry run: would clear hosts file");
+        } else {
+            blocking::unblock_all_domains()?;
         }
+    } else {
+        let domains_to_block = blocking::get_effective_blocked_domains(&config);
 
         info!("Domains to block: {} total", domains_to_block.len());
 
-        if domains_to_block.is_empty() {
+        if dry_run {
+            info!("Dry run: would write {} domains to hosts file", domains_to_block.len());
+        } else if domains_to_block.is_empty() {
             info!("No domains to block, clearing hosts file");
             blocking::unblock_all_domains()?;
         } else {
             // Use direct write since daemon runs as root
             blocking::block_domains_direct(&domains_to_block)?;
+            report.domains_blocked = domains_to_block.len();
+            check_browser_bypasses(&mut report);
         }
     }
 
+    let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+
     // Apply firewall blocking if needed (Linux only for now)
     #[cfg(target_os = "linux")]
     {
-        let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
-        if should_block && any_blocking {
+        if dry_run {
+            let action = if should_block && any_blocking { "apply" } else { "remove" };
+            info!("Dry run: would {} firewall rules", action);
+        } else if should_block && any_blocking {
             // Enable firewall blocking (direct, no pkexec)
-            let _ = blocking::apply_network_blocking_direct();
-        } else {
-            let _ = blocking::remove_network_blocking_direct();
+            match blocking::apply_network_blocking_direct() {
+                Ok(()) => {
+                    report.firewall_applied = true;
+                    if let Err(e) = blocking::touch_firewall_heartbeat() {
+                        report.warn(WarningCode::HeartbeatFailed, e.to_string());
+                    }
+                }
+                Err(e) => report.warn(WarningCode::FirewallUnavailable, e.to_string()),
+            }
+        } else if let Err(e) = blocking::remove_network_blocking_direct() {
+            report.warn(WarningCode::FirewallCleanupFailed, e.to_string());
         }
     }
 
-    Ok(())
+    // Queries only reach the proxy's patterns and block log through the redirect,
+    // and it must never point at a proxy that isn't listening
+    let redirect = should_block && any_blocking && state.dns_proxy_attached();
+    if dry_run {
+        info!("Dry run: would {} the DNS redirect", if redirect { "install" } else { "remove" });
+    } else if let Err(e) = apply_dns_redirect(redirect) {
+        report.warn(WarningCode::DnsRedirectFailed, e);
+    }
+
+    let router_enabled = config.experimental_upnp_blocking && should_block && config.game_blocking_enabled;
+    if dry_run {
+        info!("Dry run: would {} router blocking", if router_enabled { "apply" } else { "lift" });
+    } else if let Err(e) = apply_router_blocking(router_enabled) {
+        report.warn(WarningCode::RouterUnavailable, e);
+    }
+
+    Ok(report)
+}
+
+/// What a blocking check and `apply_blocking_now` would do right now,
+/// worked out without terminating anything or touching hosts/firewall
+fn preview_blocking_now() -> Result<BlockingPreview, Box<dyn std::error::Error>> {
+    let config = ConfigManager::new()?.load()?;
+    let should_block = crate::scheduler::should_block_with_budget(&config);
+    if !should_block {
+        let mut domains_removed: Vec<String> = blocking::get_blocked_domains().into_iter().collect();
+        domains_removed.sort();
+        return Ok(BlockingPreview {
+            domains_removed,
+            ..BlockingPreview::default()
+        });
+    }
+
+    let blocker = process::create_blocker();
+    let processes = blocker
+        .block_processes_with_modes(
+            &blocking::get_effective_blocked_processes(&config),
+            &config.allowed_processes,
+            &config.allowed_domains,
+            config.termination_mode,
+            &config.process_termination_modes,
+            true,
+        )?
+        .into_iter()
+        .map(|p| BlockedProcessInfo {
+            pid: p.pid,
+            name: p.name,
+        })
+        .collect();
+
+    let wanted = blocking::get_effective_blocked_domains(&config);
+    let listed = blocking::get_blocked_domains();
+    let sorted = |domains: &HashSet<String>, other: &HashSet<String>| {
+        let mut diff: Vec<String> = domains.difference(other).cloned().collect();
+        diff.sort();
+        diff
+    };
+    let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+
+    Ok(BlockingPreview {
+        processes,
+        domains_added: sorted(&wanted, &listed),
+        domains_removed: sorted(&listed, &wanted),
+        firewall: cfg!(target_os = "linux") && any_blocking,
+    })
+}
+
+/// Warn about browsers that can resolve around the hosts file
+fn check_browser_bypasses(report: &mut ApplyReport) {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    for status in blocking::get_user_doh_status() {
+        record_browser_status(report, &status);
+
+        let Some(home) = sessions::home_dir_from_passwd(&passwd, &status.user) else {
+            continue;
+        };
+        for browser in blocking::find_sandboxed_browsers(std::path::Path::new(&home)) {
+            report.warn(
+                WarningCode::BrowserSandboxed,
+                format!("{} for {} ignores system DoH policies", browser, status.user),
+            );
+        }
+    }
+}
+
+/// Note which of a user's browsers have DoH off, and warn about the rest
+fn record_browser_status(report: &mut ApplyReport, status: &blocking::UserDohStatus) {
+    if status.firefox_doh_disabled {
+        report.browsers_configured.push(format!("Firefox for {}", status.user));
+    } else {
+        report.warn(
+            WarningCode::DohStillEnabled,
+            format!("Firefox DoH is still enabled for {}", status.user),
+        );
+    }
+    if status.chromium_doh_disabled {
+        report.browsers_configured.push(format!("Chromium for {}", status.user));
+    } else {
+        report.warn(
+            WarningCode::DohStillEnabled,
+            format!("Chromium DoH policy is missing for {}", status.user),
+        );
+    }
+}
+
+/// Router backend, kept so mappings removed while blocking can be restored later
+static ROUTER_BACKEND: Lazy<Mutex<Option<UpnpBackend>>> = Lazy::new(|| Mutex::new(None));
+
+/// Re-apply router blocking from the loop, as games re-request their mappings
+fn refresh_router_blocking(state: &DaemonState) {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return;
+    };
+    let enabled = config.experimental_upnp_blocking
+        && config.game_blocking_enabled
+        && crate::scheduler::should_block_with_budget(&config);
+
+    let _apply = state.begin_apply();
+    if let Err(e) = apply_router_blocking(enabled) {
+        warn!("Router blocking refresh failed: {}", e);
+    }
+}
+
+/// Send the system's DNS to the proxy, or stop doing so
+fn apply_dns_redirect(enabled: bool) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    use blocking::network::linux as platform;
+    #[cfg(target_os = "macos")]
+    use blocking::network::macos as platform;
+    #[cfg(target_os = "windows")]
+    use blocking::network::windows as platform;
+
+    let result = match enabled {
+        #[cfg(not(target_os = "windows"))]
+        true => platform::setup_dns_redirect(blocking::network::DNS_PROXY_PORT),
+        #[cfg(target_os = "windows")]
+        true => platform::setup_dns_redirect(),
+        false => platform::remove_dns_redirect(),
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Remove (or restore) game port mappings on the router (experimental, opt-in)
+fn apply_router_blocking(enabled: bool) -> Result<(), String> {
+    let mut backend = ROUTER_BACKEND.lock().map_err(|e| e.to_string())?;
+
+    if !enabled {
+        // Mappings saved by an earlier run still need putting back
+        if backend.is_none() && Path::new(upnp::REMOVED_MAPPINGS_PATH).exists() {
+            *backend = Some(UpnpBackend::discover().map_err(|e| e.to_string())?);
+        }
+        if let Some(router) = backend.take() {
+            if let Err(e) = router.remove() {
+                warn!("Failed to restore router port mappings: {}", e);
+            }
+        }
+        return Ok(());
+    }
+
+    if backend.is_none() {
+        *backend = Some(UpnpBackend::discover().map_err(|e| e.to_string())?);
+    }
+
+    match backend.as_ref() {
+        // Games re-request mappings, so this runs on every apply
+        Some(router) => router
+            .apply()
+            .map_err(|e| format!("Failed to apply {} blocking: {}", router.name(), e)),
+        None => Ok(()),
+    }
 }
 
 /// Enable firewall blocking (Linux only)
 #[cfg(target_os = "linux")]
 fn enable_firewall_blocking() -> Result<(), Box<dyn std::error::Error>> {
     blocking::block_doh_providers_direct()?;
+    blocking::touch_firewall_heartbeat()?;
     Ok(())
 }
 
@@ -533,25 +1488,905 @@ fn disable_firewall_blocking() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Re-apply managed browser policies while domain blocking is in effect
+fn refresh_browser_policies() -> Result<(), Box<dyn std::error::Error>> {
+    let manager = ConfigManager::new()?;
+    let config = manager.load()?;
+
+    if !crate::scheduler::should_block_with_budget(&config)
+        || blocking::get_effective_blocked_domains(&config).is_empty()
+    {
+        return Ok(());
+    }
+
+    let applied = blocking::apply_managed_browser_policies(config.block_private_browsing)?;
+    if !applied.is_empty() {
+        info!("Re-applied browser DoH policies: {:?}", applied);
+        hooks::dispatch(
+            &config.hooks,
+            HookEvent::TamperDetected {
+                detail: format!("browser_policy: {}", applied.join(", ")),
+            },
+        );
+    }
+
+    // Managed policies are system-wide; Firefox profiles live in each user's home
+    let sessions = sessions::list_user_sessions();
+    if !sessions.is_empty() {
+        let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+        for session in sessions::unique_users(&sessions) {
+            let Some(home) = sessions::home_dir_from_passwd(&passwd, &session.user) else {
+                continue;
+            };
+            match blocking::disable_firefox_doh_for_home(std::path::Path::new(&home)) {
+                Ok(profiles) if !profiles.is_empty() => {
+                    info!("Disabled Firefox DoH for {}: {:?}", session.user, profiles);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Could not update Firefox profiles for {}: {}", session.user, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-disable DoH in browsers where the setting was removed or changed (a
+/// user.js edit, a Local State flip) and record each re-application in the
+/// audit log
+fn reapply_drifted_browser_doh() -> Result<(), Box<dyn std::error::Error>> {
+    let manager = ConfigManager::new()?;
+    let config = manager.load()?;
+
+    if !crate::scheduler::should_block_with_budget(&config)
+        || blocking::get_effective_blocked_domains(&config).is_empty()
+    {
+        return Ok(());
+    }
+
+    let mut steps = Vec::new();
+
+    let drifted = blocking::verify_doh_disabled();
+    if !drifted.is_empty() {
+        let result = blocking::disable_doh_all_browsers(config.block_private_browsing);
+        steps.extend(drift_steps(&drifted, result.as_ref().err()));
+    }
+
+    let sessions = sessions::list_user_sessions();
+    if !sessions.is_empty() {
+        let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+        for session in sessions::unique_users(&sessions) {
+            let Some(home) = sessions::home_dir_from_passwd(&passwd, &session.user) else {
+                continue;
+            };
+            let home = Path::new(&home);
+            let drifted = blocking::verify_doh_disabled_for_home(home);
+            if drifted.is_empty() {
+                continue;
+            }
+            let result = blocking::disable_doh_for_home(home);
+            let drifted: Vec<String> = drifted.iter().map(|entry| format!("{}: {}", session.user, entry)).collect();
+            steps.extend(drift_steps(&drifted, result.as_ref().err()));
+        }
+    }
+
+    if steps.is_empty() {
+        return Ok(());
+    }
+
+    let detail: Vec<&str> = steps.iter().map(|step| step.step.as_str()).collect();
+    warn!("Browser DoH settings changed, re-applied: {:?}", detail);
+    hooks::dispatch(
+        &config.hooks,
+        HookEvent::TamperDetected {
+            detail: format!("browser_doh: {}", detail.join(", ")),
+        },
+    );
+
+    let record = AuditRecord {
+        timestamp: chrono::Utc::now(),
+        event: "browser_doh_reapplied".to_string(),
+        user: None,
+        steps,
+    };
+    if let Err(e) = recovery::append_audit(&recovery::audit_log_path(), &record) {
+        warn!("Failed to write audit log: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Rewrite the hosts section when it no longer lists exactly the domains that
+/// should be blocked right now
+fn restore_hosts_block(state: &DaemonState) {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return;
+    };
+    if !crate::scheduler::should_block_with_budget(&config) {
+        return;
+    }
+    let expected = blocking::get_effective_blocked_domains(&config);
+    if expected.is_empty() {
+        return;
+    }
+
+    let _apply = state.begin_apply();
+    if blocking::verify_block(&expected) {
+        return;
+    }
+
+    if !blocking::is_blocking_active() {
+        audit::record(TamperEvent::HostsSectionRemoved, None);
+    }
+    warn!("Hosts file was changed outside ParentShield, restoring the blocked domains");
+    if let Err(e) = blocking::block_domains_direct(&expected) {
+        error!("Failed to restore hosts file: {}", e);
+    }
+}
+
+/// Spaces out firewall restores that don't stick, e.g. rules iptables refuses
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default)]
+struct RestoreBackoff {
+    failures: u32,
+    next_attempt: Option<Instant>,
+}
+
+#[cfg(target_os = "linux")]
+impl RestoreBackoff {
+    const MAX_DELAY: Duration = Duration::from_secs(30 * 60);
+
+    fn ready(&self, now: Instant) -> bool {
+        self.next_attempt.is_none_or(|at| now >= at)
+    }
+
+    fn succeeded(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Record a restore that didn't take and return how long to wait before the next
+    fn failed(&mut self, now: Instant) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+        let delay = CHECK_INTERVAL
+            .saturating_mul(1 << self.failures.min(10))
+            .min(Self::MAX_DELAY);
+        self.next_attempt = Some(now + delay);
+        delay
+    }
+}
+
+/// Re-apply the firewall rules if they were flushed while the config calls for them
+#[cfg(target_os = "linux")]
+fn restore_firewall_rules(state: &DaemonState, backoff: &mut RestoreBackoff) {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return;
+    };
+    let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+    if !any_blocking || !crate::scheduler::should_block_with_budget(&config) {
+        return;
+    }
+
+    let now = Instant::now();
+    if !backoff.ready(now) {
+        return;
+    }
+
+    let _apply = state.begin_apply();
+    if blocking::firewall_rules_present() {
+        backoff.succeeded();
+        return;
+    }
+
+    // Only a first miss is tampering; later ones are the same gap persisting
+    if backoff.failures == 0 {
+        audit::record(TamperEvent::FirewallRulesMissing, None);
+        warn!("Firewall rules were removed outside ParentShield, restoring them");
+    }
+    match blocking::apply_network_blocking_direct() {
+        Ok(()) => {
+            if let Err(e) = blocking::touch_firewall_heartbeat() {
+                warn!("Failed to refresh firewall heartbeat: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to restore firewall rules: {}", e),
+    }
+
+    if blocking::firewall_rules_present() {
+        backoff.succeeded();
+    } else {
+        let delay = backoff.failed(now);
+        error!(
+            "Re-applying did not restore the firewall rules (attempt {}), retrying in {}s",
+            backoff.failures,
+            delay.as_secs()
+        );
+    }
+}
+
+/// Restore immutable flags and package holds that were removed, unless the
+/// parent turned uninstall protection off
+fn reassert_uninstall_protection() {
+    let enabled = ConfigManager::new()
+        .and_then(|m| m.load())
+        .is_ok_and(|c| c.uninstall_protection);
+    if !enabled {
+        return;
+    }
+
+    match crate::security::reassert_protection() {
+        Ok(missing) if !missing.is_empty() => warn!("Restored uninstall protection: {}", missing.join(", ")),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to restore uninstall protection: {}", e),
+    }
+}
+
+/// Which protection layers were in place at a tamper check
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ProtectionSnapshot {
+    file_protection: bool,
+    hosts_section: bool,
+    firewall_rules: bool,
+}
+
+impl ProtectionSnapshot {
+    /// Layers that were present last time and are gone now although the
+    /// config still calls for them
+    fn removed_since(&self, previous: &Self, expected: &Self) -> Vec<TamperEvent> {
+        let gone = |layer: fn(&Self) -> bool| layer(previous) && layer(expected) && !layer(self);
+        [
+            (gone(|s| s.file_protection), TamperEvent::ProtectionDisabled),
+            (gone(|s| s.hosts_section), TamperEvent::HostsSectionRemoved),
+            (gone(|s| s.firewall_rules), TamperEvent::FirewallRulesMissing),
+        ]
+        .into_iter()
+        .filter_map(|(gone, event)| gone.then_some(event))
+        .collect()
+    }
+}
+
+/// Record protection that disappeared since the last check in the tamper log
+fn check_for_tampering(previous: &mut ProtectionSnapshot) {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return;
+    };
+    let should_block = crate::scheduler::should_block_with_budget(&config);
+    let any_blocking = config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+
+    let expected = ProtectionSnapshot {
+        file_protection: config.uninstall_protection,
+        hosts_section: should_block && !blocking::get_effective_blocked_domains(&config).is_empty(),
+        firewall_rules: should_block && any_blocking,
+    };
+    let current = ProtectionSnapshot {
+        file_protection: crate::security::file_protection_active(),
+        hosts_section: blocking::is_blocking_active(),
+        firewall_rules: blocking::is_doh_blocked(),
+    };
+
+    for event in current.removed_since(previous, &expected) {
+        warn!("Protection removed outside the app: {:?}", event);
+        audit::record(event, None);
+        hooks::dispatch(
+            &config.hooks,
+            HookEvent::TamperDetected {
+                detail: event.kind().to_string(),
+            },
+        );
+    }
+    *previous = current;
+}
+
+/// One audit step per drifted browser, carrying the re-apply error if any
+fn drift_steps(drifted: &[String], error: Option<&std::io::Error>) -> Vec<RecoveryStep> {
+    drifted
+        .iter()
+        .map(|entry| RecoveryStep {
+            step: entry.clone(),
+            error: error.map(|e| e.to_string()),
+        })
+        .collect()
+}
+
+/// Warn logged-in users about schedules that start blocking soon, once per
+/// upcoming transition
+fn send_block_warnings(
+    schedules: &[crate::config::ScheduleEntry],
+    household_zone: Option<&str>,
+    warned: &mut std::collections::HashSet<(uuid::Uuid, u32)>,
+) {
+    let due = crate::scheduler::block_warnings_now(schedules, household_zone);
+    warned.retain(|key| due.iter().any(|w| (w.schedule_id, w.starts_at) == *key));
+
+    for warning in due {
+        if !warned.insert((warning.schedule_id, warning.starts_at)) {
+            continue;
+        }
+        info!("Schedule warning: {}", warning.message);
+
+        let sessions = sessions::list_user_sessions();
+        if sessions.is_empty() {
+            blocking::notify::show_block_message(warning.message);
+            continue;
+        }
+        for session in sessions::unique_users(&sessions) {
+            blocking::notify::show_block_message_to_user(
+                warning.message.clone(),
+                session.user.clone(),
+                session.uid,
+            );
+        }
+    }
+}
+
+/// Remove stale firewall rules on startup when the config opts into expiry
+#[cfg(target_os = "linux")]
+fn remove_stale_firewall_rules() {
+    let config = match ConfigManager::new().and_then(|m| m.load()) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Skipping stale firewall check, config unavailable: {}", e);
+            return;
+        }
+    };
+
+    let Some(ttl_minutes) = config.firewall_rule_ttl_minutes else {
+        return;
+    };
+
+    let blocking_configured =
+        config.game_blocking_enabled || config.ai_blocking_enabled || config.dns_blocking_enabled;
+
+    match blocking::remove_stale_firewall_rules(ttl_minutes * 60, blocking_configured) {
+        Ok(true) => info!("Removed stale firewall rules"),
+        Ok(false) => {}
+        Err(e) => warn!("Failed to remove stale firewall rules: {}", e),
+    }
+}
+
+/// Categories whose temporary allowance is still running
+fn active_category_allowances() -> Vec<String> {
+    let config = match ConfigManager::new().and_then(|m| m.load()) {
+        Ok(config) => config,
+        Err(_) => return Vec::new(),
+    };
+
+    let now = crate::scheduler::trusted_now();
+    let mut active: Vec<String> = config
+        .category_allowances
+        .into_iter()
+        .filter(|(_, until)| *until > now)
+        .map(|(category, _)| category)
+        .collect();
+    active.sort();
+    active
+}
+
+/// Expiry of the running temporary unlock, if any
+fn active_unlock() -> Option<chrono::DateTime<chrono::Utc>> {
+    let config = ConfigManager::new().and_then(|m| m.load()).ok()?;
+    scheduler::active_unlock_at(config.temporary_unlock.as_ref(), scheduler::trusted_now())
+}
+
+/// Drop a temporary unlock that has already expired from the config
+fn clear_expired_unlock() {
+    let Ok(manager) = ConfigManager::new() else {
+        return;
+    };
+    let Ok(mut config) = manager.load() else {
+        return;
+    };
+    let expired = config
+        .temporary_unlock
+        .is_some_and(|unlock| !unlock.is_active_at(scheduler::trusted_now()));
+    if !expired {
+        return;
+    }
+
+    config.temporary_unlock = None;
+    match manager.save(&config) {
+        Ok(()) => info!("Cleared expired temporary unlock"),
+        Err(e) => warn!("Failed to clear expired temporary unlock: {}", e),
+    }
+}
+
+/// Upstream DNS resolver from the config, or the default if unset or invalid
+fn configured_upstream_dns() -> SocketAddr {
+    let upstream = blocking::network::configured_upstream();
+    info!("Using upstream DNS resolver {}", upstream);
+    upstream
+}
+
+/// Check the system clock against the configured time server so schedules
+/// keep following real time. Without a server, or while it can't be
+/// reached, the last measurement (or the system clock) is used.
+fn sync_trusted_clock() {
+    let Ok(config) = ConfigManager::new().and_then(|m| m.load()) else {
+        return;
+    };
+    let Some(server) = config.trusted_time_server.as_deref() else {
+        return;
+    };
+
+    match scheduler::sync_trusted_clock(server, CLOCK_SYNC_TIMEOUT) {
+        Ok(offset) if offset.num_minutes().abs() >= CLOCK_SKEW_ALERT_MINUTES => {
+            warn!(
+                "System clock is {} minutes off {}; schedules follow the server",
+                offset.num_minutes(),
+                server
+            );
+            hooks::dispatch(
+                &config.hooks,
+                HookEvent::TamperDetected {
+                    detail: format!("clock: {} minutes off {}", offset.num_minutes(), server),
+                },
+            );
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Could not reach time server {}: {}", server, e),
+    }
+}
+
+/// Delete rotated logs that are past their retention
+fn prune_logs() {
+    let settings = ConfigManager::new()
+        .and_then(|m| m.load())
+        .map(|c| c.log_settings)
+        .unwrap_or_default();
+    match logs::prune_rotated(Path::new(LOG_DIR), &settings, SystemTime::now()) {
+        Ok(0) => {}
+        Ok(removed) => info!("Pruned {} old log files", removed),
+        Err(e) => warn!("Failed to prune logs: {}", e),
+    }
+}
+
 /// Background thread that runs blocking checks periodically
 fn run_blocking_loop(state: Arc<DaemonState>) {
     info!("Starting blocking check loop...");
 
+    // Clean up firewall rules a crashed or removed daemon may have left behind
+    #[cfg(target_os = "linux")]
+    remove_stale_firewall_rules();
+
+    // An unlock that ran out while the daemon was down must not linger
+    clear_expired_unlock();
+
     // Apply initial blocking
-    if let Err(e) = apply_blocking_now() {
+    if let Err(e) = state.apply_blocking() {
         error!("Failed to apply initial blocking: {}", e);
     }
 
-    let check_interval = Duration::from_secs(5);
+    let mut last_policy_refresh = Instant::now();
+    prune_logs();
+    let mut last_log_prune = Instant::now();
+    sync_trusted_clock();
+    let mut last_clock_sync = Instant::now();
+    let mut allowed_categories = active_category_allowances();
+    let mut unlocked_until = active_unlock();
+    let mut was_blocking = None;
+    let mut budget_used_up = None;
+    let mut warned = std::collections::HashSet::new();
+    let mut protection = ProtectionSnapshot::default();
+    let mut last_router_refresh = Instant::now();
+    #[cfg(target_os = "linux")]
+    let mut firewall_restore = RestoreBackoff::default();
+    // The DNS proxy loaded the config when it started
+    let mut config_seen = ConfigManager::new().ok().and_then(|m| config_modified(&m));
 
     while state.running.load(Ordering::Relaxed) {
-        std::thread::sleep(check_interval);
+        std::thread::sleep(CHECK_INTERVAL);
+
+        if let Ok(manager) = ConfigManager::new() {
+            reload_if_config_changed(&state, &manager, &mut config_seen);
+        }
+
+        // Let hooks know when a schedule starts or stops blocking, and swap
+        // the DNS proxy's lists to match
+        if let Ok(config) = ConfigManager::new().and_then(|m| m.load()) {
+            let blocking = crate::scheduler::should_block_with_budget(&config);
+            if was_blocking.is_some_and(|was| was != blocking) {
+                hooks::dispatch(&config.hooks, HookEvent::ScheduleChanged { blocking });
+                state.events.publish(DaemonEvent::ScheduleChanged { blocking });
+                let reloaded = ConfigManager::new().map_err(Into::into).and_then(|m| reload_config(&state, &m));
+                if let Err(e) = reloaded {
+                    warn!("Failed to update DNS proxy for schedule change: {}", e);
+                }
+            }
+            was_blocking = Some(blocking);
+
+            // Put domain blocking in place when the budget runs out, and lift
+            // it again when a new day starts
+            let used_up = scheduler::get_remaining_budget(&config) == Some(0);
+            if budget_used_up.is_some_and(|was| was != used_up) {
+                info!("Time budget {}", if used_up { "used up" } else { "available again" });
+                if let Err(e) = state.apply_blocking() {
+                    error!("Failed to re-apply blocking: {}", e);
+                }
+            }
+            budget_used_up = Some(used_up);
+
+            if config.show_notifications && !blocking {
+                send_block_warnings(&config.schedules, config.household_timezone.as_deref(), &mut warned);
+            }
+        }
+
+        if last_clock_sync.elapsed() >= CLOCK_SYNC_INTERVAL {
+            last_clock_sync = Instant::now();
+            sync_trusted_clock();
+        }
+
+        if last_log_prune.elapsed() >= LOG_PRUNE_INTERVAL {
+            last_log_prune = Instant::now();
+            prune_logs();
+        }
+
+        // Re-apply blocking when a temporary category allowance starts or ends
+        let current_allowances = active_category_allowances();
+        if current_allowances != allowed_categories {
+            info!("Category allowances changed: {:?}", current_allowances);
+            allowed_categories = current_allowances;
+            if let Err(e) = state.apply_blocking() {
+                error!("Failed to re-apply blocking: {}", e);
+            }
+        }
+
+        // Re-apply blocking when a temporary unlock starts or expires
+        let current_unlock = active_unlock();
+        if current_unlock != unlocked_until {
+            match current_unlock {
+                Some(until) => info!("Blocking unlocked until {}", until),
+                None => info!("Temporary unlock ended, blocking resumes"),
+            }
+            unlocked_until = current_unlock;
+            if let Err(e) = state.apply_blocking() {
+                error!("Failed to re-apply blocking: {}", e);
+            }
+        }
 
         // Run blocking check
         if let Err(e) = run_blocking_check_now(&state) {
             error!("Blocking check error: {}", e);
         }
+        state.record_check(SystemTime::now());
+
+        // Maintenance windows end on their own so an interrupted update can't
+        // leave protection off
+        let now = Instant::now();
+        let (expired, self_heal) = match state.maintenance.lock() {
+            Ok(mut window) => (window.take_expired(now), window.allows_self_heal(now)),
+            Err(_) => (None, true),
+        };
+        if let Some(restore_file_flags) = expired {
+            resume_protection(&state, restore_file_flags);
+        }
+
+        // Put the hosts section back if it was edited since the last tick
+        if self_heal {
+            restore_hosts_block(&state);
+            #[cfg(target_os = "linux")]
+            restore_firewall_rules(&state, &mut firewall_restore);
+        }
+
+        if self_heal && last_router_refresh.elapsed() >= ROUTER_REFRESH_INTERVAL {
+            last_router_refresh = Instant::now();
+            refresh_router_blocking(&state);
+        }
+
+        // Re-apply browser DoH policies in case they were removed
+        if self_heal && last_policy_refresh.elapsed() >= BROWSER_POLICY_REFRESH_INTERVAL {
+            last_policy_refresh = Instant::now();
+            let _apply = state.begin_apply();
+
+            // Keep the firewall heartbeat fresh while our rules are in place
+            #[cfg(target_os = "linux")]
+            if blocking::is_doh_blocked() {
+                if let Err(e) = blocking::touch_firewall_heartbeat() {
+                    warn!("Failed to refresh firewall heartbeat: {}", e);
+                }
+            }
+
+            if let Err(e) = refresh_browser_policies() {
+                warn!("Browser policy refresh error: {}", e);
+            }
+            if let Err(e) = reapply_drifted_browser_doh() {
+                warn!("Browser DoH verification error: {}", e);
+            }
+            check_for_tampering(&mut protection);
+            reassert_uninstall_protection();
+        }
     }
 
     info!("Blocking check loop stopped");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_status_reads_do_not_wait_for_apply() {
+        let state = Arc::new(DaemonState::new());
+        let (started_tx, started_rx) = mpsc::channel();
+        let (finish_tx, finish_rx) = mpsc::channel::<()>();
+
+        let applier = {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                let _apply = state.begin_apply();
+                started_tx.send(()).unwrap();
+                finish_rx.recv().unwrap();
+            })
+        };
+        started_rx.recv().unwrap();
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        state.blocked_count.fetch_add(1, Ordering::Relaxed);
+                        assert!(state.snapshot().applying);
+                    }
+                })
+            })
+            .collect();
+        // Readers finish while the apply is still holding the lock
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(state.snapshot().blocked_count, 400);
+
+        finish_tx.send(()).unwrap();
+        applier.join().unwrap();
+        assert!(!state.snapshot().applying);
+    }
+
+    #[test]
+    fn test_applies_are_serialized() {
+        let state = Arc::new(DaemonState::new());
+        let inside = Arc::new(AtomicU32::new(0));
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                let inside = Arc::clone(&inside);
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        let _apply = state.begin_apply();
+                        assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+                        std::thread::yield_now();
+                        inside.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_last_check_advances() {
+        let state = DaemonState::new();
+        assert_eq!(state.snapshot().last_check_unix, 0);
+
+        let first = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        state.record_check(first);
+        assert_eq!(state.snapshot().last_check_unix, 1_700_000_000);
+
+        state.record_check(first + CHECK_INTERVAL);
+        assert_eq!(state.snapshot().last_check_unix, 1_700_000_000 + CHECK_INTERVAL.as_secs());
+    }
+
+    #[test]
+    fn test_only_expected_layers_that_vanish_are_tamper() {
+        let all = ProtectionSnapshot {
+            file_protection: true,
+            hosts_section: true,
+            firewall_rules: true,
+        };
+        let hosts_gone = ProtectionSnapshot {
+            hosts_section: false,
+            ..all
+        };
+
+        assert_eq!(hosts_gone.removed_since(&all, &all), vec![TamperEvent::HostsSectionRemoved]);
+
+        // A schedule ending takes the hosts section away legitimately
+        assert!(hosts_gone.removed_since(&all, &hosts_gone).is_empty());
+
+        // A layer that was never seen in place isn't reported missing
+        assert!(hosts_gone.removed_since(&hosts_gone, &all).is_empty());
+    }
+
+    #[test]
+    fn test_reload_picks_up_new_blocked_domain() {
+        let temp = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::in_dir(temp.path());
+        let mut config = manager.initialize(&Zeroizing::new("parent-password".to_string())).unwrap();
+
+        // Set up the way the daemon sets up its proxy at startup
+        let state = DaemonState::new();
+        let proxy = create_dns_proxy(&state, &manager, &temp.path().join("blocked-dns.log")).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        assert!(!runtime.block_on(proxy.should_block("newgame.example")));
+
+        // The GUI saves an edited blocklist, then asks for a reload
+        config.dns_blocking_enabled = true;
+        config.blocked_domains.insert("newgame.example".to_string());
+        manager.save(&config).unwrap();
+        reload_config(&state, &manager).unwrap();
+
+        assert!(runtime.block_on(proxy.should_block("newgame.example")));
+        assert!(runtime.block_on(proxy.should_block("cdn.newgame.example")));
+        assert!(!runtime.block_on(proxy.should_block("example.org")));
+    }
+
+    #[test]
+    fn test_authenticate_counts_towards_lockout() {
+        let temp = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::in_dir(temp.path());
+        let password = Zeroizing::new("parent-password".to_string());
+        manager.initialize(&password).unwrap();
+        let state = DaemonState::new();
+
+        assert!(matches!(
+            authenticate(&state, &manager, &password, None).unwrap(),
+            DaemonResponse::Authenticated { .. }
+        ));
+
+        let wrong = Zeroizing::new("guess".to_string());
+        for _ in 0..crate::security::ratelimit::MAX_FAILURES {
+            assert!(authenticate(&state, &manager, &wrong, None).is_err());
+        }
+        // Locked out: even the right password is refused without being checked
+        let locked = authenticate(&state, &manager, &password, None).unwrap_err();
+        assert!(locked.to_string().contains("Too many failed attempts"), "{}", locked);
+    }
+
+    #[test]
+    fn test_first_sign_in_records_parent_uid() {
+        let temp = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::in_dir(temp.path());
+        let password = Zeroizing::new("parent-password".to_string());
+        // A config from before the parent account was kept
+        let mut config = manager.initialize(&password).unwrap();
+        config.parent_uid = None;
+        manager.save(&config).unwrap();
+        let state = DaemonState::new();
+
+        assert!(authenticate(&state, &manager, &Zeroizing::new("guess".to_string()), Some(1001)).is_err());
+        assert_eq!(manager.load().unwrap().parent_uid, None);
+
+        authenticate(&state, &manager, &password, Some(1000)).unwrap();
+        assert_eq!(manager.load().unwrap().parent_uid, Some(1000));
+        // Later sign-ins don't move it
+        authenticate(&state, &manager, &password, Some(1002)).unwrap();
+        assert_eq!(manager.load().unwrap().parent_uid, Some(1000));
+    }
+
+    #[test]
+    fn test_saved_patterns_reach_proxy() {
+        let temp = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::in_dir(temp.path());
+        let mut config = manager.initialize(&Zeroizing::new("parent-password".to_string())).unwrap();
+
+        let state = DaemonState::new();
+        let proxy = create_dns_proxy(&state, &manager, &temp.path().join("blocked-dns.log")).unwrap();
+        let mut seen = config_modified(&manager);
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        assert!(!runtime.block_on(proxy.should_block("cdn.newgame.example")));
+
+        // The GUI saves a new pattern without asking the daemon to reload
+        config.blocked_domain_patterns.push("*.newgame.example".to_string());
+        manager.save(&config).unwrap();
+        let later = seen.unwrap() + Duration::from_secs(60);
+        std::fs::File::options().write(true).open(manager.config_path()).unwrap().set_modified(later).unwrap();
+        reload_if_config_changed(&state, &manager, &mut seen);
+
+        assert_eq!(seen, Some(later));
+        assert!(runtime.block_on(proxy.should_block("cdn.newgame.example")));
+    }
+
+    #[test]
+    fn test_proxy_forwards_to_configured_upstream() {
+        let temp = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::in_dir(temp.path());
+        manager.initialize(&Zeroizing::new("parent-password".to_string())).unwrap();
+
+        let upstream: SocketAddr = "[2620:fe::fe]:53".parse().unwrap();
+        let state = DaemonState {
+            upstream_dns: upstream,
+            ..DaemonState::new()
+        };
+        let proxy = create_dns_proxy(&state, &manager, &temp.path().join("blocked-dns.log")).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        assert_eq!(runtime.block_on(proxy.upstream()), upstream);
+        assert!(state.dns_proxy.lock().unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_subscriber_sees_blocked_process() {
+        let state = Arc::new(DaemonState::new());
+        let (client, server) = UnixStream::pair().unwrap();
+        let handler = {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || handle_client_unix(server, state))
+        };
+
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        write_message(&mut BufWriter::new(client), &DaemonRequest::Subscribe).unwrap();
+        assert!(matches!(read_message(&mut reader).unwrap(), DaemonResponse::Ok));
+
+        // The kill bookkeeping of a blocking check that found one game
+        let killed = process::ProcessInfo {
+            pid: 4242,
+            name: "steam".to_string(),
+            exe_path: None,
+            uid: None,
+        };
+        record_kills(&state, &AppConfig::default(), &[killed]);
+
+        match read_message(&mut reader).unwrap() {
+            DaemonResponse::Event { event } => assert_eq!(
+                event,
+                DaemonEvent::ProcessBlocked {
+                    pid: 4242,
+                    name: "steam".to_string()
+                }
+            ),
+            other => panic!("expected an event, got {:?}", other),
+        }
+
+        state.running.store(false, Ordering::Relaxed);
+        handler.join().unwrap().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_firewall_restore_backs_off_until_rules_stick() {
+        let start = Instant::now();
+        let mut backoff = RestoreBackoff::default();
+        assert!(backoff.ready(start));
+
+        let first = backoff.failed(start);
+        assert!(!backoff.ready(start + CHECK_INTERVAL));
+        assert!(backoff.ready(start + first));
+        let second = backoff.failed(start);
+        assert!(second > first);
+
+        for _ in 0..40 {
+            backoff.failed(start);
+        }
+        assert!(backoff.ready(start + RestoreBackoff::MAX_DELAY));
+
+        backoff.succeeded();
+        assert!(backoff.ready(start));
+        assert_eq!(backoff.failures, 0);
+    }
+
+    #[test]
+    fn test_report_counts_match_browser_status() {
+        let status = |user: &str, firefox: bool, chromium: bool| blocking::UserDohStatus {
+            user: user.to_string(),
+            firefox_doh_disabled: firefox,
+            chromium_doh_disabled: chromium,
+            private_browsing_disabled: false,
+        };
+        let statuses = [status("alice", true, true), status("bob", true, false), status("carol", false, false)];
+
+        let mut report = ApplyReport::new();
+        for status in &statuses {
+            record_browser_status(&mut report, status);
+        }
+
+        assert_eq!(
+            report.browsers_configured,
+            vec!["Firefox for alice", "Chromium for alice", "Firefox for bob"]
+        );
+        assert_eq!(report.warnings.len(), 3);
+        assert!(report.warnings.iter().all(|w| w.code == WarningCode::DohStillEnabled));
+    }
+}
@@ -1,5 +1,6 @@
 //! Cross-platform service management for background daemon.
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,6 +11,12 @@ pub enum ServiceError {
     RemoveFailed(String),
     #[error("Service control failed: {0}")]
     ControlFailed(String),
+    #[error("The service refuses to be stopped directly; uninstall it with the parent password instead")]
+    ManualStopRefused,
+    #[error("The ParentShield service is already installed under another definition; uninstall it first")]
+    AlreadyInstalled,
+    #[error("The old ParentShield service is still being removed; restart the computer and install again")]
+    PendingDeletion,
     #[error("Platform not supported")]
     NotSupported,
 }
@@ -23,10 +30,22 @@ pub enum ServiceStatus {
     Unknown,
 }
 
+/// What an install call did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallOutcome {
+    /// Service did not exist and was created
+    Installed,
+    /// Service existed with stale settings and was updated
+    Upgraded,
+    /// Service already existed with the current settings
+    Unchanged,
+}
+
 /// Service manager trait for cross-platform implementation
 pub trait ServiceManager {
-    /// Install the service
-    fn install(&self) -> Result<(), ServiceError>;
+    /// Install the service, or upgrade an existing installation in place
+    fn install(&self) -> Result<InstallOutcome, ServiceError>;
 
     /// Uninstall the service
     fn uninstall(&self) -> Result<(), ServiceError>;
@@ -71,7 +90,7 @@ struct StubServiceManager;
 
 #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
 impl ServiceManager for StubServiceManager {
-    fn install(&self) -> Result<(), ServiceError> {
+    fn install(&self) -> Result<InstallOutcome, ServiceError> {
         Err(ServiceError::NotSupported)
     }
 
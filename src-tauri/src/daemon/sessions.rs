@@ -0,0 +1,142 @@
+//! Enumeration of logged-in user sessions on multi-seat Linux machines.
+
+use std::collections::HashSet;
+
+/// A logged-in user session as reported by logind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserSession {
+    pub id: String,
+    pub uid: u32,
+    pub user: String,
+    pub seat: Option<String>,
+    pub tty: Option<String>,
+}
+
+/// Parse `loginctl list-sessions --no-legend` output.
+///
+/// Columns are `SESSION UID USER [SEAT] [TTY] ...`; newer systemd versions
+/// append `STATE IDLE SINCE`, and sessions without a seat leave it blank.
+pub fn parse_loginctl_sessions(output: &str) -> Vec<UserSession> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let id = fields.next()?.to_string();
+            let uid = fields.next()?.parse().ok()?;
+            let user = fields.next()?.to_string();
+
+            let rest: Vec<&str> = fields.collect();
+            let seat = rest.iter().find(|f| f.starts_with("seat")).map(|s| s.to_string());
+            let tty = rest
+                .iter()
+                .find(|f| f.starts_with("tty") || f.starts_with("pts/"))
+                .map(|s| s.to_string());
+
+            Some(UserSession {
+                id,
+                uid,
+                user,
+                seat,
+                tty,
+            })
+        })
+        .collect()
+}
+
+/// List sessions of real users (system accounts such as gdm are skipped)
+#[cfg(target_os = "linux")]
+pub fn list_user_sessions() -> Vec<UserSession> {
+    let output = match std::process::Command::new("loginctl")
+        .args(["list-sessions", "--no-legend"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    parse_loginctl_sessions(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|s| s.uid >= 1000 && s.uid != 65534)
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn list_user_sessions() -> Vec<UserSession> {
+    Vec::new()
+}
+
+/// One session per user, preferring the one attached to a seat
+pub fn unique_users(sessions: &[UserSession]) -> Vec<&UserSession> {
+    let mut seen = HashSet::new();
+    let mut ordered: Vec<&UserSession> = sessions.iter().filter(|s| s.seat.is_some()).collect();
+    ordered.extend(sessions.iter().filter(|s| s.seat.is_none()));
+
+    ordered.into_iter().filter(|s| seen.insert(s.uid)).collect()
+}
+
+/// Look up a user's home directory in passwd-format content
+pub fn home_dir_from_passwd(passwd: &str, user: &str) -> Option<String> {
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() >= 6 && fields[0] == user {
+            Some(fields[5].to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_loginctl_sessions() {
+        let output = "\
+      2 1000 alice seat0 tty2
+     c3 1001 bob   seat1
+     14 1000 alice       pts/1
+";
+        let sessions = parse_loginctl_sessions(output);
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(
+            sessions[0],
+            UserSession {
+                id: "2".to_string(),
+                uid: 1000,
+                user: "alice".to_string(),
+                seat: Some("seat0".to_string()),
+                tty: Some("tty2".to_string()),
+            }
+        );
+        assert_eq!(sessions[1].seat.as_deref(), Some("seat1"));
+        assert_eq!(sessions[1].tty, None);
+        assert_eq!(sessions[2].seat, None);
+        assert_eq!(sessions[2].tty.as_deref(), Some("pts/1"));
+
+        let users = unique_users(&sessions);
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].id, "2");
+    }
+
+    #[test]
+    fn test_parse_loginctl_sessions_with_state_columns() {
+        let output = "     3 1000 alice seat0 tty2 active no -\n   126  120 gdm   seat0 tty1 online yes 2h ago\n";
+        let sessions = parse_loginctl_sessions(output);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].user, "alice");
+        assert_eq!(sessions[1].uid, 120);
+    }
+
+    #[test]
+    fn test_parse_loginctl_skips_garbage() {
+        assert!(parse_loginctl_sessions("No sessions.\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_home_dir_from_passwd() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000:Alice:/home/alice:/bin/zsh\n";
+        assert_eq!(home_dir_from_passwd(passwd, "alice").as_deref(), Some("/home/alice"));
+        assert_eq!(home_dir_from_passwd(passwd, "bob"), None);
+    }
+}
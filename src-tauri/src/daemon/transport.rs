@@ -0,0 +1,253 @@
+//! How the GUI reaches the daemon: a Unix socket on Linux/macOS, a named
+//! pipe on Windows. Both carry the same length-prefixed messages
+//! (`ipc::read_message`/`write_message`), so the client only needs a
+//! connected stream from the platform's [`Transport`].
+
+use crate::daemon::client::DaemonClientError;
+use crate::daemon::ipc::{read_message, write_message, DaemonRequest, DaemonResponse};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+/// Longest the client waits for the daemon's response
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest the client waits to hand over a request
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A way to open a connection to the daemon
+pub trait Transport {
+    type Stream: Read + Write;
+
+    /// Connect, mapping failures to the client's error kinds
+    fn connect(&self) -> Result<Self::Stream, DaemonClientError>;
+
+    /// A second handle on the same connection, so reading and writing can be buffered separately
+    fn try_clone(stream: &Self::Stream) -> io::Result<Self::Stream>;
+
+    /// Let reads wait indefinitely, for connections that sit idle between messages
+    fn clear_read_timeout(stream: &Self::Stream) -> io::Result<()>;
+}
+
+/// Send one request over a fresh connection and read the response
+pub fn round_trip<T: Transport>(transport: &T, request: &DaemonRequest) -> Result<DaemonResponse, DaemonClientError> {
+    let stream = transport.connect()?;
+    let mut reader =
+        BufReader::new(T::try_clone(&stream).map_err(|e| DaemonClientError::ConnectionFailed(e.to_string()))?);
+    let mut writer = BufWriter::new(stream);
+
+    write_message(&mut writer, request).map_err(|e| DaemonClientError::SendFailed(e.to_string()))?;
+    read_message(&mut reader).map_err(|e| DaemonClientError::ReceiveFailed(e.to_string()))
+}
+
+/// Send `request` and pass every message the daemon sends back to
+/// `on_message` until it returns false or the daemon closes the connection
+pub fn stream<T: Transport>(
+    transport: &T,
+    request: &DaemonRequest,
+    mut on_message: impl FnMut(DaemonResponse) -> bool,
+) -> Result<(), DaemonClientError> {
+    let stream = transport.connect()?;
+    T::clear_read_timeout(&stream).map_err(|e| DaemonClientError::ConnectionFailed(e.to_string()))?;
+    let mut reader =
+        BufReader::new(T::try_clone(&stream).map_err(|e| DaemonClientError::ConnectionFailed(e.to_string()))?);
+    let mut writer = BufWriter::new(stream);
+
+    write_message(&mut writer, request).map_err(|e| DaemonClientError::SendFailed(e.to_string()))?;
+    loop {
+        match read_message(&mut reader) {
+            Ok(response) => {
+                if !on_message(response) {
+                    return Ok(());
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(DaemonClientError::ReceiveFailed(e.to_string())),
+        }
+    }
+}
+
+/// The daemon's Unix socket, waiting with backoff (delays in ms) while it isn't there yet
+#[cfg(unix)]
+pub struct UnixSocket {
+    pub path: PathBuf,
+    pub retry_delays: &'static [u64],
+}
+
+#[cfg(unix)]
+impl Transport for UnixSocket {
+    type Stream = UnixStream;
+
+    fn connect(&self) -> Result<UnixStream, DaemonClientError> {
+        let mut delays = self.retry_delays.iter();
+
+        loop {
+            let err = match UnixStream::connect(&self.path) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(READ_TIMEOUT)).ok();
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok();
+                    return Ok(stream);
+                }
+                Err(e) => classify_connect_error(e),
+            };
+
+            match (&err, delays.next()) {
+                (DaemonClientError::SocketNotReady, Some(&delay)) => {
+                    tracing::debug!("Daemon socket not ready, retrying in {}ms", delay);
+                    std::thread::sleep(Duration::from_millis(delay));
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+
+    fn try_clone(stream: &UnixStream) -> io::Result<UnixStream> {
+        stream.try_clone()
+    }
+
+    fn clear_read_timeout(stream: &UnixStream) -> io::Result<()> {
+        stream.set_read_timeout(None)
+    }
+}
+
+/// Map a socket connection error to a client error
+#[cfg(unix)]
+fn classify_connect_error(e: io::Error) -> DaemonClientError {
+    match e.kind() {
+        // Socket file (or its runtime directory) doesn't exist yet
+        io::ErrorKind::NotFound => DaemonClientError::SocketNotReady,
+        // Socket exists but nothing is listening on it
+        io::ErrorKind::ConnectionRefused => DaemonClientError::DaemonNotRunning,
+        _ => DaemonClientError::ConnectionFailed(e.to_string()),
+    }
+}
+
+/// A named pipe served by the daemon. Pipes have no read/write timeouts;
+/// the daemon answers each request on its own thread.
+#[cfg(windows)]
+pub struct NamedPipe {
+    pub name: String,
+    /// Delays (ms) between attempts while every pipe instance is busy
+    pub retry_delays: &'static [u64],
+}
+
+#[cfg(windows)]
+impl Transport for NamedPipe {
+    type Stream = std::fs::File;
+
+    fn connect(&self) -> Result<std::fs::File, DaemonClientError> {
+        // All instances are serving other clients; the daemon creates a new one shortly
+        const ERROR_PIPE_BUSY: i32 = 231;
+        let mut delays = self.retry_delays.iter();
+
+        loop {
+            let err = match std::fs::OpenOptions::new().read(true).write(true).open(&self.name) {
+                Ok(pipe) => return Ok(pipe),
+                Err(e) => e,
+            };
+
+            match (err.raw_os_error(), delays.next()) {
+                (Some(ERROR_PIPE_BUSY), Some(&delay)) => {
+                    tracing::debug!("Daemon pipe busy, retrying in {}ms", delay);
+                    std::thread::sleep(Duration::from_millis(delay));
+                }
+                _ if err.kind() == io::ErrorKind::NotFound => return Err(DaemonClientError::DaemonNotRunning),
+                _ => return Err(DaemonClientError::ConnectionFailed(err.to_string())),
+            }
+        }
+    }
+
+    fn try_clone(stream: &std::fs::File) -> io::Result<std::fs::File> {
+        stream.try_clone()
+    }
+
+    fn clear_read_timeout(_stream: &std::fs::File) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Create one instance of the pipe `name` and wait for a client to connect to it
+#[cfg(windows)]
+pub fn accept_pipe_client(name: &str) -> io::Result<std::fs::File> {
+    use std::os::windows::io::FromRawHandle;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{CloseHandle, GetLastError, ERROR_PIPE_CONNECTED};
+    use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_MESSAGE, PIPE_TYPE_MESSAGE, PIPE_UNLIMITED_INSTANCES,
+        PIPE_WAIT,
+    };
+
+    let wide: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            PCWSTR(wide.as_ptr()),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            None,
+        )
+    };
+    if pipe.is_invalid() {
+        return Err(io::Error::last_os_error());
+    }
+
+    // A client that opened the pipe between create and connect is already connected
+    let connected = unsafe { ConnectNamedPipe(pipe, None) };
+    if connected.is_err() && unsafe { GetLastError() } != ERROR_PIPE_CONNECTED {
+        let err = io::Error::last_os_error();
+        unsafe {
+            let _ = CloseHandle(pipe);
+        }
+        return Err(err);
+    }
+
+    Ok(unsafe { std::fs::File::from_raw_handle(pipe.0 as *mut std::ffi::c_void) })
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_pipe_round_trip() {
+        let name = format!(r"\\.\pipe\parentshield-test-{}", std::process::id());
+        let server = {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                let pipe = accept_pipe_client(&name).unwrap();
+                let mut reader = BufReader::new(pipe.try_clone().unwrap());
+                let request: DaemonRequest = read_message(&mut reader).unwrap();
+                assert!(matches!(request, DaemonRequest::Ping));
+                write_message(&mut BufWriter::new(pipe), &DaemonResponse::Pong).unwrap();
+            })
+        };
+
+        // The server may not have created the pipe yet
+        let transport = NamedPipe {
+            name,
+            retry_delays: &[],
+        };
+        let mut response = None;
+        for _ in 0..50 {
+            match round_trip(&transport, &DaemonRequest::Ping) {
+                Ok(r) => {
+                    response = Some(r);
+                    break;
+                }
+                Err(DaemonClientError::DaemonNotRunning) => std::thread::sleep(Duration::from_millis(20)),
+                Err(e) => panic!("round trip failed: {}", e),
+            }
+        }
+
+        assert!(matches!(response, Some(DaemonResponse::Pong)));
+        server.join().unwrap();
+    }
+}
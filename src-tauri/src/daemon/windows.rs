@@ -1,6 +1,6 @@
 //! Windows service management.
 
-use super::{ServiceError, ServiceManager, ServiceStatus};
+use super::{InstallOutcome, ServiceError, ServiceManager, ServiceStatus};
 use std::process::Command;
 
 const SERVICE_NAME: &str = "ParentShield";
@@ -19,34 +19,137 @@ impl WindowsServiceManager {
     }
 }
 
+/// Extract the BINARY_PATH_NAME value from `sc qc` output
+fn parse_sc_binary_path(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim() == "BINARY_PATH_NAME" {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Typed error for an sc.exe failure the user can act on, from its output.
+/// sc.exe prints failures to stdout as `[SC] <Call> FAILED <code>:` and a message.
+fn classify_sc_failure(output: &str) -> Option<ServiceError> {
+    // ERROR_SERVICE_MARKED_FOR_DELETE: gone only once every handle closes, often after a reboot
+    if output.contains("FAILED 1072") || output.contains("marked for deletion") {
+        Some(ServiceError::PendingDeletion)
+    } else if output.contains("FAILED 1073") || output.contains("already exists") {
+        // ERROR_SERVICE_EXISTS
+        Some(ServiceError::AlreadyInstalled)
+    } else {
+        None
+    }
+}
+
+/// Error for a failed sc.exe install step
+fn sc_install_error(output: &std::process::Output) -> ServiceError {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    classify_sc_failure(&stdout).or_else(|| classify_sc_failure(&stderr)).unwrap_or_else(|| {
+        let message = format!("{}\n{}", stdout.trim(), stderr.trim());
+        ServiceError::InstallFailed(message.trim().to_string())
+    })
+}
+
+/// Service command line for `exe_path`. The exe is quoted so a path with
+/// spaces isn't read as `C:\Program` plus arguments.
+fn service_binary_path(exe_path: &str) -> String {
+    format!("\"{}\" --daemon", exe_path)
+}
+
+/// sc.exe takes each `name= value` option as two arguments, the name with its
+/// `=` and then the value. Passed as one argument, the quotes around the exe
+/// get escaped again and end up inside the stored path.
+fn sc_option<'a>(name: &'a str, value: &'a str) -> [&'a str; 2] {
+    [name, value]
+}
+
+impl WindowsServiceManager {
+    fn binary_path(&self) -> String {
+        service_binary_path(&self.exe_path)
+    }
+
+    /// Read the service back and check sc.exe stored the command line we gave it
+    fn verify_binary_path(&self, expected: &str) -> Result<(), ServiceError> {
+        match self.installed_binary_path() {
+            Some(actual) if actual == expected => Ok(()),
+            Some(actual) => Err(ServiceError::InstallFailed(format!(
+                "Service binary path is {} instead of {}",
+                actual, expected
+            ))),
+            None => Err(ServiceError::InstallFailed(
+                "Service not found after installing it".to_string(),
+            )),
+        }
+    }
+
+    /// Binary path of the installed service, if it exists
+    fn installed_binary_path(&self) -> Option<String> {
+        let output = Command::new("sc.exe")
+            .args(["qc", SERVICE_NAME])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        parse_sc_binary_path(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
 impl ServiceManager for WindowsServiceManager {
-    fn install(&self) -> Result<(), ServiceError> {
+    fn install(&self) -> Result<InstallOutcome, ServiceError> {
+        let bin_path = self.binary_path();
+
+        if let Some(existing) = self.installed_binary_path() {
+            if existing == bin_path {
+                tracing::info!("ParentShield Windows service already installed");
+                return Ok(InstallOutcome::Unchanged);
+            }
+
+            // Point the existing service at the current executable
+            let output = Command::new("sc.exe")
+                .args(["config", SERVICE_NAME])
+                .args(sc_option("binPath=", &bin_path))
+                .output()
+                .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
+
+            if !output.status.success() {
+                return Err(sc_install_error(&output));
+            }
+            self.verify_binary_path(&bin_path)?;
+
+            let _ = self.stop();
+            self.start()?;
+
+            tracing::info!("ParentShield Windows service upgraded");
+            return Ok(InstallOutcome::Upgraded);
+        }
+
         // Create Windows service using sc.exe
         let output = Command::new("sc.exe")
-            .args([
-                "create",
-                SERVICE_NAME,
-                &format!("binPath= \"{}\" --daemon", self.exe_path),
-                "start= auto",
-                "DisplayName= ParentShield Parental Control",
-            ])
+            .args(["create", SERVICE_NAME])
+            .args(sc_option("binPath=", &bin_path))
+            .args(sc_option("start=", "auto"))
+            .args(sc_option("DisplayName=", "ParentShield Parental Control"))
             .output()
             .map_err(|e| ServiceError::InstallFailed(e.to_string()))?;
 
         if !output.status.success() {
-            return Err(ServiceError::InstallFailed(
-                String::from_utf8_lossy(&output.stderr).to_string(),
-            ));
+            return Err(sc_install_error(&output));
         }
+        self.verify_binary_path(&bin_path)?;
 
         // Configure recovery options (restart on failure)
         let _ = Command::new("sc.exe")
-            .args([
-                "failure",
-                SERVICE_NAME,
-                "reset= 86400",
-                "actions= restart/5000/restart/10000/restart/30000",
-            ])
+            .args(["failure", SERVICE_NAME])
+            .args(sc_option("reset=", "86400"))
+            .args(sc_option("actions=", "restart/5000/restart/10000/restart/30000"))
             .output();
 
         // Set description
@@ -59,7 +162,7 @@ impl ServiceManager for WindowsServiceManager {
             .output();
 
         tracing::info!("ParentShield Windows service installed");
-        Ok(())
+        Ok(InstallOutcome::Installed)
     }
 
     fn uninstall(&self) -> Result<(), ServiceError> {
@@ -147,3 +250,42 @@ impl ServiceManager for WindowsServiceManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sc_binary_path() {
+        let output = "[SC] QueryServiceConfig SUCCESS\r\n\r\nSERVICE_NAME: ParentShield\r\n        TYPE               : 10  WIN32_OWN_PROCESS\r\n        BINARY_PATH_NAME   : \"C:\\Old\\parentshield.exe\" --daemon\r\n";
+        assert_eq!(
+            parse_sc_binary_path(output).as_deref(),
+            Some("\"C:\\Old\\parentshield.exe\" --daemon")
+        );
+        assert_eq!(parse_sc_binary_path("SERVICE_NAME: ParentShield"), None);
+    }
+
+    #[test]
+    fn test_classify_sc_failure() {
+        let exists = "[SC] CreateService FAILED 1073:\r\n\r\nThe specified service already exists.\r\n";
+        assert!(matches!(classify_sc_failure(exists), Some(ServiceError::AlreadyInstalled)));
+
+        let deleting = "[SC] ChangeServiceConfig FAILED 1072:\r\n\r\n\
+            The specified service has been marked for deletion.\r\n";
+        assert!(matches!(classify_sc_failure(deleting), Some(ServiceError::PendingDeletion)));
+
+        let denied = "[SC] OpenSCManager FAILED 5:\r\n\r\nAccess is denied.\r\n";
+        assert!(classify_sc_failure(denied).is_none());
+    }
+
+    #[test]
+    fn test_binary_path_with_spaces() {
+        let bin_path = service_binary_path("C:\\Program Files\\ParentShield\\parentshield.exe");
+        assert_eq!(bin_path, "\"C:\\Program Files\\ParentShield\\parentshield.exe\" --daemon");
+        assert_eq!(sc_option("binPath=", &bin_path), ["binPath=", bin_path.as_str()]);
+
+        // What `sc qc` reports for it compares equal, so reinstalling is a no-op
+        let output = format!("SERVICE_NAME: ParentShield\r\n        BINARY_PATH_NAME   : {}\r\n", bin_path);
+        assert_eq!(parse_sc_binary_path(&output), Some(bin_path));
+    }
+}
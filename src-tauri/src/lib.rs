@@ -8,7 +8,7 @@ pub mod scheduler;
 pub mod security;
 
 use commands::{
-    auth::*, blocking::*, blocklist::*, daemon::*, license::*, schedule::*,
+    auth::*, blocking::*, blocklist::*, daemon::*, license::*, schedule::*, settings::*,
 };
 use daemon::service::{get_service_manager, ServiceStatus};
 use tauri::{
@@ -153,6 +153,22 @@ pub fn run() {
                 }
             });
 
+            // Forward live daemon events to the frontend, reconnecting while
+            // the daemon is down or restarting
+            let app_handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                let result = daemon::client::subscribe(|event| {
+                    if let Some(window) = app_handle.get_webview_window("main") {
+                        let _ = window.emit("daemon-event", &event);
+                    }
+                    true
+                });
+                if let Err(e) = result {
+                    tracing::debug!("Daemon event subscription ended: {}", e);
+                }
+                std::thread::sleep(std::time::Duration::from_secs(10));
+            });
+
             // Ensure daemon is running on app startup
             std::thread::spawn(|| {
                 ensure_daemon_running();
@@ -178,9 +194,13 @@ pub fn run() {
             get_auth_status,
             setup_password,
             verify_password,
+            logout,
             change_password,
             reset_with_master,
             get_master_password,
+            set_master_password_format,
+            rotate_master_password,
+            get_machine_fingerprint,
             quit_with_password,
             force_quit_unconfigured,
             enable_uninstall_protection,
@@ -191,31 +211,67 @@ pub fn run() {
             set_game_blocking,
             set_ai_blocking,
             set_dns_blocking,
+            set_all_blocking,
             set_browser_blocking,
+            set_block_message,
+            allow_category_temporarily,
+            grant_temporary_unlock,
+            get_active_unlock,
             run_blocking_check,
             list_processes,
+            list_processes_annotated,
             apply_blocking,
+            restore_hosts_backup,
             disable_browser_doh,
             enable_browser_doh,
             is_doh_disabled,
+            is_private_browsing_disabled,
             enable_firewall_blocking,
             disable_firewall_blocking,
             is_firewall_blocking_active,
+            get_blocked_doh_ips,
+            get_capabilities,
+            audit_bypass_risks,
             // Schedule commands
             get_schedules,
             add_schedule,
             update_schedule,
             delete_schedule,
             add_preset_schedule,
+            validate_schedules,
+            diff_config,
+            export_config,
+            import_config,
+            list_date_exceptions,
+            add_date_exception,
+            remove_date_exception,
             should_block_now,
+            get_remaining_budget,
+            why_blocking,
             // Blocklist commands
             get_blocklists,
+            set_category_blocking,
             add_blocked_process,
             remove_blocked_process,
+            set_termination_mode,
             add_blocked_domain,
             remove_blocked_domain,
             add_to_whitelist,
             remove_from_whitelist,
+            add_doh_block_ip,
+            remove_doh_block_ip,
+            add_vpn_block,
+            remove_vpn_block,
+            export_hosts,
+            import_blocklist,
+            import_blocklist_from_url,
+            import_blocklist_from_text,
+            add_blocklist_subscription,
+            remove_blocklist_subscription,
+            refresh_subscriptions_now,
+            clear_imported_blocklist,
+            clear_all_imported,
+            test_coverage,
             // Daemon commands
             is_daemon_installed,
             is_daemon_running,
@@ -227,8 +283,18 @@ pub fn run() {
             daemon_update_config,
             daemon_run_blocking_check,
             daemon_apply_blocking,
+            daemon_reload_config,
+            daemon_preview_blocking,
             daemon_enable_firewall,
             daemon_disable_firewall,
+            daemon_apply_browser_policies,
+            daemon_enter_maintenance,
+            daemon_exit_maintenance,
+            diagnose_daemon_connection,
+            purge_logs,
+            get_recent_blocks,
+            get_tamper_log,
+            get_dns_metrics,
             // License commands
             platform_login,
             platform_logout,
@@ -254,7 +320,12 @@ fn ensure_daemon_running() {
     let status = manager.status();
     match status {
         ServiceStatus::Running => {
-            tracing::info!("Daemon is already running");
+            let diag = daemon::client::diagnose_daemon_connection();
+            if diag.is_healthy() {
+                tracing::info!("Daemon is already running");
+            } else {
+                tracing::warn!("Daemon is running but not reachable: {:?}", diag);
+            }
         }
         ServiceStatus::Stopped => {
             tracing::info!("Daemon is stopped, attempting to start...");
@@ -0,0 +1,258 @@
+//! Daily time budget for games.
+//! Instead of (or on top of) fixed schedule windows, games may run for a
+//! number of minutes per calendar day. The daemon charges time while a
+//! blocked process is allowed to run and starts blocking once the budget is
+//! used up. Usage is kept in a small file in the daemon's root-owned state
+//! directory so neither a reboot nor the child can hand out a fresh budget;
+//! it starts over at midnight on the household clock.
+
+use crate::config::{AppConfig, ConfigError};
+use super::clock::household_date;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Usage record, written only by the daemon
+#[cfg(unix)]
+const USAGE_PATH: &str = "/var/lib/parentshield/budget.json";
+#[cfg(windows)]
+const USAGE_PATH: &str = r"C:\ProgramData\ParentShield\budget.json";
+
+/// Minutes of games allowed per day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeBudget {
+    /// Minutes allowed on days without an override
+    pub daily_minutes: u16,
+    /// Minutes per weekday, Sunday first; replaces `daily_minutes` when set
+    #[serde(default)]
+    pub per_weekday: Option<[u16; 7]>,
+}
+
+impl TimeBudget {
+    /// Minutes allowed on `date`
+    pub fn minutes_for(&self, date: NaiveDate) -> u16 {
+        match self.per_weekday {
+            Some(days) => days[date.weekday().num_days_from_sunday() as usize],
+            None => self.daily_minutes,
+        }
+    }
+}
+
+/// Time charged against the budget on one day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub date: NaiveDate,
+    pub used_seconds: u64,
+}
+
+impl BudgetUsage {
+    pub fn new(date: NaiveDate) -> Self {
+        Self { date, used_seconds: 0 }
+    }
+
+    /// The whole of `date`'s budget already used
+    pub fn spent(date: NaiveDate) -> Self {
+        Self {
+            date,
+            used_seconds: u64::MAX,
+        }
+    }
+
+    /// Start over if `today` is a new day
+    fn roll_over(&mut self, today: NaiveDate) {
+        if self.date != today {
+            *self = Self::new(today);
+        }
+    }
+
+    /// Charge `seconds` of play on `today`
+    pub fn consume(&mut self, today: NaiveDate, seconds: u64) {
+        self.roll_over(today);
+        self.used_seconds = self.used_seconds.saturating_add(seconds);
+    }
+
+    /// Whole minutes left of `budget` on `today`
+    pub fn remaining_minutes(&self, budget: &TimeBudget, today: NaiveDate) -> u16 {
+        let used = if self.date == today { self.used_seconds } else { 0 };
+        let allowed = budget.minutes_for(today) as u64 * 60;
+        (allowed.saturating_sub(used) / 60) as u16
+    }
+}
+
+/// Where usage is persisted: the daemon's state directory
+pub fn usage_path() -> PathBuf {
+    PathBuf::from(USAGE_PATH)
+}
+
+/// Read usage from `path`. A missing file counts as nothing used today, but
+/// one that exists and can't be read counts as the budget already spent.
+pub fn load_usage(path: &Path, today: NaiveDate) -> BudgetUsage {
+    let mut usage = match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|_| BudgetUsage::spent(today)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => BudgetUsage::new(today),
+        Err(_) => BudgetUsage::spent(today),
+    };
+    usage.roll_over(today);
+    usage
+}
+
+/// Write usage to `path`, creating its directory if needed
+pub fn save_usage(path: &Path, usage: &BudgetUsage) -> Result<(), ConfigError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_vec(usage)?)?;
+    Ok(())
+}
+
+/// Minutes left today, or `None` when no budget is configured
+pub fn get_remaining_budget(config: &AppConfig) -> Option<u16> {
+    let budget = config.time_budget.as_ref()?;
+    let today = household_date(config.household_timezone.as_deref(), super::trusted_now());
+    let usage = load_usage(&usage_path(), today);
+    Some(usage.remaining_minutes(budget, today))
+}
+
+/// Whether blocking is active given the calendar's decision (see
+/// [`super::scheduled_blocking`]) and the minutes left of the budget. Without
+/// a decision blocking defaults to on, except that with a budget and no
+/// schedules games are allowed whenever there is budget left. A used-up
+/// budget blocks even on an allowed date.
+pub fn should_block_with(decision: Option<bool>, has_schedules: bool, remaining: Option<u16>) -> bool {
+    match remaining {
+        None => decision.unwrap_or(true),
+        Some(0) => true,
+        Some(_) => decision.unwrap_or(has_schedules),
+    }
+}
+
+/// Check if blocking should be active, taking a temporary unlock, date
+/// exceptions, the household zone, the trusted clock and the daily budget
+/// into account
+pub fn should_block_with_budget(config: &AppConfig) -> bool {
+    should_block_with_budget_at(config, super::trusted_now())
+}
+
+/// [`should_block_with_budget`] at `now` rather than the trusted clock
+pub fn should_block_with_budget_at(config: &AppConfig, now: DateTime<Utc>) -> bool {
+    if super::active_unlock_at(config.temporary_unlock.as_ref(), now).is_some() {
+        return false;
+    }
+
+    let decision = super::scheduled_blocking(
+        &config.schedules,
+        &config.date_exceptions,
+        config.household_timezone.as_deref(),
+        now,
+    );
+    should_block_with(decision, !config.schedules.is_empty(), get_remaining_budget(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn budget(minutes: u16) -> TimeBudget {
+        TimeBudget {
+            daily_minutes: minutes,
+            per_weekday: None,
+        }
+    }
+
+    #[test]
+    fn test_consumption_counts_down() {
+        let today = date(2026, 3, 4);
+        let mut usage = BudgetUsage::new(today);
+
+        usage.consume(today, 30 * 60);
+        assert_eq!(usage.remaining_minutes(&budget(90), today), 60);
+
+        usage.consume(today, 61 * 60);
+        assert_eq!(usage.remaining_minutes(&budget(90), today), 0);
+    }
+
+    #[test]
+    fn test_midnight_rollover_resets_usage() {
+        let monday = date(2026, 3, 2);
+        let tuesday = date(2026, 3, 3);
+        let mut usage = BudgetUsage::new(monday);
+        usage.consume(monday, 90 * 60);
+        assert_eq!(usage.remaining_minutes(&budget(90), monday), 0);
+
+        // Yesterday's usage doesn't count today, even before anything is charged
+        assert_eq!(usage.remaining_minutes(&budget(90), tuesday), 90);
+
+        usage.consume(tuesday, 60);
+        assert_eq!(usage.date, tuesday);
+        assert_eq!(usage.used_seconds, 60);
+    }
+
+    #[test]
+    fn test_weekday_overrides() {
+        let budget = TimeBudget {
+            daily_minutes: 60,
+            per_weekday: Some([180, 30, 30, 30, 30, 30, 180]),
+        };
+        // 2026-03-01 is a Sunday
+        assert_eq!(budget.minutes_for(date(2026, 3, 1)), 180);
+        assert_eq!(budget.minutes_for(date(2026, 3, 2)), 30);
+        assert_eq!(budget.minutes_for(date(2026, 3, 7)), 180);
+    }
+
+    #[test]
+    fn test_usage_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("budget.json");
+        let today = date(2026, 3, 4);
+
+        assert_eq!(load_usage(&path, today), BudgetUsage::new(today));
+
+        let mut usage = BudgetUsage::new(today);
+        usage.consume(today, 600);
+        save_usage(&path, &usage).unwrap();
+        assert_eq!(load_usage(&path, today), usage);
+        // Loading on a later day starts over
+        assert_eq!(load_usage(&path, date(2026, 3, 5)), BudgetUsage::new(date(2026, 3, 5)));
+    }
+
+    #[test]
+    fn test_unreadable_usage_counts_as_spent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("budget.json");
+        let today = date(2026, 3, 4);
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert_eq!(load_usage(&path, today).remaining_minutes(&budget(90), today), 0);
+
+        // A new day still starts over
+        let tomorrow = date(2026, 3, 5);
+        assert_eq!(load_usage(&path, tomorrow).remaining_minutes(&budget(90), tomorrow), 90);
+    }
+
+    #[test]
+    fn test_budget_decides_without_schedules() {
+        assert!(should_block_with(None, false, None));
+        assert!(!should_block_with(None, false, Some(15)));
+        assert!(should_block_with(None, false, Some(0)));
+        assert!(should_block_with(Some(false), true, Some(0)));
+        assert!(should_block_with(None, true, Some(15)));
+    }
+
+    #[test]
+    fn test_unlock_overrides_blocking_until_expiry() {
+        let now = crate::scheduler::trusted_now();
+        let mut config = AppConfig::default();
+        assert!(should_block_with_budget(&config));
+
+        config.temporary_unlock = Some(crate::scheduler::TemporaryUnlock::for_minutes(30, now));
+        assert!(!should_block_with_budget(&config));
+
+        config.temporary_unlock = Some(crate::scheduler::TemporaryUnlock { until: now });
+        assert!(should_block_with_budget(&config));
+    }
+}
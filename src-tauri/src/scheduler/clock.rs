@@ -0,0 +1,244 @@
+//! The time schedules are evaluated at.
+//! Schedules can be pinned to a zone (their own or the household's) so a
+//! laptop that travels, or a child changing the system timezone, doesn't
+//! shift them. Zones are fixed UTC offsets or IANA names (`Europe/Berlin`),
+//! which follow daylight saving. The system clock itself can be checked
+//! against an NTP server, and the measured offset is applied to every
+//! schedule evaluation.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Default NTP port, used when the server has none
+const NTP_PORT: u16 = 123;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+/// Offset of the trusted clock from the system clock, once measured
+static CLOCK_OFFSET: Lazy<Mutex<Option<chrono::Duration>>> = Lazy::new(|| Mutex::new(None));
+
+/// A zone schedules can be read in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleZone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl ScheduleZone {
+    /// UTC offset in effect at `now`
+    pub fn offset_at(&self, now: DateTime<Utc>) -> FixedOffset {
+        match self {
+            ScheduleZone::Fixed(offset) => *offset,
+            ScheduleZone::Named(tz) => tz.offset_from_utc_datetime(&now.naive_utc()).fix(),
+        }
+    }
+}
+
+/// Parse a schedule zone: `UTC`/`GMT`/`Z`, an offset such as `+05:30`,
+/// `-0800` or `UTC+2`, or an IANA name such as `America/New_York`.
+/// Returns `None` for anything else.
+pub fn parse_zone(zone: &str) -> Option<ScheduleZone> {
+    let zone = zone.trim();
+    match parse_offset(zone) {
+        Some(offset) => Some(ScheduleZone::Fixed(offset)),
+        None => zone.parse::<Tz>().ok().map(ScheduleZone::Named),
+    }
+}
+
+/// The fixed-offset forms [`parse_zone`] accepts
+fn parse_offset(zone: &str) -> Option<FixedOffset> {
+    let offset = ["UTC", "GMT"]
+        .iter()
+        .find_map(|prefix| zone.strip_prefix(prefix))
+        .unwrap_or(zone);
+    if offset.is_empty() || offset == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, rest) = match offset.as_bytes()[0] {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    if hours.is_empty() || !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Offset a schedule is read in at `now`: its own zone, else the household
+/// zone, else the system's local time. Zones that don't parse fall back the
+/// same way; schedule validation reports them.
+pub fn zone_offset(zone: Option<&str>, household: Option<&str>, now: DateTime<Utc>) -> FixedOffset {
+    zone.and_then(parse_zone)
+        .or_else(|| household.and_then(parse_zone))
+        .map(|zone| zone.offset_at(now))
+        .unwrap_or_else(|| Local.offset_from_utc_datetime(&now.naive_utc()).fix())
+}
+
+/// Date on the household clock at `now`, or the local date without a
+/// household zone; days for the budget and kill counts change over here
+pub fn household_date(household: Option<&str>, now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&zone_offset(None, household, now)).date_naive()
+}
+
+/// Current time, corrected by the last NTP measurement if there was one
+pub fn trusted_now() -> DateTime<Utc> {
+    let offset = *CLOCK_OFFSET.lock().unwrap_or_else(PoisonError::into_inner);
+    Utc::now() + offset.unwrap_or_else(chrono::Duration::zero)
+}
+
+/// Measure how far the system clock is from `server` and correct
+/// [`trusted_now`] by it. Returns the offset (positive when the system
+/// clock is behind).
+pub fn sync_trusted_clock(server: &str, timeout: Duration) -> io::Result<chrono::Duration> {
+    let sent = Utc::now();
+    let server_time = query_ntp(server, timeout)?;
+    let received = Utc::now();
+
+    let offset = server_time - (sent + (received - sent) / 2);
+    *CLOCK_OFFSET.lock().unwrap_or_else(PoisonError::into_inner) = Some(offset);
+    Ok(offset)
+}
+
+/// Ask an NTP server (`host` or `host:port`) for the time with a single SNTP request
+pub fn query_ntp(server: &str, timeout: Duration) -> io::Result<DateTime<Utc>> {
+    let addr = resolve_server(server)?;
+    let bind: SocketAddr = if addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(addr)?;
+
+    // LI 0, version 4, mode 3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    socket.send(&request)?;
+
+    let mut response = [0u8; 48];
+    let len = socket.recv(&mut response)?;
+    parse_ntp_response(&response[..len])
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid NTP response from {}", server)))
+}
+
+fn resolve_server(server: &str) -> io::Result<SocketAddr> {
+    let addrs = match server.to_socket_addrs() {
+        Ok(addrs) => addrs.collect::<Vec<_>>(),
+        Err(_) => (server, NTP_PORT).to_socket_addrs()?.collect(),
+    };
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve", server)))
+}
+
+/// Transmit time of a server reply, or `None` if it isn't a usable reply
+pub fn parse_ntp_response(packet: &[u8]) -> Option<DateTime<Utc>> {
+    if packet.len() < 48 {
+        return None;
+    }
+    // Mode 4 (server); stratum 0 is a kiss-o'-death telling us to back off
+    if packet[0] & 0x07 != 4 || packet[1] == 0 {
+        return None;
+    }
+
+    let seconds = u32::from_be_bytes(packet[40..44].try_into().ok()?) as i64;
+    let fraction = u32::from_be_bytes(packet[44..48].try_into().ok()?) as u64;
+    if seconds == 0 {
+        return None;
+    }
+    // Timestamps wrap in 2036; small values belong to the next era
+    let seconds = if seconds < 1 << 31 { seconds + (1 << 32) } else { seconds };
+    let nanos = ((fraction * 1_000_000_000) >> 32) as u32;
+    DateTime::from_timestamp(seconds - NTP_UNIX_OFFSET, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_zone() {
+        let fixed = |zone| parse_zone(zone).map(|zone| zone.offset_at(Utc::now()));
+        assert_eq!(fixed("UTC"), FixedOffset::east_opt(0));
+        assert_eq!(fixed("Z"), FixedOffset::east_opt(0));
+        assert_eq!(fixed("+05:30"), FixedOffset::east_opt(5 * 3600 + 30 * 60));
+        assert_eq!(fixed("-0800"), FixedOffset::west_opt(8 * 3600));
+        assert_eq!(fixed("UTC+2"), FixedOffset::east_opt(2 * 3600));
+        assert_eq!(fixed("GMT-03:30"), FixedOffset::west_opt(3 * 3600 + 30 * 60));
+
+        assert_eq!(parse_zone("Europe/Berlin"), Some(ScheduleZone::Named(Tz::Europe__Berlin)));
+        assert_eq!(parse_zone("Mars/Olympus"), None);
+        assert_eq!(parse_zone("+25:00"), None);
+        assert_eq!(parse_zone("+"), None);
+    }
+
+    #[test]
+    fn test_household_date_follows_household_zone() {
+        // 23:30 UTC on the 4th is already the 5th in Tokyo
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 23, 30, 0).unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+        assert_eq!(household_date(Some("UTC"), now), date(4));
+        assert_eq!(household_date(Some("+09:00"), now), date(5));
+        assert_eq!(household_date(Some("-05:00"), now), date(4));
+    }
+
+    #[test]
+    fn test_named_zone_follows_daylight_saving() {
+        let winter = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2026, 7, 15, 12, 0, 0).unwrap();
+        assert_eq!(zone_offset(Some("Europe/Berlin"), None, winter), FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(zone_offset(Some("Europe/Berlin"), None, summer), FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(zone_offset(None, Some("America/New_York"), winter), FixedOffset::west_opt(5 * 3600).unwrap());
+    }
+
+    #[test]
+    fn test_schedule_zone_wins_over_household() {
+        let now = Utc::now();
+        assert_eq!(zone_offset(Some("+09:00"), Some("-05:00"), now), FixedOffset::east_opt(9 * 3600).unwrap());
+        assert_eq!(zone_offset(None, Some("-05:00"), now), FixedOffset::west_opt(5 * 3600).unwrap());
+        assert_eq!(zone_offset(Some("Mars/Olympus"), Some("-05:00"), now), FixedOffset::west_opt(5 * 3600).unwrap());
+    }
+
+    #[test]
+    fn test_parse_ntp_response() {
+        let mut packet = [0u8; 48];
+        packet[0] = 0x24; // version 4, server
+        packet[1] = 2;
+        // 2024-01-01T00:00:00Z and a half second
+        let seconds = (1_704_067_200i64 + NTP_UNIX_OFFSET) as u32;
+        packet[40..44].copy_from_slice(&seconds.to_be_bytes());
+        packet[44..48].copy_from_slice(&(1u32 << 31).to_be_bytes());
+
+        let time = parse_ntp_response(&packet).unwrap();
+        assert_eq!(time.timestamp(), 1_704_067_200);
+        assert_eq!(time.timestamp_subsec_millis(), 500);
+
+        // Kiss-o'-death and client packets are rejected
+        packet[1] = 0;
+        assert_eq!(parse_ntp_response(&packet), None);
+        packet[1] = 2;
+        packet[0] = 0x23;
+        assert_eq!(parse_ntp_response(&packet), None);
+        assert_eq!(parse_ntp_response(&packet[..40]), None);
+    }
+}
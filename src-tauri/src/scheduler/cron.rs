@@ -0,0 +1,180 @@
+//! Cron-style expressions for schedules the day/minute window can't express.
+//!
+//! Standard five fields: minute, hour, day of month, month, day of week
+//! (0 or 7 = Sunday). Each field takes `*`, numbers, ranges (`1-5`), steps
+//! (`*/15`, `8-18/2`) and comma lists. A minute the expression matches is an
+//! active minute of the schedule. Unlike classic cron, day of month and day
+//! of week must both match, so `* 8-14 1-7 * 1-5` means "school-day
+//! mornings in the first week of the month".
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    #[error("expected 5 fields (minute hour day month weekday), found {0}")]
+    FieldCount(usize),
+    #[error("invalid {field} field {value:?}")]
+    InvalidField { field: &'static str, value: String },
+    #[error("{field} value {value} is outside {min}-{max}")]
+    OutOfRange {
+        field: &'static str,
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+}
+
+/// A parsed expression; each field is a bit set of the values it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+}
+
+impl CronExpr {
+    /// Whether the expression matches the minute `at`
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        self.matches_weekly(at.weekday().num_days_from_sunday() as u8, (at.hour() * 60 + at.minute()) as u16)
+            && has(self.days_of_month, at.day())
+            && has(self.months, at.month())
+    }
+
+    /// Whether the expression can match `day` (0 = Sunday) at `minutes` past
+    /// midnight in some week, ignoring day of month and month. Used where
+    /// schedules are looked at as a repeating week.
+    pub fn matches_weekly(&self, day: u8, minutes: u16) -> bool {
+        has(self.days_of_week, day as u32)
+            && has(self.hours, minutes as u32 / 60)
+            && has(self.minutes, minutes as u32 % 60)
+    }
+}
+
+impl FromStr for CronExpr {
+    type Err = CronError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(CronError::FieldCount(fields.len()));
+        }
+
+        let mut days_of_week = parse_field(fields[4], "weekday", 0, 7)?;
+        // 7 is another name for Sunday
+        if has(days_of_week, 7) {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], "minute", 0, 59)?,
+            hours: parse_field(fields[1], "hour", 0, 23)?,
+            days_of_month: parse_field(fields[2], "day", 1, 31)?,
+            months: parse_field(fields[3], "month", 1, 12)?,
+            days_of_week,
+        })
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    value < 64 && set & (1 << value) != 0
+}
+
+/// Parse one field into the set of values it matches
+fn parse_field(text: &str, field: &'static str, min: u32, max: u32) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField {
+        field,
+        value: text.to_string(),
+    };
+    let number = |s: &str| -> Result<u32, CronError> {
+        let value: u32 = s.parse().map_err(|_| invalid())?;
+        if value < min || value > max {
+            return Err(CronError::OutOfRange { field, value, min, max });
+        }
+        Ok(value)
+    };
+
+    let mut set = 0u64;
+    for item in text.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(invalid)?),
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/10` runs from 5 to the end of the range
+                None if step > 1 => (number(range)?, max),
+                None => {
+                    let value = number(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_match() {
+        let expr: CronExpr = "*/15 8-18/2 * * 1-5".parse().unwrap();
+        // 2026-03-02 is a Monday
+        assert!(expr.matches(at(2026, 3, 2, 8, 0)));
+        assert!(expr.matches(at(2026, 3, 2, 10, 45)));
+        assert!(!expr.matches(at(2026, 3, 2, 9, 0)));
+        assert!(!expr.matches(at(2026, 3, 2, 10, 20)));
+        assert!(!expr.matches(at(2026, 3, 1, 8, 0)));
+    }
+
+    #[test]
+    fn test_day_of_month_and_weekday_both_apply() {
+        let first_week: CronExpr = "* * 1-7 * 1-5".parse().unwrap();
+        assert!(first_week.matches(at(2026, 3, 2, 9, 0)));
+        // A Monday, but in the second week
+        assert!(!first_week.matches(at(2026, 3, 9, 9, 0)));
+        // In the first week, but a Sunday
+        assert!(!first_week.matches(at(2026, 3, 1, 9, 0)));
+    }
+
+    #[test]
+    fn test_sunday_as_seven() {
+        let sunday: CronExpr = "* * * * 7".parse().unwrap();
+        assert_eq!(sunday, "* * * * 0".parse().unwrap());
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert_eq!("* * * *".parse::<CronExpr>(), Err(CronError::FieldCount(4)));
+        assert_eq!(
+            "60 * * * *".parse::<CronExpr>(),
+            Err(CronError::OutOfRange {
+                field: "minute",
+                value: 60,
+                min: 0,
+                max: 59
+            })
+        );
+        assert!(matches!("* 5-2 * * *".parse::<CronExpr>(), Err(CronError::InvalidField { field: "hour", .. })));
+        assert!(matches!("*/0 * * * *".parse::<CronExpr>(), Err(CronError::InvalidField { .. })));
+        assert!(matches!("* * * JAN *".parse::<CronExpr>(), Err(CronError::InvalidField { field: "month", .. })));
+    }
+}
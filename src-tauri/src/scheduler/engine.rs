@@ -1,89 +1,397 @@
 //! Schedule evaluation engine for time-based blocking rules.
 
-use crate::config::ScheduleEntry;
-use chrono::{Datelike, Local, Timelike};
+use super::clock::{trusted_now, zone_offset};
+use super::cron::CronExpr;
+use super::exceptions::{exception_on, DateException, ExceptionMode};
+use crate::config::{AppConfig, ScheduleEntry};
+use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, Timelike, Utc};
+use uuid::Uuid;
 
-/// Check if blocking should be active based on current schedules
-pub fn should_block_now(schedules: &[ScheduleEntry]) -> bool {
-    if schedules.is_empty() {
-        return true; // No schedules = always blocking
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+
+/// Default warning shown ahead of a schedule that starts blocking
+pub const DEFAULT_WARN_MESSAGE: &str = "{schedule} blocking starts in {minutes} minutes.";
+
+/// Snapshot of what the schedules say right now
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleState {
+    /// Whether blocking is currently active
+    pub blocking: bool,
+    /// Name of the schedule window that decided the current state, if any
+    pub active_schedule: Option<String>,
+    /// Minutes until the next schedule transition, if known
+    pub minutes_until_change: Option<u32>,
+}
+
+/// Get the current schedule state (blocking flag, deciding window, next change).
+/// Agrees with [`super::should_block_with_budget`]: schedules are read in
+/// their own zone, else the household zone.
+pub fn current_schedule_state(config: &AppConfig) -> ScheduleState {
+    schedule_state_at(config, trusted_now())
+}
+
+/// [`current_schedule_state`] at `now` rather than the trusted clock
+pub fn schedule_state_at(config: &AppConfig, now: DateTime<Utc>) -> ScheduleState {
+    let explained = super::explain_at(
+        &config.schedules,
+        &config.date_exceptions,
+        config.household_timezone.as_deref(),
+        super::get_remaining_budget(config),
+        config.temporary_unlock.as_ref(),
+        now,
+    );
+    ScheduleState {
+        blocking: explained.blocking,
+        active_schedule: explained.active_schedule,
+        // Rounded up so the end time shown is never before the change
+        minutes_until_change: explained
+            .until
+            .map(|until| ((until - now).num_seconds().max(0) as u32).div_ceil(60)),
     }
+}
 
-    let now = Local::now();
-    let current_day = now.weekday().num_days_from_sunday() as u8;
-    let current_minutes = (now.hour() * 60 + now.minute()) as u16;
+/// Default message shown when a blocked app is closed
+pub const DEFAULT_BLOCK_MESSAGE: &str = "{process} is blocked right now.";
 
-    for schedule in schedules {
-        if !schedule.enabled {
-            continue;
+/// Compose the message shown after a blocked process is terminated.
+/// Supports `{process}`, `{schedule}` and `{until}` placeholders; when the
+/// template doesn't mention `{until}` but the end time is known, it is appended.
+pub fn compose_block_message(
+    template: Option<&str>,
+    process_name: &str,
+    state: &ScheduleState,
+    now: NaiveTime,
+) -> String {
+    let template = template
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or(DEFAULT_BLOCK_MESSAGE);
+
+    let until = state.minutes_until_change.map(|minutes| {
+        let (time, _) = now.overflowing_add_signed(chrono::Duration::minutes(minutes as i64));
+        time.format("%-I:%M %p").to_string()
+    });
+
+    let mut message = template
+        .replace("{process}", process_name)
+        .replace("{schedule}", state.active_schedule.as_deref().unwrap_or("ParentShield"));
+
+    match until {
+        Some(ref until) if message.contains("{until}") => {
+            message = message.replace("{until}", until);
+        }
+        Some(ref until) => {
+            message.push_str(&format!(" Blocking ends at {}.", until));
         }
+        None => {
+            message = message.replace(" until {until}", "").replace("{until}", "later");
+        }
+    }
 
-        // Check if current day is in schedule
-        if !schedule.days.contains(&current_day) {
-            continue;
+    message
+}
+
+/// Find the schedule window deciding the given day and time
+fn find_active_schedule(
+    schedules: &[ScheduleEntry],
+    current_day: u8,
+    current_minutes: u16,
+) -> Option<&ScheduleEntry> {
+    decisive_schedule(
+        schedules
+            .iter()
+            .filter(|schedule| schedule_covers(schedule, current_day, current_minutes)),
+    )
+}
+
+/// Pick the schedule that decides among several covering the same moment.
+///
+/// The highest `priority` wins. Between equal priorities a blocking window
+/// beats an allow window, so an accidental overlap fails safe. Remaining
+/// ties go to the entry listed first (they agree on blocking anyway).
+pub fn decisive_schedule<'a>(matching: impl IntoIterator<Item = &'a ScheduleEntry>) -> Option<&'a ScheduleEntry> {
+    matching.into_iter().fold(None, |best, schedule| match best {
+        Some(best) if (best.priority, best.blocking_enabled) >= (schedule.priority, schedule.blocking_enabled) => {
+            Some(best)
         }
+        _ => Some(schedule),
+    })
+}
 
-        // Check if current time is in schedule window
-        if schedule.start_minutes <= schedule.end_minutes {
-            // Normal time range (e.g., 9:00 - 17:00)
-            if current_minutes >= schedule.start_minutes && current_minutes < schedule.end_minutes {
-                return schedule.blocking_enabled;
-            }
-        } else {
-            // Overnight range (e.g., 22:00 - 06:00)
-            if current_minutes >= schedule.start_minutes || current_minutes < schedule.end_minutes {
-                return schedule.blocking_enabled;
-            }
+/// Parsed cron expression of a schedule that has one. Invalid expressions
+/// are rejected when saving, so here they simply never match.
+fn schedule_cron(schedule: &ScheduleEntry) -> Option<Option<CronExpr>> {
+    schedule.cron.as_deref().map(|expr| expr.parse().ok())
+}
+
+/// Whether an enabled schedule covers the given day and time. Cron
+/// schedules are read as a repeating week here (day of month and month are
+/// ignored); [`schedule_covers_at`] checks them exactly.
+fn schedule_covers(schedule: &ScheduleEntry, current_day: u8, current_minutes: u16) -> bool {
+    if !schedule.enabled {
+        return false;
+    }
+
+    if let Some(cron) = schedule_cron(schedule) {
+        return cron.is_some_and(|cron| cron.matches_weekly(current_day, current_minutes));
+    }
+
+    // Check if current time is in schedule window
+    if schedule.start_minutes <= schedule.end_minutes {
+        // Normal time range (e.g., 9:00 - 17:00)
+        schedule.days.contains(&current_day)
+            && current_minutes >= schedule.start_minutes
+            && current_minutes < schedule.end_minutes
+    } else {
+        // Overnight range (e.g., 22:00 - 06:00): the morning part belongs to
+        // the window that started the evening before
+        let previous_day = (current_day + 6) % 7;
+        (schedule.days.contains(&current_day) && current_minutes >= schedule.start_minutes)
+            || (schedule.days.contains(&previous_day) && current_minutes < schedule.end_minutes)
+    }
+}
+
+/// Whether an enabled schedule covers the local date and time `at`
+fn schedule_covers_at(schedule: &ScheduleEntry, at: NaiveDateTime) -> bool {
+    match schedule_cron(schedule) {
+        Some(cron) => schedule.enabled && cron.is_some_and(|cron| cron.matches(at)),
+        None => schedule_covers(
+            schedule,
+            at.weekday().num_days_from_sunday() as u8,
+            (at.hour() * 60 + at.minute()) as u16,
+        ),
+    }
+}
+
+/// `now` as a local date and time in the schedule's zone
+fn schedule_local_time(schedule: &ScheduleEntry, household_zone: Option<&str>, now: DateTime<Utc>) -> NaiveDateTime {
+    now.with_timezone(&zone_offset(schedule.timezone.as_deref(), household_zone, now))
+        .naive_local()
+}
+
+/// Check if blocking should be active based on current schedules
+pub fn should_block_now(schedules: &[ScheduleEntry]) -> bool {
+    should_block_in_zone(schedules, None, trusted_now())
+}
+
+/// Check if blocking should be active at `now`, reading each schedule in its
+/// own zone, else `household_zone`, else local time
+pub fn should_block_in_zone(schedules: &[ScheduleEntry], household_zone: Option<&str>, now: DateTime<Utc>) -> bool {
+    // No schedules = always blocking; otherwise default to blocking if none matches
+    scheduled_blocking(schedules, &[], household_zone, now).unwrap_or(true)
+}
+
+/// What decided the calendar's answer for a moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalendarDecision<'a> {
+    /// A date exception covers the whole day
+    Exception(ExceptionMode),
+    /// The schedule window that won by [`decisive_schedule`]
+    Schedule(&'a ScheduleEntry),
+}
+
+impl CalendarDecision<'_> {
+    pub fn blocking(&self) -> bool {
+        match self {
+            CalendarDecision::Exception(mode) => *mode == ExceptionMode::Block,
+            CalendarDecision::Schedule(schedule) => schedule.blocking_enabled,
         }
     }
+}
+
+/// What the calendar says about `now`: a date exception for the day (in the
+/// household zone) decides first, then the matching schedule window that
+/// wins by [`decisive_schedule`].
+/// `None` when neither applies.
+pub fn scheduled_blocking(
+    schedules: &[ScheduleEntry],
+    exceptions: &[DateException],
+    household_zone: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<bool> {
+    calendar_decision(schedules, exceptions, household_zone, now).map(|decision| decision.blocking())
+}
+
+/// Like [`scheduled_blocking`], but says which exception or schedule decided
+pub fn calendar_decision<'a>(
+    schedules: &'a [ScheduleEntry],
+    exceptions: &[DateException],
+    household_zone: Option<&str>,
+    now: DateTime<Utc>,
+) -> Option<CalendarDecision<'a>> {
+    let today = now.with_timezone(&zone_offset(None, household_zone, now)).date_naive();
+    if let Some(mode) = exception_on(exceptions, today) {
+        return Some(CalendarDecision::Exception(mode));
+    }
+
+    decisive_schedule(
+        schedules
+            .iter()
+            .filter(|schedule| schedule_covers_at(schedule, schedule_local_time(schedule, household_zone, now))),
+    )
+    .map(CalendarDecision::Schedule)
+}
 
+/// Check if blocking should be active on `day` (0 = Sunday) at `minutes` past midnight
+pub fn should_block_at(schedules: &[ScheduleEntry], day: u8, minutes: u16) -> bool {
     // Default to blocking if no schedule matches
-    true
+    find_active_schedule(schedules, day, minutes)
+        .map(|s| s.blocking_enabled)
+        .unwrap_or(true)
 }
 
-/// Get minutes until the next schedule change
-pub fn minutes_until_change(schedules: &[ScheduleEntry]) -> Option<u32> {
+/// Minutes from `day` (0 = Sunday) at `minutes` past midnight until the
+/// blocking state differs, resolving overlaps by priority like
+/// [`should_block_at`]. Windows that start or end without changing the
+/// outcome (e.g. an allow window hidden by a higher-priority block) don't
+/// count. `None` if the state never changes.
+pub fn minutes_until_change_at(schedules: &[ScheduleEntry], day: u8, minutes: u16) -> Option<u32> {
     if schedules.is_empty() {
         return None;
     }
 
-    let now = Local::now();
-    let current_day = now.weekday().num_days_from_sunday() as u8;
-    let current_minutes = (now.hour() * 60 + now.minute()) as u16;
+    let now = day as u32 * MINUTES_PER_DAY + minutes as u32;
+    let blocking = should_block_at(schedules, day, minutes);
 
-    let mut min_minutes = u32::MAX;
-
-    for schedule in schedules {
-        if !schedule.enabled {
+    // The state can only change where a window starts or ends, or at midnight
+    let mut boundaries: Vec<u32> = (0..7).map(|day| day * MINUTES_PER_DAY).collect();
+    for schedule in schedules.iter().filter(|s| s.enabled) {
+        if let Some(cron) = schedule_cron(schedule) {
+            // Cron slots can start and end at any minute
+            let Some(cron) = cron else { continue };
+            let active = |at: u32| cron.matches_weekly((at / MINUTES_PER_DAY) as u8, (at % MINUTES_PER_DAY) as u16);
+            boundaries.extend((0..MINUTES_PER_WEEK).filter(|&at| {
+                active(at) != active((at + MINUTES_PER_WEEK - 1) % MINUTES_PER_WEEK)
+            }));
             continue;
         }
-
-        // Check same-day transitions
-        if schedule.days.contains(&current_day) {
-            if current_minutes < schedule.start_minutes {
-                let diff = (schedule.start_minutes - current_minutes) as u32;
-                min_minutes = min_minutes.min(diff);
-            }
-            if current_minutes < schedule.end_minutes {
-                let diff = (schedule.end_minutes - current_minutes) as u32;
-                min_minutes = min_minutes.min(diff);
-            }
-        }
-
-        // Check next day transitions
-        let next_day = (current_day + 1) % 7;
-        if schedule.days.contains(&next_day) {
-            let minutes_until_midnight = 24 * 60 - current_minutes as u32;
-            let diff = minutes_until_midnight + schedule.start_minutes as u32;
-            min_minutes = min_minutes.min(diff);
+        for &day in schedule.days.iter().filter(|&&day| day < 7) {
+            let day_start = day as u32 * MINUTES_PER_DAY;
+            let start = schedule.start_minutes as u32 % MINUTES_PER_DAY;
+            let end = schedule.end_minutes as u32 % MINUTES_PER_DAY;
+            boundaries.push(day_start + start);
+            // Overnight windows end the next morning
+            let end_day = if end < start { day_start + MINUTES_PER_DAY } else { day_start };
+            boundaries.push((end_day + end) % MINUTES_PER_WEEK);
         }
     }
 
-    if min_minutes == u32::MAX {
-        None
-    } else {
-        Some(min_minutes)
+    let mut distances: Vec<u32> = boundaries
+        .into_iter()
+        .map(|at| (at + MINUTES_PER_WEEK - now) % MINUTES_PER_WEEK)
+        .filter(|&until| until > 0)
+        .collect();
+    distances.sort_unstable();
+    distances.dedup();
+
+    distances.into_iter().find(|&until| {
+        let at = (now + until) % MINUTES_PER_WEEK;
+        should_block_at(
+            schedules,
+            (at / MINUTES_PER_DAY) as u8,
+            (at % MINUTES_PER_DAY) as u16,
+        ) != blocking
+    })
+}
+
+/// A schedule about to start blocking whose warning is due
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockWarning {
+    pub schedule_id: Uuid,
+    /// Minutes until blocking starts
+    pub minutes_until: u32,
+    /// Minute of the week (0 = Sunday 00:00) blocking starts at; identifies
+    /// the transition so it's only announced once
+    pub starts_at: u32,
+    pub message: String,
+}
+
+/// Minute of the week at which `schedule` next makes blocking start, and how
+/// far away that is. Blocking windows start blocking at their start, free
+/// windows at their end.
+fn next_block_start(schedule: &ScheduleEntry, now: u32) -> Option<(u32, u32)> {
+    // Cron schedules have no fixed start to warn ahead of
+    if schedule.cron.is_some() {
+        return None;
     }
+
+    schedule
+        .days
+        .iter()
+        .filter(|&&day| day < 7)
+        .map(|&day| {
+            let day_start = day as u32 * MINUTES_PER_DAY;
+            let at = if schedule.blocking_enabled {
+                day_start + schedule.start_minutes as u32
+            } else if schedule.end_minutes <= schedule.start_minutes {
+                // Overnight free window ends the next day
+                day_start + MINUTES_PER_DAY + schedule.end_minutes as u32
+            } else {
+                day_start + schedule.end_minutes as u32
+            };
+            let at = at % MINUTES_PER_WEEK;
+            let until = (at + MINUTES_PER_WEEK - now) % MINUTES_PER_WEEK;
+            (at, until)
+        })
+        .filter(|&(_, until)| until > 0)
+        .min_by_key(|&(_, until)| until)
+}
+
+/// Warnings due on `day` at `minutes` past midnight: schedules with a lead
+/// time whose blocking starts within that lead time
+pub fn block_warnings_at(schedules: &[ScheduleEntry], day: u8, minutes: u16) -> Vec<BlockWarning> {
+    let now = day as u32 * MINUTES_PER_DAY + minutes as u32;
+
+    schedules
+        .iter()
+        .filter(|s| s.enabled)
+        .filter_map(|schedule| {
+            let warn_minutes = schedule.warn_minutes.filter(|&m| m > 0)?;
+            let (starts_at, until) = next_block_start(schedule, now)?;
+            if until > warn_minutes {
+                return None;
+            }
+
+            let template = schedule
+                .warn_message
+                .as_deref()
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or(DEFAULT_WARN_MESSAGE);
+            Some(BlockWarning {
+                schedule_id: schedule.id,
+                minutes_until: until,
+                starts_at,
+                message: template
+                    .replace("{schedule}", &schedule.name)
+                    .replace("{minutes}", &until.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Warnings due at `now`, reading each schedule in its own zone, else
+/// `household_zone`, else local time
+pub fn block_warnings_in_zone(
+    schedules: &[ScheduleEntry],
+    household_zone: Option<&str>,
+    now: DateTime<Utc>,
+) -> Vec<BlockWarning> {
+    schedules
+        .iter()
+        .flat_map(|schedule| {
+            let at = schedule_local_time(schedule, household_zone, now);
+            block_warnings_at(
+                std::slice::from_ref(schedule),
+                at.weekday().num_days_from_sunday() as u8,
+                (at.hour() * 60 + at.minute()) as u16,
+            )
+        })
+        .collect()
+}
+
+/// Warnings due right now
+pub fn block_warnings_now(schedules: &[ScheduleEntry], household_zone: Option<&str>) -> Vec<BlockWarning> {
+    block_warnings_in_zone(schedules, household_zone, trusted_now())
 }
 
 /// Create a school hours schedule (Mon-Fri, 8:00-15:00, blocking enabled)
@@ -96,6 +404,11 @@ pub fn create_school_hours_schedule() -> ScheduleEntry {
         start_minutes: 8 * 60,     // 8:00 AM
         end_minutes: 15 * 60,      // 3:00 PM
         blocking_enabled: true,
+        warn_minutes: None,
+        warn_message: None,
+        timezone: None,
+        priority: 0,
+        cron: None,
     }
 }
 
@@ -109,6 +422,11 @@ pub fn create_bedtime_schedule() -> ScheduleEntry {
         start_minutes: 21 * 60,          // 9:00 PM
         end_minutes: 7 * 60,             // 7:00 AM
         blocking_enabled: true,
+        warn_minutes: None,
+        warn_message: None,
+        timezone: None,
+        priority: 0,
+        cron: None,
     }
 }
 
@@ -122,9 +440,41 @@ pub fn create_weekend_gaming_schedule() -> ScheduleEntry {
         start_minutes: 14 * 60, // 2:00 PM
         end_minutes: 18 * 60, // 6:00 PM
         blocking_enabled: false, // Blocking disabled during this window
+        warn_minutes: None,
+        warn_message: None,
+        timezone: None,
+        priority: 0,
+        cron: None,
     }
 }
 
+/// The schedules a named preset creates, or `None` for an unknown preset
+pub fn preset_schedules(preset: &str) -> Option<Vec<ScheduleEntry>> {
+    let schedules = match preset {
+        "school" => vec![create_school_hours_schedule()],
+        "bedtime" => vec![create_bedtime_schedule()],
+        "weekend" => vec![create_weekend_gaming_schedule()],
+        // School hours and bedtime, the usual weekday setup
+        "weekday_default" => vec![create_school_hours_schedule(), create_bedtime_schedule()],
+        _ => return None,
+    };
+    Some(schedules)
+}
+
+/// Add preset schedules, skipping any whose name an enabled schedule already
+/// uses. Returns the schedules actually added.
+pub fn add_presets(schedules: &mut Vec<ScheduleEntry>, presets: Vec<ScheduleEntry>) -> Vec<ScheduleEntry> {
+    let mut added = Vec::new();
+    for preset in presets {
+        if schedules.iter().any(|s| s.enabled && s.name == preset.name) {
+            continue;
+        }
+        schedules.push(preset.clone());
+        added.push(preset);
+    }
+    added
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,6 +484,176 @@ mod tests {
         assert!(should_block_now(&[]));
     }
 
+    #[test]
+    fn test_schedules_follow_their_zone() {
+        use chrono::TimeZone;
+
+        // Monday 12:30 UTC: 21:30 in Tokyo (+09:00), 07:30 in New York (-05:00)
+        let now = Utc.with_ymd_and_hms(2026, 3, 2, 12, 30, 0).unwrap();
+        let bedtime = |zone: &str| ScheduleEntry {
+            timezone: Some(zone.to_string()),
+            ..create_bedtime_schedule()
+        };
+        let gaming = |zone: &str| ScheduleEntry {
+            timezone: Some(zone.to_string()),
+            days: vec![1],
+            start_minutes: 7 * 60,
+            end_minutes: 8 * 60,
+            ..create_weekend_gaming_schedule()
+        };
+
+        assert!(should_block_in_zone(&[bedtime("+09:00")], None, now));
+        // Outside bedtime in New York, so no window matches
+        assert!(should_block_in_zone(&[bedtime("-05:00")], None, now));
+        assert!(!should_block_in_zone(&[gaming("-05:00")], None, now));
+        assert!(should_block_in_zone(&[gaming("+09:00")], None, now));
+
+        // The household zone applies to schedules without their own
+        let free = ScheduleEntry {
+            timezone: None,
+            ..gaming("UTC")
+        };
+        assert!(!should_block_in_zone(std::slice::from_ref(&free), Some("-05:00"), now));
+        assert!(should_block_in_zone(&[free], Some("+09:00"), now));
+    }
+
+    #[test]
+    fn test_allow_exception_lifts_school_hours() {
+        use chrono::{NaiveDate, TimeZone};
+
+        // Wednesday 10:00 UTC, inside school hours
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap();
+        let school = vec![create_school_hours_schedule()];
+        let exception = |mode| DateException {
+            date: NaiveDate::from_ymd_opt(2026, 3, 4).unwrap(),
+            mode,
+        };
+
+        assert_eq!(scheduled_blocking(&school, &[], Some("UTC"), now), Some(true));
+        assert_eq!(
+            scheduled_blocking(&school, &[exception(ExceptionMode::Allow)], Some("UTC"), now),
+            Some(false)
+        );
+
+        // A blocking exception applies even on a day no window covers
+        let sunday = Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap();
+        let sick_day = DateException {
+            date: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            mode: ExceptionMode::Block,
+        };
+        assert_eq!(scheduled_blocking(&school, &[], Some("UTC"), sunday), None);
+        assert_eq!(scheduled_blocking(&school, &[sick_day], Some("UTC"), sunday), Some(true));
+    }
+
+    #[test]
+    fn test_priority_resolves_nested_windows() {
+        // School blocks Mon-Fri 8:00-15:00; a higher-priority lunch break allows 12:00-13:00
+        let school = create_school_hours_schedule();
+        let lunch = ScheduleEntry {
+            name: "Lunch".to_string(),
+            start_minutes: 12 * 60,
+            end_minutes: 13 * 60,
+            blocking_enabled: false,
+            priority: 1,
+            ..create_school_hours_schedule()
+        };
+
+        // The order of the list doesn't matter
+        for schedules in [vec![school.clone(), lunch.clone()], vec![lunch.clone(), school.clone()]] {
+            assert!(should_block_at(&schedules, 1, 10 * 60));
+            assert!(!should_block_at(&schedules, 1, 12 * 60 + 30));
+            assert!(should_block_at(&schedules, 1, 13 * 60));
+            assert_eq!(minutes_until_change_at(&schedules, 1, 10 * 60), Some(120));
+            assert_eq!(minutes_until_change_at(&schedules, 1, 12 * 60 + 30), Some(30));
+        }
+
+        // At the same priority blocking wins, so the break never frees anything
+        let same_priority = ScheduleEntry { priority: 0, ..lunch };
+        for schedules in [vec![school.clone(), same_priority.clone()], vec![same_priority, school]] {
+            assert!(should_block_at(&schedules, 1, 12 * 60 + 30));
+            assert_eq!(minutes_until_change_at(&schedules, 1, 10 * 60), None);
+        }
+    }
+
+    #[test]
+    fn test_cron_matches_legacy_window() {
+        use chrono::TimeZone;
+
+        let window = ScheduleEntry {
+            name: "After school".to_string(),
+            days: vec![1, 2, 3, 4, 5],
+            start_minutes: 15 * 60,
+            end_minutes: 18 * 60,
+            blocking_enabled: false,
+            ..create_school_hours_schedule()
+        };
+        let cron = ScheduleEntry {
+            cron: Some("* 15-17 * * 1-5".to_string()),
+            days: Vec::new(),
+            ..window.clone()
+        };
+
+        for minute in 0..MINUTES_PER_WEEK {
+            let (day, minutes) = ((minute / MINUTES_PER_DAY) as u8, (minute % MINUTES_PER_DAY) as u16);
+            assert_eq!(
+                should_block_at(std::slice::from_ref(&window), day, minutes),
+                should_block_at(std::slice::from_ref(&cron), day, minutes),
+                "day {} minute {}",
+                day,
+                minutes
+            );
+        }
+
+        // Exact evaluation agrees too: Wednesday 16:30 and 18:00 UTC
+        let during = Utc.with_ymd_and_hms(2026, 3, 4, 16, 30, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2026, 3, 4, 18, 0, 0).unwrap();
+        assert_eq!(scheduled_blocking(std::slice::from_ref(&cron), &[], Some("UTC"), during), Some(false));
+        assert_eq!(scheduled_blocking(std::slice::from_ref(&cron), &[], Some("UTC"), after), None);
+        assert_eq!(minutes_until_change_at(&[cron], 3, 16 * 60 + 30), Some(90));
+    }
+
+    #[test]
+    fn test_minutes_until_change_across_midnight() {
+        // Friday night gaming, 20:00 until 02:00 Saturday
+        let late = ScheduleEntry {
+            name: "Late gaming".to_string(),
+            days: vec![5],
+            start_minutes: 20 * 60,
+            end_minutes: 2 * 60,
+            blocking_enabled: false,
+            ..create_school_hours_schedule()
+        };
+        let schedules = [late];
+
+        assert!(!should_block_at(&schedules, 5, 23 * 60));
+        assert_eq!(minutes_until_change_at(&schedules, 5, 23 * 60), Some(180));
+        assert!(!should_block_at(&schedules, 6, 60));
+        assert_eq!(minutes_until_change_at(&schedules, 6, 60), Some(60));
+        // Friday morning is not part of Thursday's (missing) window
+        assert!(should_block_at(&schedules, 5, 60));
+        assert_eq!(minutes_until_change_at(&schedules, 5, 60), Some(19 * 60));
+    }
+
+    #[test]
+    fn test_minutes_until_change_at_weekend_gaming_edges() {
+        let schedules = [create_weekend_gaming_schedule()];
+
+        assert_eq!(minutes_until_change_at(&schedules, 6, 14 * 60 - 1), Some(1));
+        assert_eq!(minutes_until_change_at(&schedules, 6, 14 * 60), Some(240));
+        assert_eq!(minutes_until_change_at(&schedules, 6, 18 * 60 - 1), Some(1));
+        // Saturday 18:00 until Sunday 14:00
+        assert_eq!(minutes_until_change_at(&schedules, 6, 18 * 60), Some(20 * 60));
+        // Sunday 18:00 until next Saturday 14:00
+        assert_eq!(minutes_until_change_at(&schedules, 0, 18 * 60), Some(6 * MINUTES_PER_DAY - 4 * 60));
+    }
+
+    #[test]
+    fn test_minutes_until_change_none_when_constant() {
+        // Bedtime blocks, and so does every minute no schedule covers
+        assert_eq!(minutes_until_change_at(&[create_bedtime_schedule()], 3, 12 * 60), None);
+        assert_eq!(minutes_until_change_at(&[], 3, 12 * 60), None);
+    }
+
     #[test]
     fn test_create_presets() {
         let school = create_school_hours_schedule();
@@ -147,4 +667,150 @@ mod tests {
         let weekend = create_weekend_gaming_schedule();
         assert!(!weekend.blocking_enabled);
     }
+
+    #[test]
+    fn test_block_warning_fires_at_lead_time() {
+        let bedtime = ScheduleEntry {
+            warn_minutes: Some(10),
+            warn_message: Some("{schedule} blocking starts in {minutes} minutes — save your game".to_string()),
+            ..create_bedtime_schedule()
+        };
+        let schedules = vec![bedtime.clone()];
+
+        // Bedtime starts at 21:00; nothing before 20:50
+        assert!(block_warnings_at(&schedules, 3, 20 * 60 + 49).is_empty());
+
+        let warnings = block_warnings_at(&schedules, 3, 20 * 60 + 50);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].schedule_id, bedtime.id);
+        assert_eq!(warnings[0].minutes_until, 10);
+        assert_eq!(warnings[0].starts_at, 3 * MINUTES_PER_DAY + 21 * 60);
+        assert_eq!(
+            warnings[0].message,
+            "Bedtime blocking starts in 10 minutes — save your game"
+        );
+
+        // Same transition a few minutes later, then nothing once it has started
+        assert_eq!(block_warnings_at(&schedules, 3, 20 * 60 + 58)[0].starts_at, warnings[0].starts_at);
+        assert!(block_warnings_at(&schedules, 3, 21 * 60).is_empty());
+    }
+
+    #[test]
+    fn test_block_warning_at_end_of_free_window() {
+        let gaming = ScheduleEntry {
+            warn_minutes: Some(5),
+            ..create_weekend_gaming_schedule()
+        };
+
+        // Saturday free window ends at 18:00, when blocking resumes
+        let warnings = block_warnings_at(&[gaming], 6, 17 * 60 + 55);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Weekend Gaming blocking starts in 5 minutes.");
+
+        // Schedules without a lead time never warn
+        let silent = create_weekend_gaming_schedule();
+        assert!(block_warnings_at(&[silent], 6, 17 * 60 + 55).is_empty());
+    }
+
+    #[test]
+    fn test_block_warnings_follow_their_zone() {
+        use chrono::TimeZone;
+
+        // Wednesday 11:50 UTC: 20:50 in Tokyo (+09:00), 06:50 in New York (-05:00)
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 11, 50, 0).unwrap();
+        let bedtime = ScheduleEntry {
+            warn_minutes: Some(10),
+            ..create_bedtime_schedule()
+        };
+
+        let tokyo = ScheduleEntry {
+            timezone: Some("+09:00".to_string()),
+            ..bedtime.clone()
+        };
+        assert_eq!(block_warnings_in_zone(&[tokyo], None, now).len(), 1);
+
+        // The household zone applies to schedules without their own
+        assert_eq!(block_warnings_in_zone(std::slice::from_ref(&bedtime), Some("+09:00"), now).len(), 1);
+        assert!(block_warnings_in_zone(&[bedtime], Some("-05:00"), now).is_empty());
+    }
+
+    #[test]
+    fn test_compose_block_message() {
+        let state = ScheduleState {
+            blocking: true,
+            active_schedule: Some("School Hours".to_string()),
+            minutes_until_change: Some(90),
+        };
+        let now = NaiveTime::from_hms_opt(13, 30, 0).unwrap();
+
+        let message = compose_block_message(
+            Some("Gaming is blocked until {until} ({schedule})"),
+            "steam",
+            &state,
+            now,
+        );
+        assert_eq!(message, "Gaming is blocked until 3:00 PM (School Hours)");
+
+        let default = compose_block_message(None, "steam", &state, now);
+        assert_eq!(default, "steam is blocked right now. Blocking ends at 3:00 PM.");
+    }
+
+    #[test]
+    fn test_compose_block_message_without_end_time() {
+        let state = ScheduleState {
+            blocking: true,
+            active_schedule: None,
+            minutes_until_change: None,
+        };
+        let now = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+        let message = compose_block_message(Some("Gaming is blocked until {until}."), "steam", &state, now);
+        assert_eq!(message, "Gaming is blocked.");
+    }
+
+    #[test]
+    fn test_schedule_state_reads_the_household_zone() {
+        use chrono::TimeZone;
+
+        let free_time = ScheduleEntry {
+            name: "Free time".to_string(),
+            start_minutes: 15 * 60,
+            end_minutes: 20 * 60,
+            blocking_enabled: false,
+            ..create_bedtime_schedule()
+        };
+        let config = AppConfig {
+            schedules: vec![free_time],
+            household_timezone: Some("Europe/Berlin".to_string()),
+            ..AppConfig::default()
+        };
+
+        // 13:30 UTC is 15:30 in Berlin in summer
+        let now = Utc.with_ymd_and_hms(2026, 7, 15, 13, 30, 0).unwrap();
+        let state = schedule_state_at(&config, now);
+        assert_eq!(state.blocking, super::super::should_block_with_budget_at(&config, now));
+        assert!(!state.blocking);
+        assert_eq!(state.active_schedule.as_deref(), Some("Free time"));
+        assert_eq!(state.minutes_until_change, Some(270));
+    }
+
+    #[test]
+    fn test_weekday_default_preset_is_not_duplicated() {
+        let mut schedules = Vec::new();
+        let added = add_presets(&mut schedules, preset_schedules("weekday_default").unwrap());
+        assert_eq!(added.len(), 2);
+        assert_eq!(schedules.len(), 2);
+        assert!(schedules.iter().any(|s| s.name == "School Hours"));
+        assert!(schedules.iter().any(|s| s.name == "Bedtime"));
+
+        let added = add_presets(&mut schedules, preset_schedules("weekday_default").unwrap());
+        assert!(added.is_empty());
+        assert_eq!(schedules.len(), 2);
+
+        // A disabled copy doesn't count
+        schedules[1].enabled = false;
+        let added = add_presets(&mut schedules, preset_schedules("bedtime").unwrap());
+        assert_eq!(added.len(), 1);
+        assert!(preset_schedules("nap").is_none());
+    }
 }
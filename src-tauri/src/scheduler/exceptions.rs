@@ -0,0 +1,59 @@
+//! One-off date overrides for the weekly schedules (holidays, sick days).
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// What a date exception does to its day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionMode {
+    /// Block all day, whatever the schedules say
+    Block,
+    /// No schedule blocking all day
+    Allow,
+}
+
+/// Override of the weekly schedules for one calendar day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateException {
+    pub date: NaiveDate,
+    pub mode: ExceptionMode,
+}
+
+/// The exception for `date`, if there is one
+pub fn exception_on(exceptions: &[DateException], date: NaiveDate) -> Option<ExceptionMode> {
+    exceptions.iter().rev().find(|e| e.date == date).map(|e| e.mode)
+}
+
+/// Add or replace the exception for `exception.date`, dropping days before
+/// `today` and keeping the list in date order
+pub fn set_exception(exceptions: &mut Vec<DateException>, exception: DateException, today: NaiveDate) {
+    exceptions.retain(|e| e.date != exception.date && e.date >= today);
+    exceptions.push(exception);
+    exceptions.sort_by_key(|e| e.date);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 12, d).unwrap()
+    }
+
+    #[test]
+    fn test_set_exception_replaces_and_prunes() {
+        let mut exceptions = vec![DateException {
+            date: date(1),
+            mode: ExceptionMode::Allow,
+        }];
+        set_exception(&mut exceptions, DateException { date: date(25), mode: ExceptionMode::Allow }, date(10));
+        set_exception(&mut exceptions, DateException { date: date(24), mode: ExceptionMode::Allow }, date(10));
+        set_exception(&mut exceptions, DateException { date: date(25), mode: ExceptionMode::Block }, date(10));
+
+        let dates: Vec<NaiveDate> = exceptions.iter().map(|e| e.date).collect();
+        assert_eq!(dates, vec![date(24), date(25)]);
+        assert_eq!(exception_on(&exceptions, date(25)), Some(ExceptionMode::Block));
+        assert_eq!(exception_on(&exceptions, date(26)), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Why blocking is on or off right now. Combines a temporary unlock, the
+//! daily budget, date exceptions and the schedules in the same order as
+//! [`super::should_block_with_budget`], naming whichever one decided.
+
+use super::budget::should_block_with;
+use super::clock::{trusted_now, zone_offset};
+use super::engine::{calendar_decision, minutes_until_change_at, scheduled_blocking, CalendarDecision};
+use super::exceptions::{DateException, ExceptionMode};
+use super::unlock::{active_unlock_at, TemporaryUnlock};
+use crate::config::{AppConfig, ScheduleEntry};
+use chrono::{DateTime, Datelike, DurationRound, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Whether blocking is on, and what decided it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockDecision {
+    pub blocking: bool,
+    pub reason: String,
+    /// Name of the schedule that decided, if one did
+    pub active_schedule: Option<String>,
+    /// When the answer is next expected to change, if known
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Explain the current blocking state for `config`
+pub fn explain_now(config: &AppConfig) -> BlockDecision {
+    explain_at(
+        &config.schedules,
+        &config.date_exceptions,
+        config.household_timezone.as_deref(),
+        super::get_remaining_budget(config),
+        config.temporary_unlock.as_ref(),
+        trusted_now(),
+    )
+}
+
+/// Explain the blocking state at `now`, given the minutes left of the daily
+/// budget (`None` without a budget) and any temporary unlock
+pub fn explain_at(
+    schedules: &[ScheduleEntry],
+    exceptions: &[DateException],
+    household_zone: Option<&str>,
+    remaining_budget: Option<u16>,
+    unlock: Option<&TemporaryUnlock>,
+    now: DateTime<Utc>,
+) -> BlockDecision {
+    if let Some(until) = active_unlock_at(unlock, now) {
+        return decision(false, "temporary unlock", Some(until));
+    }
+
+    // The budget starts over at midnight on the household clock
+    let zone = zone_offset(None, household_zone, now);
+    if remaining_budget == Some(0) {
+        let tomorrow = now.with_timezone(&zone).date_naive().succ_opt();
+        return decision(true, "daily budget exhausted", tomorrow.and_then(|d| midnight(&zone, d)));
+    }
+
+    match calendar_decision(schedules, exceptions, household_zone, now) {
+        Some(CalendarDecision::Exception(mode)) => {
+            let reason = match mode {
+                ExceptionMode::Block => "date exception: blocked all day",
+                ExceptionMode::Allow => "date exception: allowed all day",
+            };
+            let tomorrow = now.with_timezone(&zone).date_naive().succ_opt();
+            decision(mode == ExceptionMode::Block, reason, tomorrow.and_then(|d| midnight(&zone, d)))
+        }
+        Some(CalendarDecision::Schedule(schedule)) => BlockDecision {
+            active_schedule: Some(schedule.name.clone()),
+            ..decision(
+                schedule.blocking_enabled,
+                &format!("schedule \"{}\"", schedule.name),
+                next_change(schedules, household_zone, now),
+            )
+        },
+        None => {
+            let blocking = should_block_with(None, !schedules.is_empty(), remaining_budget);
+            let reason = if blocking { "default (no schedule)" } else { "daily budget remaining" };
+            decision(blocking, reason, next_change(schedules, household_zone, now))
+        }
+    }
+}
+
+/// A decision no schedule made
+fn decision(blocking: bool, reason: &str, until: Option<DateTime<Utc>>) -> BlockDecision {
+    BlockDecision {
+        blocking,
+        reason: reason.to_string(),
+        active_schedule: None,
+        until,
+    }
+}
+
+/// Start of `date` in `zone`
+fn midnight<Tz: TimeZone>(zone: &Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// When the schedules next flip the blocking state. Schedules that share a
+/// zone are walked window by window in it; a mix of zones is stepped through
+/// a minute at a time, up to a week ahead.
+fn next_change(schedules: &[ScheduleEntry], household_zone: Option<&str>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let minute_start = now.duration_trunc(chrono::Duration::minutes(1)).ok()?;
+    let mut zones = schedules
+        .iter()
+        .filter(|schedule| schedule.enabled)
+        .map(|schedule| zone_offset(schedule.timezone.as_deref(), household_zone, now));
+    let zone = zones.next().unwrap_or_else(|| zone_offset(None, household_zone, now));
+
+    if zones.all(|other| other == zone) {
+        let local = now.with_timezone(&zone).naive_local();
+        let minutes = minutes_until_change_at(
+            schedules,
+            local.weekday().num_days_from_sunday() as u8,
+            (local.hour() * 60 + local.minute()) as u16,
+        )?;
+        return Some(minute_start + chrono::Duration::minutes(minutes as i64));
+    }
+
+    let blocking_at = |at| scheduled_blocking(schedules, &[], household_zone, at).unwrap_or(true);
+    let blocking = blocking_at(now);
+    (1..=7 * 24 * 60)
+        .map(|minutes| minute_start + chrono::Duration::minutes(minutes))
+        .find(|at| blocking_at(*at) != blocking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTC: Option<&str> = Some("UTC");
+
+    /// Wednesday 2026-03-04 at `hour`:00 UTC
+    fn wednesday_at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 4, hour, 0, 0).unwrap()
+    }
+
+    /// Games allowed every afternoon from 15:00 to 20:00
+    fn free_time() -> ScheduleEntry {
+        ScheduleEntry {
+            name: "Free time".to_string(),
+            days: vec![0, 1, 2, 3, 4, 5, 6],
+            start_minutes: 15 * 60,
+            end_minutes: 20 * 60,
+            blocking_enabled: false,
+            ..super::super::create_bedtime_schedule()
+        }
+    }
+
+    #[test]
+    fn test_schedule_reason_names_the_window() {
+        let schedules = vec![free_time()];
+        let explained = explain_at(&schedules, &[], UTC, None, None, wednesday_at(16));
+        assert!(!explained.blocking);
+        assert_eq!(explained.reason, "schedule \"Free time\"");
+        assert_eq!(explained.active_schedule.as_deref(), Some("Free time"));
+        assert_eq!(explained.until, Some(wednesday_at(20)));
+    }
+
+    #[test]
+    fn test_default_and_budget_reasons() {
+        let schedules = vec![free_time()];
+        let explained = explain_at(&schedules, &[], UTC, None, None, wednesday_at(12));
+        assert!(explained.blocking);
+        assert_eq!(explained.reason, "default (no schedule)");
+        assert_eq!(explained.active_schedule, None);
+        assert_eq!(explained.until, Some(wednesday_at(15)));
+
+        let explained = explain_at(&[], &[], UTC, Some(0), None, wednesday_at(12));
+        assert!(explained.blocking);
+        assert_eq!(explained.reason, "daily budget exhausted");
+        assert!(explained.until.is_some_and(|until| until > wednesday_at(12)));
+
+        let explained = explain_at(&[], &[], UTC, Some(30), None, wednesday_at(12));
+        assert!(!explained.blocking);
+        assert_eq!(explained.reason, "daily budget remaining");
+    }
+
+    #[test]
+    fn test_unlock_and_exception_reasons() {
+        let schedules = vec![free_time()];
+        let unlock = TemporaryUnlock::for_minutes(30, wednesday_at(22));
+        let explained = explain_at(&schedules, &[], UTC, Some(0), Some(&unlock), wednesday_at(22));
+        assert!(!explained.blocking);
+        assert_eq!(explained.reason, "temporary unlock");
+        assert_eq!(explained.until, Some(unlock.until));
+
+        let holiday = DateException {
+            date: NaiveDate::from_ymd_opt(2026, 3, 4).unwrap(),
+            mode: ExceptionMode::Allow,
+        };
+        let explained = explain_at(&schedules, &[holiday], UTC, None, None, wednesday_at(22));
+        assert!(!explained.blocking);
+        assert_eq!(explained.reason, "date exception: allowed all day");
+        assert_eq!(explained.until, Some(Utc.with_ymd_and_hms(2026, 3, 5, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_next_change_with_schedules_in_different_zones() {
+        let away = ScheduleEntry {
+            timezone: Some("+02:00".to_string()),
+            ..free_time()
+        };
+        let evening = ScheduleEntry {
+            name: "Evening".to_string(),
+            start_minutes: 21 * 60,
+            end_minutes: 22 * 60,
+            ..free_time()
+        };
+
+        // 12:00 UTC is 14:00 at +02:00, an hour before its free time starts
+        let explained = explain_at(&[away, evening], &[], UTC, None, None, wednesday_at(12));
+        assert!(explained.blocking);
+        assert_eq!(explained.until, Some(wednesday_at(13)));
+    }
+}
@@ -1,3 +1,17 @@
+pub mod budget;
+pub mod clock;
+pub mod cron;
 pub mod engine;
+pub mod exceptions;
+pub mod explain;
+pub mod unlock;
+pub mod validate;
 
+pub use budget::*;
+pub use clock::*;
+pub use cron::*;
 pub use engine::*;
+pub use exceptions::*;
+pub use explain::*;
+pub use unlock::*;
+pub use validate::*;
@@ -0,0 +1,62 @@
+//! Temporary unlocks: a parent grants "30 more minutes" and blocking stays
+//! off until the expiry, whatever the schedules, exceptions or budget say.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest unlock that can be granted in one go
+pub const MAX_UNLOCK_MINUTES: u32 = 24 * 60;
+
+/// Blocking is lifted until `until`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemporaryUnlock {
+    pub until: DateTime<Utc>,
+}
+
+impl TemporaryUnlock {
+    /// An unlock for `minutes` from `now`
+    pub fn for_minutes(minutes: u32, now: DateTime<Utc>) -> Self {
+        Self {
+            until: now + chrono::Duration::minutes(minutes as i64),
+        }
+    }
+
+    /// Whether the unlock still applies at `now`; it ends at `until` exactly
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        now < self.until
+    }
+}
+
+/// Expiry of `unlock` if it is still running at `now`
+pub fn active_unlock_at(unlock: Option<&TemporaryUnlock>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    unlock.filter(|u| u.is_active_at(now)).map(|u| u.until)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_unlock_expires_at_until() {
+        let granted = Utc.with_ymd_and_hms(2026, 3, 4, 18, 0, 0).unwrap();
+        let unlock = TemporaryUnlock::for_minutes(30, granted);
+        let until = Utc.with_ymd_and_hms(2026, 3, 4, 18, 30, 0).unwrap();
+        assert_eq!(unlock.until, until);
+
+        assert!(unlock.is_active_at(granted));
+        assert!(unlock.is_active_at(until - chrono::Duration::milliseconds(1)));
+        assert!(!unlock.is_active_at(until));
+        assert!(!unlock.is_active_at(until + chrono::Duration::seconds(1)));
+    }
+
+    #[test]
+    fn test_active_unlock() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 18, 0, 0).unwrap();
+        let unlock = TemporaryUnlock::for_minutes(10, now);
+
+        assert_eq!(active_unlock_at(Some(&unlock), now), Some(unlock.until));
+        assert_eq!(active_unlock_at(Some(&unlock), unlock.until), None);
+        assert_eq!(active_unlock_at(None, now), None);
+    }
+}
@@ -0,0 +1,277 @@
+//! Validation of schedule sets before they are saved.
+
+use crate::config::ScheduleEntry;
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: usize = 24 * 60;
+const MINUTES_PER_WEEK: usize = 7 * MINUTES_PER_DAY;
+
+/// Kind of problem found in a schedule set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleWarningKind {
+    /// A field is out of range or empty
+    InvalidField,
+    /// A blocking window and an allow window cover the same time
+    Overlap,
+    /// The entry never decides the blocking state (duplicate or fully shadowed)
+    Redundant,
+    /// Blocking is active every minute of the week
+    AlwaysBlocked,
+    /// Blocking is never active
+    AlwaysFree,
+}
+
+/// A problem found in a proposed schedule set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleWarning {
+    pub kind: ScheduleWarningKind,
+    /// IDs of the schedules involved (empty for whole-set warnings)
+    pub schedule_ids: Vec<String>,
+    pub message: String,
+}
+
+impl ScheduleWarning {
+    fn new(kind: ScheduleWarningKind, schedule_ids: Vec<String>, message: String) -> Self {
+        Self {
+            kind,
+            schedule_ids,
+            message,
+        }
+    }
+}
+
+/// Check a schedule set for invalid fields, overlaps, redundant entries and
+/// always-blocked/always-free outcomes. Nothing is saved.
+pub fn validate_schedules(schedules: &[ScheduleEntry]) -> Vec<ScheduleWarning> {
+    let mut warnings = Vec::new();
+
+    let mut valid = Vec::new();
+    for schedule in schedules {
+        let problems = field_problems(schedule);
+        if problems.is_empty() {
+            valid.push(schedule);
+        }
+        for problem in problems {
+            warnings.push(ScheduleWarning::new(
+                ScheduleWarningKind::InvalidField,
+                vec![schedule.id.to_string()],
+                format!("\"{}\": {}", schedule.name, problem),
+            ));
+        }
+    }
+
+    let active: Vec<&ScheduleEntry> = valid.into_iter().filter(|s| s.enabled).collect();
+    if active.is_empty() {
+        return warnings;
+    }
+
+    let coverage: Vec<Vec<bool>> = active.iter().map(|s| week_coverage(s)).collect();
+
+    // Conflicting block/allow windows at the same priority; blocking wins at
+    // runtime. A different priority marks the overlap as intended.
+    for i in 0..active.len() {
+        for j in (i + 1)..active.len() {
+            if active[i].blocking_enabled == active[j].blocking_enabled || active[i].priority != active[j].priority {
+                continue;
+            }
+            if coverage[i].iter().zip(&coverage[j]).any(|(a, b)| *a && *b) {
+                let blocking = if active[i].blocking_enabled { active[i] } else { active[j] };
+                warnings.push(ScheduleWarning::new(
+                    ScheduleWarningKind::Overlap,
+                    vec![active[i].id.to_string(), active[j].id.to_string()],
+                    format!(
+                        "\"{}\" and \"{}\" overlap with different blocking settings at the same priority; \"{}\" takes precedence",
+                        active[i].name, active[j].name, blocking.name
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Resolve which entry decides each minute of the week, as the engine does
+    let winner: Vec<Option<usize>> = (0..MINUTES_PER_WEEK)
+        .map(|slot| {
+            let covering = (0..active.len()).filter(|&index| coverage[index][slot]);
+            let decisive = super::decisive_schedule(covering.clone().map(|index| active[index]))?;
+            covering.into_iter().find(|&index| std::ptr::eq(active[index], decisive))
+        })
+        .collect();
+
+    for (index, schedule) in active.iter().enumerate() {
+        if !winner.contains(&Some(index)) {
+            warnings.push(ScheduleWarning::new(
+                ScheduleWarningKind::Redundant,
+                vec![schedule.id.to_string()],
+                format!("\"{}\" is fully covered by other schedules and never applies", schedule.name),
+            ));
+        }
+    }
+
+    // Minutes without a matching schedule default to blocking
+    let blocked_minutes = winner
+        .iter()
+        .filter(|w| w.map(|i| active[i].blocking_enabled).unwrap_or(true))
+        .count();
+
+    if blocked_minutes == MINUTES_PER_WEEK {
+        warnings.push(ScheduleWarning::new(
+            ScheduleWarningKind::AlwaysBlocked,
+            Vec::new(),
+            "Blocking is active at all times; these schedules never allow free time".to_string(),
+        ));
+    } else if blocked_minutes == 0 {
+        warnings.push(ScheduleWarning::new(
+            ScheduleWarningKind::AlwaysFree,
+            Vec::new(),
+            "Blocking is never active with these schedules".to_string(),
+        ));
+    }
+
+    warnings
+}
+
+/// Check a single schedule's fields before it is saved, describing every problem
+pub fn check_schedule_fields(schedule: &ScheduleEntry) -> Result<(), String> {
+    let problems = field_problems(schedule);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid schedule \"{}\": {}", schedule.name, problems.join("; ")))
+    }
+}
+
+/// Describe any out-of-range or empty fields
+fn field_problems(schedule: &ScheduleEntry) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if schedule.name.trim().is_empty() {
+        problems.push("name is empty".to_string());
+    }
+    if let Some(zone) = schedule.timezone.as_deref() {
+        if super::parse_zone(zone).is_none() {
+            problems.push(format!("timezone {:?} is not a UTC offset or IANA zone name", zone));
+        }
+    }
+    if let Some(expr) = schedule.cron.as_deref() {
+        // Days and times are not used with a cron expression
+        if let Err(e) = expr.parse::<super::CronExpr>() {
+            problems.push(format!("cron expression {:?}: {}", expr, e));
+        }
+        return problems;
+    }
+    if schedule.days.is_empty() {
+        problems.push("no days selected".to_string());
+    }
+    if let Some(day) = schedule.days.iter().find(|d| **d > 6) {
+        problems.push(format!("day {} is out of range (0-6)", day));
+    }
+    if schedule.start_minutes as usize >= MINUTES_PER_DAY {
+        problems.push(format!("start time {} is out of range", schedule.start_minutes));
+    }
+    if schedule.end_minutes as usize >= MINUTES_PER_DAY {
+        problems.push(format!("end time {} is out of range", schedule.end_minutes));
+    }
+    if schedule.start_minutes == schedule.end_minutes {
+        problems.push("start and end time are the same".to_string());
+    }
+
+    problems
+}
+
+/// Minutes of the week covered by a schedule, matching the engine's window rules
+fn week_coverage(schedule: &ScheduleEntry) -> Vec<bool> {
+    let mut covered = vec![false; MINUTES_PER_WEEK];
+    if let Some(cron) = schedule.cron.as_deref().and_then(|expr| expr.parse::<super::CronExpr>().ok()) {
+        for (slot, is_covered) in covered.iter_mut().enumerate() {
+            *is_covered = cron.matches_weekly((slot / MINUTES_PER_DAY) as u8, (slot % MINUTES_PER_DAY) as u16);
+        }
+        return covered;
+    }
+    let start = schedule.start_minutes as usize;
+    let end = schedule.end_minutes as usize;
+
+    for day in schedule.days.iter().filter(|day| **day < 7) {
+        let base = *day as usize * MINUTES_PER_DAY;
+        if start <= end {
+            covered[base + start..base + end].fill(true);
+        } else {
+            // Overnight range (e.g., 22:00 - 06:00) runs into the next morning
+            covered[base + start..base + MINUTES_PER_DAY].fill(true);
+            let next = (*day as usize + 1) % 7 * MINUTES_PER_DAY;
+            covered[next..next + end].fill(true);
+        }
+    }
+
+    covered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn entry(name: &str, days: Vec<u8>, start: u16, end: u16, blocking: bool) -> ScheduleEntry {
+        ScheduleEntry {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            enabled: true,
+            days,
+            start_minutes: start,
+            end_minutes: end,
+            blocking_enabled: blocking,
+            warn_minutes: None,
+            warn_message: None,
+            timezone: None,
+            priority: 0,
+            cron: None,
+        }
+    }
+
+    fn kinds(warnings: &[ScheduleWarning]) -> Vec<ScheduleWarningKind> {
+        warnings.iter().map(|w| w.kind).collect()
+    }
+
+    #[test]
+    fn test_detects_block_allow_overlap() {
+        let school = entry("School", vec![1, 2, 3, 4, 5], 8 * 60, 15 * 60, true);
+        let free = entry("Free", vec![5], 14 * 60, 18 * 60, false);
+
+        let warnings = validate_schedules(&[school.clone(), free.clone()]);
+        let overlap = warnings
+            .iter()
+            .find(|w| w.kind == ScheduleWarningKind::Overlap)
+            .expect("overlap warning");
+        assert_eq!(overlap.schedule_ids, vec![school.id.to_string(), free.id.to_string()]);
+    }
+
+    #[test]
+    fn test_detects_always_free() {
+        let all_day = entry("Free", vec![0, 1, 2, 3, 4, 5, 6], 0, 23 * 60 + 59, false);
+        let late = entry("Late", vec![0, 1, 2, 3, 4, 5, 6], 23 * 60 + 59, 0, false);
+
+        let warnings = validate_schedules(&[all_day, late]);
+        assert!(kinds(&warnings).contains(&ScheduleWarningKind::AlwaysFree));
+        assert!(!kinds(&warnings).contains(&ScheduleWarningKind::Overlap));
+    }
+
+    #[test]
+    fn test_detects_redundant_and_invalid() {
+        let school = entry("School", vec![1, 2, 3, 4, 5], 8 * 60, 15 * 60, true);
+        let mut duplicate = school.clone();
+        duplicate.id = Uuid::new_v4();
+        let broken = entry("", vec![9], 10 * 60, 10 * 60, true);
+
+        let warnings = validate_schedules(&[school, duplicate.clone(), broken]);
+        assert!(warnings
+            .iter()
+            .any(|w| w.kind == ScheduleWarningKind::Redundant && w.schedule_ids == vec![duplicate.id.to_string()]));
+        assert_eq!(
+            kinds(&warnings)
+                .iter()
+                .filter(|k| **k == ScheduleWarningKind::InvalidField)
+                .count(),
+            3
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Tamper-evident log of protection and blocking events.
+//!
+//! The daemon appends an entry whenever protection it had in place goes
+//! missing: immutable flags cleared, the hosts section removed, firewall
+//! rules gone, or the service stopping. Each line carries an HMAC over the
+//! entry and the previous line's HMAC, keyed from the machine id, and a head
+//! file remembers the last one. Editing, removing or reordering lines, or
+//! cutting the log short, breaks the chain and shows up in `verify`.
+
+use crate::daemon::logs::LOG_DIR;
+use crate::security::master_password;
+use crate::security::session::constant_time_eq;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Serializes appends within the process so the chain stays linear
+static APPEND_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// HMAC chained from by the first entry
+const GENESIS_MAC: &str = "";
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Machine ID not available")]
+    NoMachineId,
+    #[error("Audit log line {0} was altered or removed")]
+    Tampered(usize),
+    #[error("Audit log was cut short (expected {expected} entries, found {found})")]
+    Truncated { expected: u64, found: u64 },
+}
+
+/// Something that weakened protection without going through the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperEvent {
+    /// Immutable flags were cleared from ParentShield's files
+    ProtectionDisabled,
+    /// The daemon stopped
+    ServiceStopped,
+    /// The ParentShield section disappeared from the hosts file
+    HostsSectionRemoved,
+    /// Firewall rules were missing when checked
+    FirewallRulesMissing,
+}
+
+impl TamperEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TamperEvent::ProtectionDisabled => "protection_disabled",
+            TamperEvent::ServiceStopped => "service_stopped",
+            TamperEvent::HostsSectionRemoved => "hosts_section_removed",
+            TamperEvent::FirewallRulesMissing => "firewall_rules_missing",
+        }
+    }
+}
+
+/// One event in the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 0
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub event: TamperEvent,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// Where the daemon keeps the tamper log
+pub fn tamper_log_path() -> PathBuf {
+    Path::new(LOG_DIR).join("tamper.log")
+}
+
+/// A chained log file and the key its lines are signed with
+pub struct AuditLog {
+    path: PathBuf,
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, machine_id: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(machine_id.as_bytes());
+        hasher.update(b"parentshield-audit-key-v1");
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&hasher.finalize());
+        Self { path, key }
+    }
+
+    /// The daemon's log, keyed from this machine's id
+    pub fn system() -> Result<Self, AuditError> {
+        let machine_id = master_password::get_machine_id().ok_or(AuditError::NoMachineId)?;
+        Ok(Self::new(tamper_log_path(), &machine_id))
+    }
+
+    fn head_path(&self) -> PathBuf {
+        self.path.with_extension("head")
+    }
+
+    /// Sequence number and HMAC of the last entry written, if any
+    fn read_head(&self) -> Option<(u64, String)> {
+        let head = fs::read_to_string(self.head_path()).ok()?;
+        let (seq, mac) = head.trim().split_once('\t')?;
+        Some((seq.parse().ok()?, mac.to_string()))
+    }
+
+    fn sign(&self, prev_mac: &str, body: &str) -> String {
+        let mut message = Vec::with_capacity(prev_mac.len() + body.len() + 1);
+        message.extend_from_slice(prev_mac.as_bytes());
+        message.push(b'\n');
+        message.extend_from_slice(body.as_bytes());
+        hmac_sha256(&self.key, &message).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Append an event, chained to the last entry
+    pub fn append(&self, event: TamperEvent, detail: Option<String>) -> Result<AuditEntry, AuditError> {
+        let _guard = APPEND_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let (seq, prev_mac) = match self.read_head() {
+            Some((seq, mac)) => (seq + 1, mac),
+            None => (0, GENESIS_MAC.to_string()),
+        };
+        let entry = AuditEntry {
+            seq,
+            timestamp: Utc::now(),
+            event,
+            detail,
+        };
+        // Compact JSON never contains a raw tab, so it can separate the HMAC
+        let body = serde_json::to_string(&entry)?;
+        let mac = self.sign(&prev_mac, &body);
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}\t{}", body, mac)?;
+        fs::write(self.head_path(), format!("{}\t{}\n", seq, mac))?;
+
+        Ok(entry)
+    }
+
+    /// Check every line's HMAC and the chain, returning the entries in order
+    pub fn verify(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        let mut prev_mac = GENESIS_MAC.to_string();
+        for (index, line) in content.lines().enumerate() {
+            let line_no = index + 1;
+            let (body, mac) = line.rsplit_once('\t').ok_or(AuditError::Tampered(line_no))?;
+            if !constant_time_eq(self.sign(&prev_mac, body).as_bytes(), mac.as_bytes()) {
+                return Err(AuditError::Tampered(line_no));
+            }
+            let entry: AuditEntry = serde_json::from_str(body).map_err(|_| AuditError::Tampered(line_no))?;
+            if entry.seq != index as u64 {
+                return Err(AuditError::Tampered(line_no));
+            }
+            entries.push(entry);
+            prev_mac = mac.to_string();
+        }
+
+        // Lines cut from the end leave an intact chain, but not the head
+        if let Some((seq, mac)) = self.read_head() {
+            let found = entries.len() as u64;
+            if found != seq + 1 {
+                return Err(AuditError::Truncated { expected: seq + 1, found });
+            }
+            if mac != prev_mac {
+                return Err(AuditError::Tampered(entries.len()));
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Check the daemon's tamper log, returning how many entries it holds
+pub fn verify_audit_chain() -> Result<usize, AuditError> {
+    Ok(AuditLog::system()?.verify()?.len())
+}
+
+/// Append to the daemon's tamper log, logging rather than failing on errors
+pub fn record(event: TamperEvent, detail: Option<String>) {
+    let result = AuditLog::system().and_then(|log| log.append(event, detail));
+    if let Err(e) = result {
+        tracing::warn!("Failed to write tamper log: {}", e);
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104) with a 32-byte key
+fn hmac_sha256(key: &[u8; 32], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut inner_pad = [0x36u8; BLOCK_SIZE];
+    let mut outer_pad = [0x5cu8; BLOCK_SIZE];
+    for (i, byte) in key.iter().enumerate() {
+        inner_pad[i] ^= byte;
+        outer_pad[i] ^= byte;
+    }
+
+    let inner = Sha256::new().chain_update(inner_pad).chain_update(message).finalize();
+    Sha256::new().chain_update(outer_pad).chain_update(inner).finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_log(dir: &Path) -> AuditLog {
+        AuditLog::new(dir.join("tamper.log"), "test-machine-id")
+    }
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        // Test case 2 uses a short key, which is zero-padded like ours
+        let mut key = [0u8; 32];
+        key[..4].copy_from_slice(b"Jefe");
+        let mac = hmac_sha256(&key, b"what do ya want for nothing?");
+        let hex: String = mac.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn test_chain_verifies_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = test_log(dir.path());
+        assert!(log.verify().unwrap().is_empty());
+
+        log.append(TamperEvent::HostsSectionRemoved, None).unwrap();
+        log.append(TamperEvent::ServiceStopped, Some("shutdown request".to_string())).unwrap();
+
+        let entries = log.verify().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].seq, 1);
+        assert_eq!(entries[1].event, TamperEvent::ServiceStopped);
+    }
+
+    #[test]
+    fn test_edited_line_breaks_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = test_log(dir.path());
+        for event in [
+            TamperEvent::ProtectionDisabled,
+            TamperEvent::FirewallRulesMissing,
+            TamperEvent::ServiceStopped,
+        ] {
+            log.append(event, None).unwrap();
+        }
+
+        let original = fs::read_to_string(dir.path().join("tamper.log")).unwrap();
+        let edited = original.replacen("firewall_rules_missing", "service_stopped", 1);
+        fs::write(dir.path().join("tamper.log"), &edited).unwrap();
+        assert!(matches!(log.verify(), Err(AuditError::Tampered(2))));
+
+        // Dropping a line from the middle breaks the next one
+        let mut lines: Vec<&str> = original.lines().collect();
+        lines.remove(1);
+        fs::write(dir.path().join("tamper.log"), lines.join("\n") + "\n").unwrap();
+        assert!(matches!(log.verify(), Err(AuditError::Tampered(2))));
+    }
+
+    #[test]
+    fn test_truncated_log_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = test_log(dir.path());
+        log.append(TamperEvent::ProtectionDisabled, None).unwrap();
+        log.append(TamperEvent::ServiceStopped, None).unwrap();
+
+        let original = fs::read_to_string(dir.path().join("tamper.log")).unwrap();
+        let first_line = original.lines().next().unwrap();
+        fs::write(dir.path().join("tamper.log"), format!("{}\n", first_line)).unwrap();
+
+        assert!(matches!(
+            log.verify(),
+            Err(AuditError::Truncated { expected: 2, found: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_other_machine_key_fails_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        test_log(dir.path()).append(TamperEvent::ServiceStopped, None).unwrap();
+
+        let other = AuditLog::new(dir.path().join("tamper.log"), "another-machine");
+        assert!(matches!(other.verify(), Err(AuditError::Tampered(1))));
+    }
+}
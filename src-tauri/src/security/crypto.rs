@@ -38,17 +38,35 @@ const ARGON2_OUTPUT_LEN: usize = 32;
 /// Nonce size for AES-256-GCM (96 bits)
 const NONCE_SIZE: usize = 12;
 
-/// Hash a password using Argon2id with OWASP recommended parameters
-pub fn hash_password(password: &str) -> Result<String, CryptoError> {
-    let salt = SaltString::generate(&mut OsRng);
+/// Result of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyOutcome {
+    pub valid: bool,
+    /// The password was right but the hash was made with weaker parameters
+    /// than new hashes get, so it should be hashed again
+    pub needs_rehash: bool,
+}
 
-    let params = Params::new(
+/// The parameters new hashes are made with
+fn current_params() -> Result<Params, CryptoError> {
+    Params::new(
         ARGON2_MEMORY_COST,
         ARGON2_TIME_COST,
         ARGON2_PARALLELISM,
         Some(ARGON2_OUTPUT_LEN),
     )
-    .map_err(|e| CryptoError::HashingFailed(e.to_string()))?;
+    .map_err(|e| CryptoError::HashingFailed(e.to_string()))
+}
+
+/// Hash a password using Argon2id with OWASP recommended parameters.
+/// Passwords arrive in `Zeroizing` buffers so they are wiped once dropped.
+pub fn hash_password(password: &Zeroizing<String>) -> Result<String, CryptoError> {
+    hash_password_with(password, current_params()?)
+}
+
+/// Hash a password using Argon2id with the given parameters
+pub(crate) fn hash_password_with(password: &Zeroizing<String>, params: Params) -> Result<String, CryptoError> {
+    let salt = SaltString::generate(&mut OsRng);
 
     let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
 
@@ -60,25 +78,40 @@ pub fn hash_password(password: &str) -> Result<String, CryptoError> {
 }
 
 /// Verify a password against a stored hash
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, CryptoError> {
+pub fn verify_password(password: &Zeroizing<String>, hash: &str) -> Result<bool, CryptoError> {
+    Ok(verify_password_outcome(password, hash)?.valid)
+}
+
+/// Verify a password against a stored hash, and say whether a correct
+/// password's hash is due an upgrade to the current parameters
+pub fn verify_password_outcome(password: &Zeroizing<String>, hash: &str) -> Result<VerifyOutcome, CryptoError> {
     let parsed_hash = PasswordHash::new(hash)
         .map_err(|e| CryptoError::HashingFailed(e.to_string()))?;
 
-    let params = Params::new(
-        ARGON2_MEMORY_COST,
-        ARGON2_TIME_COST,
-        ARGON2_PARALLELISM,
-        Some(ARGON2_OUTPUT_LEN),
-    )
-    .map_err(|e| CryptoError::HashingFailed(e.to_string()))?;
+    // Verification uses the parameters embedded in the hash
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, current_params()?);
 
-    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let valid = match argon2.verify_password(password.as_bytes(), &parsed_hash) {
+        Ok(()) => true,
+        Err(argon2::password_hash::Error::Password) => false,
+        Err(e) => return Err(CryptoError::HashingFailed(e.to_string())),
+    };
 
-    match argon2.verify_password(password.as_bytes(), &parsed_hash) {
-        Ok(()) => Ok(true),
-        Err(argon2::password_hash::Error::Password) => Ok(false),
-        Err(e) => Err(CryptoError::HashingFailed(e.to_string())),
-    }
+    Ok(VerifyOutcome {
+        valid,
+        needs_rehash: valid && is_outdated(&parsed_hash),
+    })
+}
+
+/// Whether a hash was made with a different algorithm or cheaper parameters than `hash_password` uses
+fn is_outdated(hash: &PasswordHash) -> bool {
+    let Ok(params) = Params::try_from(hash) else {
+        return true;
+    };
+    hash.algorithm != argon2::Algorithm::Argon2id.ident()
+        || params.m_cost() < ARGON2_MEMORY_COST
+        || params.t_cost() < ARGON2_TIME_COST
+        || params.p_cost() < ARGON2_PARALLELISM
 }
 
 /// Derive an encryption key from a machine-specific identifier
@@ -137,14 +170,50 @@ pub fn decrypt(encrypted_data: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, CryptoE
 #[cfg(test)]
 mod tests {
     use super::*;
+    use zeroize::Zeroize;
+
+    fn secret(password: &str) -> Zeroizing<String> {
+        Zeroizing::new(password.to_string())
+    }
 
     #[test]
     fn test_password_hash_and_verify() {
-        let password = "test_password_123!";
-        let hash = hash_password(password).expect("Hashing should succeed");
+        let password = secret("test_password_123!");
+        let hash = hash_password(&password).expect("Hashing should succeed");
 
-        assert!(verify_password(password, &hash).expect("Verification should succeed"));
-        assert!(!verify_password("wrong_password", &hash).expect("Verification should succeed"));
+        assert!(verify_password(&password, &hash).expect("Verification should succeed"));
+        assert!(!verify_password(&secret("wrong_password"), &hash).expect("Verification should succeed"));
+    }
+
+    #[test]
+    fn test_password_paths_take_zeroizing_buffers() {
+        // The hashing path only accepts buffers that wipe themselves on drop
+        let _: fn(&Zeroizing<String>) -> Result<String, CryptoError> = hash_password;
+        let _: fn(&Zeroizing<String>, &str) -> Result<bool, CryptoError> = verify_password;
+
+        let mut password = secret("test_password_123!");
+        hash_password(&password).unwrap();
+        password.zeroize();
+        assert!(password.is_empty());
+    }
+
+    #[test]
+    fn test_weaker_hash_needs_rehash() {
+        let password = secret("test_password_123!");
+        let weak_params =
+            Params::new(8 * 1024, ARGON2_TIME_COST, ARGON2_PARALLELISM, Some(ARGON2_OUTPUT_LEN)).unwrap();
+        let weak_hash = hash_password_with(&password, weak_params).unwrap();
+
+        let outcome = verify_password_outcome(&password, &weak_hash).unwrap();
+        assert_eq!(outcome, VerifyOutcome { valid: true, needs_rehash: true });
+
+        // Only a correct password asks for a rehash
+        let outcome = verify_password_outcome(&secret("wrong_password"), &weak_hash).unwrap();
+        assert_eq!(outcome, VerifyOutcome { valid: false, needs_rehash: false });
+
+        let current_hash = hash_password(&password).unwrap();
+        let outcome = verify_password_outcome(&password, &current_hash).unwrap();
+        assert_eq!(outcome, VerifyOutcome { valid: true, needs_rehash: false });
     }
 
     #[test]
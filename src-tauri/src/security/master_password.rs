@@ -1,6 +1,7 @@
 //! Master recovery password generation using NATO phonetic alphabet.
 //! The master password is derived from hardware fingerprint and never stored.
 
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 /// NATO phonetic alphabet words for human-readable password generation
@@ -11,65 +12,178 @@ const NATO_ALPHABET: [&str; 26] = [
     "XRAY", "YANKEE", "ZULU",
 ];
 
+/// Fewest words a master password can have, so it can't be guessed by
+/// trying every 4-digit number
+pub const MIN_MASTER_WORDS: u8 = 3;
+
+/// Most words a master password can have
+pub const MAX_MASTER_WORDS: u8 = 8;
+
+/// Shape of the master password, chosen at install time and kept in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MasterPasswordFormat {
+    /// Number of NATO words, at least `MIN_MASTER_WORDS`
+    pub words: u8,
+    /// Whether a 4-digit number goes before the last word
+    pub include_number: bool,
+}
+
+impl Default for MasterPasswordFormat {
+    /// "WORD-WORD-NNNN-WORD", the format every install used before it was configurable
+    fn default() -> Self {
+        Self {
+            words: 3,
+            include_number: true,
+        }
+    }
+}
+
+impl MasterPasswordFormat {
+    /// Whether the word count is one a master password can be generated with
+    pub fn is_supported(&self) -> bool {
+        (MIN_MASTER_WORDS..=MAX_MASTER_WORDS).contains(&self.words)
+    }
+
+    /// Clamp to a supported size: between `MIN_MASTER_WORDS` and
+    /// `MAX_MASTER_WORDS` words
+    fn normalized(self) -> Self {
+        Self {
+            words: self.words.clamp(MIN_MASTER_WORDS, MAX_MASTER_WORDS),
+            ..self
+        }
+    }
+}
+
 /// Generate a master recovery password from hardware fingerprint
-/// Format: "WORD-WORD-NNNN-WORD" (e.g., "ALPHA-BRAVO-1234-DELTA")
+/// Default format: "WORD-WORD-NNNN-WORD" (e.g., "ALPHA-BRAVO-1234-DELTA")
 /// This password is computed on-demand and never stored on the device.
-pub fn generate_master_password(machine_id: &str, installation_timestamp: u64) -> String {
+/// Bumping `epoch` rotates it; epoch 0 gives the password installs had
+/// before rotation existed.
+pub fn generate_master_password(
+    machine_id: &str,
+    installation_timestamp: u64,
+    epoch: u64,
+    format: MasterPasswordFormat,
+) -> String {
     // Create a deterministic hash from machine info
     let mut hasher = Sha256::new();
     hasher.update(machine_id.as_bytes());
     hasher.update(installation_timestamp.to_le_bytes());
+    if epoch > 0 {
+        hasher.update(epoch.to_le_bytes());
+    }
     hasher.update(b"parentshield-master-recovery-v1");
 
     let hash = hasher.finalize();
+    let format = format.normalized();
+
+    // The first three words come from bytes 0-2 and the number from bytes 3-4,
+    // as in the original format; further words continue after the number
+    let mut parts: Vec<String> = (0..format.words as usize)
+        .map(|i| {
+            let byte = if i < 3 { hash[i] } else { hash[i + 2] };
+            NATO_ALPHABET[byte as usize % NATO_ALPHABET.len()].to_string()
+        })
+        .collect();
+
+    if format.include_number {
+        // Generate 4-digit number from hash bytes
+        let number = ((hash[3] as u16) << 8 | hash[4] as u16) % 10000;
+        let at = parts.len().saturating_sub(1);
+        parts.insert(at, format!("{:04}", number));
+    }
 
-    // Extract parts from the hash
-    let word1_idx = (hash[0] as usize) % NATO_ALPHABET.len();
-    let word2_idx = (hash[1] as usize) % NATO_ALPHABET.len();
-    let word3_idx = (hash[2] as usize) % NATO_ALPHABET.len();
-
-    // Generate 4-digit number from hash bytes
-    let number = ((hash[3] as u16) << 8 | hash[4] as u16) % 10000;
-
-    format!(
-        "{}-{}-{:04}-{}",
-        NATO_ALPHABET[word1_idx],
-        NATO_ALPHABET[word2_idx],
-        number,
-        NATO_ALPHABET[word3_idx]
-    )
+    parts.join("-")
 }
 
-/// Verify a master password against the expected value
+/// Verify a master password against the expected value for the format it was issued in
 pub fn verify_master_password(
     input: &str,
     machine_id: &str,
     installation_timestamp: u64,
+    epoch: u64,
+    format: MasterPasswordFormat,
 ) -> bool {
-    let expected = generate_master_password(machine_id, installation_timestamp);
+    let expected = generate_master_password(machine_id, installation_timestamp, epoch, format);
 
     // Case-insensitive comparison
     input.to_uppercase().trim() == expected
 }
 
+/// Where the machine identifier was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MachineIdSource {
+    /// /etc/machine-id (systemd)
+    EtcMachineId,
+    /// /var/lib/dbus/machine-id
+    DbusMachineId,
+    /// HKLM\SOFTWARE\Microsoft\Cryptography\MachineGuid
+    WindowsMachineGuid,
+    /// IOPlatformUUID from ioreg
+    MacPlatformUuid,
+}
+
+/// Which machine id is in use, without revealing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineFingerprint {
+    pub source: MachineIdSource,
+    /// Truncated SHA-256 of the id, enough to tell two ids apart
+    pub id_hash: String,
+}
+
+/// Hash a machine id for display; the raw id never leaves the device
+pub fn fingerprint_id(source: MachineIdSource, machine_id: &str) -> MachineFingerprint {
+    let mut hasher = Sha256::new();
+    hasher.update(b"parentshield-machine-fingerprint-v1");
+    hasher.update(machine_id.as_bytes());
+    let hash = hasher.finalize();
+
+    MachineFingerprint {
+        source,
+        id_hash: hash[..8].iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+/// Fingerprint of the machine id the app is currently using
+pub fn machine_fingerprint() -> Option<MachineFingerprint> {
+    let (source, machine_id) = get_machine_id_with_source()?;
+    Some(fingerprint_id(source, &machine_id))
+}
+
 /// Get the machine identifier for master password generation
-#[cfg(target_os = "linux")]
 pub fn get_machine_id() -> Option<String> {
-    // Try /etc/machine-id first (systemd)
-    if let Ok(id) = std::fs::read_to_string("/etc/machine-id") {
-        return Some(id.trim().to_string());
-    }
+    get_machine_id_with_source().map(|(_, id)| id)
+}
 
-    // Fallback to /var/lib/dbus/machine-id
-    if let Ok(id) = std::fs::read_to_string("/var/lib/dbus/machine-id") {
-        return Some(id.trim().to_string());
-    }
+/// Read the first machine id file that exists
+#[cfg(any(target_os = "linux", test))]
+fn read_first_machine_id(
+    candidates: &[(MachineIdSource, &std::path::Path)],
+) -> Option<(MachineIdSource, String)> {
+    candidates.iter().find_map(|(source, path)| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|id| (*source, id.trim().to_string()))
+    })
+}
 
-    None
+/// Get the machine identifier along with where it came from
+#[cfg(target_os = "linux")]
+pub fn get_machine_id_with_source() -> Option<(MachineIdSource, String)> {
+    use std::path::Path;
+
+    // /etc/machine-id (systemd) first, then the D-Bus copy
+    read_first_machine_id(&[
+        (MachineIdSource::EtcMachineId, Path::new("/etc/machine-id")),
+        (MachineIdSource::DbusMachineId, Path::new("/var/lib/dbus/machine-id")),
+    ])
 }
 
 #[cfg(target_os = "windows")]
-pub fn get_machine_id() -> Option<String> {
+pub fn get_machine_id_with_source() -> Option<(MachineIdSource, String)> {
     // Use Windows registry MachineGuid
     use winreg::enums::*;
     use winreg::RegKey;
@@ -77,7 +191,7 @@ pub fn get_machine_id() -> Option<String> {
     let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
     if let Ok(key) = hklm.open_subkey("SOFTWARE\\Microsoft\\Cryptography") {
         if let Ok(guid) = key.get_value::<String, _>("MachineGuid") {
-            return Some(guid);
+            return Some((MachineIdSource::WindowsMachineGuid, guid));
         }
     }
 
@@ -85,7 +199,7 @@ pub fn get_machine_id() -> Option<String> {
 }
 
 #[cfg(target_os = "macos")]
-pub fn get_machine_id() -> Option<String> {
+pub fn get_machine_id_with_source() -> Option<(MachineIdSource, String)> {
     // Use IOKit to get hardware UUID
     use std::process::Command;
 
@@ -100,7 +214,7 @@ pub fn get_machine_id() -> Option<String> {
     for line in output_str.lines() {
         if line.contains("IOPlatformUUID") {
             if let Some(uuid) = line.split('"').nth(3) {
-                return Some(uuid.to_string());
+                return Some((MachineIdSource::MacPlatformUuid, uuid.to_string()));
             }
         }
     }
@@ -109,7 +223,7 @@ pub fn get_machine_id() -> Option<String> {
 }
 
 #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
-pub fn get_machine_id() -> Option<String> {
+pub fn get_machine_id_with_source() -> Option<(MachineIdSource, String)> {
     None
 }
 
@@ -119,7 +233,8 @@ mod tests {
 
     #[test]
     fn test_generate_master_password_format() {
-        let password = generate_master_password("test-machine-id", 1234567890);
+        let password =
+            generate_master_password("test-machine-id", 1234567890, 0, MasterPasswordFormat::default());
 
         // Check format: WORD-WORD-NNNN-WORD
         let parts: Vec<&str> = password.split('-').collect();
@@ -137,16 +252,16 @@ mod tests {
 
     #[test]
     fn test_master_password_deterministic() {
-        let password1 = generate_master_password("test-machine", 1000);
-        let password2 = generate_master_password("test-machine", 1000);
+        let password1 = generate_master_password("test-machine", 1000, 0, MasterPasswordFormat::default());
+        let password2 = generate_master_password("test-machine", 1000, 0, MasterPasswordFormat::default());
 
         assert_eq!(password1, password2);
     }
 
     #[test]
     fn test_master_password_unique() {
-        let password1 = generate_master_password("machine-1", 1000);
-        let password2 = generate_master_password("machine-2", 1000);
+        let password1 = generate_master_password("machine-1", 1000, 0, MasterPasswordFormat::default());
+        let password2 = generate_master_password("machine-2", 1000, 0, MasterPasswordFormat::default());
 
         assert_ne!(password1, password2);
     }
@@ -155,10 +270,132 @@ mod tests {
     fn test_verify_master_password() {
         let machine_id = "test-machine";
         let timestamp = 1234567890u64;
-        let password = generate_master_password(machine_id, timestamp);
+        let format = MasterPasswordFormat::default();
+        let password = generate_master_password(machine_id, timestamp, 0, format);
+
+        assert!(verify_master_password(&password, machine_id, timestamp, 0, format));
+        assert!(verify_master_password(&password.to_lowercase(), machine_id, timestamp, 0, format));
+        assert!(!verify_master_password("WRONG-PASSWORD-1234-TEST", machine_id, timestamp, 0, format));
+    }
 
-        assert!(verify_master_password(&password, machine_id, timestamp));
-        assert!(verify_master_password(&password.to_lowercase(), machine_id, timestamp));
-        assert!(!verify_master_password("WRONG-PASSWORD-1234-TEST", machine_id, timestamp));
+    #[test]
+    fn test_default_format_matches_original_layout() {
+        let mut hasher = Sha256::new();
+        hasher.update(b"test-machine");
+        hasher.update(1000u64.to_le_bytes());
+        hasher.update(b"parentshield-master-recovery-v1");
+        let hash = hasher.finalize();
+
+        let word = |byte: u8| NATO_ALPHABET[byte as usize % NATO_ALPHABET.len()];
+        let number = ((hash[3] as u16) << 8 | hash[4] as u16) % 10000;
+        let original = format!("{}-{}-{:04}-{}", word(hash[0]), word(hash[1]), number, word(hash[2]));
+
+        assert_eq!(
+            generate_master_password("test-machine", 1000, 0, MasterPasswordFormat::default()),
+            original
+        );
+    }
+
+    #[test]
+    fn test_four_word_format_round_trips() {
+        let format = MasterPasswordFormat {
+            words: 4,
+            include_number: true,
+        };
+        let password = generate_master_password("test-machine", 1000, 0, format);
+
+        let parts: Vec<&str> = password.split('-').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[3].parse::<u16>().is_ok());
+        assert!(verify_master_password(&password, "test-machine", 1000, 0, format));
+
+        // Only verifies in the format it was issued in
+        let default = MasterPasswordFormat::default();
+        assert!(!verify_master_password(&password, "test-machine", 1000, 0, default));
+    }
+
+    #[test]
+    fn test_number_free_format_round_trips() {
+        let words_only = MasterPasswordFormat {
+            words: 5,
+            include_number: false,
+        };
+        let password = generate_master_password("test-machine", 1000, 0, words_only);
+        let parts: Vec<&str> = password.split('-').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts.iter().all(|part| NATO_ALPHABET.contains(part)));
+        assert!(verify_master_password(&password, "test-machine", 1000, 0, words_only));
+    }
+
+    #[test]
+    fn test_unsupported_formats_are_clamped() {
+        let no_words = MasterPasswordFormat {
+            words: 0,
+            include_number: true,
+        };
+        assert!(!no_words.is_supported());
+        let password = generate_master_password("test-machine", 1000, 0, no_words);
+        assert_eq!(password.split('-').count(), MIN_MASTER_WORDS as usize + 1);
+
+        let one_word = MasterPasswordFormat {
+            words: 1,
+            include_number: false,
+        };
+        let password = generate_master_password("test-machine", 1000, 0, one_word);
+        assert_eq!(password.split('-').count(), MIN_MASTER_WORDS as usize);
+
+        assert!(MasterPasswordFormat::default().is_supported());
+        assert!(!MasterPasswordFormat {
+            words: MAX_MASTER_WORDS + 1,
+            include_number: false,
+        }
+        .is_supported());
+    }
+
+    #[test]
+    fn test_new_epoch_invalidates_previous_master() {
+        let format = MasterPasswordFormat::default();
+        let first = generate_master_password("test-machine", 1000, 0, format);
+        let second = generate_master_password("test-machine", 1000, 1, format);
+
+        assert_ne!(first, second);
+        assert!(verify_master_password(&second, "test-machine", 1000, 1, format));
+        assert!(!verify_master_password(&first, "test-machine", 1000, 1, format));
+    }
+
+    #[test]
+    fn test_fingerprint_source_is_first_available_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let etc = dir.path().join("etc-machine-id");
+        let dbus = dir.path().join("dbus-machine-id");
+        std::fs::write(&dbus, "dbus-id\n").unwrap();
+        let candidates = [
+            (MachineIdSource::EtcMachineId, etc.as_path()),
+            (MachineIdSource::DbusMachineId, dbus.as_path()),
+        ];
+
+        let (source, id) = read_first_machine_id(&candidates).unwrap();
+        assert_eq!(source, MachineIdSource::DbusMachineId);
+        assert_eq!(id, "dbus-id");
+
+        std::fs::write(&etc, "etc-id\n").unwrap();
+        let (source, id) = read_first_machine_id(&candidates).unwrap();
+        assert_eq!(source, MachineIdSource::EtcMachineId);
+        assert_eq!(fingerprint_id(source, &id).source, MachineIdSource::EtcMachineId);
+    }
+
+    #[test]
+    fn test_fingerprint_hides_raw_id() {
+        let fingerprint = fingerprint_id(MachineIdSource::EtcMachineId, "0123456789abcdef");
+        assert_eq!(fingerprint.id_hash.len(), 16);
+        assert!(!fingerprint.id_hash.contains("0123456789abcdef"));
+        assert_eq!(
+            fingerprint,
+            fingerprint_id(MachineIdSource::EtcMachineId, "0123456789abcdef")
+        );
+        assert_ne!(
+            fingerprint.id_hash,
+            fingerprint_id(MachineIdSource::EtcMachineId, "fedcba9876543210").id_hash
+        );
     }
 }
@@ -1,5 +1,9 @@
+pub mod audit;
 pub mod crypto;
 pub mod master_password;
+pub mod ratelimit;
+pub mod recovery;
+pub mod session;
 pub mod uninstall_protection;
 
 pub use crypto::*;
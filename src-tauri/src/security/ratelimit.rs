@@ -0,0 +1,128 @@
+//! Failed password attempt tracking.
+//! Too many wrong passwords in a short window lock verification for a while,
+//! and each lockout in a row lasts twice as long as the one before, so a weak
+//! PIN can't be guessed through the unlock dialog. The record is kept in the
+//! encrypted config, so neither a restart nor deleting a file clears it.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Failures within `FAILURE_WINDOW` that trigger a lockout
+pub const MAX_FAILURES: usize = 5;
+
+/// How far back failures are counted
+pub const FAILURE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Length of the first lockout
+pub const BASE_LOCKOUT: Duration = Duration::from_secs(60);
+
+/// Longest a lockout can grow to
+pub const MAX_LOCKOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Recent failures and lockout state, in unix seconds
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttemptTracker {
+    failures: Vec<u64>,
+    locked_until: Option<u64>,
+    /// Lockouts since the last correct password
+    lockouts: u32,
+}
+
+impl AttemptTracker {
+    /// How long until another attempt is allowed, if verification is locked
+    pub fn retry_after(&self, now: u64) -> Option<Duration> {
+        self.locked_until
+            .filter(|&until| until > now)
+            .map(|until| Duration::from_secs(until - now))
+    }
+
+    /// Record a wrong password, starting a lockout once too many pile up
+    pub fn record_failure(&mut self, now: u64) {
+        let window_start = now.saturating_sub(FAILURE_WINDOW.as_secs());
+        self.failures.retain(|&at| at > window_start);
+        self.failures.push(now);
+
+        if self.failures.len() >= MAX_FAILURES {
+            let lockout = BASE_LOCKOUT
+                .saturating_mul(1 << self.lockouts.min(16))
+                .min(MAX_LOCKOUT);
+            self.locked_until = Some(now + lockout.as_secs());
+            self.lockouts = self.lockouts.saturating_add(1);
+            self.failures.clear();
+        }
+    }
+
+    /// Forget everything after a correct password
+    pub fn record_success(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether there is anything worth saving
+    pub fn is_clear(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_700_000_000;
+
+    fn fail(tracker: &mut AttemptTracker, times: usize, at: u64) {
+        for _ in 0..times {
+            tracker.record_failure(at);
+        }
+    }
+
+    #[test]
+    fn test_lockout_after_max_failures() {
+        let mut tracker = AttemptTracker::default();
+        fail(&mut tracker, MAX_FAILURES - 1, START);
+        assert_eq!(tracker.retry_after(START), None);
+
+        tracker.record_failure(START + 10);
+        assert_eq!(tracker.retry_after(START + 10), Some(BASE_LOCKOUT));
+        assert_eq!(tracker.retry_after(START + 40), Some(BASE_LOCKOUT - Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_failures_outside_window_are_forgotten() {
+        let mut tracker = AttemptTracker::default();
+        fail(&mut tracker, MAX_FAILURES - 1, START);
+
+        let later = START + FAILURE_WINDOW.as_secs() + 1;
+        tracker.record_failure(later);
+        assert_eq!(tracker.retry_after(later), None);
+    }
+
+    #[test]
+    fn test_lockout_expires_and_backs_off() {
+        let mut tracker = AttemptTracker::default();
+        fail(&mut tracker, MAX_FAILURES, START);
+
+        // Allowed again once the lockout has passed
+        let after_first = START + BASE_LOCKOUT.as_secs();
+        assert_eq!(tracker.retry_after(after_first), None);
+
+        // The next lockout is twice as long
+        fail(&mut tracker, MAX_FAILURES, after_first);
+        assert_eq!(tracker.retry_after(after_first), Some(BASE_LOCKOUT * 2));
+
+        // A correct password resets the backoff
+        tracker.record_success();
+        assert!(tracker.is_clear());
+        fail(&mut tracker, MAX_FAILURES, after_first);
+        assert_eq!(tracker.retry_after(after_first), Some(BASE_LOCKOUT));
+    }
+
+    #[test]
+    fn test_lockout_survives_serialization() {
+        let mut tracker = AttemptTracker::default();
+        fail(&mut tracker, MAX_FAILURES, START);
+
+        let json = serde_json::to_vec(&tracker).unwrap();
+        let restored: AttemptTracker = serde_json::from_slice(&json).unwrap();
+        assert_eq!(restored.retry_after(START), Some(BASE_LOCKOUT));
+    }
+}
@@ -0,0 +1,270 @@
+//! Admin recovery mode: the escape hatch for a parent who is locked out.
+//!
+//! When the config is corrupt, or the password is forgotten and the master
+//! password can't be computed because the machine id changed, a local
+//! administrator can run `parentshield-daemon --recover`. Recovery stops the
+//! daemon, removes every blocking layer and file protection, and moves the
+//! encrypted config aside so the app starts at first-run setup again.
+//!
+//! This is deliberately gated on OS admin rights rather than the parent
+//! password: a child with root/Administrator can already remove the software.
+//! Every attempt that gets past the admin check is written to the audit log
+//! before anything is changed.
+
+use crate::config::ConfigManager;
+use crate::daemon::logs::LOG_DIR;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Text the admin must type to go ahead
+pub const RECOVERY_CONFIRMATION: &str = "RESET PARENTSHIELD";
+
+/// Shown before asking for confirmation
+pub const RECOVERY_WARNING: &str = "\
+WARNING: ParentShield recovery mode
+
+This removes ALL blocking (hosts file, firewall rules, browser policies),
+turns off uninstall protection, stops the ParentShield service and resets
+the parent password. Schedules and blocklists are not kept.
+
+Only use this if the parent password and master recovery password no longer
+work. This action is recorded in the audit log.";
+
+#[derive(Error, Debug)]
+pub enum RecoveryError {
+    #[error("Recovery requires root or Administrator rights")]
+    NotAdmin,
+    #[error("Recovery was not confirmed")]
+    NotConfirmed,
+    #[error("Could not write audit log: {0}")]
+    Audit(#[from] std::io::Error),
+}
+
+/// Result of one recovery action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryStep {
+    pub step: String,
+    /// Why the step failed, if it did; recovery carries on regardless
+    pub error: Option<String>,
+}
+
+impl RecoveryStep {
+    fn from_result<E: std::fmt::Display>(step: &str, result: Result<(), E>) -> Self {
+        Self {
+            step: step.to_string(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+}
+
+/// One line in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub event: String,
+    /// Account that invoked recovery (via sudo if applicable)
+    pub user: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<RecoveryStep>,
+}
+
+/// Audit log all recovery attempts are recorded in
+pub fn audit_log_path() -> PathBuf {
+    Path::new(LOG_DIR).join("audit.log")
+}
+
+/// Whether the current process has root/Administrator rights
+#[cfg(unix)]
+pub fn is_admin() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(windows)]
+pub fn is_admin() -> bool {
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    unsafe {
+        let mut token = HANDLE::default();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).is_err() {
+            return false;
+        }
+
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut returned = 0u32;
+        let queried = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut TOKEN_ELEVATION as *mut _),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut returned,
+        )
+        .is_ok();
+        let _ = CloseHandle(token);
+
+        queried && elevation.TokenIsElevated != 0
+    }
+}
+
+/// Best guess at the human behind the admin session
+fn invoking_user() -> Option<String> {
+    ["SUDO_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+}
+
+/// Append a record to the audit log
+pub fn append_audit(path: &Path, record: &AuditRecord) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(record)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// Check admin rights and confirmation, audit, then run `reset`.
+/// Nothing is changed unless the "started" record was written.
+pub fn run_recovery<F>(
+    is_admin: bool,
+    confirmation: &str,
+    audit_log: &Path,
+    reset: F,
+) -> Result<Vec<RecoveryStep>, RecoveryError>
+where
+    F: FnOnce() -> Vec<RecoveryStep>,
+{
+    if !is_admin {
+        return Err(RecoveryError::NotAdmin);
+    }
+    if confirmation.trim() != RECOVERY_CONFIRMATION {
+        return Err(RecoveryError::NotConfirmed);
+    }
+
+    let user = invoking_user();
+    append_audit(
+        audit_log,
+        &AuditRecord {
+            timestamp: Utc::now(),
+            event: "recovery_started".to_string(),
+            user: user.clone(),
+            steps: Vec::new(),
+        },
+    )?;
+    tracing::warn!("Recovery mode started by {:?}", user);
+
+    let steps = reset();
+
+    append_audit(
+        audit_log,
+        &AuditRecord {
+            timestamp: Utc::now(),
+            event: "recovery_completed".to_string(),
+            user,
+            steps: steps.clone(),
+        },
+    )?;
+    Ok(steps)
+}
+
+/// Move the encrypted config aside so the app starts at setup again
+fn reset_config() -> Result<(), String> {
+    let path = ConfigManager::default_config_path().map_err(|e| e.to_string())?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut backup = path.clone().into_os_string();
+    backup.push(format!(".recovered-{}", Utc::now().format("%Y%m%d%H%M%S")));
+    fs::rename(&path, &backup).map_err(|e| e.to_string())
+}
+
+/// Remove every blocking layer and reset the auth config
+pub fn remove_all_blocking_and_reset() -> Vec<RecoveryStep> {
+    let service = crate::daemon::get_service_manager();
+    let firewall = {
+        #[cfg(target_os = "linux")]
+        {
+            crate::blocking::remove_network_blocking_direct().map_err(|e| e.to_string())
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            crate::blocking::remove_network_blocking().map_err(|e| e.to_string())
+        }
+    };
+
+    vec![
+        RecoveryStep::from_result("stop_service", service.stop()),
+        RecoveryStep::from_result("disable_protection", crate::security::disable_protection()),
+        RecoveryStep::from_result("hosts_file", crate::blocking::unblock_all_domains_direct()),
+        RecoveryStep::from_result("firewall", firewall),
+        RecoveryStep::from_result(
+            "browser_policies",
+            crate::blocking::enable_doh_all_browsers().map(|_| ()),
+        ),
+        RecoveryStep::from_result("reset_config", reset_config()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_records(path: &Path) -> Vec<AuditRecord> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_recovery_requires_admin() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = dir.path().join("audit.log");
+        let mut ran = false;
+
+        let result = run_recovery(false, RECOVERY_CONFIRMATION, &audit_log, || {
+            ran = true;
+            Vec::new()
+        });
+        assert!(matches!(result, Err(RecoveryError::NotAdmin)));
+        assert!(!ran);
+        assert!(!audit_log.exists());
+    }
+
+    #[test]
+    fn test_recovery_requires_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = dir.path().join("audit.log");
+
+        let result = run_recovery(true, "yes", &audit_log, Vec::new);
+        assert!(matches!(result, Err(RecoveryError::NotConfirmed)));
+        assert!(!audit_log.exists());
+    }
+
+    #[test]
+    fn test_recovery_writes_audit_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = dir.path().join("logs").join("audit.log");
+
+        let steps = run_recovery(true, "RESET PARENTSHIELD\n", &audit_log, || {
+            vec![
+                RecoveryStep::from_result::<String>("hosts_file", Ok(())),
+                RecoveryStep::from_result("firewall", Err("iptables missing")),
+            ]
+        })
+        .unwrap();
+        assert_eq!(steps.len(), 2);
+
+        let records = read_records(&audit_log);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].event, "recovery_started");
+        assert_eq!(records[1].event, "recovery_completed");
+        assert_eq!(records[1].steps[1].error.as_deref(), Some("iptables missing"));
+    }
+}
@@ -0,0 +1,126 @@
+//! In-memory parent authentication session.
+//! A correct password opens a session identified by a random token; sensitive
+//! commands accept the token instead of the password until it expires.
+
+use aes_gcm::aead::OsRng;
+use argon2::password_hash::rand_core::RngCore;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a session stays valid without being used
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// The current parent session, shared by all commands
+pub static AUTH_SESSION: Lazy<Mutex<SessionStore>> =
+    Lazy::new(|| Mutex::new(SessionStore::new(SESSION_TIMEOUT)));
+
+struct Session {
+    token: String,
+    expires_at: Instant,
+}
+
+/// Holds at most one authenticated session
+pub struct SessionStore {
+    session: Option<Session>,
+    timeout: Duration,
+}
+
+impl SessionStore {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            session: None,
+            timeout,
+        }
+    }
+
+    /// Start a new session, replacing any existing one, and return its token
+    pub fn establish(&mut self, now: Instant) -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        self.session = Some(Session {
+            token: token.clone(),
+            expires_at: now + self.timeout,
+        });
+        token
+    }
+
+    /// Check a token; a valid token extends the session
+    pub fn validate(&mut self, token: &str, now: Instant) -> bool {
+        if !self.is_active(now) {
+            return false;
+        }
+
+        let timeout = self.timeout;
+        match self.session.as_mut() {
+            Some(session) if constant_time_eq(session.token.as_bytes(), token.as_bytes()) => {
+                session.expires_at = now + timeout;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether an unexpired session exists (expired sessions are dropped)
+    pub fn is_active(&mut self, now: Instant) -> bool {
+        if self.session.as_ref().is_some_and(|s| now >= s.expires_at) {
+            self.session = None;
+        }
+        self.session.is_some()
+    }
+
+    /// End the session
+    pub fn clear(&mut self) {
+        self.session = None;
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_establish_and_validate() {
+        let mut store = SessionStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(!store.is_active(now));
+
+        let token = store.establish(now);
+        assert_eq!(token.len(), 64);
+        assert!(store.is_active(now));
+        assert!(store.validate(&token, now + Duration::from_secs(30)));
+        assert!(!store.validate("not-the-token", now));
+    }
+
+    #[test]
+    fn test_session_expires() {
+        let mut store = SessionStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let token = store.establish(now);
+
+        // Use extends the session
+        assert!(store.validate(&token, now + Duration::from_secs(50)));
+        assert!(store.validate(&token, now + Duration::from_secs(100)));
+
+        // Idle past the timeout
+        assert!(!store.validate(&token, now + Duration::from_secs(200)));
+        assert!(!store.is_active(now + Duration::from_secs(200)));
+    }
+
+    #[test]
+    fn test_rejects_without_session() {
+        let mut store = SessionStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+        let token = store.establish(now);
+        store.clear();
+
+        assert!(!store.validate(&token, now));
+        assert!(!store.validate("", now));
+    }
+}
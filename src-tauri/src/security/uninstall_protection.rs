@@ -4,9 +4,9 @@
 //! children from uninstalling or disabling the parental control software.
 
 use crate::config::ConfigManager;
-use crate::security::crypto;
 use std::process::Command;
 use thiserror::Error;
+use zeroize::Zeroizing;
 
 #[derive(Error, Debug)]
 pub enum ProtectionError {
@@ -19,11 +19,10 @@ pub enum ProtectionError {
 }
 
 /// Verify parent password before allowing uninstall
-pub fn verify_uninstall_password(password: &str) -> Result<bool, ProtectionError> {
+pub fn verify_uninstall_password(password: &Zeroizing<String>) -> Result<bool, ProtectionError> {
     let manager = ConfigManager::new().map_err(|e| ProtectionError::ConfigError(e.to_string()))?;
-    let config = manager.load().map_err(|e| ProtectionError::ConfigError(e.to_string()))?;
-
-    let valid = crypto::verify_password(password, &config.password_hash)
+    let valid = manager
+        .verify_password(password)
         .map_err(|e| ProtectionError::ConfigError(e.to_string()))?;
 
     if valid {
@@ -61,6 +60,174 @@ pub fn disable_protection() -> Result<(), ProtectionError> {
     Ok(())
 }
 
+/// Whether ParentShield's own files currently carry immutable flags
+#[cfg(target_os = "linux")]
+pub fn file_protection_active() -> bool {
+    // lsattr prints the flags field first, e.g. "----i---------e------- /usr/bin/parentshield"
+    Command::new("lsattr")
+        .args(["-d", "/usr/bin/parentshield-daemon"])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|out| out.split_whitespace().next().map(|flags| flags.contains('i')))
+        .unwrap_or(false)
+}
+
+/// Whether ParentShield's own files currently carry immutable flags
+#[cfg(target_os = "macos")]
+pub fn file_protection_active() -> bool {
+    Command::new("ls")
+        .args(["-lO", "/Library/LaunchDaemons/com.parentshield.daemon.plist"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains("uchg"))
+        .unwrap_or(false)
+}
+
+/// Windows protects files with ACLs, which don't get in an installer's way
+#[cfg(target_os = "windows")]
+pub fn file_protection_active() -> bool {
+    false
+}
+
+/// Files and directories that carry the immutable flag on Linux
+#[cfg(target_os = "linux")]
+const LINUX_PROTECTED_PATHS: &[&str] = &[
+    "/etc/systemd/system/parentshield-daemon.service",
+    "/usr/bin/parentshield",
+    "/usr/bin/parentshield-daemon",
+    "/etc/parentshield",
+];
+
+/// Package name held back from apt/dnf removal
+#[cfg(target_os = "linux")]
+const LINUX_PACKAGE: &str = "parentshield";
+
+/// Paths whose `lsattr -d` line shows the immutable (`i`) flag, e.g.
+/// "----i---------e------- /usr/bin/parentshield"
+#[cfg(any(target_os = "linux", test))]
+fn parse_lsattr_immutable(output: &str) -> std::collections::HashSet<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .filter(|(flags, _)| flags.contains('i'))
+        .map(|(_, path)| path.trim().to_string())
+        .collect()
+}
+
+/// Whether `apt-mark showhold` output lists `package`
+#[cfg(any(target_os = "linux", test))]
+fn parse_showhold(output: &str, package: &str) -> bool {
+    output.lines().any(|line| line.trim() == package)
+}
+
+/// Whether the package was installed through dpkg, so apt can hold it
+#[cfg(target_os = "linux")]
+fn installed_with_dpkg() -> bool {
+    Command::new("dpkg-query")
+        .args(["-W", "-f=${Status}", LINUX_PACKAGE])
+        .output()
+        .map(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).contains("install ok installed"))
+        .unwrap_or(false)
+}
+
+/// List the protections that should be in place but aren't: immutable flags
+/// on files that exist, and the apt hold on a dpkg-installed package
+#[cfg(target_os = "linux")]
+pub fn verify_protection() -> Vec<String> {
+    let existing: Vec<&str> = LINUX_PROTECTED_PATHS
+        .iter()
+        .copied()
+        .filter(|path| std::path::Path::new(path).exists())
+        .collect();
+
+    let mut missing = Vec::new();
+    if !existing.is_empty() {
+        let immutable = Command::new("lsattr")
+            .arg("-d")
+            .args(&existing)
+            .output()
+            .map(|o| parse_lsattr_immutable(&String::from_utf8_lossy(&o.stdout)))
+            .unwrap_or_default();
+        missing.extend(
+            existing
+                .iter()
+                .filter(|path| !immutable.contains(**path))
+                .map(|path| format!("immutable flag on {}", path)),
+        );
+    }
+
+    if installed_with_dpkg() {
+        let held = Command::new("apt-mark")
+            .arg("showhold")
+            .output()
+            .map(|o| parse_showhold(&String::from_utf8_lossy(&o.stdout), LINUX_PACKAGE))
+            .unwrap_or(false);
+        if !held {
+            missing.push(format!("apt hold on {}", LINUX_PACKAGE));
+        }
+    }
+
+    missing
+}
+
+/// Immutable flags and package holds are only checked on Linux
+#[cfg(not(target_os = "linux"))]
+pub fn verify_protection() -> Vec<String> {
+    Vec::new()
+}
+
+/// Put back whatever `verify_protection` finds missing, returning what that was
+pub fn reassert_protection() -> Result<Vec<String>, ProtectionError> {
+    let missing = verify_protection();
+
+    #[cfg(target_os = "linux")]
+    if !missing.is_empty() {
+        protect_linux_service()?;
+        protect_linux_files()?;
+        protect_linux_package()?;
+    }
+
+    Ok(missing)
+}
+
+/// Clear immutable flags on ParentShield's own files so an update can replace them.
+/// Unlike disable_protection this leaves package holds and ACLs in place.
+pub fn suspend_file_protection() -> Result<(), ProtectionError> {
+    #[cfg(target_os = "linux")]
+    for path in LINUX_PROTECTED_PATHS {
+        let _ = Command::new("chattr").args(["-i", path]).output();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = Command::new("chflags")
+            .args(["nouchg", "/Library/LaunchDaemons/com.parentshield.daemon.plist"])
+            .output();
+        let _ = Command::new("chflags")
+            .args(["-R", "nouchg", "/Applications/ParentShield.app"])
+            .output();
+    }
+
+    Ok(())
+}
+
+/// Put back the immutable flags cleared by suspend_file_protection
+pub fn restore_file_protection() -> Result<(), ProtectionError> {
+    #[cfg(target_os = "linux")]
+    {
+        protect_linux_service()?;
+        protect_linux_files()?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        protect_macos_daemon()?;
+        protect_macos_files()?;
+    }
+
+    Ok(())
+}
+
 // =============================================================================
 // Windows Protection
 // =============================================================================
@@ -365,10 +532,15 @@ fn protect_linux_package() -> Result<(), ProtectionError> {
 // =============================================================================
 
 /// Perform a password-protected uninstall
-pub fn uninstall_with_password(password: &str) -> Result<(), ProtectionError> {
+pub fn uninstall_with_password(password: &Zeroizing<String>) -> Result<(), ProtectionError> {
     // First verify the password
     verify_uninstall_password(password)?;
 
+    uninstall_authorized()
+}
+
+/// Uninstall after the caller has already authenticated the parent
+pub fn uninstall_authorized() -> Result<(), ProtectionError> {
     // Disable protection
     disable_protection()?;
 
@@ -474,4 +646,25 @@ mod tests {
         let err = ProtectionError::AccessDenied;
         assert!(err.to_string().contains("incorrect password"));
     }
+
+    #[test]
+    fn test_lsattr_parser_finds_immutable_paths() {
+        let output = "\
+----i---------e------- /usr/bin/parentshield
+--------------e------- /usr/bin/parentshield-daemon
+----i----------------- /etc/parentshield
+";
+        let immutable = parse_lsattr_immutable(output);
+        assert!(immutable.contains("/usr/bin/parentshield"));
+        assert!(immutable.contains("/etc/parentshield"));
+        assert!(!immutable.contains("/usr/bin/parentshield-daemon"));
+        assert!(parse_lsattr_immutable("").is_empty());
+    }
+
+    #[test]
+    fn test_showhold_parser_matches_whole_names() {
+        assert!(parse_showhold("firefox\nparentshield\n", "parentshield"));
+        assert!(!parse_showhold("parentshield-beta\n", "parentshield"));
+        assert!(!parse_showhold("", "parentshield"));
+    }
 }
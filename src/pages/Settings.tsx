@@ -577,6 +577,14 @@ export function Settings({ onBack }: SettingsPageProps) {
                 <p>Uptime: {Math.floor((daemonStatus.uptimeSecs || 0) / 60)} minutes</p>
                 <p>Processes blocked: {daemonStatus.blockedCount || 0}</p>
                 <p>Firewall active: {daemonStatus.firewallActive ? "Yes" : "No"}</p>
+                {daemonStatus.lastCheckUnix > 0 && (
+                  <p>Last check: {new Date(daemonStatus.lastCheckUnix * 1000).toLocaleTimeString()}</p>
+                )}
+                {daemonStatus.checkStale && (
+                  <p className="text-amber-700 dark:text-amber-400">
+                    The daemon is running but hasn't checked for blocked apps recently. Try restarting it.
+                  </p>
+                )}
               </div>
             )}
 
@@ -65,6 +65,7 @@ interface ChangePasswordResult {
 interface AuthStore {
   isConfigured: boolean;
   isAuthenticated: boolean;
+  sessionToken: string | null;
   masterPassword: string | null;
   isLoading: boolean;
   error: string | null;
@@ -81,6 +82,7 @@ interface AuthStore {
 export const useAuthStore = create<AuthStore>((set) => ({
   isConfigured: false,
   isAuthenticated: false,
+  sessionToken: null,
   masterPassword: null,
   isLoading: true,
   error: null,
@@ -127,13 +129,13 @@ export const useAuthStore = create<AuthStore>((set) => ({
   login: async (password: string) => {
     try {
       set({ isLoading: true, error: null });
-      const isValid = await invoke<boolean>("verify_password", { password });
-      if (isValid) {
-        set({ isAuthenticated: true, isLoading: false });
+      const token = await invoke<string | null>("verify_password", { password });
+      if (token) {
+        set({ isAuthenticated: true, sessionToken: token, isLoading: false });
       } else {
         set({ error: "Invalid password", isLoading: false });
       }
-      return isValid;
+      return token !== null;
     } catch (error) {
       set({ error: extractErrorMessage(error), isLoading: false });
       return false;
@@ -141,7 +143,8 @@ export const useAuthStore = create<AuthStore>((set) => ({
   },
 
   logout: () => {
-    set({ isAuthenticated: false });
+    invoke("logout").catch(() => {});
+    set({ isAuthenticated: false, sessionToken: null });
   },
 
   changePassword: async (oldPassword: string, newPassword: string) => {
@@ -12,6 +12,9 @@ export interface DaemonStatus {
   firewallActive: boolean;
   blockedCount: number;
   uptimeSecs: number;
+  lastCheckUnix: number;
+  checkIntervalSecs: number;
+  checkStale: boolean;
 }
 
 interface DaemonStore {
@@ -38,6 +41,9 @@ const defaultStatus: DaemonStatus = {
   firewallActive: false,
   blockedCount: 0,
   uptimeSecs: 0,
+  lastCheckUnix: 0,
+  checkIntervalSecs: 0,
+  checkStale: false,
 };
 
 export const useDaemonStore = create<DaemonStore>((set) => ({
@@ -20,7 +20,7 @@ interface ScheduleStore {
   addSchedule: (schedule: Omit<Schedule, "id">) => Promise<Schedule | null>;
   updateSchedule: (schedule: Schedule) => Promise<boolean>;
   deleteSchedule: (id: string) => Promise<boolean>;
-  addPresetSchedule: (preset: "school" | "bedtime" | "weekend") => Promise<Schedule | null>;
+  addPresetSchedule: (preset: "school" | "bedtime" | "weekend" | "weekday_default") => Promise<Schedule[]>;
 }
 
 export const useScheduleStore = create<ScheduleStore>((set) => ({
@@ -92,15 +92,15 @@ export const useScheduleStore = create<ScheduleStore>((set) => ({
   addPresetSchedule: async (preset) => {
     try {
       set({ isLoading: true, error: null });
-      const schedule = await invoke<Schedule>("add_preset_schedule", { preset });
+      const added = await invoke<Schedule[]>("add_preset_schedule", { preset });
       set((state) => ({
-        schedules: [...state.schedules, schedule],
+        schedules: [...state.schedules, ...added],
         isLoading: false,
       }));
-      return schedule;
+      return added;
     } catch (error) {
       set({ error: String(error), isLoading: false });
-      return null;
+      return [];
     }
   },
 }));